@@ -0,0 +1,288 @@
+//! Reading the RustSec advisory database, for crates that are no longer maintained or have known
+//! vulnerabilities.
+
+use crate::errors::*;
+use crate::net::NetConfig;
+use crate::registry::cargo_home;
+use regex::Regex;
+use semver::{Version, VersionReq};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+
+/// Where the RustSec advisory database is cloned from
+const ADVISORY_DB_URL: &str = "https://github.com/rustsec/advisory-db";
+
+/// Links to the pages of crates on crates.io or lib.rs, which advisories name replacements with
+fn crate_links() -> Regex {
+    Regex::new(r"https?://(?:crates\.io|lib\.rs)/crates/([A-Za-z0-9_-]+)").unwrap()
+}
+
+/// An advisory about a crate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Advisory {
+    /// Its identifier, e.g. `RUSTSEC-2020-0036`
+    pub id: String,
+    /// The crate it is about
+    pub package: String,
+    /// The kind of informational advisory, e.g. `unmaintained`, unless it reports a vulnerability
+    pub informational: Option<String>,
+    /// Crates the advisory suggests using instead
+    pub alternatives: Vec<String>,
+    /// Versions that the advisory doesn't apply to
+    fixed: Vec<VersionReq>,
+    withdrawn: bool,
+}
+
+impl Advisory {
+    /// Whether the advisory applies to a version of its crate
+    pub fn affects(&self, version: &Version) -> bool {
+        !self.withdrawn && !self.fixed.iter().any(|req| req.matches(version))
+    }
+
+    /// Parse an advisory, a Markdown file starting with its metadata in a TOML code block
+    ///
+    /// Replacements are taken from links to crates in the description, which match `links`.
+    fn parse(contents: &str, links: &Regex) -> Option<Self> {
+        #[derive(Deserialize)]
+        struct Metadata {
+            advisory: AdvisoryMetadata,
+            #[serde(default)]
+            versions: Versions,
+        }
+        #[derive(Deserialize)]
+        struct AdvisoryMetadata {
+            id: String,
+            package: String,
+            informational: Option<String>,
+            withdrawn: Option<toml::Value>,
+        }
+        #[derive(Default, Deserialize)]
+        struct Versions {
+            #[serde(default)]
+            patched: Vec<String>,
+            #[serde(default)]
+            unaffected: Vec<String>,
+        }
+
+        let rest = contents.trim_start().strip_prefix("```toml")?;
+        let end = rest.find("\n```")?;
+        let metadata: Metadata = toml::from_str(&rest[..end]).ok()?;
+        let description = &rest[end..];
+
+        let mut alternatives = Vec::new();
+        for link in links.captures_iter(description) {
+            let name = &link[1];
+            if name != metadata.advisory.package && !alternatives.iter().any(|n| n == name) {
+                alternatives.push(name.to_owned());
+            }
+        }
+
+        let versions = metadata.versions;
+        Some(Advisory {
+            id: metadata.advisory.id,
+            package: metadata.advisory.package,
+            informational: metadata.advisory.informational,
+            alternatives,
+            fixed: versions
+                .patched
+                .iter()
+                .chain(&versions.unaffected)
+                .filter_map(|req| VersionReq::parse(req).ok())
+                .collect(),
+            withdrawn: metadata.advisory.withdrawn.is_some(),
+        })
+    }
+}
+
+/// A local copy of the RustSec advisory database
+#[derive(Debug, Clone, Default)]
+pub struct AdvisoryDb {
+    advisories: BTreeMap<String, Vec<Advisory>>,
+}
+
+impl AdvisoryDb {
+    /// Where cargo-audit keeps its copy of the database, which is shared with it
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(cargo_home()?.join("advisory-db"))
+    }
+
+    /// Read the advisories about crates from a copy of the database
+    ///
+    /// Files that aren't advisories in the expected format are skipped.
+    pub fn open(path: &Path) -> Result<Self> {
+        let crates = path.join("crates");
+        let dirs = fs::read_dir(&crates).chain_err(|| {
+            format!(
+                "Failed to read the advisory database at `{}`",
+                path.display()
+            )
+        })?;
+
+        let links = crate_links();
+        let mut advisories: BTreeMap<String, Vec<Advisory>> = BTreeMap::new();
+        for dir in dirs {
+            let dir = dir?.path();
+            if !dir.is_dir() {
+                continue;
+            }
+            for file in fs::read_dir(dir)? {
+                let file = file?.path();
+                if file.extension().is_some_and(|ext| ext == "md") {
+                    let contents = fs::read_to_string(&file)?;
+                    if let Some(advisory) = Advisory::parse(&contents, &links) {
+                        advisories
+                            .entry(advisory.package.clone())
+                            .or_default()
+                            .push(advisory);
+                    }
+                }
+            }
+        }
+        Ok(AdvisoryDb { advisories })
+    }
+
+    /// The advisory saying that a crate is unmaintained, if there is one that applies to the
+    /// version
+    pub fn unmaintained(&self, name: &str, version: &Version) -> Option<&Advisory> {
+        self.advisories.get(name)?.iter().find(|advisory| {
+            advisory.informational.as_deref() == Some("unmaintained") && advisory.affects(version)
+        })
+    }
+
+    /// The advisories reporting vulnerabilities that apply to a version of a crate
+    pub fn vulnerabilities(&self, name: &str, version: &Version) -> Vec<&Advisory> {
+        self.advisories
+            .get(name)
+            .into_iter()
+            .flatten()
+            .filter(|advisory| advisory.informational.is_none() && advisory.affects(version))
+            .collect()
+    }
+}
+
+/// Clone the advisory database to `path`, or bring the copy there up to date
+pub fn update_advisory_db(path: &Path, quiet: bool, net: &NetConfig) -> Result<()> {
+    let colorchoice = if atty::is(atty::Stream::Stdout) {
+        ColorChoice::Auto
+    } else {
+        ColorChoice::Never
+    };
+    let mut output = StandardStream::stdout(colorchoice);
+    if !quiet {
+        output.set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))?;
+        write!(output, "{:>12}", "Updating")?;
+        output.reset()?;
+        writeln!(output, " RustSec advisory database")?;
+    }
+
+    let cmd = subprocess::Exec::cmd("git").args(&net.tls.git_options());
+    let cmd = if path.join(".git").exists() {
+        cmd.arg("pull").arg("--ff-only").arg("--quiet").cwd(path)
+    } else {
+        cmd.arg("clone")
+            .arg("--quiet")
+            .arg(ADVISORY_DB_URL)
+            .arg(path)
+    };
+    let capture = cmd.env_remove("GIT_DIR").capture().map_err(|e| match e {
+        subprocess::PopenError::IoError(io) => ErrorKind::Io(io),
+        _ => unreachable!("expected only io error"),
+    })?;
+    if !capture.success() {
+        bail!(
+            "Failed to update the advisory database: {}",
+            capture.stderr_str().trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNMAINTAINED: &str = r#"```toml
+[advisory]
+id = "RUSTSEC-2020-0036"
+package = "failure"
+date = "2020-05-02"
+informational = "unmaintained"
+
+[versions]
+patched = []
+```
+
+# failure is officially deprecated/unmaintained
+
+## Suggested alternatives
+
+- [`anyhow`](https://crates.io/crates/anyhow)
+- [`thiserror`](https://crates.io/crates/thiserror)
+"#;
+
+    #[test]
+    fn parses_advisories() {
+        let advisory = Advisory::parse(UNMAINTAINED, &crate_links()).unwrap();
+        assert_eq!(advisory.id, "RUSTSEC-2020-0036");
+        assert_eq!(advisory.package, "failure");
+        assert_eq!(advisory.informational.as_deref(), Some("unmaintained"));
+        assert_eq!(advisory.alternatives, vec!["anyhow", "thiserror"]);
+        assert!(advisory.affects(&Version::parse("0.1.8").unwrap()));
+
+        assert!(Advisory::parse("# Not an advisory", &crate_links()).is_none());
+    }
+
+    #[test]
+    fn finds_unmaintained_crates() {
+        let dir = tempfile::tempdir().unwrap();
+        let crates = dir.path().join("crates");
+        fs::create_dir_all(crates.join("failure")).unwrap();
+        fs::write(crates.join("failure/RUSTSEC-2020-0036.md"), UNMAINTAINED).unwrap();
+        fs::create_dir_all(crates.join("revived")).unwrap();
+        fs::write(
+            crates.join("revived/RUSTSEC-2021-0001.md"),
+            UNMAINTAINED
+                .replace("failure", "revived")
+                .replace("patched = []", "patched = [\">= 2.0.0\"]"),
+        )
+        .unwrap();
+
+        let db = AdvisoryDb::open(dir.path()).unwrap();
+        let version = |v: &str| Version::parse(v).unwrap();
+        assert!(db.unmaintained("failure", &version("0.1.8")).is_some());
+        assert!(db.unmaintained("revived", &version("1.0.0")).is_some());
+        assert!(db.unmaintained("revived", &version("2.1.0")).is_none());
+        assert!(db.unmaintained("anyhow", &version("1.0.0")).is_none());
+    }
+
+    #[test]
+    fn finds_vulnerabilities() {
+        let dir = tempfile::tempdir().unwrap();
+        let crates = dir.path().join("crates");
+        fs::create_dir_all(crates.join("smallvec")).unwrap();
+        fs::write(
+            crates.join("smallvec/RUSTSEC-2021-0003.md"),
+            UNMAINTAINED
+                .replace("RUSTSEC-2020-0036", "RUSTSEC-2021-0003")
+                .replace("failure", "smallvec")
+                .replace("informational = \"unmaintained\"\n", "")
+                .replace("patched = []", "patched = [\">= 1.6.1\"]"),
+        )
+        .unwrap();
+
+        let db = AdvisoryDb::open(dir.path()).unwrap();
+        let version = |v: &str| Version::parse(v).unwrap();
+        let ids = |v| {
+            db.vulnerabilities("smallvec", &version(v))
+                .into_iter()
+                .map(|advisory| advisory.id.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids("1.6.0"), vec!["RUSTSEC-2021-0003"]);
+        assert!(ids("1.6.1").is_empty());
+        assert!(db.unmaintained("smallvec", &version("1.6.0")).is_none());
+    }
+}
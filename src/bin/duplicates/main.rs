@@ -0,0 +1,5 @@
+//! `cargo duplicates`, as `cargo_edit::commands::duplicates` runs it
+
+fn main() {
+    cargo_edit::commands::duplicates::run(std::env::args_os());
+}
@@ -0,0 +1,5 @@
+//! `cargo edit`, as `cargo_edit::commands::edit` runs it
+
+fn main() {
+    cargo_edit::commands::edit::run(std::env::args_os());
+}
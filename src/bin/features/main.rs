@@ -0,0 +1,5 @@
+//! `cargo features`, as `cargo_edit::commands::features` runs it
+
+fn main() {
+    cargo_edit::commands::features::run(std::env::args_os());
+}
@@ -0,0 +1,5 @@
+//! `cargo inventory`, as `cargo_edit::commands::inventory` runs it
+
+fn main() {
+    cargo_edit::commands::inventory::run(std::env::args_os());
+}
@@ -0,0 +1,5 @@
+//! `cargo manifest`, as `cargo_edit::commands::manifest` runs it
+
+fn main() {
+    cargo_edit::commands::manifest::run(std::env::args_os());
+}
@@ -0,0 +1,5 @@
+//! `cargo pin`, as `cargo_edit::commands::pin` runs it
+
+fn main() {
+    cargo_edit::commands::pin::run(std::env::args_os());
+}
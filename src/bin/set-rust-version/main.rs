@@ -0,0 +1,5 @@
+//! `cargo set-rust-version`, as `cargo_edit::commands::set_rust_version` runs it
+
+fn main() {
+    cargo_edit::commands::set_rust_version::run(std::env::args_os());
+}
@@ -0,0 +1,5 @@
+//! `cargo staleness`, as `cargo_edit::commands::staleness` runs it
+
+fn main() {
+    cargo_edit::commands::staleness::run(std::env::args_os());
+}
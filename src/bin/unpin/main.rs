@@ -0,0 +1,5 @@
+//! `cargo unpin`, as `cargo_edit::commands::unpin` runs it
+
+fn main() {
+    cargo_edit::commands::unpin::run(std::env::args_os());
+}
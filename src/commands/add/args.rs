@@ -0,0 +1,690 @@
+//! Handle `cargo add` arguments
+
+use crate::commands::{ClientIdentityArgs, NetArgs};
+use crate::{add_defaults, find, registry_source, Dependency, RegistrySource};
+use crate::{get_git_packages, get_latest_dependency, CrateName};
+use crate::{NetConfig, Policy, POLICY_ENV};
+use std::path::PathBuf;
+use structopt::{clap::AppSettings, StructOpt};
+use url::Url;
+
+use super::errors::*;
+use super::locked::locked_version;
+use super::siblings::{pick_requirement, sibling_requirements};
+
+#[derive(Debug, StructOpt)]
+#[structopt(bin_name = "cargo")]
+pub enum Command {
+    /// Add dependency to a Cargo.toml manifest file.
+    #[structopt(name = "add")]
+    #[structopt(after_help = "\
+This command allows you to add a dependency to a Cargo.toml manifest file. If <crate> is a github \
+or gitlab repository URL (or a `gh:owner/repo` shorthand), or a local path, `cargo add` will try to \
+automatically get the crate name and set the appropriate `--git` or `--path` value. A crates.io \
+URL such as `https://crates.io/crates/serde` adds that crate from the registry.
+
+Please note that Cargo treats versions like '1.2.3' as '^1.2.3' (and that '^1.2.3' is specified \
+as '>=1.2.3 and <2.0.0'). By default, `cargo add` will use this format, as it is the one that the \
+crates.io registry suggests. One goal of `cargo add` is to prevent you from using wildcard \
+dependencies (version set to '*').
+
+HTTPS connections also trust the CA bundle set by `http.cainfo` in the cargo config (or the \
+`CARGO_HTTP_CAINFO` environment variable), as cargo does. A client certificate for registries that \
+require one can be given with '--client-cert' and '--client-key'.
+
+A policy file, given with '--policy' or the `CARGO_EDIT_POLICY` environment variable, can ban \
+crates and restrict the versions of others, with a table for each crate: e.g. `[crates.openssl]` \
+with `banned = true` and an optional `reason`, or `[crates.time]` with `minimum = \"0.2.23\"` and \
+`allowed = \"<0.3\"`. Banned crates and versions against the policy are refused, and if the latest \
+version is outside the allowed range, the latest one inside it is added instead.
+
+With '--search', crates.io is searched instead of naming the crate to add, e.g. \
+`cargo add --search \"http client\"`. The top results are listed with their description, downloads \
+and last release, and the crate to add is picked from them by number.
+
+With '--git-package', the crate to add is picked out of a git repository holding several, e.g. a \
+workspace: `cargo add foo --git <url> --git-package foo-core` adds \
+`foo = { git = \"<url>\", package = \"foo-core\" }`. The repository is checked for the package first, \
+unless offline.
+
+With '--match-workspace', a crate that workspace members already depend on is added with the \
+version requirement, features and `default-features` they have, instead of the latest version, to \
+keep the workspace consistent. If they differ, the one most members have is copied, with a \
+warning. Crates no member depends on get the latest version.
+
+With '--offline' (or `net.offline` in the cargo config), nothing is fetched. A crate that \
+`Cargo.lock` already has, e.g. as a dependency of another crate, is added at the newest version \
+it has there, which cargo has downloaded already, and other crates at the latest version in the \
+local copy of the registry index, or in cargo's index cache if there is no usable copy. A note says \
+which version was used, and a warning is printed if it was read from the index cache.
+
+With '--lockfile-version', a crate that `Cargo.lock` already has is added at the newest version it \
+has there whether or not cargo is offline, e.g. to make a crate that is only depended on \
+indirectly a direct dependency without moving the whole dependency graph to its latest version. \
+Crates the lock file doesn't have get the latest version.
+
+With '--comment', a `# comment` line is put above the added dependency. To have the description \
+of each crate added from crates.io put there instead, set `cargo-edit.describe-dependencies = true` \
+in the cargo config.
+
+Crates can be added in a standard shape by default with a table for each in the cargo config, e.g. \
+`[cargo-edit.add-defaults]` with `serde = { features = [\"derive\"] }` or \
+`openssl = { default-features = false }`. '--features' and '--no-default-features' take precedence \
+over them, and so do the features '--match-workspace' copies.
+
+With '--feature-of', the optional dependency is enabled with a `dep:name` entry in the feature, \
+unless the package's `rust-version` is older than 1.60, whose cargo can't parse such entries. A \
+plain `name` entry, for the feature cargo creates for the dependency, is added then.
+
+Crates from crates.io that look suspicious are warned about before they are added: ones first \
+published days ago that have hardly been downloaded, ones named one typo away from a popular crate, \
+and ones whose owners have published nothing else. '--force' skips these checks, and \
+'--deny-suspicious' refuses to add such crates instead, e.g. in automation.")]
+    Add(Args),
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(setting = AppSettings::ColoredHelp)]
+pub struct Args {
+    /// Crates to be added.
+    #[structopt(name = "crate", required_unless = "search")]
+    pub crates: Vec<String>,
+
+    /// Search crates.io, and pick the crate to add from the top results.
+    #[structopt(
+        long = "search",
+        value_name = "query",
+        conflicts_with = "crate",
+        conflicts_with = "git",
+        conflicts_with = "path",
+        conflicts_with = "registry",
+        conflicts_with = "index",
+        conflicts_with = "local-registry",
+        conflicts_with = "offline"
+    )]
+    pub search: Option<String>,
+
+    /// Rename a dependency in Cargo.toml,
+    /// https://doc.rust-lang.org/cargo/reference/specifying-dependencies.html#renaming-dependencies-in-cargotoml.
+    /// Only works when specifying a single dependency.
+    #[structopt(long = "rename", short = "r")]
+    pub rename: Option<String>,
+
+    /// Comment to put above the added dependency, e.g. what it is used for. Only works when
+    /// specifying a single dependency.
+    #[structopt(long = "comment", value_name = "text")]
+    pub comment: Option<String>,
+
+    /// Add crate as development dependency.
+    #[structopt(long = "dev", short = "D", conflicts_with = "build")]
+    pub dev: bool,
+
+    /// Add crate as build dependency.
+    #[structopt(long = "build", short = "B", conflicts_with = "dev")]
+    pub build: bool,
+
+    /// Copy the requirement and features that other workspace members have, instead of using the
+    /// latest version.
+    #[structopt(
+        long = "match-workspace",
+        conflicts_with = "vers",
+        conflicts_with = "git",
+        conflicts_with = "path"
+    )]
+    pub match_workspace: bool,
+
+    /// Add a crate that `Cargo.lock` already has, e.g. as a dependency of another crate, at the
+    /// version it has there, instead of the latest version.
+    #[structopt(
+        long = "lockfile-version",
+        conflicts_with = "vers",
+        conflicts_with = "git",
+        conflicts_with = "path"
+    )]
+    pub lockfile_version: bool,
+
+    /// Specify the version to grab from the registry(crates.io).
+    /// You can also specify version as part of name, e.g
+    /// `cargo add bitflags@0.3.2`.
+    #[structopt(long = "vers", value_name = "uri", conflicts_with = "git")]
+    pub vers: Option<String>,
+
+    /// Specify a git repository to download the crate from.
+    #[structopt(
+        long = "git",
+        value_name = "uri",
+        conflicts_with = "vers",
+        conflicts_with = "path"
+    )]
+    pub git: Option<String>,
+
+    /// Specify a git branch to download the crate from.
+    #[structopt(
+        long = "branch",
+        value_name = "branch",
+        conflicts_with = "vers",
+        conflicts_with = "path"
+    )]
+    pub branch: Option<String>,
+
+    /// Package to depend on in a git repository holding several (e.g. a workspace). The crate name
+    /// is used for the dependency, with a `package` key if they differ.
+    #[structopt(
+        long = "git-package",
+        value_name = "name",
+        requires = "git",
+        conflicts_with = "rename"
+    )]
+    pub git_package: Option<String>,
+
+    /// Specify the path the crate should be loaded from.
+    #[structopt(long = "path", conflicts_with = "git")]
+    pub path: Option<PathBuf>,
+
+    /// Add as dependency to the given target platform.
+    #[structopt(long = "target", conflicts_with = "dev", conflicts_with = "build")]
+    pub target: Option<String>,
+
+    /// Add as an optional dependency (for use in features).
+    #[structopt(long = "optional", conflicts_with = "dev", conflicts_with = "build")]
+    pub optional: bool,
+
+    /// Name of a feature in `[features]` to enable the optional dependency, with a `dep:` entry.
+    /// The feature is added if it doesn't exist yet.
+    #[structopt(long = "feature-of", value_name = "feature", requires = "optional")]
+    pub feature_of: Option<String>,
+
+    /// Path to the manifest to add a dependency to.
+    #[structopt(long = "manifest-path", value_name = "path", conflicts_with = "pkgid")]
+    pub manifest_path: Option<PathBuf>,
+
+    /// Package id of the crate to add this dependency to.
+    #[structopt(
+        long = "package",
+        short = "p",
+        value_name = "pkgid",
+        conflicts_with = "path"
+    )]
+    pub pkgid: Option<String>,
+
+    /// Choose method of semantic version upgrade.  Must be one of "none" (exact version, `=`
+    /// modifier), "patch" (`~` modifier), "minor" (`^` modifier), "all" (`>=`), or "default" (no
+    /// modifier).
+    #[structopt(
+        long = "upgrade",
+        value_name = "method",
+        possible_value = "none",
+        possible_value = "patch",
+        possible_value = "minor",
+        possible_value = "all",
+        possible_value = "default",
+        default_value = "default"
+    )]
+    pub upgrade: String,
+
+    /// Include prerelease versions when fetching from crates.io (e.g.
+    /// '0.6.0-alpha').
+    #[structopt(long = "allow-prerelease")]
+    pub allow_prerelease: bool,
+
+    /// Space-separated list of features to add. For an alternative approach to
+    /// enabling features, consider installing the `cargo-feature` utility.
+    #[structopt(long = "features", number_of_values = 1)]
+    pub features: Option<Vec<String>>,
+
+    /// Set `default-features = false` for the added dependency.
+    #[structopt(long = "no-default-features")]
+    pub no_default_features: bool,
+
+    /// Do not print any output in case of success.
+    #[structopt(long = "quiet", short = "q")]
+    pub quiet: bool,
+
+    #[structopt(flatten)]
+    pub net: NetArgs,
+
+    /// Sort dependencies even if currently unsorted
+    #[structopt(long = "sort", short = "s")]
+    pub sort: bool,
+
+    /// Registry to use
+    #[structopt(long = "registry", conflicts_with = "git", conflicts_with = "path")]
+    pub registry: Option<String>,
+
+    /// Registry index to resolve versions against instead of crates.io (e.g. a mirror, or a
+    /// `file://` URL of an index on disk).
+    #[structopt(
+        long = "index",
+        value_name = "url",
+        parse(try_from_str = Url::parse),
+        conflicts_with = "registry",
+        conflicts_with = "git",
+        conflicts_with = "path"
+    )]
+    pub index: Option<Url>,
+
+    /// Resolve versions from a local registry or `cargo vendor` directory instead of crates.io.
+    #[structopt(
+        long = "local-registry",
+        value_name = "path",
+        conflicts_with = "index",
+        conflicts_with = "registry",
+        conflicts_with = "git",
+        conflicts_with = "path"
+    )]
+    pub local_registry: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    pub identity: ClientIdentityArgs,
+
+    /// Policy file declaring banned crates and the versions others may be added at.
+    #[structopt(long = "policy", value_name = "path", env = POLICY_ENV)]
+    pub policy: Option<PathBuf>,
+
+    /// Add crates without checking whether they look suspicious.
+    #[structopt(long = "force", conflicts_with = "deny-suspicious")]
+    pub force: bool,
+
+    /// Refuse to add crates that look suspicious, rather than warning about them.
+    #[structopt(long = "deny-suspicious", conflicts_with = "force")]
+    pub deny_suspicious: bool,
+}
+
+fn parse_version_req(s: &str) -> Result<&str> {
+    semver::VersionReq::parse(s).chain_err(|| "Invalid dependency version requirement")?;
+    Ok(s)
+}
+
+impl Args {
+    /// Get dependency section
+    pub fn get_section(&self) -> Vec<String> {
+        if self.dev {
+            vec!["dev-dependencies".to_owned()]
+        } else if self.build {
+            vec!["build-dependencies".to_owned()]
+        } else if let Some(ref target) = self.target {
+            if target.is_empty() {
+                panic!("Target specification may not be empty");
+            }
+            vec![
+                "target".to_owned(),
+                target.clone(),
+                "dependencies".to_owned(),
+            ]
+        } else {
+            vec!["dependencies".to_owned()]
+        }
+    }
+
+    fn parse_single_dependency(&self, crate_name: &str) -> Result<Dependency> {
+        let crate_name = CrateName::new(crate_name);
+
+        if let Some(spec) = crate_name.crates_io_spec() {
+            // crate specifier is a crates.io page (e.g. `https://crates.io/crates/docopt`)
+            return self.parse_single_dependency(&spec);
+        }
+
+        if let Some(mut dependency) = crate_name.parse_as_version()? {
+            // crate specifier includes a version (e.g. `docopt@0.8`)
+            if let Some(ref url) = self.git {
+                let url = url.clone();
+                let version = dependency.version().unwrap().to_string();
+                return Err(ErrorKind::GitUrlWithVersion(url, version).into());
+            }
+
+            if let Some(ref path) = self.path {
+                dependency = dependency.set_path(path.to_str().unwrap());
+            }
+
+            Ok(dependency)
+        } else if crate_name.is_url_or_path() {
+            Ok(crate_name.parse_crate_name_from_uri(&self.net_config()?)?)
+        } else {
+            assert!(!(self.git.is_some() && self.vers.is_some()));
+            assert!(!(self.git.is_some() && self.path.is_some()));
+            assert!(!(self.git.is_some() && self.registry.is_some()));
+            assert!(!(self.path.is_some() && self.registry.is_some()));
+
+            let mut dependency = match &self.git_package {
+                Some(package) => {
+                    self.check_git_package(package)?;
+                    let dependency = Dependency::new(package);
+                    if package == crate_name.name() {
+                        dependency
+                    } else {
+                        dependency.set_rename(crate_name.name())
+                    }
+                }
+                None => Dependency::new(crate_name.name()),
+            };
+
+            if let Some(repo) = &self.git {
+                dependency = dependency.set_git(repo, self.branch.clone());
+            }
+            if let Some(path) = &self.path {
+                dependency = dependency.set_path(path.to_str().unwrap());
+            }
+            if let Some(version) = &self.vers {
+                dependency = dependency.set_version(parse_version_req(version)?);
+            }
+            let registry = self.registry_source()?;
+
+            let sibling = if self.match_workspace {
+                let requirements = sibling_requirements(
+                    &find(&self.manifest_path)?,
+                    crate_name.name(),
+                    self.registry.as_deref(),
+                )?;
+                pick_requirement(crate_name.name(), &requirements)
+            } else {
+                None
+            };
+
+            // Offline, a version that the lock file has already is sure to be in the cache. With
+            // `--lockfile-version`, it is used anyway, to leave the dependency graph as it is.
+            let offline = self.net_config()?.offline;
+            let latest_wanted = self.git.is_none() && self.path.is_none() && self.vers.is_none();
+            let own_source = self.local_registry.is_some() || self.index.is_some();
+            let locked = match &sibling {
+                None if latest_wanted && (self.lockfile_version || offline && !own_source) => {
+                    locked_version(
+                        &find(&self.manifest_path)?,
+                        crate_name.name(),
+                        self.registry.as_deref(),
+                        self.allow_prerelease,
+                    )?
+                }
+                _ => None,
+            };
+
+            if let Some(sibling) = sibling {
+                dependency = sibling.to_dependency(crate_name.name());
+            } else if let Some(locked) = locked {
+                if self.lockfile_version {
+                    eprintln!(
+                        "note: Adding `{}` at {}, the version in `Cargo.lock`",
+                        crate_name.name(),
+                        locked
+                    );
+                } else {
+                    eprintln!(
+                        "note: Adding `{}` at {}, the version in `Cargo.lock`, as cargo is offline",
+                        crate_name.name(),
+                        locked
+                    );
+                }
+                let v = format!("{}{}", self.get_upgrade_prefix(), locked);
+                dependency = Dependency::new(crate_name.name()).set_version(&v);
+            } else if latest_wanted {
+                let dep = get_latest_dependency(
+                    crate_name.name(),
+                    self.allow_prerelease,
+                    &find(&self.manifest_path)?,
+                    &registry,
+                )?;
+                // If version is unavailable `get_latest_dependency` must have
+                // returned `Err(FetchVersionError::GetVersion)`
+                let latest = dep.version().unwrap_or_else(|| unreachable!());
+                let version = match semver::Version::parse(latest) {
+                    Ok(latest) => {
+                        let allowed = Policy::from_path(self.policy.as_deref())?.clamp(
+                            &dep.name,
+                            &latest,
+                            &find(&self.manifest_path)?,
+                            &registry,
+                        )?;
+                        if allowed != latest {
+                            eprintln!(
+                                "WARN: Adding `{}` at {}, as the policy doesn't allow {}",
+                                dep.name, allowed, latest
+                            );
+                        }
+                        allowed.to_string()
+                    }
+                    Err(_) => latest.to_owned(),
+                };
+                if offline {
+                    eprintln!(
+                        "note: Adding `{}` at {}, the latest version in the local copy of the \
+                         registry index, as cargo is offline",
+                        dep.name, version
+                    );
+                }
+                let v = format!(
+                    "{prefix}{version}",
+                    prefix = self.get_upgrade_prefix(),
+                    version = version
+                );
+                dependency = dep.set_version(&v);
+            }
+
+            // Set the registry after getting the latest version as
+            // get_latest_dependency returns a registry-less Dependency
+            if let Some(registry) = &self.registry {
+                dependency = dependency.set_registry(registry);
+            }
+            Ok(dependency)
+        }
+    }
+
+    /// Build dependencies from arguments
+    pub fn parse_dependencies(&self) -> Result<Vec<Dependency>> {
+        if self.crates.len() > 1
+            && (self.git.is_some() || self.path.is_some() || self.vers.is_some())
+        {
+            return Err(ErrorKind::MultipleCratesWithGitOrPathOrVers.into());
+        }
+
+        if self.crates.len() > 1 && self.rename.is_some() {
+            return Err(ErrorKind::MultipleCratesWithRename.into());
+        }
+
+        if self.crates.len() > 1 && self.features.is_some() {
+            return Err(ErrorKind::MultipleCratesWithFeatures.into());
+        }
+
+        if self.crates.len() > 1 && self.comment.is_some() {
+            return Err(ErrorKind::MultipleCratesWithComment.into());
+        }
+
+        self.crates
+            .iter()
+            .map(|crate_name| {
+                self.parse_single_dependency(crate_name).and_then(|x| {
+                    // Features and `default-features` matched from the workspace are kept unless
+                    // given, and the defaults from the cargo config fill in the rest.
+                    let mut x = x.set_optional(self.optional);
+                    let defaults = add_defaults(&find(&self.manifest_path)?, &x.name)?;
+                    if x.features.is_none() {
+                        x = x.set_features(defaults.features);
+                    }
+                    if defaults.default_features == Some(false) {
+                        x = x.set_default_features(false);
+                    }
+                    if self.features.is_some() {
+                        x = x.set_features(self.features.clone());
+                    }
+                    if self.no_default_features {
+                        x = x.set_default_features(false);
+                    }
+                    if let Some(ref rename) = self.rename {
+                        x = x.set_rename(rename);
+                    }
+                    Ok(x)
+                })
+            })
+            .collect()
+    }
+
+    /// Check that the `--git` repository has the package, unless offline
+    fn check_git_package(&self, package: &str) -> Result<()> {
+        let net = self.net_config()?;
+        let repo = match &self.git {
+            Some(repo) if !net.offline => repo,
+            _ => return Ok(()),
+        };
+        let packages = get_git_packages(repo, self.branch.as_deref(), &net)?;
+        if packages.iter().any(|name| name == package) {
+            return Ok(());
+        }
+        let packages = if packages.is_empty() {
+            "none".to_owned()
+        } else {
+            packages.join(", ")
+        };
+        Err(ErrorKind::NoSuchGitPackage(package.to_owned(), repo.clone(), packages).into())
+    }
+
+    /// The network settings, with the flags and client certificate given on the command line
+    pub fn net_config(&self) -> Result<NetConfig> {
+        Ok(self.identity.apply(self.net.net_config()?)?)
+    }
+
+    /// Get the source to look up versions in, unless it is the manifest's default registry
+    pub fn registry_source(&self) -> Result<Option<RegistrySource>> {
+        Ok(if let Some(path) = &self.local_registry {
+            Some(RegistrySource::from_local_path(path))
+        } else if let Some(index) = &self.index {
+            Some(RegistrySource::from_index_url(index.clone()))
+        } else if let Some(registry) = &self.registry {
+            Some(registry_source(
+                &find(&self.manifest_path)?,
+                Some(registry),
+            )?)
+        } else {
+            None
+        })
+    }
+
+    fn get_upgrade_prefix(&self) -> &'static str {
+        match self.upgrade.as_ref() {
+            "default" => "",
+            "none" => "=",
+            "patch" => "~",
+            "minor" => "^",
+            "all" => ">=",
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Default for Args {
+    fn default() -> Args {
+        Args {
+            crates: vec!["demo".to_owned()],
+            search: None,
+            rename: None,
+            comment: None,
+            dev: false,
+            build: false,
+            match_workspace: false,
+            lockfile_version: false,
+            vers: None,
+            git: None,
+            branch: None,
+            git_package: None,
+            path: None,
+            target: None,
+            optional: false,
+            feature_of: None,
+            manifest_path: None,
+            pkgid: None,
+            upgrade: "minor".to_string(),
+            allow_prerelease: false,
+            features: None,
+            no_default_features: false,
+            quiet: false,
+            net: NetArgs {
+                offline: true,
+                frozen: false,
+            },
+            sort: false,
+            registry: None,
+            index: None,
+            local_registry: None,
+            identity: ClientIdentityArgs::default(),
+            policy: None,
+            force: false,
+            deny_suspicious: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dependency;
+
+    #[test]
+    fn test_dependency_parsing() {
+        let args = Args {
+            vers: Some("0.4.2".to_owned()),
+            ..Args::default()
+        };
+
+        assert_eq!(
+            args.parse_dependencies().unwrap(),
+            vec![Dependency::new("demo").set_version("0.4.2")]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "test-external-apis")]
+    fn test_repo_as_arg_parsing() {
+        let github_url = "https://github.com/killercup/cargo-edit/";
+        let args_github = Args {
+            crates: vec![github_url.to_owned()],
+            ..Args::default()
+        };
+        assert_eq!(
+            args_github.parse_dependencies().unwrap(),
+            vec![Dependency::new("cargo-edit").set_git(github_url, None)]
+        );
+
+        let gitlab_url = "https://gitlab.com/Polly-lang/Polly.git";
+        let args_gitlab = Args {
+            crates: vec![gitlab_url.to_owned()],
+            ..Args::default()
+        };
+        assert_eq!(
+            args_gitlab.parse_dependencies().unwrap(),
+            vec![Dependency::new("polly").set_git(gitlab_url, None)]
+        );
+
+        let args_shorthand = Args {
+            crates: vec!["gh:killercup/cargo-edit".to_owned()],
+            ..Args::default()
+        };
+        assert_eq!(
+            args_shorthand.parse_dependencies().unwrap(),
+            vec![Dependency::new("cargo-edit")
+                .set_git("https://github.com/killercup/cargo-edit", None)]
+        );
+    }
+
+    #[test]
+    fn test_crates_io_url_as_arg_parsing() {
+        let args = Args {
+            crates: vec!["https://crates.io/crates/docopt/0.8.3".to_owned()],
+            ..Args::default()
+        };
+        assert_eq!(
+            args.parse_dependencies().unwrap(),
+            vec![Dependency::new("docopt").set_version("0.8.3")]
+        );
+    }
+
+    #[test]
+    fn test_path_as_arg_parsing() {
+        let self_path = ".";
+        let args_path = Args {
+            crates: vec![self_path.to_owned()],
+            ..Args::default()
+        };
+        assert_eq!(
+            args_path.parse_dependencies().unwrap(),
+            vec![Dependency::new("cargo-edit").set_path(self_path)]
+        );
+    }
+}
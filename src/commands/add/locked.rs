@@ -0,0 +1,44 @@
+//! Reading the versions that `Cargo.lock` already holds, for adding crates offline or with
+//! `--lockfile-version`.
+
+use super::errors::*;
+use crate::{registry_url, workspace_root, LockedPackage, Lockfile};
+use semver::Version;
+use std::path::Path;
+
+/// The newest version of a crate from a registry (crates.io if `registry` is `None`) in the lock
+/// file of the workspace a manifest is in, e.g. as a dependency of another crate. Prereleases are
+/// left out unless `allow_prerelease` is set.
+pub fn locked_version(
+    manifest_path: &Path,
+    name: &str,
+    registry: Option<&str>,
+    allow_prerelease: bool,
+) -> Result<Option<Version>> {
+    let lockfile = Lockfile::read_or_default(&workspace_root(manifest_path)?.join("Cargo.lock"))?;
+
+    // An alternative registry is told apart by its index URL, as cargo records it.
+    let registry_url = match registry {
+        Some(registry) => match registry_url(manifest_path, Some(registry)) {
+            Ok(url) => Some(url.as_str().trim_end_matches('/').to_owned()),
+            Err(_) => return Ok(None),
+        },
+        None => None,
+    };
+    let from_registry = |package: &LockedPackage| match (&registry_url, &package.source) {
+        (Some(url), Some(source)) => {
+            let source = source.trim_end_matches('/');
+            source == url || source.strip_prefix("registry+") == Some(url)
+        }
+        (Some(_), None) => false,
+        (None, _) => package.is_from_crates_io(),
+    };
+    Ok(lockfile
+        .package
+        .into_iter()
+        .filter(|package| package.name == name)
+        .filter(|package| from_registry(package))
+        .map(|package| package.version)
+        .filter(|version| allow_prerelease || !version.is_prerelease())
+        .max())
+}
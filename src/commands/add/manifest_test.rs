@@ -1,5 +1,5 @@
 use args::Args;
-use cargo_edit::Manifest;
+use crate::Manifest;
 
 static DEFAULT_CARGO_TOML: &'static str = r#"[package]
 authors = ["Some Guy"]
@@ -0,0 +1,317 @@
+//! `cargo add`
+
+use self::args::{Args, Command};
+use crate::{
+    describe_dependencies, find, get_crate_stats, get_description, get_popular_crates,
+    manifest_from_pkgid, notify_of_newer_release, record_for_undo, registry_source,
+    requirement_floor, update_registry_index, Dependency, Edits, Manifest, NetConfig, Policy,
+    RegistrySource,
+};
+use std::borrow::Cow;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::Path;
+use std::time::SystemTime;
+use structopt::StructOpt;
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+use toml_edit::Item as TomlItem;
+
+mod args;
+mod locked;
+mod search;
+mod siblings;
+
+mod errors {
+    error_chain::error_chain! {
+        errors {
+            /// Specified a dependency with both a git URL and a version.
+            GitUrlWithVersion(git: String, version: String) {
+                description("Specified git URL with version")
+                display("Cannot specify a git URL (`{}`) with a version (`{}`).", git, version)
+            }
+            /// Specified multiple crates with path or git or vers
+            MultipleCratesWithGitOrPathOrVers {
+                description("Specified multiple crates with path or git or vers")
+                display("Cannot specify multiple crates with path or git or vers")
+            }
+            /// Specified multiple crates with renaming.
+            MultipleCratesWithRename {
+                description("Specified multiple crates with rename")
+                display("Cannot specify multiple crates with rename")
+            }
+            /// Specified multiple crates with features.
+            MultipleCratesWithFeatures {
+                description("Specified multiple crates with features")
+                display("Cannot specify multiple crates with features")
+            }
+            /// Specified multiple crates with a comment.
+            MultipleCratesWithComment {
+                description("Specified multiple crates with comment")
+                display("Cannot specify multiple crates with comment")
+            }
+            /// The git repository has no package by that name.
+            NoSuchGitPackage(package: String, repo: String, packages: String) {
+                description("The git repository has no package by that name")
+                display("The repository `{}` has no package `{}`. Its packages are: {}",
+                        repo, package, packages)
+            }
+            /// Refused to add a crate that looks suspicious.
+            SuspiciousCrate(name: String) {
+                description("Refused to add a suspicious crate")
+                display("Refusing to add `{}`, which looks suspicious. Add it with `--force` if it \
+                         can be trusted.", name)
+            }
+        }
+        links {
+            CargoEditLib(crate::Error, crate::ErrorKind);
+        }
+        foreign_links {
+            CargoMetadata(::failure::Compat<::cargo_metadata::Error>);
+            Io(::std::io::Error);
+        }
+    }
+}
+
+use self::errors::*;
+
+fn print_msg(dep: &Dependency, section: &[String], optional: bool) -> Result<()> {
+    let colorchoice = if atty::is(atty::Stream::Stdout) {
+        ColorChoice::Auto
+    } else {
+        ColorChoice::Never
+    };
+    let mut output = StandardStream::stdout(colorchoice);
+    output.set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))?;
+    write!(output, "{:>12}", "Adding")?;
+    output.reset()?;
+    write!(output, " {}", dep.name)?;
+    if let Some(version) = dep.version() {
+        write!(output, " v{}", version)?;
+    } else {
+        write!(output, " (unknown version)")?;
+    }
+    write!(output, " to")?;
+    if optional {
+        write!(output, " optional")?;
+    }
+    let section = if section.len() == 1 {
+        section[0].clone()
+    } else {
+        format!("{} for target `{}`", &section[2], &section[1])
+    };
+    write!(output, " {}", section)?;
+    if let Some(f) = &dep.features {
+        writeln!(output, " with features: {:?}", f)?
+    } else {
+        writeln!(output)?
+    }
+    Ok(())
+}
+
+// Based on Iterator::is_sorted from nightly std; remove in favor of that when stabilized.
+fn is_sorted(mut it: impl Iterator<Item = impl PartialOrd>) -> bool {
+    let mut last = match it.next() {
+        Some(e) => e,
+        None => return true,
+    };
+
+    for curr in it {
+        if curr < last {
+            return false;
+        }
+        last = curr;
+    }
+
+    true
+}
+
+/// How many of the most downloaded crates are checked for names that the added crates could be a
+/// typo of
+const POPULAR_CRATES: usize = 100;
+
+/// Warn about crates that look suspicious, or refuse to add them with `--deny-suspicious`.
+fn check_suspicious(
+    deps: &[Dependency],
+    args: &Args,
+    manifest_path: &Path,
+    net: &NetConfig,
+) -> Result<()> {
+    let registry = args.registry_source()?;
+    let mut popular = None;
+    for dep in deps.iter().filter(|dep| dep.version().is_some()) {
+        let stats = match get_crate_stats(&dep.name, manifest_path, &registry, net)? {
+            Some(stats) => stats,
+            None => continue,
+        };
+        if popular.is_none() {
+            popular = Some(get_popular_crates(POPULAR_CRATES, net)?);
+        }
+        let suspicions = stats.suspicions(
+            &dep.name,
+            popular.as_deref().unwrap_or_default(),
+            SystemTime::now(),
+        );
+        if suspicions.is_empty() {
+            continue;
+        }
+        eprintln!(
+            "WARN: `{}` looks suspicious: it {}",
+            dep.name,
+            suspicions.join(", and it ")
+        );
+        if args.deny_suspicious {
+            return Err(ErrorKind::SuspiciousCrate(dep.name.clone()).into());
+        }
+    }
+    Ok(())
+}
+
+fn handle_add(args: &Args) -> Result<()> {
+    let net = args.net_config()?;
+
+    let manifest_path = if let Some(ref pkgid) = args.pkgid {
+        let pkg = manifest_from_pkgid(pkgid)?;
+        Cow::Owned(Some(pkg.manifest_path))
+    } else {
+        Cow::Borrowed(&args.manifest_path)
+    };
+    let mut manifest = Manifest::open(&manifest_path)?;
+    let deps = &args.parse_dependencies()?;
+    let policy = Policy::from_path(args.policy.as_deref())?;
+    for dep in deps {
+        policy.check_crate(&dep.name)?;
+        if let Some(floor) = dep.version().and_then(requirement_floor) {
+            policy.check_version(&dep.name, &floor)?;
+        }
+    }
+    let implicit_features = manifest.implicit_features();
+
+    let offline = net.offline;
+    if !offline && !args.force {
+        if let Err(err) = check_suspicious(deps, args, &find(&manifest_path)?, &net) {
+            // Automation denying suspicious crates shouldn't let them through unchecked.
+            if args.deny_suspicious {
+                return Err(err);
+            }
+            eprintln!(
+                "WARN: Failed to check whether the crates look suspicious: {}",
+                err
+            );
+        }
+    }
+    if !offline && std::env::var("CARGO_IS_TEST").is_err() {
+        let source = match args.registry_source()? {
+            Some(source) => source,
+            None => registry_source(&find(&manifest_path)?, None)?,
+        };
+        // Local sources are always up to date.
+        if let RegistrySource::Remote(url) = source {
+            if let Err(err) = update_registry_index(&url, args.quiet, &net) {
+                eprintln!(
+                    "WARN: Failed to update the registry index ({}), using the local copy which \
+                     may be out of date",
+                    err
+                );
+            }
+        }
+    }
+
+    let was_sorted = manifest
+        .get_table(&args.get_section())
+        .map(TomlItem::as_table_mut)
+        .map_or(true, |table_option| {
+            table_option.is_none_or(|table| is_sorted(table.iter().map(|(name, _)| name)))
+        });
+    deps.iter()
+        .map(|dep| {
+            if !args.quiet {
+                print_msg(dep, &args.get_section(), args.optional)?;
+            }
+            manifest
+                .insert_into_table(&args.get_section(), dep)
+                .and_then(|_| match &args.feature_of {
+                    Some(feature) => manifest.enable_in_feature(feature, dep.name_in_manifest()),
+                    None => Ok(()),
+                })
+                .map(|_| {
+                    manifest
+                        .get_table(&args.get_section())
+                        .map(TomlItem::as_table_mut)
+                        .map(|table_option| {
+                            table_option.map(|table| {
+                                if was_sorted || args.sort {
+                                    table.sort_values();
+                                }
+                            })
+                        })
+                })
+                .map_err(Into::into)
+        })
+        .collect::<Result<Vec<_>>>()
+        .map_err(|err| {
+            eprintln!("Could not edit `Cargo.toml`.\n\nERROR: {}", err);
+            err
+        })?;
+
+    let describe = args.comment.is_none() && describe_dependencies(&find(&manifest_path)?)?;
+    for dep in deps {
+        let comment = if let Some(comment) = &args.comment {
+            Some(comment.clone())
+        } else if describe && !offline && dep.version().is_some() {
+            get_description(
+                &dep.name,
+                &find(&manifest_path)?,
+                &args.registry_source()?,
+                &net,
+            )
+            .unwrap_or_else(|err| {
+                eprintln!(
+                    "WARN: Failed to look up the description of `{}`: {}",
+                    dep.name, err
+                );
+                None
+            })
+        } else {
+            None
+        };
+        if let Some(comment) = comment {
+            manifest.set_comment(&args.get_section(), dep.name_in_manifest(), &comment)?;
+        }
+    }
+
+    manifest.warn_removed_features(&implicit_features);
+
+    record_for_undo(&Edits::write_one(&manifest, &find(&manifest_path)?)?)?;
+
+    if !args.quiet {
+        if let Ok(path) = find(&manifest_path) {
+            notify_of_newer_release(&path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Run `cargo add` with a command line as cargo passes it, e.g. from
+/// `std::env::args_os`.
+pub fn run(args: impl IntoIterator<Item = OsString>) {
+    let args: Command = Command::from_iter(args);
+    let Command::Add(mut args) = args;
+
+    let result = match &args.search {
+        Some(query) => args
+            .net_config()
+            .and_then(|net| search::pick_crate(query, &net))
+            .and_then(|picked| match picked {
+                Some(name) => {
+                    args.crates = vec![name];
+                    handle_add(&args)
+                }
+                None => Ok(()),
+            }),
+        None => handle_add(&args),
+    };
+    if let Err(err) = result {
+        super::exit_with_error(&err);
+    }
+}
@@ -0,0 +1,121 @@
+//! Finding a crate to add by searching crates.io.
+
+use super::errors::*;
+use crate::{search_crates, CrateSummary, NetConfig};
+use error_chain::bail;
+use std::io::{self, BufRead, Write};
+
+/// How many results to offer.
+const RESULTS: usize = 10;
+
+/// Group the digits of a number in thousands, e.g. `1,234,567`.
+fn thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+fn print_results(output: &mut impl Write, results: &[CrateSummary]) -> io::Result<()> {
+    let width = results.len().to_string().len();
+    for (i, krate) in results.iter().enumerate() {
+        write!(
+            output,
+            "{:>width$}. {} {}",
+            i + 1,
+            krate.name,
+            krate.version,
+            width = width
+        )?;
+        match &krate.description {
+            Some(description) => writeln!(output, " - {}", description.trim())?,
+            None => writeln!(output)?,
+        }
+        writeln!(
+            output,
+            "{:width$}  {} downloads, last release {}",
+            "",
+            thousands(krate.downloads),
+            krate.updated_at.get(..10).unwrap_or(&krate.updated_at),
+            width = width
+        )?;
+    }
+    Ok(())
+}
+
+/// Search crates.io, list the top results, and ask which one to add. `None` is returned if the
+/// choice is left empty.
+pub fn pick_crate(query: &str, net: &NetConfig) -> Result<Option<String>> {
+    let results = search_crates(query, RESULTS, net)?;
+    if results.is_empty() {
+        bail!("No crates match `{}`", query);
+    }
+
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+    print_results(&mut output, &results)?;
+    let stdin = io::stdin();
+    loop {
+        write!(
+            output,
+            "Crate to add (1-{}, or nothing to cancel): ",
+            results.len()
+        )?;
+        output.flush()?;
+        let mut answer = String::new();
+        if stdin.lock().read_line(&mut answer)? == 0 {
+            return Ok(None);
+        }
+        let answer = answer.trim();
+        if answer.is_empty() {
+            return Ok(None);
+        }
+        match answer.parse::<usize>() {
+            Ok(n) if (1..=results.len()).contains(&n) => {
+                return Ok(Some(results[n - 1].name.clone()))
+            }
+            _ => {
+                // A name from the list is fine too.
+                if let Some(krate) = results.iter().find(|krate| krate.name == answer) {
+                    return Ok(Some(krate.name.clone()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_digits() {
+        assert_eq!(thousands(0), "0");
+        assert_eq!(thousands(999), "999");
+        assert_eq!(thousands(1_000), "1,000");
+        assert_eq!(thousands(1_234_567), "1,234,567");
+    }
+
+    #[test]
+    fn lists_results() {
+        let results = vec![CrateSummary {
+            name: "reqwest".to_owned(),
+            version: "0.11.4".to_owned(),
+            description: Some("higher level HTTP client library\n".to_owned()),
+            downloads: 21_345_678,
+            updated_at: "2021-06-21T20:44:43.443470+00:00".to_owned(),
+        }];
+        let mut output = Vec::new();
+        print_results(&mut output, &results).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "1. reqwest 0.11.4 - higher level HTTP client library\n   \
+             21,345,678 downloads, last release 2021-06-21\n"
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Finding how other workspace members already depend on a crate, for `--match-workspace`.
+
+use super::errors::*;
+use crate::{requirement_floor, workspace_members, Dependency, Manifest};
+use failure::Fail;
+use std::path::Path;
+
+/// How a workspace member depends on a crate from a registry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SiblingRequirement {
+    /// The member depending on the crate.
+    pub package: String,
+    /// Its version requirement.
+    pub version: String,
+    /// The features it enables, if any.
+    pub features: Option<Vec<String>>,
+    /// Whether it turns the default features off.
+    pub no_default_features: bool,
+}
+
+impl SiblingRequirement {
+    /// The dependency on `name` this requirement describes.
+    pub fn to_dependency(&self, name: &str) -> Dependency {
+        Dependency::new(name)
+            .set_version(&self.version)
+            .set_features(self.features.clone())
+            .set_default_features(!self.no_default_features)
+    }
+}
+
+/// The requirements that members of the workspace of a manifest have on a crate from a registry
+/// (crates.io if `registry` is `None`), in every dependency table. Git, path and
+/// workspace-inherited dependencies are skipped.
+pub fn sibling_requirements(
+    manifest_path: &Path,
+    name: &str,
+    registry: Option<&str>,
+) -> Result<Vec<SiblingRequirement>> {
+    let metadata = cargo_metadata::MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .no_deps()
+        .exec()
+        .map_err(|e| Error::from(e.compat()).chain_err(|| "Failed to get workspace metadata"))?;
+
+    let mut requirements = Vec::new();
+    for package in workspace_members(&metadata, true)? {
+        let manifest = Manifest::open(&Some(package.manifest_path.clone()))?;
+        for (_, table) in manifest.get_sections() {
+            let table = table.as_table_like().expect("Unexpected non-table");
+            for (key, item) in table.iter() {
+                let requirement = match item.as_table_like() {
+                    Some(t) => {
+                        let local = ["git", "path", "workspace"]
+                            .iter()
+                            .any(|key| t.get(key).is_some());
+                        let package_name = t.get("package").and_then(|p| p.as_str());
+                        let same_registry = t.get("registry").and_then(|r| r.as_str()) == registry;
+                        if local || package_name.unwrap_or(key) != name || !same_registry {
+                            continue;
+                        }
+                        let features = t.get("features").and_then(|f| f.as_array()).map(|f| {
+                            f.iter()
+                                .filter_map(|f| f.as_str().map(ToOwned::to_owned))
+                                .collect::<Vec<_>>()
+                        });
+                        t.get("version").and_then(|v| v.as_str()).map(|version| {
+                            SiblingRequirement {
+                                package: package.name.clone(),
+                                version: version.to_owned(),
+                                features: features.filter(|f| !f.is_empty()),
+                                no_default_features: t
+                                    .get("default-features")
+                                    .and_then(|d| d.as_bool())
+                                    == Some(false),
+                            }
+                        })
+                    }
+                    None if key == name && registry.is_none() => {
+                        item.as_str().map(|version| SiblingRequirement {
+                            package: package.name.clone(),
+                            version: version.to_owned(),
+                            features: None,
+                            no_default_features: false,
+                        })
+                    }
+                    None => None,
+                };
+                requirements.extend(requirement);
+            }
+        }
+    }
+    Ok(requirements)
+}
+
+/// The requirement to match out of those of the workspace members: the one most of them have,
+/// or the one allowing the newest versions if there is a tie. A warning is printed if they differ.
+pub fn pick_requirement(
+    name: &str,
+    requirements: &[SiblingRequirement],
+) -> Option<SiblingRequirement> {
+    let same = |a: &SiblingRequirement, b: &SiblingRequirement| {
+        a.version == b.version
+            && a.features == b.features
+            && a.no_default_features == b.no_default_features
+    };
+    let mut kinds: Vec<(&SiblingRequirement, Vec<&str>)> = Vec::new();
+    for requirement in requirements {
+        match kinds.iter_mut().find(|(kind, _)| same(kind, requirement)) {
+            Some((_, packages)) if packages.contains(&requirement.package.as_str()) => {}
+            Some((_, packages)) => packages.push(&requirement.package),
+            None => kinds.push((requirement, vec![&requirement.package])),
+        }
+    }
+    let (picked, _) = kinds.iter().max_by(|(a, a_packages), (b, b_packages)| {
+        let a_floor = requirement_floor(&a.version);
+        let b_floor = requirement_floor(&b.version);
+        (a_packages.len(), a_floor).cmp(&(b_packages.len(), b_floor))
+    })?;
+
+    if kinds.len() > 1 {
+        let kinds: Vec<_> = kinds
+            .iter()
+            .map(|(kind, packages)| {
+                let features = kind
+                    .features
+                    .as_ref()
+                    .map(|f| format!(" with features {:?}", f))
+                    .unwrap_or_default();
+                format!("{}{} in {}", kind.version, features, packages.join(", "))
+            })
+            .collect();
+        eprintln!(
+            "WARN: Workspace members depend on `{}` differently ({}), matching {}",
+            name,
+            kinds.join("; "),
+            picked.version
+        );
+    }
+    Some((*picked).clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requirement(package: &str, version: &str) -> SiblingRequirement {
+        SiblingRequirement {
+            package: package.to_owned(),
+            version: version.to_owned(),
+            features: None,
+            no_default_features: false,
+        }
+    }
+
+    #[test]
+    fn picks_the_most_common_requirement() {
+        let requirements = [
+            requirement("a", "0.3"),
+            requirement("b", "0.2"),
+            requirement("c", "0.2"),
+        ];
+        assert_eq!(
+            pick_requirement("rand", &requirements).unwrap().version,
+            "0.2"
+        );
+
+        // A tie goes to the requirement allowing the newest versions.
+        assert_eq!(
+            pick_requirement("rand", &requirements[..2])
+                .unwrap()
+                .version,
+            "0.3"
+        );
+
+        assert_eq!(pick_requirement("rand", &[]), None);
+    }
+}
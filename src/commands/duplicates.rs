@@ -0,0 +1,320 @@
+//! `cargo duplicates`
+
+use self::errors::*;
+use super::NetArgs;
+use crate::{Lockfile, Manifest};
+use error_chain::bail;
+use failure::Fail;
+use semver::Version;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use structopt::{clap::AppSettings, StructOpt};
+
+mod errors {
+    error_chain::error_chain! {
+        links {
+            CargoEditLib(crate::Error, crate::ErrorKind);
+        }
+        foreign_links {
+            CargoMetadata(::failure::Compat<::cargo_metadata::Error>);
+            Io(::std::io::Error);
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(bin_name = "cargo")]
+enum Command {
+    /// Find crates locked at more than one version, and how to build just one of them.
+    #[structopt(name = "duplicates")]
+    #[structopt(after_help = "\
+Every crate that `Cargo.lock` holds at more than one version is listed, along with the dependency \
+chains from workspace members that pull in each of its older versions.
+
+For each chain, a change to a member's manifest is suggested that would leave only the newest \
+version: raising the requirement on the crate itself if the member depends on it directly, or \
+otherwise upgrading the direct dependency that pulls it in, to a version in the lock file that \
+depends on the newest one if there is any.
+
+Nothing is written, and the network isn't accessed.")]
+    Duplicates(Args),
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(setting = AppSettings::ColoredHelp)]
+struct Args {
+    /// Crates to report on (all duplicated crates by default).
+    #[structopt(name = "crate")]
+    crates: Vec<String>,
+
+    /// Path to the manifest of the workspace to report on.
+    #[structopt(long = "manifest-path", value_name = "path")]
+    manifest_path: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    net: NetArgs,
+}
+
+/// A package in the lock file, by name and version.
+type Node = (String, Version);
+
+/// The packages in a lock file, and their dependencies.
+#[derive(Debug, Default)]
+struct Graph {
+    dependencies: HashMap<Node, Vec<Node>>,
+}
+
+impl Graph {
+    fn new(lockfile: &Lockfile) -> Self {
+        let mut versions: HashMap<&str, Vec<&Version>> = HashMap::new();
+        for package in &lockfile.package {
+            versions
+                .entry(&package.name)
+                .or_default()
+                .push(&package.version);
+        }
+
+        let mut graph = Graph::default();
+        for package in &lockfile.package {
+            // Entries are `name`, or `name version` (followed by the source) if the name is ambiguous.
+            let dependencies = package
+                .dependencies
+                .iter()
+                .filter_map(|entry| {
+                    let mut parts = entry.split_whitespace();
+                    let name = parts.next()?;
+                    let version = match parts.next() {
+                        Some(version) => Version::parse(version).ok()?,
+                        None => (*versions.get(name)?.first()?).clone(),
+                    };
+                    Some((name.to_owned(), version))
+                })
+                .collect();
+            graph.dependencies.insert(
+                (package.name.clone(), package.version.clone()),
+                dependencies,
+            );
+        }
+        graph
+    }
+
+    /// The versions of each crate, oldest first.
+    fn versions(&self) -> BTreeMap<&str, Vec<&Version>> {
+        let mut versions: BTreeMap<&str, Vec<&Version>> = BTreeMap::new();
+        for (name, version) in self.dependencies.keys() {
+            versions.entry(name).or_default().push(version);
+        }
+        for list in versions.values_mut() {
+            list.sort();
+        }
+        versions
+    }
+
+    /// The shortest chain of dependencies from one package to another, including both.
+    fn path(&self, from: &Node, to: &Node) -> Option<Vec<Node>> {
+        let mut previous: HashMap<&Node, &Node> = HashMap::new();
+        let mut seen: HashSet<&Node> = HashSet::new();
+        let mut queue = VecDeque::new();
+        seen.insert(from);
+        queue.push_back(from);
+        while let Some(node) = queue.pop_front() {
+            if node == to {
+                let mut path = vec![node.clone()];
+                let mut node = node;
+                while let Some(prev) = previous.get(node) {
+                    path.push((*prev).clone());
+                    node = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for next in self.dependencies.get(node).into_iter().flatten() {
+                if seen.insert(next) {
+                    previous.insert(next, node);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// A workspace member.
+struct Member {
+    node: Node,
+    manifest_path: PathBuf,
+}
+
+impl Member {
+    /// The requirement on a dependency in the member's manifest, by package name.
+    fn requirement(&self, name: &str) -> Result<Option<String>> {
+        let manifest = Manifest::open(&Some(self.manifest_path.clone()))?;
+        Ok(manifest
+            .get_sections()
+            .iter()
+            .flat_map(|(_, table)| {
+                table
+                    .as_table_like()
+                    .unwrap()
+                    .iter()
+                    .filter(|(key, dep)| dep["package"].as_str().unwrap_or(key) == name)
+                    .filter_map(|(_, dep)| {
+                        dep.as_str()
+                            .or_else(|| dep["version"].as_str())
+                            .map(ToOwned::to_owned)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .next())
+    }
+}
+
+fn format_path(path: &[Node]) -> String {
+    path.iter()
+        .map(|(name, version)| format!("{} {}", name, version))
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+/// Suggest a change to `member`'s manifest that stops `path` pulling in an old version of a crate.
+fn suggestion(
+    graph: &Graph,
+    member: &Member,
+    manifest: &str,
+    path: &[Node],
+    newest: &Node,
+) -> Result<String> {
+    let (direct, direct_version) = &path[1];
+    let requirement = member
+        .requirement(direct)?
+        .map(|req| format!("from \"{}\" ", req))
+        .unwrap_or_default();
+
+    if path.len() == 2 {
+        return Ok(format!(
+            "{}: change the `{}` requirement {}to \"{}\"",
+            manifest, direct, requirement, newest.1
+        ));
+    }
+
+    // A newer version of the direct dependency in the lock file may already use the newest version.
+    let fixed = graph
+        .dependencies
+        .keys()
+        .filter(|(name, version)| name == direct && version > direct_version)
+        .filter(|node| graph.path(node, newest).is_some())
+        .map(|(_, version)| version)
+        .min();
+    Ok(match fixed {
+        Some(version) => format!(
+            "{}: change the `{}` requirement {}to \"{}\", which depends on {} {}",
+            manifest, direct, requirement, version, newest.0, newest.1
+        ),
+        None => format!(
+            "{}: upgrade `{}` to a release that depends on {} {} (e.g. with `cargo upgrade {}`)",
+            manifest, direct, newest.0, newest.1, direct
+        ),
+    })
+}
+
+fn report(graph: &Graph, members: &[Member], root: &Path, crates: &[String]) -> Result<()> {
+    let versions = graph.versions();
+    for name in crates {
+        if !versions.contains_key(name.as_str()) {
+            bail!("The crate `{}` is not in the lock file", name);
+        }
+    }
+
+    let mut found = false;
+    for (name, versions) in versions {
+        if versions.len() < 2 || !(crates.is_empty() || crates.iter().any(|c| c == name)) {
+            continue;
+        }
+        found = true;
+
+        let listed: Vec<_> = versions.iter().map(ToString::to_string).collect();
+        println!("{} {}", name, listed.join(", "));
+        let newest = (name.to_owned(), (*versions.last().unwrap()).clone());
+        for old in &versions[..versions.len() - 1] {
+            let old = (name.to_owned(), (*old).clone());
+            println!("    {} is pulled in by:", old.1);
+            let mut suggestions = Vec::new();
+            for member in members {
+                let manifest = member
+                    .manifest_path
+                    .strip_prefix(root)
+                    .unwrap_or(&member.manifest_path)
+                    .display()
+                    .to_string();
+                let direct_deps = graph.dependencies.get(&member.node).into_iter().flatten();
+                for direct in direct_deps {
+                    if let Some(mut path) = graph.path(direct, &old) {
+                        path.insert(0, member.node.clone());
+                        println!("        {}", format_path(&path));
+                        let suggestion = suggestion(graph, member, &manifest, &path, &newest)?;
+                        if !suggestions.contains(&suggestion) {
+                            suggestions.push(suggestion);
+                        }
+                    }
+                }
+            }
+            for suggestion in suggestions {
+                println!("      - {}", suggestion);
+            }
+        }
+    }
+
+    if !found {
+        println!("No crates are locked at more than one version");
+    }
+    Ok(())
+}
+
+fn handle_duplicates(args: &Args) -> Result<()> {
+    let net = args.net.net_config()?;
+    let mut cmd = cargo_metadata::MetadataCommand::new();
+    cmd.no_deps();
+    cmd.other_options(net.cargo_flags());
+    if let Some(path) = &args.manifest_path {
+        cmd.manifest_path(path);
+    }
+    let metadata = cmd
+        .exec()
+        .map_err(|e| Error::from(e.compat()).chain_err(|| "Failed to get workspace metadata"))?;
+
+    let lock_path = metadata.workspace_root.join("Cargo.lock");
+    if !lock_path.exists() {
+        return Err(format!(
+            "Failed to read `{}`, try running `cargo generate-lockfile` first",
+            lock_path.display()
+        )
+        .into());
+    }
+    let lockfile = Lockfile::read(&lock_path)?;
+    let graph = Graph::new(&lockfile);
+
+    let members: HashSet<_> = metadata.workspace_members.iter().collect();
+    let members: Vec<Member> = metadata
+        .packages
+        .iter()
+        .filter(|package| members.contains(&package.id))
+        .map(|package| Member {
+            node: (package.name.clone(), package.version.clone()),
+            manifest_path: package.manifest_path.clone(),
+        })
+        .collect();
+
+    report(&graph, &members, &metadata.workspace_root, &args.crates)
+}
+
+/// Run `cargo duplicates` with a command line as cargo passes it, e.g. from
+/// `std::env::args_os`.
+pub fn run(args: impl IntoIterator<Item = OsString>) {
+    let args: Command = Command::from_iter(args);
+    let Command::Duplicates(args) = args;
+
+    if let Err(err) = handle_duplicates(&args) {
+        super::exit_with_error(&err);
+    }
+}
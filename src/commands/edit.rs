@@ -0,0 +1,236 @@
+//! `cargo edit`, which also runs the other commands, e.g. `cargo edit add` or through a link
+//! named `cargo-add`
+
+use self::errors::*;
+use crate::{find, last_operation, undo};
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use structopt::{clap::AppSettings, StructOpt};
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+
+mod errors {
+    error_chain::error_chain! {
+        links {
+            CargoEditLib(crate::Error, crate::ErrorKind);
+        }
+        foreign_links {
+            Io(::std::io::Error);
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(bin_name = "cargo")]
+enum Command {
+    /// Manage what the cargo-edit commands have done, or run them.
+    #[structopt(name = "edit")]
+    #[structopt(after_help = "\
+This executable runs the other cargo-edit commands too: `cargo edit add serde` runs \
+`cargo add serde`, and so does running it under the name `cargo-add`. It is the only executable \
+that `cargo install cargo-edit` installs, and `cargo edit link` links those names to it, so that \
+cargo finds the commands. The 'separate-bins' feature installs an executable per command \
+instead.")]
+    Edit(Action),
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(setting = AppSettings::ColoredHelp)]
+enum Action {
+    /// Undo the last command that changed manifests, e.g. `cargo upgrade`.
+    #[structopt(name = "undo")]
+    #[structopt(after_help = "\
+The cargo-edit commands record what each manifest looked like before and after they changed it, \
+in a journal under `cargo-edit/` in the workspace's target directory, e.g. `target/cargo-edit/` \
+unless `CARGO_TARGET_DIR` or `build.target-dir` say otherwise. `cargo edit undo` puts back the \
+manifests that the last of those commands changed, however many there were, and drops it from the \
+journal, so that running it again undoes the command before. The journal keeps the last 10 \
+commands.
+
+If a manifest has been changed since, nothing is restored, as those changes would be lost, unless \
+'--force' is supplied. Use '--dry-run' to see which command would be undone and which manifests \
+restored. `Cargo.lock` isn't restored, but cargo brings it back in line \
+with the manifests on the next build.")]
+    Undo(UndoArgs),
+    /// Link the names of the other commands, e.g. `cargo-add`, to this executable.
+    #[structopt(name = "link")]
+    #[structopt(after_help = "\
+Cargo runs `cargo add` as the executable `cargo-add` on the `PATH`, so the links are put next to \
+this executable unless '--dir' says otherwise. They are symbolic links, so that they keep \
+running this executable when it is updated, except on Windows, where they are hard links (or \
+copies, if the file system has no hard links) that `cargo edit link --force` updates. Executables \
+that are there already, e.g. installed separately, are left alone unless '--force' is supplied.")]
+    Link(LinkArgs),
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(setting = AppSettings::ColoredHelp)]
+struct UndoArgs {
+    /// Path to a manifest in the workspace to undo the last command in.
+    #[structopt(long = "manifest-path", value_name = "path")]
+    manifest_path: Option<PathBuf>,
+
+    /// Print what would be undone without restoring anything.
+    #[structopt(long = "dry-run")]
+    dry_run: bool,
+
+    /// Undo the command even if manifests have been changed since, losing those changes.
+    #[structopt(long = "force")]
+    force: bool,
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(setting = AppSettings::ColoredHelp)]
+struct LinkArgs {
+    /// Directory to put the links in, instead of that of this executable.
+    #[structopt(long = "dir", value_name = "path")]
+    dir: Option<PathBuf>,
+
+    /// Replace executables of those names that are there already.
+    #[structopt(long = "force")]
+    force: bool,
+}
+
+/// Run the other command a command line is for, if it is one: run as `cargo-add` (through a link),
+/// `cargo-edit edit add` (for `cargo edit add`) or `cargo-edit add`, it runs `cargo add`. Returns
+/// whether it was.
+fn dispatch(args: &[OsString]) -> bool {
+    let program = args
+        .first()
+        .map(Path::new)
+        .and_then(Path::file_stem)
+        .and_then(OsStr::to_str)
+        .unwrap_or_default();
+    match program.strip_prefix("cargo-") {
+        Some("edit") | None => {}
+        Some(name) => return super::run(name, args.to_vec()),
+    }
+
+    let rest = match args.get(1).and_then(|arg| arg.to_str()) {
+        Some("edit") => &args[2..],
+        _ => args.get(1..).unwrap_or_default(),
+    };
+    match rest.first().and_then(|arg| arg.to_str()) {
+        Some(name) => {
+            let program = OsString::from(format!("cargo-{}", name));
+            let args = std::iter::once(program).chain(rest.iter().cloned());
+            super::run(name, args.collect())
+        }
+        None => false,
+    }
+}
+
+fn print_msg(status: &str, message: &str) -> Result<()> {
+    let colorchoice = if atty::is(atty::Stream::Stdout) {
+        ColorChoice::Auto
+    } else {
+        ColorChoice::Never
+    };
+    let mut output = StandardStream::stdout(colorchoice);
+    output.set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))?;
+    write!(output, "{:>12}", status)?;
+    output.reset()?;
+    writeln!(output, " {}", message)?;
+    Ok(())
+}
+
+fn handle_undo(args: &UndoArgs) -> Result<()> {
+    let manifest_path = find(&args.manifest_path)?;
+    let operation = if args.dry_run {
+        println!("Starting dry run. Nothing will be restored.");
+        last_operation(&manifest_path)?
+    } else {
+        undo(&manifest_path, args.force)?
+    };
+    let operation = match operation {
+        Some(operation) => operation,
+        None => {
+            println!("Nothing to undo");
+            return Ok(());
+        }
+    };
+
+    print_msg("Undoing", &format!("`{}`", operation.command))?;
+    let changed = if args.dry_run {
+        operation.changed_since()
+    } else {
+        Vec::new()
+    };
+    for file in &operation.files {
+        if changed.contains(&file.path.as_path()) {
+            print_msg(
+                "Restoring",
+                &format!("{} (changed since)", file.path.display()),
+            )?;
+        } else {
+            print_msg("Restoring", &file.path.display().to_string())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn link_executable(exe: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(exe, link)
+}
+
+#[cfg(not(unix))]
+fn link_executable(exe: &Path, link: &Path) -> std::io::Result<()> {
+    fs::hard_link(exe, link).or_else(|_| fs::copy(exe, link).map(|_| ()))
+}
+
+fn handle_link(args: &LinkArgs) -> Result<()> {
+    let exe = env::current_exe().chain_err(|| "Failed to find this executable")?;
+    let dir = match &args.dir {
+        Some(dir) => dir.clone(),
+        None => exe
+            .parent()
+            .chain_err(|| "Failed to find the directory of this executable")?
+            .to_owned(),
+    };
+    for name in super::names() {
+        let link = dir.join(format!("cargo-{}{}", name, env::consts::EXE_SUFFIX));
+        if fs::read_link(&link).is_ok_and(|target| target == exe) {
+            continue;
+        }
+        if link.exists() {
+            if !args.force {
+                print_msg(
+                    "Skipping",
+                    &format!(
+                        "{}, which exists (use '--force' to replace it)",
+                        link.display()
+                    ),
+                )?;
+                continue;
+            }
+            fs::remove_file(&link)
+                .chain_err(|| format!("Failed to remove `{}`", link.display()))?;
+        }
+        link_executable(&exe, &link)
+            .chain_err(|| format!("Failed to link `{}`", link.display()))?;
+        print_msg("Linking", &link.display().to_string())?;
+    }
+    Ok(())
+}
+
+/// Run `cargo edit` with a command line as cargo passes it, e.g. from `std::env::args_os`, or the
+/// other command it is for, see `dispatch`.
+pub fn run(args: impl IntoIterator<Item = OsString>) {
+    let args: Vec<OsString> = args.into_iter().collect();
+    if dispatch(&args) {
+        return;
+    }
+    let args: Command = Command::from_iter(args);
+    let Command::Edit(action) = args;
+
+    let result = match &action {
+        Action::Undo(args) => handle_undo(args),
+        Action::Link(args) => handle_link(args),
+    };
+    if let Err(err) = result {
+        super::exit_with_error(&err);
+    }
+}
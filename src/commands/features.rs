@@ -0,0 +1,519 @@
+//! `cargo features`
+
+use self::errors::*;
+use super::NetArgs;
+use crate::{find, record_for_undo, workspace_members, Edits, Manifest, NetConfig};
+use cargo_metadata::Package;
+use error_chain::bail;
+use failure::Fail;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use structopt::{clap::AppSettings, StructOpt};
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+
+mod errors {
+    error_chain::error_chain! {
+        links {
+            CargoEditLib(crate::Error, crate::ErrorKind);
+        }
+        foreign_links {
+            CargoMetadata(::failure::Compat<::cargo_metadata::Error>);
+            Io(::std::io::Error);
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(bin_name = "cargo")]
+enum Command {
+    /// Report which workspace members enable which features of each dependency.
+    #[structopt(name = "features")]
+    #[structopt(after_help = "\
+For each dependency of the workspace, every feature that is enabled is listed along with the \
+members that enable it. Features enabled through the member's own `[features]` are shown with the \
+chain of features that leads to them, e.g. `one (default -> full)`.
+
+Cargo builds a dependency once, with the features of every member that uses it combined. Features \
+that only some of the members using a dependency ask for are marked with a `*`, as the others get \
+them anyway (e.g. one member dragging `tokio/full` into everyone's build).
+
+The report writes nothing. Only the workspace's `default-members` are included if it lists them, unless \
+the `--all-members` flag is supplied.
+
+Use `cargo features rename <old> <new>` to rename a feature of a workspace member, and \
+`cargo features add <feature> <entry>...` to add entries to one.")]
+    Features(Args),
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(setting = AppSettings::ColoredHelp)]
+struct Args {
+    /// Dependencies to report on (all of them by default).
+    dependency: Vec<String>,
+
+    /// Path to the manifest of the workspace to report on.
+    #[structopt(long = "manifest-path", value_name = "path")]
+    manifest_path: Option<PathBuf>,
+
+    /// Include workspace members left out of the workspace's `default-members`.
+    #[structopt(long = "all-members")]
+    all_members: bool,
+
+    #[structopt(flatten)]
+    net: NetArgs,
+
+    #[structopt(subcommand)]
+    action: Option<Action>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Action {
+    /// Rename a feature, along with every reference to it in the workspace.
+    #[structopt(name = "rename")]
+    #[structopt(after_help = "\
+The feature is renamed in `[features]`, and references to it are updated in the package's other \
+features, in the `required-features` of its targets and in the features docs.rs builds it with. \
+Workspace members that depend on the package have the feature renamed in their dependency \
+entries and in `[features]` entries such as `package/old`.
+
+Packages outside the workspace that enable the feature aren't updated, and will fail to build \
+against a release with the new name until they change to it.")]
+    Rename(RenameArgs),
+
+    /// Add entries to a feature, such as `dep:name` or `name?/feature`.
+    #[structopt(name = "add")]
+    #[structopt(after_help = "\
+Each entry is one cargo accepts in `[features]`: the name of another feature, `dep:name` to \
+enable the optional dependency `name` without a feature of its own, `name/feature` to enable a \
+feature of a dependency, or `name?/feature` to enable a feature of an optional dependency only if \
+something else enables the dependency. Dependencies are referred to by their name in the manifest, \
+which differs from the package name if they are renamed. A package depended on at more than one \
+version can be given with the version its requirement starts at instead, e.g. `rand@0.7/std`.
+
+The feature is added to `[features]` if it isn't there yet. Entries are checked against the \
+manifest before anything is written, and entries the feature already has are left alone. Once a \
+`dep:` entry refers to an optional dependency, it no longer has a feature of its own, so other \
+features enabling it by name are changed to `dep:` entries too.
+
+If the package's `rust-version` is older than 1.60, whose cargo can't parse `dep:` and `?/` \
+entries, `dep:name` is written as a plain `name` entry, for the feature cargo creates for the \
+dependency, and `name?/feature` is refused.")]
+    Add(AddArgs),
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(setting = AppSettings::ColoredHelp)]
+struct RenameArgs {
+    /// The feature to rename.
+    old: String,
+
+    /// Its new name.
+    new: String,
+
+    /// Path to the manifest of the package to rename the feature of.
+    #[structopt(long = "manifest-path", value_name = "path", conflicts_with = "pkgid")]
+    manifest_path: Option<PathBuf>,
+
+    /// Package id of the crate to rename the feature of.
+    #[structopt(long = "package", short = "p", value_name = "pkgid")]
+    pkgid: Option<String>,
+
+    /// Print changes to be made without making them.
+    #[structopt(long = "dry-run")]
+    dry_run: bool,
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(setting = AppSettings::ColoredHelp)]
+struct AddArgs {
+    /// The feature to add entries to.
+    feature: String,
+
+    /// The entries, e.g. `dep:serde` or `serde?/std`.
+    #[structopt(required = true)]
+    entries: Vec<String>,
+
+    /// Path to the manifest of the package to add to.
+    #[structopt(long = "manifest-path", value_name = "path", conflicts_with = "pkgid")]
+    manifest_path: Option<PathBuf>,
+
+    /// Package id of the crate to add to.
+    #[structopt(long = "package", short = "p", value_name = "pkgid")]
+    pkgid: Option<String>,
+
+    /// Print changes to be made without making them.
+    #[structopt(long = "dry-run")]
+    dry_run: bool,
+}
+
+/// A workspace member enabling a feature, through a chain of its own features (empty if the
+/// dependency declaration enables it directly).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Enabler {
+    member: String,
+    chain: Vec<String>,
+}
+
+/// How a dependency is used across the workspace.
+#[derive(Debug, Default)]
+struct Usage {
+    /// The members that depend on it.
+    members: BTreeSet<String>,
+    /// The members enabling each of its features.
+    features: BTreeMap<String, BTreeSet<Enabler>>,
+}
+
+/// The dependencies of a workspace, by package name.
+type Report = BTreeMap<String, Usage>;
+
+/// Record the dependencies of a member, and the features their declarations enable.
+fn add_dependencies(report: &mut Report, member: &str, manifest: &Manifest) {
+    let direct = Enabler {
+        member: member.to_owned(),
+        chain: Vec::new(),
+    };
+    for (_, table) in manifest.get_sections() {
+        for (name, dep) in table.as_table_like().unwrap().iter() {
+            let package = dep["package"].as_str().unwrap_or(name);
+            let usage = report.entry(package.to_owned()).or_default();
+            usage.members.insert(member.to_owned());
+
+            let default_features = dep["default-features"]
+                .as_bool()
+                .or_else(|| dep["default_features"].as_bool());
+            let features = dep["features"]
+                .as_array()
+                .into_iter()
+                .flat_map(toml_edit::Array::iter)
+                .filter_map(toml_edit::Value::as_str);
+            let defaults = Some("default").filter(|_| default_features != Some(false));
+            for feature in features.chain(defaults) {
+                usage
+                    .features
+                    .entry(feature.to_owned())
+                    .or_default()
+                    .insert(direct.clone());
+            }
+        }
+    }
+}
+
+/// Record the features of dependencies that a member enables through its own `[features]`,
+/// following each chain from a feature that no other feature enables.
+fn add_feature_chains(report: &mut Report, member: &str, manifest: &Manifest) {
+    // Features refer to dependencies by their name in the manifest, which may be a rename.
+    let packages: HashMap<String, String> = manifest
+        .get_sections()
+        .iter()
+        .flat_map(|(_, table)| {
+            table
+                .as_table_like()
+                .unwrap()
+                .iter()
+                .map(|(name, dep)| {
+                    let package = dep["package"].as_str().unwrap_or(name);
+                    (name.to_owned(), package.to_owned())
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let features: BTreeMap<&str, Vec<&str>> = match manifest.data["features"].as_table_like() {
+        Some(features) => features
+            .iter()
+            .map(|(name, entries)| {
+                let entries = entries
+                    .as_array()
+                    .into_iter()
+                    .flat_map(toml_edit::Array::iter)
+                    .filter_map(toml_edit::Value::as_str)
+                    .collect();
+                (name, entries)
+            })
+            .collect(),
+        None => return,
+    };
+    let enabled: HashSet<&str> = features.values().flatten().copied().collect();
+
+    fn walk(
+        report: &mut Report,
+        member: &str,
+        packages: &HashMap<String, String>,
+        features: &BTreeMap<&str, Vec<&str>>,
+        chain: &mut Vec<String>,
+    ) {
+        let feature = chain.last().unwrap().clone();
+        for entry in &features[feature.as_str()] {
+            if let Some((dep, dep_feature)) = entry.split_once('/') {
+                let dep = dep.trim_end_matches('?');
+                if let Some(package) = packages.get(dep) {
+                    report
+                        .entry(package.clone())
+                        .or_default()
+                        .features
+                        .entry(dep_feature.to_owned())
+                        .or_default()
+                        .insert(Enabler {
+                            member: member.to_owned(),
+                            chain: chain.clone(),
+                        });
+                }
+            } else if features.contains_key(entry) && !chain.iter().any(|f| f == entry) {
+                chain.push((*entry).to_owned());
+                walk(report, member, packages, features, chain);
+                chain.pop();
+            }
+        }
+    }
+
+    for root in features.keys().filter(|name| !enabled.contains(*name)) {
+        let mut chain = vec![(*root).to_owned()];
+        walk(report, member, &packages, &features, &mut chain);
+    }
+}
+
+fn print_report(report: &Report) -> Result<()> {
+    let colorchoice = if atty::is(atty::Stream::Stdout) {
+        ColorChoice::Auto
+    } else {
+        ColorChoice::Never
+    };
+    let mut output = StandardStream::stdout(colorchoice);
+
+    let mut unified = false;
+    for (dependency, usage) in report {
+        output.set_color(ColorSpec::new().set_bold(true))?;
+        write!(output, "{}", dependency)?;
+        output.reset()?;
+        writeln!(
+            output,
+            " (used by {})",
+            usage.members.iter().cloned().collect::<Vec<_>>().join(", ")
+        )?;
+
+        let width = usage.features.keys().map(String::len).max().unwrap_or(0);
+        for (feature, enablers) in &usage.features {
+            let askers: BTreeSet<_> = enablers.iter().map(|e| &e.member).collect();
+            if askers.len() < usage.members.len() {
+                unified = true;
+                output.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)).set_bold(true))?;
+                write!(output, "  * ")?;
+                output.reset()?;
+            } else {
+                write!(output, "    ")?;
+            }
+            let enablers: Vec<_> = enablers
+                .iter()
+                .map(|enabler| {
+                    if enabler.chain.is_empty() {
+                        enabler.member.clone()
+                    } else {
+                        format!("{} ({})", enabler.member, enabler.chain.join(" -> "))
+                    }
+                })
+                .collect();
+            writeln!(
+                output,
+                "{:width$}  {}",
+                feature,
+                enablers.join(", "),
+                width = width
+            )?;
+        }
+    }
+
+    if unified {
+        writeln!(
+            output,
+            "\n* Enabled for every member that uses the dependency, though only some of them ask for it."
+        )?;
+    }
+    Ok(())
+}
+
+fn handle_features(args: &Args) -> Result<()> {
+    let net = args.net.net_config()?;
+    match &args.action {
+        Some(Action::Rename(args)) => return handle_rename(args, &net),
+        Some(Action::Add(args)) => return handle_add(args, &net),
+        None => {}
+    }
+
+    let mut cmd = cargo_metadata::MetadataCommand::new();
+    cmd.no_deps();
+    cmd.other_options(net.cargo_flags());
+    if let Some(path) = &args.manifest_path {
+        cmd.manifest_path(path);
+    }
+    let metadata = cmd
+        .exec()
+        .map_err(|e| Error::from(e.compat()).chain_err(|| "Failed to get workspace metadata"))?;
+
+    let mut report = Report::new();
+    for package in workspace_members(&metadata, args.all_members)? {
+        let manifest = Manifest::open(&Some(package.manifest_path.clone()))?;
+        add_dependencies(&mut report, &package.name, &manifest);
+        add_feature_chains(&mut report, &package.name, &manifest);
+    }
+
+    if !args.dependency.is_empty() {
+        for dependency in &args.dependency {
+            if !report.contains_key(dependency) {
+                bail!("The workspace doesn't depend on `{}`", dependency);
+            }
+        }
+        report.retain(|name, _| args.dependency.contains(name));
+    }
+
+    print_report(&report)
+}
+
+fn dry_run_message() -> Result<()> {
+    let colorchoice = if atty::is(atty::Stream::Stdout) {
+        ColorChoice::Auto
+    } else {
+        ColorChoice::Never
+    };
+    let mut output = StandardStream::stdout(colorchoice);
+    output.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bold(true))?;
+    write!(output, "Starting dry run. ")?;
+    output.reset()?;
+    writeln!(output, "Changes will not be saved.")?;
+    Ok(())
+}
+
+fn print_msg(status: &str, message: &str) -> Result<()> {
+    let colorchoice = if atty::is(atty::Stream::Stdout) {
+        ColorChoice::Auto
+    } else {
+        ColorChoice::Never
+    };
+    let mut output = StandardStream::stdout(colorchoice);
+    output.set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))?;
+    write!(output, "{:>12}", status)?;
+    output.reset()?;
+    writeln!(output, " {}", message)?;
+    Ok(())
+}
+
+/// The workspace members, and the one selected by `--package` or `--manifest-path`.
+fn select_package(
+    manifest_path: &Option<PathBuf>,
+    pkgid: &Option<String>,
+    net: &NetConfig,
+) -> Result<(Vec<Package>, Package)> {
+    let mut cmd = cargo_metadata::MetadataCommand::new();
+    cmd.no_deps();
+    cmd.other_options(net.cargo_flags());
+    if let Some(path) = manifest_path {
+        cmd.manifest_path(path);
+    }
+    let metadata = cmd
+        .exec()
+        .map_err(|e| Error::from(e.compat()).chain_err(|| "Failed to get workspace metadata"))?;
+    let members: HashSet<_> = metadata.workspace_members.iter().collect();
+    let members: Vec<Package> = metadata
+        .packages
+        .into_iter()
+        .filter(|package| members.contains(&package.id))
+        .collect();
+
+    let package = if let Some(pkgid) = pkgid {
+        members
+            .iter()
+            .find(|package| &package.name == pkgid)
+            .chain_err(|| format!("Package `{}` not found in the workspace", pkgid))?
+    } else {
+        let manifest_path = fs::canonicalize(find(manifest_path)?)?;
+        members
+            .iter()
+            .find(|package| package.manifest_path == manifest_path)
+            // If we have successfully got metadata, but our manifest path does not correspond to a
+            // package, we must have been called against a virtual manifest.
+            .chain_err(|| {
+                "Found virtual manifest, but this command requires running against an \
+                 actual package in this workspace. Try adding `--package`."
+            })?
+    }
+    .clone();
+    Ok((members, package))
+}
+
+fn handle_rename(args: &RenameArgs, net: &NetConfig) -> Result<()> {
+    let (members, package) = select_package(&args.manifest_path, &args.pkgid, net)?;
+    let mut manifest = Manifest::open(&Some(package.manifest_path.clone()))?;
+    manifest.rename_feature(&args.old, &args.new)?;
+    let mut changed = vec![(&package, manifest)];
+    for member in members.iter().filter(|member| member.id != package.id) {
+        let mut manifest = Manifest::open(&Some(member.manifest_path.clone()))?;
+        if manifest.rename_dependency_feature(&package.name, &args.old, &args.new)? {
+            changed.push((member, manifest));
+        }
+    }
+
+    if args.dry_run {
+        dry_run_message()?;
+    }
+    let mut edits = Edits::new();
+    for (i, (member, manifest)) in changed.iter().enumerate() {
+        if i == 0 {
+            let message = format!(
+                "feature `{}` of {} to `{}`",
+                args.old, member.name, args.new
+            );
+            print_msg("Renaming", &message)?;
+        } else {
+            let message = format!("references to it in {}", member.name);
+            print_msg("Updating", &message)?;
+        }
+        if !args.dry_run {
+            edits.save(manifest, &member.manifest_path)?;
+        }
+    }
+    record_for_undo(&edits.write()?)?;
+    eprintln!(
+        "WARN: Packages outside the workspace that enable the `{}` feature of {} must change to \
+         `{}`.",
+        args.old, package.name, args.new
+    );
+    Ok(())
+}
+
+fn handle_add(args: &AddArgs, net: &NetConfig) -> Result<()> {
+    let (_, package) = select_package(&args.manifest_path, &args.pkgid, net)?;
+    let mut manifest = Manifest::open(&Some(package.manifest_path.clone()))?;
+    manifest.add_feature_entries(&args.feature, &args.entries)?;
+
+    if args.dry_run {
+        dry_run_message()?;
+    }
+    let message = format!(
+        "{} to feature `{}` of {}",
+        args.entries
+            .iter()
+            .map(|entry| format!("`{}`", entry))
+            .collect::<Vec<_>>()
+            .join(", "),
+        args.feature,
+        package.name
+    );
+    print_msg("Adding", &message)?;
+    if !args.dry_run {
+        record_for_undo(&Edits::write_one(&manifest, &package.manifest_path)?)?;
+    }
+    Ok(())
+}
+
+/// Run `cargo features` with a command line as cargo passes it, e.g. from
+/// `std::env::args_os`.
+pub fn run(args: impl IntoIterator<Item = OsString>) {
+    let args: Command = Command::from_iter(args);
+    let Command::Features(args) = args;
+
+    if let Err(err) = handle_features(&args) {
+        super::exit_with_error(&err);
+    }
+}
@@ -0,0 +1,283 @@
+//! `cargo inventory`
+
+use self::errors::*;
+use super::NetArgs;
+use crate::{find, workspace_members, LocalManifest};
+use cargo_metadata::{Metadata, Package, PackageId};
+use failure::Fail;
+use serde_derive::Serialize;
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use std::ffi::OsString;
+use std::fs;
+use std::path::PathBuf;
+use structopt::{clap::AppSettings, StructOpt};
+use url::Url;
+
+mod errors {
+    error_chain::error_chain! {
+        links {
+            CargoEditLib(crate::Error, crate::ErrorKind);
+        }
+        foreign_links {
+            CargoMetadata(::failure::Compat<::cargo_metadata::Error>);
+            Io(::std::io::Error);
+            Json(::serde_json::Error);
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(bin_name = "cargo")]
+enum Command {
+    /// Export the dependencies of packages, with their versions, licenses and sources.
+    #[structopt(name = "inventory")]
+    #[structopt(after_help = "\
+Every dependency in the manifests is listed with its version requirement, the version `Cargo.lock` \
+resolves it to, its license expression, its source (e.g. \
+`registry+https://github.com/rust-lang/crates.io-index`) and its repository. The dependencies \
+those pull in are listed too, without a requirement, if the `--transitive` flag is supplied.
+
+The list is printed as a JSON array of objects, or with `--format csv`, as CSV with a header row. \
+Fields that a package doesn't declare (e.g. a repository) are null in JSON and empty in CSV.
+
+All packages in the workspace are reported on if the `--workspace` flag is supplied. This is also \
+the default when run against a virtual manifest (a workspace root without a package of its own). \
+As with cargo, only the workspace's `default-members` are reported on if it lists them, unless the \
+`--all-members` flag is supplied.
+
+The manifests aren't changed. Like any cargo command, this writes `Cargo.lock` if it is missing or \
+out of date, and downloads the dependencies it hasn't got yet, unless offline.")]
+    Inventory(Args),
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(setting = AppSettings::ColoredHelp)]
+struct Args {
+    /// Path to the manifest to report on.
+    #[structopt(long = "manifest-path", value_name = "path", conflicts_with = "pkgid")]
+    manifest_path: Option<PathBuf>,
+
+    /// Package id of the crate to report on.
+    #[structopt(
+        long = "package",
+        short = "p",
+        value_name = "pkgid",
+        conflicts_with = "workspace"
+    )]
+    pkgid: Option<String>,
+
+    /// Report on all packages in the workspace.
+    #[structopt(long = "workspace", conflicts_with = "pkgid")]
+    workspace: bool,
+
+    /// Include workspace members left out of the workspace's `default-members`.
+    #[structopt(long = "all-members", conflicts_with = "pkgid")]
+    all_members: bool,
+
+    /// Also list the dependencies of dependencies, as resolved in the lock file.
+    #[structopt(long = "transitive")]
+    transitive: bool,
+
+    /// Output format: `json` or `csv`.
+    #[structopt(
+        long = "format",
+        value_name = "fmt",
+        default_value = "json",
+        possible_values = &["json", "csv"]
+    )]
+    format: String,
+
+    #[structopt(flatten)]
+    net: NetArgs,
+}
+
+/// A dependency in the inventory.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+struct Item {
+    name: String,
+    /// The requirement in the manifest, for direct dependencies.
+    requirement: Option<String>,
+    /// The version it resolves to.
+    version: Option<String>,
+    license: Option<String>,
+    source: Option<String>,
+    repository: Option<String>,
+    /// Whether a manifest depends on it, rather than another dependency.
+    direct: bool,
+}
+
+impl Item {
+    fn new(package: Option<&Package>, name: &str, requirement: Option<String>) -> Self {
+        Item {
+            name: name.to_owned(),
+            direct: requirement.is_some(),
+            requirement,
+            version: package.map(|package| package.version.to_string()),
+            license: package.and_then(|package| package.license.clone()),
+            source: package.and_then(source),
+            repository: package.and_then(|package| package.repository.clone()),
+        }
+    }
+}
+
+/// Where a package comes from: its registry or git repository, or its directory for a path
+/// dependency.
+fn source(package: &Package) -> Option<String> {
+    match &package.source {
+        Some(source) => Some(source.repr.clone()),
+        None => {
+            let dir = package.manifest_path.parent()?;
+            Url::from_file_path(dir)
+                .ok()
+                .map(|url| format!("path+{}", url))
+        }
+    }
+}
+
+/// The packages a package depends on, as resolved.
+fn resolved_dependencies<'a>(metadata: &'a Metadata, id: &PackageId) -> Vec<&'a Package> {
+    let node = metadata
+        .resolve
+        .as_ref()
+        .and_then(|resolve| resolve.nodes.iter().find(|node| &node.id == id));
+    node.map(|node| {
+        node.dependencies
+            .iter()
+            .filter_map(|id| metadata.packages.iter().find(|package| &package.id == id))
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// The direct dependencies of the packages, and with `transitive`, everything they pull in.
+fn inventory(metadata: &Metadata, packages: &[Package], transitive: bool) -> Vec<Item> {
+    let mut items = BTreeSet::new();
+    let mut listed = HashSet::new();
+    let mut queue = VecDeque::new();
+    for package in packages {
+        let resolved = resolved_dependencies(metadata, &package.id);
+        for dependency in &package.dependencies {
+            let locked = resolved.iter().copied().find(|locked| {
+                locked.name == dependency.name && dependency.req.matches(&locked.version)
+            });
+            // Path and git dependencies without a version come through as `>=0.0.0`.
+            let requirement = if dependency.req == semver::VersionReq::parse("*").unwrap() {
+                "*".to_owned()
+            } else {
+                dependency.req.to_string()
+            };
+            items.insert(Item::new(locked, &dependency.name, Some(requirement)));
+            if let Some(locked) = locked {
+                listed.insert(&locked.id);
+                queue.push_back(locked);
+            }
+        }
+    }
+
+    if transitive {
+        // Anything reached from the direct dependencies that isn't one itself.
+        let mut seen: HashSet<_> = queue.iter().map(|package| &package.id).collect();
+        while let Some(package) = queue.pop_front() {
+            for dependency in resolved_dependencies(metadata, &package.id) {
+                if seen.insert(&dependency.id) {
+                    queue.push_back(dependency);
+                }
+            }
+        }
+        for id in seen.difference(&listed) {
+            if let Some(package) = metadata.packages.iter().find(|package| &package.id == *id) {
+                items.insert(Item::new(Some(package), &package.name, None));
+            }
+        }
+    }
+    items.into_iter().collect()
+}
+
+/// A CSV field, quoted if it needs to be.
+fn csv_field(field: Option<&str>) -> String {
+    let field = field.unwrap_or_default();
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn print_csv(items: &[Item]) {
+    println!("name,requirement,version,license,source,repository,direct");
+    for item in items {
+        let direct = item.direct.to_string();
+        let fields = [
+            Some(item.name.as_str()),
+            item.requirement.as_deref(),
+            item.version.as_deref(),
+            item.license.as_deref(),
+            item.source.as_deref(),
+            item.repository.as_deref(),
+            Some(direct.as_str()),
+        ];
+        let fields: Vec<_> = fields.iter().map(|field| csv_field(*field)).collect();
+        println!("{}", fields.join(","));
+    }
+}
+
+fn handle_inventory(args: Args) -> Result<()> {
+    let net = args.net.net_config()?;
+    let mut cmd = cargo_metadata::MetadataCommand::new();
+    if let Some(path) = &args.manifest_path {
+        cmd.manifest_path(path);
+    }
+    cmd.features(cargo_metadata::CargoOpt::AllFeatures);
+    cmd.other_options(net.cargo_flags());
+    let metadata = cmd
+        .exec()
+        .map_err(|e| Error::from(e.compat()).chain_err(|| "Failed to get workspace metadata"))?;
+    let members: HashSet<_> = metadata.workspace_members.iter().collect();
+
+    let packages = metadata
+        .packages
+        .iter()
+        .filter(|package| members.contains(&package.id));
+    let workspace = args.workspace
+        || (args.pkgid.is_none() && LocalManifest::find(&args.manifest_path)?.is_virtual());
+    let packages: Vec<_> = if workspace {
+        workspace_members(&metadata, args.all_members)?
+    } else if let Some(pkgid) = &args.pkgid {
+        vec![packages
+            .clone()
+            .find(|package| &package.name == pkgid)
+            .chain_err(|| format!("Package `{}` not found in the workspace", pkgid))?
+            .clone()]
+    } else {
+        let manifest_path = fs::canonicalize(find(&args.manifest_path)?)?;
+        vec![packages
+            .clone()
+            .find(|package| package.manifest_path == manifest_path)
+            // If we have successfully got metadata, but our manifest path does not correspond to a
+            // package, we must have been called against a virtual manifest.
+            .chain_err(|| {
+                "Found virtual manifest, but this command requires running against an \
+                 actual package in this workspace. Try adding `--workspace`."
+            })?
+            .clone()]
+    };
+
+    let items = inventory(&metadata, &packages, args.transitive);
+    if args.format == "csv" {
+        print_csv(&items);
+    } else {
+        println!("{}", serde_json::to_string_pretty(&items)?);
+    }
+    Ok(())
+}
+
+/// Run `cargo inventory` with a command line as cargo passes it, e.g. from
+/// `std::env::args_os`.
+pub fn run(args: impl IntoIterator<Item = OsString>) {
+    let args: Command = Command::from_iter(args);
+    let Command::Inventory(args) = args;
+
+    if let Err(err) = handle_inventory(args) {
+        super::exit_with_error(&err);
+    }
+}
@@ -0,0 +1,392 @@
+//! `cargo manifest`
+
+use crate::{
+    find, fix_lints, lint, manifest_from_pkgid, record_for_undo, workspace_root, Edits, Manifest,
+};
+use error_chain::bail;
+use std::borrow::Cow;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use structopt::{clap::AppSettings, StructOpt};
+
+mod errors {
+    error_chain::error_chain! {
+        links {
+            CargoEditLib(crate::Error, crate::ErrorKind);
+        }
+        foreign_links {
+            Json(::serde_json::Error);
+        }
+    }
+}
+use self::errors::*;
+use super::NetArgs;
+
+#[derive(Debug, StructOpt)]
+#[structopt(bin_name = "cargo")]
+enum Command {
+    /// Read or edit arbitrary fields of a Cargo.toml manifest file.
+    #[structopt(name = "manifest")]
+    #[structopt(after_help = "\
+Fields are addressed by their dotted key, e.g. `package.description` or \
+`profile.release.opt-level`. Keys containing dots can be quoted, e.g. \
+`dependencies.\"my.crate\".version`.
+
+`get` prints strings without quotes and anything else as TOML, or with '--output json', the value \
+as JSON, for scripts to read. Fields that the package inherits from its workspace, e.g. \
+`version = { workspace = true }`, are read from the workspace root, with a dependency's own \
+`features` added to those of `[workspace.dependencies]`.
+
+The value given to `set` is parsed as a TOML value, so `true`, `3`, `[\"cli\"]` and \
+`{ version = \"1.0\" }` keep their types. Anything that isn't valid TOML is stored as a string, \
+as are values of fields that cargo only accepts as strings (e.g. `package.version`, \
+`package.edition` or a dependency's version), and the '--string' flag forces a string (e.g. for a \
+description of `1.0`). Missing tables are created.
+
+`lint` reports common mistakes: dev-dependencies that repeat a normal dependency \
+(`duplicate-dependency`), `default-features = true` (`redundant-default-features`), `*` \
+requirements (`wildcard-requirement`), path or git dependencies without a version in a \
+publishable package (`missing-version`), and `[features]` entries referring to dependencies or \
+features that don't exist (`unknown-feature-reference`). The '--fix' flag fixes redundant \
+`default-features` and dev-dependencies identical to a normal dependency. The command fails if \
+any problem is left.")]
+    Manifest(Args),
+}
+
+// The cargo flags are only accepted, as other cargo commands accept them: reading and editing
+// manifests accesses neither the network nor `Cargo.lock`, so there is nothing for them to change.
+#[allow(dead_code)]
+#[derive(Debug, StructOpt)]
+#[structopt(setting = AppSettings::ColoredHelp)]
+enum Args {
+    /// Print the value of a field.
+    #[structopt(name = "get")]
+    Get {
+        /// Dotted key of the field, e.g. `package.version`.
+        key: String,
+
+        /// Output format: `text` (strings without quotes, anything else as TOML) or `json`.
+        #[structopt(
+            long = "output",
+            value_name = "fmt",
+            default_value = "text",
+            possible_values = &["text", "json"]
+        )]
+        output: String,
+
+        #[structopt(flatten)]
+        target: Target,
+
+        #[structopt(flatten)]
+        cargo: NetArgs,
+    },
+    /// Set the value of a field.
+    #[structopt(name = "set")]
+    Set {
+        /// Dotted key of the field, e.g. `package.description`.
+        key: String,
+
+        /// New value, as TOML (e.g. `'["cli"]'`) or a plain string.
+        value: String,
+
+        /// Store the value as a string, even if it is valid TOML.
+        #[structopt(long = "string")]
+        string: bool,
+
+        #[structopt(flatten)]
+        target: Target,
+
+        #[structopt(flatten)]
+        cargo: NetArgs,
+    },
+    /// Report common mistakes in the manifest.
+    #[structopt(name = "lint")]
+    Lint {
+        /// Fix the problems that have a mechanical fix.
+        #[structopt(long = "fix")]
+        fix: bool,
+
+        #[structopt(flatten)]
+        target: Target,
+
+        #[structopt(flatten)]
+        cargo: NetArgs,
+    },
+}
+
+/// The manifest to read or edit.
+#[derive(Debug, StructOpt)]
+struct Target {
+    /// Path to the manifest to read or edit.
+    #[structopt(long = "manifest-path", value_name = "path", conflicts_with = "pkgid")]
+    manifest_path: Option<PathBuf>,
+
+    /// Package id of the crate whose manifest to read or edit.
+    #[structopt(long = "package", short = "p", value_name = "pkgid")]
+    pkgid: Option<String>,
+}
+
+impl Target {
+    fn manifest_path(&self) -> Result<Cow<'_, Option<PathBuf>>> {
+        Ok(if let Some(ref pkgid) = self.pkgid {
+            let pkg = manifest_from_pkgid(pkgid)?;
+            Cow::Owned(Some(pkg.manifest_path))
+        } else {
+            Cow::Borrowed(&self.manifest_path)
+        })
+    }
+}
+
+/// Split a dotted key into its parts, e.g. `dependencies."my.crate".version`.
+fn parse_key(key: &str) -> Result<Vec<String>> {
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let mut quoted = false;
+    for c in key.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            '.' if !quoted => parts.push(std::mem::take(&mut part)),
+            _ => part.push(c),
+        }
+    }
+    parts.push(part);
+
+    if quoted || parts.iter().any(String::is_empty) {
+        bail!("Invalid key `{}`", key);
+    }
+    Ok(parts)
+}
+
+/// The fields of `[package]` (and `[workspace.package]`) that cargo only accepts as strings, or as
+/// a table inheriting them.
+const PACKAGE_STRING_FIELDS: &[&str] = &[
+    "name",
+    "version",
+    "edition",
+    "rust-version",
+    "description",
+    "documentation",
+    "homepage",
+    "repository",
+    "license",
+    "license-file",
+    "links",
+    "default-run",
+    "resolver",
+];
+
+/// The fields of a dependency's table that cargo only accepts as strings.
+const DEPENDENCY_STRING_FIELDS: &[&str] = &[
+    "version", "path", "git", "branch", "tag", "rev", "registry", "package",
+];
+
+/// Whether cargo only accepts a string for the field at `key`, e.g. `package.version`, or a
+/// dependency's requirement.
+fn is_string_field(key: &[String]) -> bool {
+    let key: Vec<&str> = key.iter().map(String::as_str).collect();
+    match key.as_slice() {
+        ["package", field] | ["project", field] | ["workspace", "package", field] => {
+            PACKAGE_STRING_FIELDS.contains(field)
+        }
+        ["workspace", "resolver"] => true,
+        [.., table, _] if table.ends_with("dependencies") => true,
+        [.., table, _, field] if table.ends_with("dependencies") => {
+            DEPENDENCY_STRING_FIELDS.contains(field)
+        }
+        _ => false,
+    }
+}
+
+/// Parse a value as TOML, falling back to a string, which is also kept for a field that cargo
+/// only accepts as a string (e.g. `1.0` for `package.version` isn't a float).
+fn parse_value(key: &[String], value: &str, string: bool) -> toml_edit::Value {
+    let parsed = if string { None } else { value.parse().ok() };
+    match parsed {
+        // e.g. `version = { workspace = true }`
+        Some(parsed @ toml_edit::Value::InlineTable(_)) => parsed,
+        Some(parsed) if parsed.is_str() || !is_string_field(key) => parsed,
+        _ => value.into(),
+    }
+}
+
+/// Check that the edited manifest still parses, before it is written.
+fn check_edited(manifest: &Manifest) -> Result<()> {
+    manifest
+        .to_string_like("")
+        .parse::<toml::Value>()
+        .chain_err(|| "The edited manifest is not valid TOML")?;
+    Ok(())
+}
+
+/// Convert a TOML item to JSON, with dates and times as strings.
+fn item_to_json(item: &toml_edit::Item) -> serde_json::Value {
+    match item {
+        toml_edit::Item::Value(value) => value_to_json(value),
+        toml_edit::Item::Table(table) => table
+            .iter()
+            .map(|(key, item)| (key.to_owned(), item_to_json(item)))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+        toml_edit::Item::ArrayOfTables(array) => array
+            .iter()
+            .map(|table| item_to_json(&toml_edit::Item::Table(table.clone())))
+            .collect(),
+        toml_edit::Item::None => serde_json::Value::Null,
+    }
+}
+
+fn value_to_json(value: &toml_edit::Value) -> serde_json::Value {
+    match value {
+        toml_edit::Value::String(s) => s.value().as_str().into(),
+        toml_edit::Value::Integer(i) => (*i.value()).into(),
+        toml_edit::Value::Float(f) => (*f.value()).into(),
+        toml_edit::Value::Boolean(b) => (*b.value()).into(),
+        toml_edit::Value::DateTime(d) => d.value().to_string().into(),
+        toml_edit::Value::Array(array) => array.iter().map(value_to_json).collect(),
+        toml_edit::Value::InlineTable(table) => table
+            .iter()
+            .map(|(key, value)| (key.to_owned(), value_to_json(value)))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+    }
+}
+
+fn handle_manifest(args: &Args) -> Result<()> {
+    match args {
+        Args::Get {
+            key,
+            output,
+            target,
+            ..
+        } => {
+            let manifest_path = target.manifest_path()?;
+            let manifest = Manifest::open(&manifest_path)?;
+            let manifest_path = find(&manifest_path)?;
+            let workspace = workspace_root(&manifest_path)?.join("Cargo.toml");
+            let workspace = Manifest::open(&Some(workspace))?;
+            let item = manifest
+                .get_inherited_item(&parse_key(key)?, &workspace)
+                .chain_err(|| format!("The key `{}` could not be found.", key))?;
+            if output == "json" {
+                println!("{}", serde_json::to_string_pretty(&item_to_json(&item))?);
+                return Ok(());
+            }
+            // Print strings without quotes, so the output can be used directly in scripts.
+            match item {
+                toml_edit::Item::Value(value) => match value.as_str() {
+                    Some(s) => println!("{}", s),
+                    None => println!("{}", value.to_string().trim()),
+                },
+                toml_edit::Item::Table(table) => print!("{}", table),
+                toml_edit::Item::ArrayOfTables(array) => {
+                    for table in array.iter() {
+                        println!("{}", table);
+                    }
+                }
+                toml_edit::Item::None => unreachable!("missing items are errors"),
+            }
+        }
+        Args::Set {
+            key,
+            value,
+            string,
+            target,
+            ..
+        } => {
+            let manifest_path = target.manifest_path()?;
+            let mut manifest = Manifest::open(&manifest_path)?;
+            let key = parse_key(key)?;
+            manifest.set_value(&key, parse_value(&key, value, *string))?;
+            check_edited(&manifest)?;
+            record_for_undo(&Edits::write_one(&manifest, &find(&manifest_path)?)?)?;
+        }
+        Args::Lint { fix, target, .. } => {
+            let manifest_path = target.manifest_path()?;
+            let mut manifest = Manifest::open(&manifest_path)?;
+            let mut lints = lint(&manifest);
+            if *fix && fix_lints(&mut manifest, &lints)? > 0 {
+                record_for_undo(&Edits::write_one(&manifest, &find(&manifest_path)?)?)?;
+                for fixed in lints.iter().filter(|lint| lint.fixable) {
+                    println!("fixed {}", fixed);
+                }
+                lints.retain(|lint| !lint.fixable);
+            }
+
+            for lint in &lints {
+                println!("{}", lint);
+            }
+            if !lints.is_empty() {
+                let fixable = lints.iter().filter(|lint| lint.fixable).count();
+                bail!(
+                    "Found {} problem(s) in the manifest, {} of which `--fix` can fix",
+                    lints.len(),
+                    fixable
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Run `cargo manifest` with a command line as cargo passes it, e.g. from
+/// `std::env::args_os`.
+pub fn run(args: impl IntoIterator<Item = OsString>) {
+    let args: Command = Command::from_iter(args);
+    let Command::Manifest(args) = args;
+
+    if let Err(err) = handle_manifest(&args) {
+        super::exit_with_error(&err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_keys() {
+        assert_eq!(
+            parse_key("package.description").unwrap(),
+            vec!["package", "description"]
+        );
+        assert_eq!(
+            parse_key("dependencies.\"my.crate\".version").unwrap(),
+            vec!["dependencies", "my.crate", "version"]
+        );
+        assert!(parse_key("package.").is_err());
+        assert!(parse_key("\"package").is_err());
+    }
+
+    #[test]
+    fn parses_typed_values() {
+        let key = parse_key("package.metadata.docs").unwrap();
+        assert_eq!(parse_value(&key, "true", false).as_bool(), Some(true));
+        assert_eq!(parse_value(&key, "3", false).as_integer(), Some(3));
+        assert!(parse_value(&key, "[\"cli\"]", false).is_array());
+        assert!(parse_value(&key, "{ version = \"1.0\" }", false).is_inline_table());
+        assert_eq!(parse_value(&key, "A tool", false).as_str(), Some("A tool"));
+        assert_eq!(
+            parse_value(&key, "\"quoted\"", false).as_str(),
+            Some("quoted")
+        );
+        assert_eq!(parse_value(&key, "1.0", true).as_str(), Some("1.0"));
+    }
+
+    #[test]
+    fn keeps_string_fields_strings() {
+        for (key, value) in &[
+            ("package.version", "1.0"),
+            ("package.edition", "2021"),
+            ("package.rust-version", "1.70"),
+            ("workspace.package.license", "2"),
+            ("dependencies.serde", "1.0"),
+            ("target.\"cfg(unix)\".dev-dependencies.libc.version", "0.2"),
+        ] {
+            let key = parse_key(key).unwrap();
+            assert_eq!(parse_value(&key, value, false).as_str(), Some(*value));
+        }
+        let key = parse_key("package.version").unwrap();
+        assert!(parse_value(&key, "{ workspace = true }", false).is_inline_table());
+    }
+}
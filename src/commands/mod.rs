@@ -0,0 +1,195 @@
+//! The cargo-edit commands, e.g. `cargo add`
+//!
+//! Each executable, such as `cargo-add`, runs one of these, and `cargo-edit` runs any of them, so
+//! they share the handling of the flags they have in common, the network settings read from the
+//! cargo config and the client that talks to registries.
+
+use crate::errors::*;
+use crate::net::NetConfig;
+use crate::tls::{CLIENT_CERT_ENV, CLIENT_KEY_ENV};
+use error_chain::ChainedError;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process;
+use structopt::StructOpt;
+
+#[cfg(feature = "add")]
+pub mod add;
+#[cfg(feature = "duplicates")]
+pub mod duplicates;
+#[cfg(feature = "edit")]
+pub mod edit;
+#[cfg(feature = "features")]
+pub mod features;
+#[cfg(feature = "inventory")]
+pub mod inventory;
+#[cfg(feature = "manifest")]
+pub mod manifest;
+#[cfg(feature = "pin")]
+pub mod pin;
+#[cfg(feature = "rm")]
+pub mod rm;
+#[cfg(feature = "set-rust-version")]
+pub mod set_rust_version;
+#[cfg(feature = "staleness")]
+pub mod staleness;
+#[cfg(feature = "unpin")]
+pub mod unpin;
+#[cfg(feature = "upgrade")]
+pub mod upgrade;
+
+/// The flags that keep a command, and the cargo commands it runs, off the network, as cargo's own
+/// commands take them.
+#[derive(Debug, Clone, Copy, Default, StructOpt)]
+pub struct NetArgs {
+    /// Run without accessing the network.
+    #[structopt(long = "offline")]
+    pub offline: bool,
+
+    /// Require `Cargo.lock` to be up to date, and run without accessing the network.
+    #[structopt(long = "frozen")]
+    pub frozen: bool,
+}
+
+impl NetArgs {
+    /// The network settings that apply to the current directory, with these flags applied.
+    pub fn net_config(self) -> Result<NetConfig> {
+        Ok(NetConfig::from_current_dir()?.with_cargo_flags(self.offline, self.frozen))
+    }
+}
+
+/// The flags giving the client certificate for registries that require one.
+#[derive(Debug, Clone, Default, StructOpt)]
+pub struct ClientIdentityArgs {
+    /// PEM client certificate to present to registries that require one.
+    #[structopt(
+        long = "client-cert",
+        value_name = "path",
+        env = CLIENT_CERT_ENV,
+        requires = "client-key"
+    )]
+    pub client_cert: Option<PathBuf>,
+
+    /// Private key of the `--client-cert` certificate, in PEM format.
+    #[structopt(
+        long = "client-key",
+        value_name = "path",
+        env = CLIENT_KEY_ENV,
+        requires = "client-cert"
+    )]
+    pub client_key: Option<PathBuf>,
+}
+
+impl ClientIdentityArgs {
+    /// Network settings that present this certificate, if one was given.
+    pub fn apply(&self, mut net: NetConfig) -> Result<NetConfig> {
+        net.tls = net
+            .tls
+            .with_client_identity(self.client_cert.clone(), self.client_key.clone())?;
+        Ok(net)
+    }
+}
+
+/// Report the error a command failed with, and what caused it, and exit with status 1.
+pub(crate) fn exit_with_error(err: &impl ChainedError) -> ! {
+    eprintln!("Command failed due to unhandled error: {}\n", err);
+
+    for e in err.iter().skip(1) {
+        eprintln!("Caused by: {}", e);
+    }
+
+    if let Some(backtrace) = err.backtrace() {
+        eprintln!("Backtrace: {:?}", backtrace);
+    }
+
+    process::exit(1);
+}
+
+/// The names of the commands that are built, other than `cargo edit` itself.
+pub fn names() -> Vec<&'static str> {
+    let built = [
+        ("add", cfg!(feature = "add")),
+        ("duplicates", cfg!(feature = "duplicates")),
+        ("features", cfg!(feature = "features")),
+        ("inventory", cfg!(feature = "inventory")),
+        ("manifest", cfg!(feature = "manifest")),
+        ("pin", cfg!(feature = "pin")),
+        ("rm", cfg!(feature = "rm")),
+        ("set-rust-version", cfg!(feature = "set-rust-version")),
+        ("staleness", cfg!(feature = "staleness")),
+        ("unpin", cfg!(feature = "unpin")),
+        ("upgrade", cfg!(feature = "upgrade")),
+    ];
+    built
+        .iter()
+        .filter(|(_, built)| *built)
+        .map(|(name, _)| *name)
+        .collect()
+}
+
+/// Run the command `name` (e.g. `add`) with a command line as cargo passes it (e.g.
+/// `cargo-add add serde`), if it is built. Returns whether it is.
+pub fn run(name: &str, args: Vec<OsString>) -> bool {
+    match name {
+        #[cfg(feature = "add")]
+        "add" => {
+            add::run(args);
+            true
+        }
+        #[cfg(feature = "duplicates")]
+        "duplicates" => {
+            duplicates::run(args);
+            true
+        }
+        #[cfg(feature = "features")]
+        "features" => {
+            features::run(args);
+            true
+        }
+        #[cfg(feature = "inventory")]
+        "inventory" => {
+            inventory::run(args);
+            true
+        }
+        #[cfg(feature = "manifest")]
+        "manifest" => {
+            manifest::run(args);
+            true
+        }
+        #[cfg(feature = "pin")]
+        "pin" => {
+            pin::run(args);
+            true
+        }
+        #[cfg(feature = "rm")]
+        "rm" => {
+            rm::run(args);
+            true
+        }
+        #[cfg(feature = "set-rust-version")]
+        "set-rust-version" => {
+            set_rust_version::run(args);
+            true
+        }
+        #[cfg(feature = "staleness")]
+        "staleness" => {
+            staleness::run(args);
+            true
+        }
+        #[cfg(feature = "unpin")]
+        "unpin" => {
+            unpin::run(args);
+            true
+        }
+        #[cfg(feature = "upgrade")]
+        "upgrade" => {
+            upgrade::run(args);
+            true
+        }
+        _ => {
+            // Unused when no other command is built.
+            drop(args);
+            false
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! `cargo pin`
+
+use self::errors::*;
+use super::NetArgs;
+use crate::{pin_dependencies, record_for_undo, PinDirection, PinOptions};
+use std::ffi::OsString;
+use std::path::PathBuf;
+use structopt::{clap::AppSettings, StructOpt};
+
+mod errors {
+    error_chain::error_chain! {
+        links {
+            CargoEditLib(crate::Error, crate::ErrorKind);
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(bin_name = "cargo")]
+enum Command {
+    /// Pin dependencies to the exact versions they resolve to.
+    #[structopt(name = "pin")]
+    #[structopt(after_help = "\
+Each requirement is replaced by an exact `=x.y.z` requirement on the version in `Cargo.lock` that \
+it matches. Dependencies missing from the lock file are pinned to the newest version in their \
+registry that the requirement matches, as a fresh resolve would pick. Use `cargo unpin` to go back \
+to caret requirements.
+
+Only dependencies on registries are pinned, and a dependency that is already pinned to the version \
+it resolves to is left alone.
+
+All packages in the workspace will be pinned if the `--workspace` flag is supplied. This is also \
+the default when run against a virtual manifest (a workspace root without a package of its own). \
+As with cargo, only the workspace's `default-members` are pinned if it lists them, unless the \
+`--all-members` flag is supplied.")]
+    Pin(Args),
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(setting = AppSettings::ColoredHelp)]
+struct Args {
+    /// Dependencies to pin (all registry dependencies by default).
+    #[structopt(name = "dependency")]
+    dependencies: Vec<String>,
+
+    /// Path to the manifest to pin dependencies in.
+    #[structopt(long = "manifest-path", value_name = "path", conflicts_with = "pkgid")]
+    manifest_path: Option<PathBuf>,
+
+    /// Package id of the crate to pin dependencies in.
+    #[structopt(
+        long = "package",
+        short = "p",
+        value_name = "pkgid",
+        conflicts_with = "workspace"
+    )]
+    pkgid: Option<String>,
+
+    /// Pin dependencies in all packages in the workspace.
+    #[structopt(long = "workspace", conflicts_with = "pkgid")]
+    workspace: bool,
+
+    /// Include workspace members left out of the workspace's `default-members`.
+    #[structopt(long = "all-members", conflicts_with = "pkgid")]
+    all_members: bool,
+
+    /// Print changes to be made without making them.
+    #[structopt(long = "dry-run")]
+    dry_run: bool,
+
+    #[structopt(flatten)]
+    net: NetArgs,
+}
+
+fn handle_pin(args: Args) -> Result<()> {
+    let options = PinOptions {
+        dependencies: args.dependencies,
+        manifest_path: args.manifest_path,
+        pkgid: args.pkgid,
+        workspace: args.workspace,
+        all_members: args.all_members,
+        dry_run: args.dry_run,
+        net: args.net.net_config()?,
+    };
+    record_for_undo(&pin_dependencies(PinDirection::Pin, &options)?)?;
+    Ok(())
+}
+
+/// Run `cargo pin` with a command line as cargo passes it, e.g. from
+/// `std::env::args_os`.
+pub fn run(args: impl IntoIterator<Item = OsString>) {
+    let args: Command = Command::from_iter(args);
+    let Command::Pin(args) = args;
+
+    if let Err(err) = handle_pin(args) {
+        super::exit_with_error(&err);
+    }
+}
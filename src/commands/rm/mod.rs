@@ -0,0 +1,497 @@
+//! `cargo rm`
+
+use self::unused::{Resolved, Sources};
+use crate::{
+    find, manifest_from_pkgid, record_for_undo, workspace_root, Edits, Lockfile, Manifest,
+    NetConfig,
+};
+use failure::Fail;
+use semver::Version;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use structopt::{clap::AppSettings, StructOpt};
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+
+mod errors {
+    error_chain::error_chain! {
+        links {
+            CargoEditLib(crate::Error, crate::ErrorKind);
+        }
+        foreign_links {
+            CargoMetadata(::failure::Compat<::cargo_metadata::Error>);
+            Io(::std::io::Error);
+        }
+    }
+}
+use self::errors::*;
+use super::NetArgs;
+
+mod unused;
+
+#[derive(Debug, StructOpt)]
+#[structopt(bin_name = "cargo")]
+enum Command {
+    /// Remove a dependency from a Cargo.toml manifest file.
+    #[structopt(name = "rm")]
+    #[structopt(after_help = "\
+A crate is given by its name in the manifest, which differs from the package name if it is \
+renamed, or by its package name if only one entry depends on it. A crate depended on at more than \
+one version is picked out with the version its requirement starts at, e.g. `rand@0.7` for \
+`rand07 = { package = \"rand\", version = \"0.7.3\" }`.
+
+If the '--target' flag is supplied, crates are removed from the tables of that target platform \
+only, e.g. `[target.'cfg(windows)'.dependencies]` for `--target 'cfg(windows)'` (or its \
+dev-dependencies with '--dev'), leaving the other sections untouched. Without it, they are \
+removed from the sections that apply to every platform.
+
+If the '--unused' flag is supplied, the dependencies that the package's code never refers to are \
+listed instead, and removed if the '--yes' flag is supplied too. The Rust files of the package are \
+searched for `extern crate`, `use`, and paths starting with the name of each dependency's library, \
+as cargo resolves it (or its name in the manifest, with `-` as `_`, if cargo can't); only the \
+build script is searched for build dependencies, and it is searched for all the others too. Dependencies that link a native library (with a `links` \
+key, or named `*-sys`) are never listed. Dependencies that are only needed for their other side \
+effects, e.g. to enable a feature of another crate, are listed too, so review the list before \
+removing them.
+
+If the '--prune-lockfile' flag is supplied, `Cargo.lock` is brought up to date afterwards, like \
+cargo does before a build, so that the removed crates and any crates only they depended on are \
+dropped from it. Nothing else in the lock file changes.
+
+Entries of the workspace's `[patch]`, `[replace]` and `[profile.<profile>.package]` tables that \
+refer to a removed crate are pointed out once no package of the workspace depends on it directly, \
+and removed with the '--prune-overrides' flag. They may still apply to crates depended on \
+indirectly, so review them with '--dry-run' first.")]
+    Rm(Args),
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(setting = AppSettings::ColoredHelp)]
+struct Args {
+    /// Crates to be removed, e.g. `rand` or `rand@0.7`.
+    #[structopt(name = "crates", required_unless = "unused")]
+    crates: Vec<String>,
+
+    /// List the dependencies that the package's code never refers to.
+    #[structopt(long = "unused", conflicts_with = "crates")]
+    unused: bool,
+
+    /// Remove the dependencies that '--unused' lists.
+    #[structopt(long = "yes", short = "y", requires = "unused")]
+    yes: bool,
+
+    /// Print the dependencies to be removed without removing them.
+    #[structopt(long = "dry-run")]
+    dry_run: bool,
+
+    /// Remove crate as development dependency.
+    #[structopt(long = "dev", short = "D", conflicts_with = "build")]
+    dev: bool,
+
+    /// Remove crate as build dependency.
+    #[structopt(long = "build", short = "B", conflicts_with = "dev")]
+    build: bool,
+
+    /// Remove crate as dependency of the given target platform, e.g. `cfg(windows)`.
+    #[structopt(long = "target", value_name = "target")]
+    target: Option<String>,
+
+    /// Path to the manifest to remove a dependency from.
+    #[structopt(long = "manifest-path", value_name = "path", conflicts_with = "pkgid")]
+    manifest_path: Option<PathBuf>,
+
+    /// Package id of the crate to remove this dependency from.
+    #[structopt(
+        long = "package",
+        short = "p",
+        value_name = "pkgid",
+        conflicts_with = "path"
+    )]
+    pkgid: Option<String>,
+
+    /// Drop crates that are no longer needed from the workspace's lock file.
+    #[structopt(
+        long = "prune-lockfile",
+        conflicts_with = "dry-run",
+        conflicts_with = "frozen"
+    )]
+    prune_lockfile: bool,
+
+    /// Remove the `[patch]`, `[replace]` and profile entries of removed crates.
+    #[structopt(long = "prune-overrides")]
+    prune_overrides: bool,
+
+    /// Do not print any output in case of success.
+    #[structopt(long = "quiet", short = "q")]
+    quiet: bool,
+
+    #[structopt(flatten)]
+    net: NetArgs,
+}
+
+impl Args {
+    /// Get depenency section
+    pub fn get_section(&self) -> Vec<String> {
+        let section = if self.dev {
+            "dev-dependencies"
+        } else if self.build {
+            "build-dependencies"
+        } else {
+            "dependencies"
+        };
+        self.in_target(section)
+    }
+
+    /// The path of a section, in the tables of the target platform if one is given.
+    fn in_target(&self, section: &str) -> Vec<String> {
+        match self.target {
+            Some(ref target) => vec!["target".to_owned(), target.clone(), section.to_owned()],
+            None => vec![section.to_owned()],
+        }
+    }
+
+    /// The sections to look for unused dependencies in.
+    fn unused_sections(&self) -> Vec<Vec<String>> {
+        if self.dev || self.build {
+            vec![self.get_section()]
+        } else {
+            ["dependencies", "dev-dependencies", "build-dependencies"]
+                .iter()
+                .map(|section| self.in_target(section))
+                .collect()
+        }
+    }
+}
+
+fn print_msg(name: &str, section: &[String]) -> Result<()> {
+    let colorchoice = if atty::is(atty::Stream::Stdout) {
+        ColorChoice::Auto
+    } else {
+        ColorChoice::Never
+    };
+    let mut output = StandardStream::stdout(colorchoice);
+    output.set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))?;
+    write!(output, "{:>12}", "Removing")?;
+    output.reset()?;
+    let section = if section.len() == 1 {
+        section[0].clone()
+    } else {
+        format!("{} for target `{}`", &section[2], &section[1])
+    };
+    writeln!(output, " {} from {}", name, section)?;
+    Ok(())
+}
+
+fn dry_run_message() -> Result<()> {
+    let colorchoice = if atty::is(atty::Stream::Stdout) {
+        ColorChoice::Auto
+    } else {
+        ColorChoice::Never
+    };
+    let mut output = StandardStream::stdout(colorchoice);
+    output.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bold(true))?;
+    write!(output, "Starting dry run. ")?;
+    output.reset()?;
+    writeln!(output, "Changes will not be saved.")?;
+    Ok(())
+}
+
+/// Bring the lock file of the workspace up to date with its manifests, which drops the packages
+/// nothing depends on anymore, and list those.
+fn prune_lockfile(manifest_path: &Path, quiet: bool, net: &NetConfig) -> Result<()> {
+    let mut cmd = cargo_metadata::MetadataCommand::new();
+    cmd.manifest_path(manifest_path);
+    cmd.no_deps();
+    let metadata = cmd
+        .exec()
+        .map_err(|e| Error::from(e.compat()).chain_err(|| "Failed to get workspace metadata"))?;
+    let lock_path = metadata.workspace_root.join("Cargo.lock");
+    // Without a lock file there is nothing to prune, and resolving would create one.
+    if !lock_path.exists() {
+        return Ok(());
+    }
+    let locked_packages = || -> Result<BTreeSet<(String, Version)>> {
+        Ok(Lockfile::read(&lock_path)?
+            .package
+            .into_iter()
+            .map(|package| (package.name, package.version))
+            .collect())
+    };
+    let before = locked_packages()?;
+
+    // Resolving the dependencies writes the lock file, without updating packages that are still
+    // used.
+    let mut cmd = cargo_metadata::MetadataCommand::new();
+    cmd.manifest_path(manifest_path);
+    cmd.other_options(net.cargo_flags());
+    cmd.exec()
+        .map_err(|e| Error::from(e.compat()).chain_err(|| "Failed to update the lock file"))?;
+
+    if !quiet {
+        let after = locked_packages()?;
+        for (name, version) in before.difference(&after) {
+            print_pruned(name, version)?;
+        }
+    }
+    Ok(())
+}
+
+fn print_pruned(name: &str, version: &Version) -> Result<()> {
+    let colorchoice = if atty::is(atty::Stream::Stdout) {
+        ColorChoice::Auto
+    } else {
+        ColorChoice::Never
+    };
+    let mut output = StandardStream::stdout(colorchoice);
+    output.set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))?;
+    write!(output, "{:>12}", "Pruning")?;
+    output.reset()?;
+    writeln!(output, " {} v{} from Cargo.lock", name, version)?;
+    Ok(())
+}
+
+/// Point out the `[patch]`, `[replace]` and `[profile.<profile>.package]` entries of the workspace
+/// that refer to removed packages, once no package of the workspace depends on them directly, or
+/// remove them with `--prune-overrides`, collecting the root manifest in `edits`.
+fn prune_overrides(
+    args: &Args,
+    manifest: &mut Manifest,
+    manifest_path: &Path,
+    removed: &BTreeSet<String>,
+    edits: &mut Edits,
+) -> Result<()> {
+    let manifest_path = manifest_path
+        .canonicalize()
+        .chain_err(|| format!("Failed to find `{}`", manifest_path.display()))?;
+    let root_path = workspace_root(&manifest_path)?.join("Cargo.toml");
+    let mut root_manifest = if root_path == manifest_path {
+        None
+    } else {
+        Some(Manifest::open(&Some(root_path.clone()))?)
+    };
+
+    let related: Vec<&String> = {
+        let root = root_manifest.as_ref().unwrap_or(manifest);
+        removed
+            .iter()
+            .filter(|name| !root.related_overrides(name).is_empty())
+            .collect()
+    };
+    if related.is_empty() {
+        return Ok(());
+    }
+    let used = direct_dependencies(manifest, &manifest_path)?;
+
+    let root = root_manifest.as_mut().unwrap_or(manifest);
+    for name in related.into_iter().filter(|name| !used.contains(*name)) {
+        for (table_path, key) in root.related_overrides(name) {
+            let table = table_path.join(".");
+            if args.prune_overrides {
+                if !args.quiet {
+                    print_msg(&key, &[table])?;
+                }
+                root.remove_override(&table_path, &key);
+            } else if !args.quiet {
+                eprintln!(
+                    "note: `{}` in `[{}]` refers to `{}`, which no package of the workspace \
+                     depends on directly anymore. Remove it with '--prune-overrides'.",
+                    key, table, name
+                );
+            }
+        }
+    }
+
+    if let Some(root_manifest) = root_manifest {
+        if args.prune_overrides && !args.dry_run {
+            edits.save(&root_manifest, &root_path)?;
+        }
+    }
+    Ok(())
+}
+
+/// The packages that the workspace depends on directly, with `manifest` in place of the one at
+/// `manifest_path`, as it has not been saved yet.
+fn direct_dependencies(manifest: &Manifest, manifest_path: &Path) -> Result<BTreeSet<String>> {
+    let mut cmd = cargo_metadata::MetadataCommand::new();
+    cmd.manifest_path(manifest_path);
+    cmd.no_deps();
+    let metadata = cmd
+        .exec()
+        .map_err(|e| Error::from(e.compat()).chain_err(|| "Failed to get workspace metadata"))?;
+
+    let mut used: BTreeSet<String> = metadata
+        .packages
+        .iter()
+        .filter(|package| metadata.workspace_members.contains(&package.id))
+        .filter(|package| package.manifest_path != manifest_path)
+        .flat_map(|package| package.dependencies.iter())
+        .map(|dependency| dependency.name.clone())
+        .collect();
+    for (_, table) in manifest.get_sections() {
+        if let Some(table) = table.as_table_like() {
+            used.extend(
+                table
+                    .iter()
+                    .map(|(key, item)| item["package"].as_str().unwrap_or(key).to_owned()),
+            );
+        }
+    }
+    Ok(used)
+}
+
+/// The dependencies that the package's code never refers to, with their sections.
+fn unused_dependencies(
+    args: &Args,
+    manifest: &Manifest,
+    manifest_path: &Option<PathBuf>,
+    net: &NetConfig,
+) -> Result<Vec<(Vec<String>, String)>> {
+    let manifest_path = find(manifest_path)?;
+    let dir = manifest_path
+        .parent()
+        .chain_err(|| "Manifest has no parent directory")?;
+    let sources = Sources::read(dir, manifest)?;
+    let resolved = Resolved::new(&manifest_path, net).unwrap_or_else(|err| {
+        if !args.quiet {
+            eprintln!(
+                "note: Looking for the dependencies by their names in the manifest, as cargo \
+                 couldn't resolve their library names: {}",
+                err.iter().last().unwrap_or(&err)
+            );
+        }
+        Resolved::default()
+    });
+
+    let mut unused = Vec::new();
+    for section in args.unused_sections() {
+        let kind = section.last().expect("sections are not empty");
+        if let Some(table) = manifest.get_item(&section).and_then(|t| t.as_table_like()) {
+            unused.extend(
+                table
+                    .iter()
+                    .filter(|(key, item)| {
+                        let package = item["package"].as_str().unwrap_or(key);
+                        !resolved.links(package)
+                            && !sources.uses(kind, &resolved.code_name(key, package))
+                    })
+                    .map(|(key, _)| (section.clone(), key.to_owned())),
+            );
+        }
+    }
+    Ok(unused)
+}
+
+fn print_unused(name: &str, section: &[String]) -> Result<()> {
+    let colorchoice = if atty::is(atty::Stream::Stdout) {
+        ColorChoice::Auto
+    } else {
+        ColorChoice::Never
+    };
+    let mut output = StandardStream::stdout(colorchoice);
+    output.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)).set_bold(true))?;
+    write!(output, "{:>12}", "Unused")?;
+    output.reset()?;
+    writeln!(output, " {} in {}", name, section.join("."))?;
+    Ok(())
+}
+
+fn handle_rm(args: &Args) -> Result<()> {
+    let net = args.net.net_config()?;
+    let manifest_path = if let Some(ref pkgid) = args.pkgid {
+        let pkg = manifest_from_pkgid(pkgid)?;
+        Cow::Owned(Some(pkg.manifest_path))
+    } else {
+        Cow::Borrowed(&args.manifest_path)
+    };
+    let mut manifest = Manifest::open(&manifest_path)?;
+    let deps = if args.unused {
+        unused_dependencies(args, &manifest, &manifest_path, &net)?
+    } else {
+        args.crates
+            .iter()
+            .map(|spec| {
+                let section = args.get_section();
+                let key = if args.target.is_some() {
+                    manifest.dependency_key_in_table(spec, &section)?
+                } else {
+                    manifest.dependency_key(spec, &[&section[0]])?
+                };
+                Ok((section, key))
+            })
+            .collect::<Result<Vec<_>>>()?
+    };
+    let implicit_features = manifest.implicit_features();
+    let packages: BTreeSet<String> = deps
+        .iter()
+        .map(|(section, key)| {
+            manifest
+                .get_item(section)
+                .and_then(|table| table[key.as_str()]["package"].as_str())
+                .unwrap_or(key)
+                .to_owned()
+        })
+        .collect();
+
+    if args.unused && deps.is_empty() && !args.quiet {
+        println!("No unused dependencies found");
+    }
+    // Unused dependencies are only listed, to be reviewed, unless their removal is confirmed.
+    if args.unused && !args.yes {
+        if !args.quiet {
+            for (section, dep) in &deps {
+                print_unused(dep, section)?;
+            }
+            if !deps.is_empty() {
+                eprintln!("note: Review the list, then remove them with '--unused --yes'.");
+            }
+        }
+        return Ok(());
+    }
+    if args.dry_run && !args.quiet {
+        dry_run_message()?;
+    }
+
+    deps.iter()
+        .map(|(section, dep)| {
+            if !args.quiet {
+                print_msg(dep, section)?;
+            }
+            manifest
+                .remove_from_table_path(section, dep)
+                .map_err(Into::into)
+        })
+        .collect::<Result<Vec<_>>>()
+        .map_err(|err| {
+            eprintln!("Could not edit `Cargo.toml`.\n\nERROR: {}", err);
+            err
+        })?;
+
+    manifest.warn_removed_features(&implicit_features);
+    let path = find(&manifest_path)?;
+    let mut edits = Edits::new();
+    prune_overrides(args, &mut manifest, &path, &packages, &mut edits)?;
+
+    if !args.dry_run {
+        edits.save(&manifest, &path)?;
+        record_for_undo(&edits.write()?)?;
+    }
+
+    if args.prune_lockfile {
+        prune_lockfile(&find(&manifest_path)?, args.quiet, &net)?;
+    }
+
+    Ok(())
+}
+
+/// Run `cargo rm` with a command line as cargo passes it, e.g. from
+/// `std::env::args_os`.
+pub fn run(args: impl IntoIterator<Item = OsString>) {
+    let args: Command = Command::from_iter(args);
+    let Command::Rm(args) = args;
+
+    if let Err(err) = handle_rm(&args) {
+        super::exit_with_error(&err);
+    }
+}
@@ -0,0 +1,213 @@
+//! Finding dependencies that the code of a package never refers to.
+
+use super::errors::*;
+use crate::{Manifest, NetConfig};
+use failure::Fail;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The Rust code of a package: its build script, and all the other source files.
+#[derive(Debug, Default)]
+pub struct Sources {
+    build_script: String,
+    code: String,
+}
+
+impl Sources {
+    /// Read the code of the package in `dir`, skipping `target` directories and any packages nested
+    /// inside it.
+    pub fn read(dir: &Path, manifest: &Manifest) -> Result<Self> {
+        let build_script = match &manifest.data["package"]["build"] {
+            item if item.as_bool() == Some(false) => None,
+            item => Some(dir.join(item.as_str().unwrap_or("build.rs"))),
+        };
+
+        let mut files = Vec::new();
+        collect_rust_files(dir, true, &mut files)?;
+        let mut sources = Sources::default();
+        for file in files {
+            let contents = fs::read_to_string(&file)
+                .chain_err(|| format!("Failed to read `{}`", file.display()))?;
+            let target = if Some(&file) == build_script.as_ref() {
+                &mut sources.build_script
+            } else {
+                &mut sources.code
+            };
+            target.push_str(&contents);
+            target.push('\n');
+        }
+        Ok(sources)
+    }
+
+    /// Whether the code that can use a section's dependencies refers to a dependency, by the name
+    /// it is referred to by in code (see `Resolved::code_name`).
+    ///
+    /// The build script is searched for every section: besides `[build-dependencies]`, it can
+    /// name other dependencies, e.g. in the code it generates, so those are never taken to be
+    /// unused either.
+    pub fn uses(&self, section: &str, code_name: &str) -> bool {
+        refers_to(&self.build_script, code_name)
+            || (section != "build-dependencies" && refers_to(&self.code, code_name))
+    }
+}
+
+/// What cargo resolves the dependencies of a package to: the names their libraries are referred to
+/// by in code, and which of them link a native library.
+#[derive(Debug, Default)]
+pub struct Resolved {
+    /// The name of each package's library, e.g. `rustc_serialize` for `rustc-serialize`
+    lib_names: HashMap<String, String>,
+    /// The packages with a `links` key, which are depended on for the native library they link
+    links: HashSet<String>,
+}
+
+impl Resolved {
+    /// Resolve the dependencies of the package at `manifest_path`, which may need to download
+    /// them
+    pub fn new(manifest_path: &Path, net: &NetConfig) -> Result<Self> {
+        let mut cmd = cargo_metadata::MetadataCommand::new();
+        cmd.manifest_path(manifest_path);
+        cmd.other_options(net.cargo_flags());
+        let metadata = cmd
+            .exec()
+            .map_err(|e| Error::from(e.compat()).chain_err(|| "Failed to resolve dependencies"))?;
+
+        let mut resolved = Resolved::default();
+        for package in metadata.packages {
+            let lib = package.targets.iter().find(|target| {
+                target
+                    .kind
+                    .iter()
+                    .any(|kind| ["lib", "rlib", "dylib", "proc-macro"].contains(&kind.as_str()))
+            });
+            if let Some(lib) = lib {
+                resolved
+                    .lib_names
+                    .insert(package.name.clone(), lib.name.replace('-', "_"));
+            }
+            if package.links.is_some() {
+                resolved.links.insert(package.name);
+            }
+        }
+        Ok(resolved)
+    }
+
+    /// The name that code refers to a dependency by, given its key in the manifest and its
+    /// package name: the key if it is renamed, and otherwise the name of its library, or its
+    /// package name (with `-` as `_`) if that isn't known.
+    pub fn code_name(&self, key: &str, package: &str) -> String {
+        if key != package {
+            return key.replace('-', "_");
+        }
+        self.lib_names
+            .get(package)
+            .cloned()
+            .unwrap_or_else(|| package.replace('-', "_"))
+    }
+
+    /// Whether a package is depended on for the native library it links, which its code needn't
+    /// refer to: it has a `links` key, or is named like a `-sys` crate.
+    pub fn links(&self, package: &str) -> bool {
+        self.links.contains(package) || package.ends_with("-sys")
+    }
+}
+
+/// Add the `.rs` files under `dir` to `files`.
+fn collect_rust_files(dir: &Path, is_root: bool, files: &mut Vec<PathBuf>) -> Result<()> {
+    if !is_root && dir.join("Cargo.toml").exists() {
+        return Ok(());
+    }
+    let entries = fs::read_dir(dir).chain_err(|| format!("Failed to read `{}`", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+        if path.is_dir() {
+            if name != "target" && !name.starts_with('.') {
+                collect_rust_files(&path, false, files)?;
+            }
+        } else if name.ends_with(".rs") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Whether code refers to a crate, with `extern crate`, `use`, or a path starting with it.
+fn refers_to(code: &str, crate_name: &str) -> bool {
+    let pattern = format!(
+        r"\bextern\s+crate\s+{0}\b|\buse\s+(::)?{0}\b|(^|[^\w:])(::)?{0}\s*::",
+        regex::escape(crate_name)
+    );
+    Regex::new(&pattern)
+        .map(|regex| regex.is_match(code))
+        .unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_references() {
+        assert!(refers_to("extern crate docopt;", "docopt"));
+        assert!(refers_to(
+            "#[macro_use]\nextern crate serde_derive;",
+            "serde_derive"
+        ));
+        assert!(refers_to("use semver::Version;", "semver"));
+        assert!(refers_to("pub use ::toml;", "toml"));
+        assert!(refers_to("let v = semver::Version::parse(s);", "semver"));
+        assert!(refers_to("regex::Regex::new(r)", "regex"));
+    }
+
+    #[test]
+    fn names_dependencies_as_code_does() {
+        let mut resolved = Resolved::default();
+        resolved
+            .lib_names
+            .insert("rustc-serialize".to_owned(), "rustc_serialize".to_owned());
+        resolved
+            .lib_names
+            .insert("fuzzy-matcher".to_owned(), "fuzzy".to_owned());
+        assert_eq!(
+            resolved.code_name("fuzzy-matcher", "fuzzy-matcher"),
+            "fuzzy"
+        );
+        assert_eq!(resolved.code_name("rand07", "rand"), "rand07");
+        assert_eq!(resolved.code_name("serde-json", "serde-json"), "serde_json");
+
+        resolved.links.insert("ring".to_owned());
+        assert!(resolved.links("ring"));
+        assert!(resolved.links("openssl-sys"));
+        assert!(!resolved.links("openssl"));
+    }
+
+    #[test]
+    fn searches_build_script_for_every_section() {
+        let sources = Sources {
+            build_script: "fn main() { cc::Build::new(); }\n\
+                           const GENERATED: &str = \"use serde::Deserialize;\";"
+                .to_owned(),
+            code: "use regex::Regex;".to_owned(),
+        };
+        assert!(sources.uses("build-dependencies", "cc"));
+        assert!(!sources.uses("build-dependencies", "regex"));
+        assert!(sources.uses("dependencies", "serde"));
+        assert!(sources.uses("dev-dependencies", "serde"));
+        assert!(sources.uses("dependencies", "regex"));
+        assert!(!sources.uses("dependencies", "docopt"));
+    }
+
+    #[test]
+    fn ignores_other_names() {
+        assert!(!refers_to("use semver_parser::Version;", "semver"));
+        assert!(!refers_to("use crate::toml::Value;", "toml"));
+        assert!(!refers_to("let pad = 3;", "pad"));
+        assert!(!refers_to("fn docopt() {}", "docopt"));
+    }
+}
@@ -0,0 +1,285 @@
+//! `cargo set-rust-version`
+
+use self::errors::*;
+use super::NetArgs;
+use crate::{
+    find, record_for_undo, workspace_members, workspace_root, Edits, LocalManifest, Manifest,
+};
+use error_chain::bail;
+use failure::Fail;
+use semver::Version;
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+use structopt::{clap::AppSettings, StructOpt};
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+
+mod errors {
+    error_chain::error_chain! {
+        links {
+            CargoEditLib(crate::Error, crate::ErrorKind);
+        }
+        foreign_links {
+            CargoMetadata(::failure::Compat<::cargo_metadata::Error>);
+            Io(::std::io::Error);
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(bin_name = "cargo")]
+enum Command {
+    /// Set the minimum supported Rust version (`rust-version`) of packages.
+    #[structopt(name = "set-rust-version")]
+    #[structopt(after_help = "\
+The version is written to the `rust-version` field of the `[package]` table, e.g. `1.56`. If the \
+'--current-toolchain' flag is supplied, the version of the installed `rustc` is used instead.
+
+All packages in the workspace will be updated if the `--workspace` flag is supplied. This is also \
+the default when run against a virtual manifest (a workspace root without a package of its own). \
+Packages that inherit their `rust-version` from the workspace (`rust-version = { workspace = true }`) \
+keep inheriting it, and the `rust-version` of the `[workspace.package]` table is set instead. As \
+with cargo, only the workspace's `default-members` are updated if it lists them, unless the \
+`--all-members` flag is supplied, and packages under its `exclude`d paths never are.
+
+Before anything is written, the `rust-version` of every dependency is checked, and the command \
+fails if any of them requires a newer Rust than the version being set. This needs the dependencies \
+to be resolved (and downloaded if necessary), which can be skipped with the \
+'--skip-dependency-check' flag.")]
+    SetRustVersion(Args),
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(setting = AppSettings::ColoredHelp)]
+struct Args {
+    /// Rust version to set, e.g. `1.56`.
+    #[structopt(
+        name = "rust-version",
+        required_unless = "current-toolchain",
+        conflicts_with = "current-toolchain"
+    )]
+    rust_version: Option<String>,
+
+    /// Use the version of the currently installed toolchain.
+    #[structopt(long = "current-toolchain")]
+    current_toolchain: bool,
+
+    /// Path to the manifest to update.
+    #[structopt(long = "manifest-path", value_name = "path", conflicts_with = "pkgid")]
+    manifest_path: Option<PathBuf>,
+
+    /// Package id of the crate to update.
+    #[structopt(
+        long = "package",
+        short = "p",
+        value_name = "pkgid",
+        conflicts_with = "workspace"
+    )]
+    pkgid: Option<String>,
+
+    /// Update all packages in the workspace.
+    #[structopt(long = "workspace", conflicts_with = "pkgid")]
+    workspace: bool,
+
+    /// Include workspace members left out of the workspace's `default-members`.
+    #[structopt(long = "all-members", conflicts_with = "pkgid")]
+    all_members: bool,
+
+    /// Don't check the version against the `rust-version` of dependencies.
+    #[structopt(long = "skip-dependency-check")]
+    skip_dependency_check: bool,
+
+    /// Print changes to be made without making them.
+    #[structopt(long = "dry-run")]
+    dry_run: bool,
+
+    #[structopt(flatten)]
+    net: NetArgs,
+}
+
+/// Parse a `rust-version` such as `1.56` or `1.56.1`, which may omit the patch version.
+fn parse_rust_version(version: &str) -> Result<Version> {
+    let mut full = version.to_owned();
+    for _ in version.split('.').count()..3 {
+        full.push_str(".0");
+    }
+    match Version::parse(&full) {
+        Ok(parsed) if parsed.pre.is_empty() && parsed.build.is_empty() => Ok(parsed),
+        _ => bail!(
+            "Invalid rust-version `{}`, expected a version like `1.56`",
+            version
+        ),
+    }
+}
+
+/// The `major.minor` version of the installed `rustc`.
+fn current_toolchain_version() -> Result<String> {
+    let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let output = process::Command::new(rustc)
+        .arg("--version")
+        .output()
+        .chain_err(|| "Failed to run `rustc --version`")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    // e.g. `rustc 1.56.0 (09c42c458 2021-10-18)` or `rustc 1.58.0-nightly (...)`
+    let version = stdout
+        .split_whitespace()
+        .nth(1)
+        .and_then(|v| Version::parse(v).ok())
+        .chain_err(|| {
+            format!(
+                "Unexpected output from `rustc --version`: {}",
+                stdout.trim()
+            )
+        })?;
+    Ok(format!("{}.{}", version.major, version.minor))
+}
+
+/// The `rust-version` of a package outside of the workspace, read from its manifest.
+fn dependency_rust_version(package: &cargo_metadata::Package) -> Result<Option<Version>> {
+    let contents = fs::read_to_string(&package.manifest_path)?;
+    let manifest: toml::Value = toml::from_str(&contents)
+        .chain_err(|| format!("Failed to parse the manifest of `{}`", package.name))?;
+    match manifest
+        .get("package")
+        .and_then(|p| p.get("rust-version"))
+        .and_then(toml::Value::as_str)
+    {
+        Some(version) => parse_rust_version(version).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn print_msg(package: &str, old: Option<&str>, new: &str) -> Result<()> {
+    let colorchoice = if atty::is(atty::Stream::Stdout) {
+        ColorChoice::Auto
+    } else {
+        ColorChoice::Never
+    };
+    let mut output = StandardStream::stdout(colorchoice);
+    output.set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))?;
+    write!(output, "{:>12}", "Setting")?;
+    output.reset()?;
+    match old {
+        Some(old) => writeln!(output, " {} rust-version {} -> {}", package, old, new)?,
+        None => writeln!(output, " {} rust-version to {}", package, new)?,
+    }
+    Ok(())
+}
+
+fn set_rust_version(args: Args) -> Result<()> {
+    let net = args.net.net_config()?;
+    let version = match args.rust_version {
+        Some(version) => version,
+        None if args.current_toolchain => current_toolchain_version()?,
+        None => unreachable!("clap requires either a version or `--current-toolchain`"),
+    };
+    let parsed_version = parse_rust_version(&version)?;
+
+    let mut cmd = cargo_metadata::MetadataCommand::new();
+    if args.skip_dependency_check {
+        cmd.no_deps();
+    }
+    if let Some(path) = &args.manifest_path {
+        cmd.manifest_path(path);
+    }
+    cmd.other_options(net.cargo_flags());
+    let metadata = cmd
+        .exec()
+        .map_err(|e| Error::from(e.compat()).chain_err(|| "Failed to get workspace metadata"))?;
+    let members: HashSet<_> = metadata.workspace_members.iter().collect();
+
+    if !args.skip_dependency_check {
+        let too_new = metadata
+            .packages
+            .iter()
+            .filter(|package| !members.contains(&package.id))
+            .map(|package| Ok((package, dependency_rust_version(package)?)))
+            .filter_map(|dependency| match dependency {
+                Ok((package, Some(msrv))) if msrv > parsed_version => Some(Ok(format!(
+                    "{} v{} (rust-version {})",
+                    package.name, package.version, msrv
+                ))),
+                Ok(_) => None,
+                Err(err) => Some(Err(err)),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if !too_new.is_empty() {
+            bail!(
+                "rust-version {} is lower than that of these dependencies:\n    {}",
+                version,
+                too_new.join("\n    ")
+            );
+        }
+    }
+
+    let packages = metadata
+        .packages
+        .iter()
+        .filter(|package| members.contains(&package.id));
+    let workspace = args.workspace
+        || (args.pkgid.is_none() && LocalManifest::find(&args.manifest_path)?.is_virtual());
+    let packages: Vec<_> = if workspace {
+        workspace_members(&metadata, args.all_members)?
+    } else if let Some(pkgid) = &args.pkgid {
+        vec![packages
+            .clone()
+            .find(|package| &package.name == pkgid)
+            .chain_err(|| format!("Package `{}` not found in the workspace", pkgid))?
+            .clone()]
+    } else {
+        let manifest_path = fs::canonicalize(find(&args.manifest_path)?)?;
+        vec![packages
+            .clone()
+            .find(|package| package.manifest_path == manifest_path)
+            // If we have successfully got metadata, but our manifest path does not correspond to a
+            // package, we must have been called against a virtual manifest.
+            .chain_err(|| {
+                "Found virtual manifest, but this command requires running against an \
+                 actual package in this workspace. Try adding `--workspace`."
+            })?
+            .clone()]
+    };
+
+    let mut edits = Edits::new();
+    let mut workspaces = HashSet::new();
+    for package in packages {
+        let mut manifest = LocalManifest::try_new(Path::new(&package.manifest_path))?;
+        if manifest.inherits_package_field("rust-version") {
+            // Members inheriting the version keep inheriting it, from the updated workspace root.
+            let root_path = workspace_root(&manifest.path)?.join("Cargo.toml");
+            if !workspaces.insert(root_path.clone()) {
+                continue;
+            }
+            let mut root = Manifest::open(&Some(root_path.clone()))?;
+            print_msg("workspace", root.workspace_rust_version(), &version)?;
+            root.set_workspace_rust_version(&version)?;
+            if !args.dry_run {
+                edits.save(&root, &root_path)?;
+            }
+            continue;
+        }
+        print_msg(&package.name, manifest.rust_version(), &version)?;
+        manifest.set_rust_version(&version)?;
+        if !args.dry_run {
+            edits.save(&manifest, &manifest.path)?;
+        }
+    }
+    record_for_undo(&edits.write()?)?;
+
+    Ok(())
+}
+
+/// Run `cargo set-rust-version` with a command line as cargo passes it, e.g. from
+/// `std::env::args_os`.
+pub fn run(args: impl IntoIterator<Item = OsString>) {
+    let args: Command = Command::from_iter(args);
+    let Command::SetRustVersion(args) = args;
+
+    if let Err(err) = set_rust_version(args) {
+        super::exit_with_error(&err);
+    }
+}
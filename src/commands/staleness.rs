@@ -0,0 +1,579 @@
+//! `cargo staleness`
+
+use self::errors::*;
+use super::NetArgs;
+use crate::{
+    find, get_latest_dependency, get_latest_matching, get_published_versions, registry_source,
+    update_registry_index, workspace_members, LocalManifest, Lockfile, NetConfig, RegistrySource,
+};
+use error_chain::bail;
+use failure::Fail;
+use semver::{Version, VersionReq};
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use structopt::{clap::AppSettings, StructOpt};
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+use url::Url;
+
+mod errors {
+    error_chain::error_chain! {
+        links {
+            CargoEditLib(crate::Error, crate::ErrorKind);
+        }
+        foreign_links {
+            CargoMetadata(::failure::Compat<::cargo_metadata::Error>);
+            Io(::std::io::Error);
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(bin_name = "cargo")]
+enum Command {
+    /// Report how far the lock file is behind what the requirements allow, and what is newest.
+    #[structopt(name = "staleness")]
+    #[structopt(after_help = "\
+For each dependency on a registry, the requirement in the manifest is listed along with the \
+version in `Cargo.lock` it resolves to, the newest version the requirement allows, and the newest \
+version of the crate. Prereleases and yanked versions aren't counted.
+
+The version in use is the locked one, or the newest the requirement allows if there is no lock \
+file. For each dependency, `behind` is the number of releases newer than the version in use, and \
+`age` the number of days since the version in use was published, where the registry records that \
+(the crates.io API is asked for crates from crates.io). Figures for the whole report follow the \
+table: the releases behind in all and on average, how many dependencies are up to date, and the \
+mean, median and greatest age of the versions in use. A crate used at the same version by several \
+packages is counted once. The publish times the crates.io API reports are checked against the \
+registry index first, and a checksum that differs from the index's fails the report, as the \
+response may have been tampered with. The '--no-verify' flag skips this check.
+
+A dependency whose locked version is a minor or major step behind the newest version its \
+requirement allows is flagged, as `cargo update` would move it that far. A newest version that the \
+requirement doesn't allow takes `cargo upgrade` instead.
+
+All packages in the workspace are reported on if the `--workspace` flag is supplied. This is also \
+the default when run against a virtual manifest (a workspace root without a package of its own). \
+As with cargo, only the workspace's `default-members` are reported on if it lists them, unless the \
+`--all-members` flag is supplied.
+
+Nothing is written. The registry index is updated first, unless offline.")]
+    Staleness(Args),
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(setting = AppSettings::ColoredHelp)]
+struct Args {
+    /// Dependencies to report on (all registry dependencies by default).
+    #[structopt(name = "dependency")]
+    dependencies: Vec<String>,
+
+    /// Path to the manifest to report on.
+    #[structopt(long = "manifest-path", value_name = "path", conflicts_with = "pkgid")]
+    manifest_path: Option<PathBuf>,
+
+    /// Package id of the crate to report on.
+    #[structopt(
+        long = "package",
+        short = "p",
+        value_name = "pkgid",
+        conflicts_with = "workspace"
+    )]
+    pkgid: Option<String>,
+
+    /// Report on all packages in the workspace.
+    #[structopt(long = "workspace", conflicts_with = "pkgid")]
+    workspace: bool,
+
+    /// Include workspace members left out of the workspace's `default-members`.
+    #[structopt(long = "all-members", conflicts_with = "pkgid")]
+    all_members: bool,
+
+    /// Resolve crates.io dependencies from a local registry or `cargo vendor` directory.
+    #[structopt(long = "local-registry", value_name = "path")]
+    local_registry: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    net: NetArgs,
+
+    /// Use what the crates.io API reports about versions without checking it against the registry
+    /// index.
+    #[structopt(long = "no-verify")]
+    no_verify: bool,
+}
+
+/// A registry dependency in a manifest.
+#[derive(Debug, PartialEq, Eq)]
+struct Entry {
+    name: String,
+    requirement: String,
+    /// The alternative registry the crate is from, if any.
+    registry: Option<String>,
+}
+
+/// The registry dependencies of a manifest, which `selected` names by entry or crate name unless
+/// it is empty. A crate required the same way in several tables is listed once.
+fn entries(manifest: &LocalManifest, selected: &[String]) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    for (_, table) in manifest.get_sections() {
+        let table = table.as_table_like().expect("Unexpected non-table");
+        for (key, item) in table.iter() {
+            let (name, requirement, registry) = match item.as_table_like() {
+                Some(t) if t.get("git").is_some() || t.get("path").is_some() => continue,
+                Some(t) => (
+                    t.get("package").and_then(|p| p.as_str()).unwrap_or(key),
+                    t.get("version").and_then(|v| v.as_str()),
+                    t.get("registry").and_then(|r| r.as_str()),
+                ),
+                None => (key, item.as_str(), None),
+            };
+            let requirement = match requirement {
+                Some(requirement) => requirement,
+                None => continue,
+            };
+            if !selected.is_empty() && !selected.iter().any(|s| s == key || s == name) {
+                continue;
+            }
+            let entry = Entry {
+                name: name.to_owned(),
+                requirement: requirement.to_owned(),
+                registry: registry.map(ToOwned::to_owned),
+            };
+            if !entries.contains(&entry) {
+                entries.push(entry);
+            }
+        }
+    }
+    entries.sort_by(|a, b| (&a.name, &a.requirement).cmp(&(&b.name, &b.requirement)));
+    entries
+}
+
+/// Looks versions up in registries, updating each registry's index once.
+struct Registries {
+    net: NetConfig,
+    updated: HashSet<Url>,
+    local_registry: Option<RegistrySource>,
+}
+
+impl Registries {
+    /// The source a dependency's versions are read from.
+    fn source(&mut self, entry: &Entry, manifest_path: &Path) -> Result<RegistrySource> {
+        if let (None, Some(source)) = (&entry.registry, &self.local_registry) {
+            return Ok(source.clone());
+        }
+        let source = registry_source(manifest_path, entry.registry.as_deref())?;
+        if let RegistrySource::Remote(url) = &source {
+            let testing = std::env::var("CARGO_IS_TEST").is_ok();
+            if !self.net.offline && !testing && self.updated.insert(url.clone()) {
+                if let Err(err) = update_registry_index(url, false, &self.net) {
+                    eprintln!(
+                        "WARN: Failed to update the registry index, its local copy may be out \
+                         of date: {}",
+                        err
+                    );
+                }
+            }
+        }
+        Ok(source)
+    }
+}
+
+/// A line of the report.
+#[derive(Debug)]
+struct Row {
+    name: String,
+    requirement: String,
+    locked: Option<Version>,
+    allowed: Option<Version>,
+    latest: Option<Version>,
+    /// How many releases are newer than the version in use, if the versions are known.
+    releases_behind: Option<usize>,
+    /// How many days ago the version in use was published, if that is known.
+    age: Option<u64>,
+}
+
+impl Row {
+    /// The version in use: the locked one, or the one a fresh resolve would pick.
+    fn current(&self) -> Option<&Version> {
+        self.locked.as_ref().or(self.allowed.as_ref())
+    }
+
+    /// How far the locked version is behind the newest one the requirement allows, if that is a
+    /// `minor` or `major` step.
+    fn behind(&self) -> Option<&'static str> {
+        match (&self.locked, &self.allowed) {
+            (Some(locked), Some(allowed)) if allowed.major != locked.major => Some("major"),
+            (Some(locked), Some(allowed)) if allowed.minor != locked.minor => Some("minor"),
+            _ => None,
+        }
+    }
+}
+
+/// Figures for the whole report, counting each crate once per version in use.
+#[derive(Debug, Default, PartialEq)]
+struct Stats {
+    /// The dependencies whose releases are known.
+    dependencies: usize,
+    /// Those of them on their newest release.
+    up_to_date: usize,
+    /// Their releases behind, added up.
+    releases_behind: usize,
+    /// The ages of the versions in use whose publish time is known, in days, youngest first.
+    ages: Vec<u64>,
+}
+
+impl Stats {
+    fn new(rows: &[&Row]) -> Self {
+        let mut stats = Stats::default();
+        let mut seen = HashSet::new();
+        for row in rows {
+            if !seen.insert((&row.name, row.current())) {
+                continue;
+            }
+            if let Some(releases_behind) = row.releases_behind {
+                stats.dependencies += 1;
+                stats.releases_behind += releases_behind;
+                if releases_behind == 0 {
+                    stats.up_to_date += 1;
+                }
+            }
+            stats.ages.extend(row.age);
+        }
+        stats.ages.sort_unstable();
+        stats
+    }
+
+    fn median_age(&self) -> Option<u64> {
+        let middle = self.ages.len() / 2;
+        match self.ages.len() {
+            0 => None,
+            len if len % 2 == 0 => Some((self.ages[middle - 1] + self.ages[middle]) / 2),
+            _ => Some(self.ages[middle]),
+        }
+    }
+
+    fn mean_age(&self) -> Option<u64> {
+        if self.ages.is_empty() {
+            return None;
+        }
+        Some(self.ages.iter().sum::<u64>() / self.ages.len() as u64)
+    }
+
+    fn print(&self) {
+        if self.dependencies > 0 {
+            println!(
+                "Releases behind: {} in all, {:.1} per dependency, with {} of {} dependencies up \
+                 to date",
+                self.releases_behind,
+                self.releases_behind as f64 / self.dependencies as f64,
+                self.up_to_date,
+                self.dependencies
+            );
+        }
+        if let (Some(mean), Some(median), Some(oldest)) =
+            (self.mean_age(), self.median_age(), self.ages.last())
+        {
+            println!(
+                "Age of the versions in use: {} days on average, {} days median, {} days at most",
+                mean, median, oldest
+            );
+        }
+    }
+}
+
+/// Whole days from one time to another, or none if it is later.
+fn days_between(from: SystemTime, to: SystemTime) -> u64 {
+    to.duration_since(from)
+        .map_or(0, |elapsed| elapsed.as_secs() / (24 * 60 * 60))
+}
+
+fn show(version: &Option<Version>) -> String {
+    version
+        .as_ref()
+        .map_or_else(|| "-".to_owned(), ToString::to_string)
+}
+
+fn print_rows(heading: Option<&str>, rows: &[Row]) -> Result<()> {
+    let colorchoice = if atty::is(atty::Stream::Stdout) {
+        ColorChoice::Auto
+    } else {
+        ColorChoice::Never
+    };
+    let mut output = StandardStream::stdout(colorchoice);
+    if let Some(heading) = heading {
+        output.set_color(ColorSpec::new().set_bold(true))?;
+        writeln!(output, "{}:", heading)?;
+        output.reset()?;
+    }
+
+    let header = [
+        "name",
+        "requirement",
+        "locked",
+        "allowed",
+        "latest",
+        "behind",
+        "age",
+    ];
+    let cells: Vec<[String; 7]> = rows
+        .iter()
+        .map(|row| {
+            [
+                row.name.clone(),
+                row.requirement.clone(),
+                show(&row.locked),
+                show(&row.allowed),
+                show(&row.latest),
+                row.releases_behind
+                    .map_or_else(|| "-".to_owned(), |behind| behind.to_string()),
+                row.age
+                    .map_or_else(|| "-".to_owned(), |age| format!("{}d", age)),
+            ]
+        })
+        .collect();
+    let mut widths = header.map(str::len);
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let line = |cells: &[String]| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+    writeln!(
+        output,
+        "{}",
+        line(&header.map(ToOwned::to_owned)).trim_end()
+    )?;
+    for (row, cells) in rows.iter().zip(&cells) {
+        write!(output, "{}", line(cells).trim_end())?;
+        match (row.behind(), &row.locked) {
+            (Some(step), _) => {
+                output.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)).set_bold(true))?;
+                write!(output, "  lock is a {} step behind", step)?;
+                output.reset()?;
+            }
+            (None, None) => write!(output, "  not locked")?,
+            (None, Some(_)) => {}
+        }
+        writeln!(output)?;
+    }
+    Ok(())
+}
+
+fn handle_staleness(args: Args) -> Result<()> {
+    let mut cmd = cargo_metadata::MetadataCommand::new();
+    cmd.no_deps();
+    if let Some(path) = &args.manifest_path {
+        cmd.manifest_path(path);
+    }
+    let metadata = cmd
+        .exec()
+        .map_err(|e| Error::from(e.compat()).chain_err(|| "Failed to get workspace metadata"))?;
+    let members: HashSet<_> = metadata.workspace_members.iter().collect();
+
+    let packages = metadata
+        .packages
+        .iter()
+        .filter(|package| members.contains(&package.id));
+    let workspace = args.workspace
+        || (args.pkgid.is_none() && LocalManifest::find(&args.manifest_path)?.is_virtual());
+    let packages: Vec<_> = if workspace {
+        workspace_members(&metadata, args.all_members)?
+    } else if let Some(pkgid) = &args.pkgid {
+        vec![packages
+            .clone()
+            .find(|package| &package.name == pkgid)
+            .chain_err(|| format!("Package `{}` not found in the workspace", pkgid))?
+            .clone()]
+    } else {
+        let manifest_path = fs::canonicalize(find(&args.manifest_path)?)?;
+        vec![packages
+            .clone()
+            .find(|package| package.manifest_path == manifest_path)
+            // If we have successfully got metadata, but our manifest path does not correspond to a
+            // package, we must have been called against a virtual manifest.
+            .chain_err(|| {
+                "Found virtual manifest, but this command requires running against an \
+                 actual package in this workspace. Try adding `--workspace`."
+            })?
+            .clone()]
+    };
+
+    let mut manifests = Vec::new();
+    for package in packages {
+        let manifest = LocalManifest::try_new(Path::new(&package.manifest_path))?;
+        let entries = entries(&manifest, &args.dependencies);
+        manifests.push((package, manifest, entries));
+    }
+    for name in &args.dependencies {
+        let found = manifests
+            .iter()
+            .flat_map(|(_, _, entries)| entries)
+            .any(|entry| &entry.name == name);
+        if !found {
+            bail!("The dependency `{}` could not be found", name);
+        }
+    }
+
+    let locked = Lockfile::read_or_default(&metadata.workspace_root.join("Cargo.lock"))?.package;
+    let mut registries = Registries {
+        net: NetConfig {
+            verify_api: !args.no_verify,
+            ..args.net.net_config()?
+        },
+        updated: HashSet::new(),
+        local_registry: args
+            .local_registry
+            .as_deref()
+            .map(RegistrySource::from_local_path),
+    };
+    let several = manifests.len() > 1;
+    let now = SystemTime::now();
+    let mut all_rows = Vec::new();
+    for (i, (package, manifest, entries)) in manifests.iter().enumerate() {
+        let mut rows = Vec::new();
+        for entry in entries {
+            // Read as cargo does, where `1.0` means `^1.0` rather than `~1.0`.
+            let requirement = entry.requirement.trim();
+            let requirement = if requirement.starts_with(|c: char| c.is_ascii_digit()) {
+                format!("^{}", requirement)
+            } else {
+                requirement.to_owned()
+            };
+            let requirement = VersionReq::parse(&requirement).chain_err(|| {
+                format!(
+                    "Invalid requirement `{}` for `{}`",
+                    entry.requirement, entry.name
+                )
+            })?;
+            let locked = locked
+                .iter()
+                .filter(|package| {
+                    package.name == entry.name
+                        && package.source.is_some()
+                        && package.is_from_crates_io() == entry.registry.is_none()
+                        && requirement.matches(&package.version)
+                })
+                .map(|package| package.version.clone())
+                .max();
+
+            let source = Some(registries.source(entry, &manifest.path)?);
+            let allowed = get_latest_matching(&entry.name, &requirement, &manifest.path, &source)?;
+            let latest = get_latest_dependency(&entry.name, false, &manifest.path, &source)?;
+            let latest = latest.version().and_then(|v| Version::parse(v).ok());
+            let versions =
+                get_published_versions(&entry.name, &manifest.path, &source, &registries.net)?;
+            let mut row = Row {
+                name: entry.name.clone(),
+                requirement: entry.requirement.clone(),
+                locked,
+                allowed,
+                latest,
+                releases_behind: None,
+                age: None,
+            };
+            if let Some(current) = row.current().cloned().filter(|_| !versions.is_empty()) {
+                let newer = versions
+                    .iter()
+                    .filter(|v| !v.version.is_prerelease() && v.version > current);
+                row.releases_behind = Some(newer.count());
+                row.age = versions
+                    .iter()
+                    .find(|v| v.version == current)
+                    .and_then(|v| v.published)
+                    .map(|published| days_between(published, now));
+            }
+            rows.push(row);
+        }
+
+        if several && i > 0 {
+            println!();
+        }
+        let heading = format!("{} ({})", package.name, package.manifest_path.display());
+        print_rows(
+            Some(&heading).filter(|_| several).map(String::as_str),
+            &rows,
+        )?;
+        all_rows.extend(rows);
+    }
+
+    let rows: Vec<_> = all_rows.iter().collect();
+    if !rows.is_empty() {
+        println!();
+        Stats::new(&rows).print();
+    }
+    let behind = rows.iter().filter(|row| row.behind().is_some()).count();
+    if behind > 0 {
+        println!(
+            "{} of {} dependencies are locked a minor or major step behind what their \
+             requirement allows, which `cargo update` would pick up",
+            behind,
+            rows.len()
+        );
+    }
+    Ok(())
+}
+
+/// Run `cargo staleness` with a command line as cargo passes it, e.g. from
+/// `std::env::args_os`.
+pub fn run(args: impl IntoIterator<Item = OsString>) {
+    let args: Command = Command::from_iter(args);
+    let Command::Staleness(args) = args;
+
+    if let Err(err) = handle_staleness(args) {
+        super::exit_with_error(&err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(name: &str, locked: &str, releases_behind: Option<usize>, age: Option<u64>) -> Row {
+        Row {
+            name: name.to_owned(),
+            requirement: locked.to_owned(),
+            locked: Version::parse(locked).ok(),
+            allowed: None,
+            latest: None,
+            releases_behind,
+            age,
+        }
+    }
+
+    #[test]
+    fn sums_up_releases_behind_and_ages() {
+        let rows = [
+            row("a", "1.0.0", Some(0), Some(10)),
+            row("b", "0.1.0", Some(3), Some(400)),
+            // Used by another package at the same version.
+            row("b", "0.1.0", Some(3), Some(400)),
+            row("c", "2.0.0", Some(2), None),
+            row("d", "1.0.0", None, Some(30)),
+        ];
+        let stats = Stats::new(&rows.iter().collect::<Vec<_>>());
+
+        assert_eq!(
+            stats,
+            Stats {
+                dependencies: 3,
+                up_to_date: 1,
+                releases_behind: 5,
+                ages: vec![10, 30, 400],
+            }
+        );
+        assert_eq!(stats.median_age(), Some(30));
+        assert_eq!(stats.mean_age(), Some(146));
+        assert_eq!(Stats::default().median_age(), None);
+    }
+}
@@ -0,0 +1,94 @@
+//! `cargo unpin`
+
+use self::errors::*;
+use super::NetArgs;
+use crate::{pin_dependencies, record_for_undo, PinDirection, PinOptions};
+use std::ffi::OsString;
+use std::path::PathBuf;
+use structopt::{clap::AppSettings, StructOpt};
+
+mod errors {
+    error_chain::error_chain! {
+        links {
+            CargoEditLib(crate::Error, crate::ErrorKind);
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(bin_name = "cargo")]
+enum Command {
+    /// Turn exact `=x.y.z` requirements back into caret requirements.
+    #[structopt(name = "unpin")]
+    #[structopt(after_help = "\
+Each requirement on a single exact version, e.g. `=1.2.3`, is replaced by the caret requirement \
+`1.2.3`, which also allows later semver compatible versions. Other requirements are left alone. \
+Nothing is looked up, so the network isn't accessed.
+
+All packages in the workspace will be unpinned if the `--workspace` flag is supplied. This is also \
+the default when run against a virtual manifest (a workspace root without a package of its own). \
+As with cargo, only the workspace's `default-members` are unpinned if it lists them, unless the \
+`--all-members` flag is supplied.")]
+    Unpin(Args),
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(setting = AppSettings::ColoredHelp)]
+struct Args {
+    /// Dependencies to unpin (all pinned dependencies by default).
+    #[structopt(name = "dependency")]
+    dependencies: Vec<String>,
+
+    /// Path to the manifest to unpin dependencies in.
+    #[structopt(long = "manifest-path", value_name = "path", conflicts_with = "pkgid")]
+    manifest_path: Option<PathBuf>,
+
+    /// Package id of the crate to unpin dependencies in.
+    #[structopt(
+        long = "package",
+        short = "p",
+        value_name = "pkgid",
+        conflicts_with = "workspace"
+    )]
+    pkgid: Option<String>,
+
+    /// Unpin dependencies in all packages in the workspace.
+    #[structopt(long = "workspace", conflicts_with = "pkgid")]
+    workspace: bool,
+
+    /// Include workspace members left out of the workspace's `default-members`.
+    #[structopt(long = "all-members", conflicts_with = "pkgid")]
+    all_members: bool,
+
+    /// Print changes to be made without making them.
+    #[structopt(long = "dry-run")]
+    dry_run: bool,
+
+    #[structopt(flatten)]
+    net: NetArgs,
+}
+
+fn handle_unpin(args: Args) -> Result<()> {
+    let options = PinOptions {
+        dependencies: args.dependencies,
+        manifest_path: args.manifest_path,
+        pkgid: args.pkgid,
+        workspace: args.workspace,
+        all_members: args.all_members,
+        dry_run: args.dry_run,
+        net: args.net.net_config()?,
+    };
+    record_for_undo(&pin_dependencies(PinDirection::Unpin, &options)?)?;
+    Ok(())
+}
+
+/// Run `cargo unpin` with a command line as cargo passes it, e.g. from
+/// `std::env::args_os`.
+pub fn run(args: impl IntoIterator<Item = OsString>) {
+    let args: Command = Command::from_iter(args);
+    let Command::Unpin(args) = args;
+
+    if let Err(err) = handle_unpin(args) {
+        super::exit_with_error(&err);
+    }
+}
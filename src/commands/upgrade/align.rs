@@ -0,0 +1,282 @@
+//! Aligning crates that workspace members require at semver incompatible versions, for `--align`.
+
+use super::errors::*;
+use super::explain::semver_compatible;
+use super::{dependency_entry, is_version_dep, registry_name};
+use crate::{requirement_floor, Dependency, LocalManifest};
+use error_chain::bail;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+/// How to align the members requiring a crate at incompatible versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    /// On the highest of their requirements, or the version upgraded to.
+    Highest,
+    /// On the lowest of their requirements, without upgrading it.
+    Lowest,
+    /// Leave the crate out of the run.
+    Skip,
+}
+
+impl Align {
+    /// The accepted values of `--align`.
+    pub const VARIANTS: &'static [&'static str] = &["highest", "lowest", "skip"];
+}
+
+impl FromStr for Align {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "highest" => Ok(Align::Highest),
+            "lowest" => Ok(Align::Lowest),
+            "skip" => Ok(Align::Skip),
+            _ => bail!("Unknown alignment `{}`", s),
+        }
+    }
+}
+
+/// A crate that members require at semver incompatible versions.
+#[derive(Debug)]
+pub struct Conflict {
+    /// The crate, with its registry.
+    dependency: Dependency,
+    /// Each requirement, with the members that have it, lowest first.
+    requirements: Vec<(String, Vec<String>)>,
+}
+
+impl Conflict {
+    /// The requirements and their members, e.g. `0.2 (in two), 0.3 (in one)`.
+    fn describe(&self) -> String {
+        self.requirements
+            .iter()
+            .map(|(req, members)| format!("{} (in {})", req, members.join(", ")))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// What to do with the crates that members require at incompatible versions.
+#[derive(Debug, Default)]
+pub struct Alignments {
+    /// The requirements to set every entry of a crate to, instead of upgrading it.
+    pub requirements: Vec<(Dependency, String)>,
+    /// The crates left out of the run.
+    pub skipped: Vec<String>,
+}
+
+impl Alignments {
+    /// Whether the crate is aligned or skipped, rather than upgraded as usual.
+    pub fn excludes(&self, name: &str) -> bool {
+        self.skipped.iter().any(|skipped| skipped == name)
+            || self.requirements.iter().any(|(dep, _)| dep.name == name)
+    }
+}
+
+/// The crates that members require at semver incompatible versions, i.e. where the lowest versions
+/// of two requirements are incompatible (e.g. `0.2` and `0.3`, or `1.0` and `2.0`), among those
+/// `in_run` picks.
+///
+/// A member depending on a crate at incompatible versions itself (e.g. with one entry renamed)
+/// does so on purpose, so that crate is left alone. So are entries inherited from the workspace.
+pub fn find_conflicts<'a>(
+    manifests: impl IntoIterator<Item = &'a (LocalManifest, cargo_metadata::Package)>,
+    in_run: impl Fn(&str) -> bool,
+) -> Vec<Conflict> {
+    // The requirements on each crate, by member.
+    let mut crates: BTreeMap<(String, Option<String>), BTreeMap<String, Vec<String>>> =
+        BTreeMap::new();
+    for (manifest, package) in manifests {
+        for dependency in package.dependencies.iter().filter(|d| is_version_dep(d)) {
+            let inherited = dependency_entry(manifest, dependency)
+                .and_then(|entry| entry.as_table_like())
+                .is_some_and(|entry| entry.get("workspace").is_some());
+            if inherited || !in_run(&dependency.name) {
+                continue;
+            }
+            let requirement = dependency_entry(manifest, dependency)
+                .and_then(|entry| match entry.as_table_like() {
+                    Some(entry) => entry.get("version").and_then(|v| v.as_str()),
+                    None => entry.as_str(),
+                })
+                .unwrap_or("*")
+                .trim()
+                .to_owned();
+            crates
+                .entry((dependency.name.clone(), registry_name(manifest, dependency)))
+                .or_default()
+                .entry(package.name.clone())
+                .or_default()
+                .push(requirement);
+        }
+    }
+
+    let mut conflicts = Vec::new();
+    for ((name, registry), members) in crates {
+        if members.values().any(|reqs| has_incompatible(reqs)) {
+            continue;
+        }
+        let mut requirements: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (member, reqs) in members {
+            requirements
+                .entry(reqs[0].clone())
+                .or_default()
+                .push(member);
+        }
+        let reqs: Vec<String> = requirements.keys().cloned().collect();
+        if !has_incompatible(&reqs) {
+            continue;
+        }
+        let mut requirements: Vec<_> = requirements.into_iter().collect();
+        requirements.sort_by_key(|(req, _)| requirement_floor(req));
+        let mut dependency = Dependency::new(&name);
+        if let Some(registry) = &registry {
+            dependency = dependency.set_registry(registry);
+        }
+        conflicts.push(Conflict {
+            dependency,
+            requirements,
+        });
+    }
+    conflicts
+}
+
+/// Whether any two of the requirements are semver incompatible, going by their lowest versions.
+fn has_incompatible(requirements: &[String]) -> bool {
+    let floors: Vec<_> = requirements
+        .iter()
+        .filter_map(|req| requirement_floor(req))
+        .collect();
+    floors
+        .iter()
+        .any(|a| floors.iter().any(|b| !semver_compatible(a, b)))
+}
+
+/// Decide what to do with each conflict: as `align` says, or else as the user answers when run in
+/// a terminal. Without either, the conflict is only warned about. Each decision is reported.
+///
+/// `upgrading` is whether crates are upgraded to their latest version, which aligns them on it, or
+/// set to their locked versions, which doesn't.
+pub fn decide(
+    conflicts: &[Conflict],
+    align: Option<Align>,
+    upgrading: bool,
+    precise: bool,
+) -> Result<Alignments> {
+    let interactive = atty::is(atty::Stream::Stdin) && atty::is(atty::Stream::Stdout);
+    let mut alignments = Alignments::default();
+    for conflict in conflicts {
+        let name = &conflict.dependency.name;
+        let decision = match align {
+            Some(align) => Some(align),
+            None if interactive => ask(conflict)?,
+            None => None,
+        };
+        let (requirement, which) = match decision {
+            None => {
+                eprintln!(
+                    "WARN: Members require incompatible versions of `{}`: {}. Use '--align' to \
+                     align them.",
+                    name,
+                    conflict.describe()
+                );
+                continue;
+            }
+            Some(Align::Skip) => {
+                eprintln!(
+                    "note: Skipping `{}`, which members require at incompatible versions: {}",
+                    name,
+                    conflict.describe()
+                );
+                alignments.skipped.push(name.clone());
+                continue;
+            }
+            Some(Align::Highest) if upgrading => {
+                eprintln!(
+                    "note: Aligning `{}` on the version it is upgraded to, as members require \
+                     incompatible versions: {}",
+                    name,
+                    conflict.describe()
+                );
+                continue;
+            }
+            Some(Align::Highest) => (conflict.requirements.last(), "highest"),
+            Some(Align::Lowest) => (conflict.requirements.first(), "lowest"),
+        };
+        let requirement = &requirement.expect("conflicts have requirements").0;
+        eprintln!(
+            "note: Aligning `{}` on {}, the {} of the incompatible versions members require: {}",
+            name,
+            requirement,
+            which,
+            conflict.describe()
+        );
+        // Exact requirements are written on a single version.
+        let requirement = match requirement_floor(requirement) {
+            Some(floor) if precise => floor.to_string(),
+            _ => requirement.clone(),
+        };
+        alignments
+            .requirements
+            .push((conflict.dependency.clone(), requirement));
+    }
+    Ok(alignments)
+}
+
+/// Ask how to align the members requiring a crate at incompatible versions. `None` is returned if
+/// the answer is left empty.
+fn ask(conflict: &Conflict) -> Result<Option<Align>> {
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+    writeln!(
+        output,
+        "Members require incompatible versions of `{}`: {}",
+        conflict.dependency.name,
+        conflict.describe()
+    )
+    .chain_err(|| "Failed to print conflict")?;
+    let stdin = io::stdin();
+    loop {
+        write!(
+            output,
+            "Align on the [h]ighest or [l]owest of them, or [s]kip it (or nothing to leave it): "
+        )
+        .and_then(|_| output.flush())
+        .chain_err(|| "Failed to print question")?;
+        let mut answer = String::new();
+        let read = stdin
+            .lock()
+            .read_line(&mut answer)
+            .chain_err(|| "Failed to read answer")?;
+        if read == 0 {
+            return Ok(None);
+        }
+        match answer.trim() {
+            "" => return Ok(None),
+            "h" | "highest" => return Ok(Some(Align::Highest)),
+            "l" | "lowest" => return Ok(Some(Align::Lowest)),
+            "s" | "skip" => return Ok(Some(Align::Skip)),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reqs(reqs: &[&str]) -> Vec<String> {
+        reqs.iter().map(|req| req.to_string()).collect()
+    }
+
+    #[test]
+    fn incompatible_requirements() {
+        assert!(has_incompatible(&reqs(&["0.2", "0.3"])));
+        assert!(has_incompatible(&reqs(&["1.0", "=2.0.1"])));
+        assert!(!has_incompatible(&reqs(&["1.0", "1.4"])));
+        assert!(!has_incompatible(&reqs(&["0.2.1", "^0.2.5"])));
+        assert!(!has_incompatible(&reqs(&["0.2", "*"])));
+    }
+}
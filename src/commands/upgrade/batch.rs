@@ -0,0 +1,126 @@
+//! Git plumbing for `--batch`, which upgrades each dependency on a branch of its own.
+
+use super::errors::*;
+use error_chain::bail;
+use std::path::{Path, PathBuf};
+
+/// The prefix of the branches that `--batch` creates.
+const BRANCH_PREFIX: &str = "cargo-upgrade/";
+
+/// The branch for upgrading a dependency to a version, e.g. `cargo-upgrade/serde-1.0.118`.
+pub fn branch_name(dependency: &str, version: &str) -> String {
+    format!("{}{}-{}", BRANCH_PREFIX, dependency, version)
+}
+
+/// The git repository that the manifests being upgraded are in.
+#[derive(Debug)]
+pub struct Repo {
+    dir: PathBuf,
+}
+
+impl Repo {
+    /// The repository containing `path`, which must have no uncommitted changes to tracked files.
+    pub fn open(path: &Path) -> Result<Self> {
+        let dir = path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        let repo = Repo {
+            dir: dir.to_owned(),
+        };
+        let top = repo
+            .git(&["rev-parse", "--show-toplevel"])
+            .chain_err(|| "`--batch` needs the manifests to be in a git repository")?;
+        let repo = Repo {
+            dir: PathBuf::from(top),
+        };
+        if !repo
+            .git(&["status", "--porcelain", "--untracked-files=no"])?
+            .is_empty()
+        {
+            bail!("`--batch` needs a clean working tree, so commit or stash your changes first");
+        }
+        Ok(repo)
+    }
+
+    /// Run git in the repository, returning its trimmed output.
+    fn git(&self, args: &[&str]) -> Result<String> {
+        let capture = subprocess::Exec::cmd("git")
+            .args(args)
+            .cwd(&self.dir)
+            .env_remove("GIT_DIR")
+            .stdout(subprocess::Redirection::Pipe)
+            .stderr(subprocess::Redirection::Pipe)
+            .capture()
+            .chain_err(|| "Failed to run git")?;
+        if !capture.success() {
+            bail!(
+                "`git {}` failed: {}",
+                args.join(" "),
+                capture.stderr_str().trim()
+            );
+        }
+        Ok(capture.stdout_str().trim().to_owned())
+    }
+
+    /// The branch that is checked out, or the commit if the HEAD is detached.
+    pub fn head(&self) -> Result<String> {
+        match self.git(&["symbolic-ref", "--quiet", "--short", "HEAD"]) {
+            Ok(branch) => Ok(branch),
+            Err(_) => self.git(&["rev-parse", "HEAD"]),
+        }
+    }
+
+    /// Whether the repository tracks a lock file, which each branch then updates.
+    pub fn tracks_lockfile(&self) -> Result<bool> {
+        let files = self.git(&["ls-files", "--", "Cargo.lock", "**/Cargo.lock"])?;
+        Ok(!files.is_empty())
+    }
+
+    /// Create a branch at `base` and check it out.
+    pub fn create_branch(&self, branch: &str, base: &str) -> Result<()> {
+        self.git(&["checkout", "--quiet", "-b", branch, base])?;
+        Ok(())
+    }
+
+    /// Check out a branch or commit, throwing away any changes to tracked files.
+    pub fn checkout(&self, target: &str) -> Result<()> {
+        self.git(&["checkout", "--quiet", "--force", target])?;
+        Ok(())
+    }
+
+    /// Delete a branch that isn't checked out.
+    pub fn delete_branch(&self, branch: &str) -> Result<()> {
+        self.git(&["branch", "--quiet", "-D", branch])?;
+        Ok(())
+    }
+
+    /// Commit the changes to tracked files, returning the new commit's id.
+    pub fn commit_all(&self, message: &str) -> Result<String> {
+        self.git(&["commit", "--quiet", "--all", "--message", message])?;
+        self.git(&["rev-parse", "HEAD"])
+    }
+}
+
+/// Bring the lock file of the workspace a manifest is in up to date with its new requirements,
+/// updating nothing else.
+pub fn update_lockfile(manifest_path: &Path, offline: bool) -> Result<()> {
+    let mut cmd = subprocess::Exec::cmd("cargo")
+        .arg("update")
+        .arg("--workspace")
+        .arg("--manifest-path")
+        .arg(manifest_path)
+        .stderr(subprocess::Redirection::Pipe);
+    if offline {
+        cmd = cmd.arg("--offline");
+    }
+    let capture = cmd.capture().chain_err(|| "Failed to run cargo")?;
+    if !capture.success() {
+        bail!(
+            "Failed to update the lock file for `{}`: {}",
+            manifest_path.display(),
+            capture.stderr_str().trim()
+        );
+    }
+    Ok(())
+}
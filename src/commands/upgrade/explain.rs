@@ -0,0 +1,155 @@
+//! Trial resolutions for `--explain`, which reports what keeps a dependency from being upgraded.
+
+use super::errors::*;
+use super::{is_locked, is_version_dep};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// A requirement on a dependency that its new version doesn't satisfy, while the dependency graph
+/// can only hold one of the two versions.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Blocker {
+    /// The package with the requirement, e.g. `helper v0.1.0`.
+    pub package: String,
+    /// The requirement, e.g. `=0.12.0-rc.3`.
+    pub requirement: String,
+}
+
+/// Whether cargo considers two versions compatible, so that a dependency graph holds at most one of
+/// them: they share the major version, or the minor version for `0.x`, or the patch for `0.0.x`.
+pub fn semver_compatible(a: &semver::Version, b: &semver::Version) -> bool {
+    a.major == b.major
+        && (a.major != 0 || (a.minor == b.minor && (a.minor != 0 || a.patch == b.patch)))
+}
+
+/// The requirements on `name`, outside the packages being upgraded, that conflict with upgrading
+/// it to `version`: those resolved to a version compatible with `version`, which they don't allow.
+pub fn blockers(
+    metadata: &cargo_metadata::Metadata,
+    name: &str,
+    version: &semver::Version,
+    upgraded: &[cargo_metadata::PackageId],
+) -> Vec<Blocker> {
+    let mut blockers: Vec<_> = metadata
+        .packages
+        .iter()
+        .filter(|package| !upgraded.contains(&package.id))
+        .flat_map(|package| {
+            package
+                .dependencies
+                .iter()
+                .filter(|dependency| dependency.name == name && is_version_dep(dependency))
+                .filter(move |dependency| {
+                    !dependency.req.matches(version)
+                        && metadata.packages.iter().any(|locked| {
+                            is_locked(locked, dependency)
+                                && semver_compatible(&locked.version, version)
+                        })
+                })
+                .map(move |dependency| Blocker {
+                    package: format!("{} v{}", package.name, package.version),
+                    requirement: dependency.req.to_string(),
+                })
+        })
+        .collect();
+    blockers.sort();
+    blockers.dedup();
+    blockers
+}
+
+/// A copy of a workspace to try upgrades in, so that its manifests, and their modification times,
+/// are left alone even if a trial is interrupted.
+///
+/// The copy is made next to the workspace, at the same depth, so that relative paths leading out
+/// of the workspace and the cargo config of the directories above it still apply.
+pub struct TrialWorkspace {
+    root: PathBuf,
+    copy: TempDir,
+}
+
+impl TrialWorkspace {
+    /// Copy the workspace at `root`, apart from its build output and hidden directories other than
+    /// `.cargo`.
+    pub fn new(root: &Path) -> Result<Self> {
+        let root = root
+            .canonicalize()
+            .chain_err(|| format!("Failed to find `{}`", root.display()))?;
+        let parent = root
+            .parent()
+            .chain_err(|| "The workspace root has no parent directory")?;
+        let copy = tempfile::Builder::new()
+            .prefix(".cargo-upgrade-")
+            .tempdir_in(parent)
+            .chain_err(|| "Failed to create a directory to try upgrades in")?;
+        copy_dir(&root, copy.path()).chain_err(|| "Failed to copy the workspace")?;
+        Ok(TrialWorkspace { root, copy })
+    }
+
+    /// Where a file of the workspace is in the copy.
+    pub fn path_of(&self, path: &Path) -> Result<PathBuf> {
+        let path = path
+            .canonicalize()
+            .chain_err(|| format!("Failed to find `{}`", path.display()))?;
+        let relative = path
+            .strip_prefix(&self.root)
+            .chain_err(|| format!("`{}` is outside of the workspace", path.display()))?;
+        Ok(self.copy.path().join(relative))
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            let name = name.to_string_lossy();
+            if name == "target" || (name.starts_with('.') && name != ".cargo") {
+                continue;
+            }
+            copy_dir(&entry.path(), &to.join(entry.file_name()))?;
+        } else if entry.path().is_file() {
+            fs::copy(entry.path(), to.join(name))?;
+        }
+    }
+    Ok(())
+}
+
+/// Resolve the dependency graph of the workspace a manifest is in, without writing the lock file,
+/// returning cargo's error if it can't be resolved.
+pub fn trial_resolve(manifest_path: &Path, offline: bool) -> Result<Option<String>> {
+    let mut cmd = subprocess::Exec::cmd("cargo")
+        .arg("update")
+        .arg("--workspace")
+        .arg("--dry-run")
+        .arg("--manifest-path")
+        .arg(manifest_path)
+        .stdout(subprocess::Redirection::Pipe)
+        .stderr(subprocess::Redirection::Pipe);
+    if offline {
+        cmd = cmd.arg("--offline");
+    }
+    let capture = cmd.capture().chain_err(|| "Failed to run cargo")?;
+    if capture.success() {
+        Ok(None)
+    } else {
+        Ok(Some(capture.stderr_str().trim().to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compatible_versions() {
+        let v = |v| semver::Version::parse(v).unwrap();
+        assert!(semver_compatible(&v("1.2.3"), &v("1.9.0")));
+        assert!(!semver_compatible(&v("1.2.3"), &v("2.0.0")));
+        assert!(semver_compatible(&v("0.12.0-rc.3"), &v("0.12.0")));
+        assert!(!semver_compatible(&v("0.12.0"), &v("0.13.0")));
+        assert!(!semver_compatible(&v("0.0.1"), &v("0.0.2")));
+    }
+}
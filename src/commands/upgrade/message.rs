@@ -0,0 +1,112 @@
+//! Machine-readable output for `--message-format json-lines`.
+
+use super::errors::*;
+use crate::FailureKind;
+use error_chain::bail;
+use serde_derive::Serialize;
+use std::str::FromStr;
+
+/// How progress and results are reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageFormat {
+    /// Coloured, human-readable text.
+    Human,
+    /// One JSON object per line, emitted as work happens.
+    JsonLines,
+}
+
+impl MessageFormat {
+    /// The accepted values of `--message-format`.
+    pub const VARIANTS: &'static [&'static str] = &["human", "json-lines"];
+}
+
+impl FromStr for MessageFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "human" => Ok(MessageFormat::Human),
+            "json-lines" => Ok(MessageFormat::JsonLines),
+            _ => bail!("Unknown message format `{}`", s),
+        }
+    }
+}
+
+/// A change to a dependency requirement, as reported in the final plan.
+#[derive(Debug, Serialize)]
+pub struct PlannedUpgrade {
+    pub package: String,
+    pub manifest: String,
+    pub dependency: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// An event, emitted as a line of JSON tagged with its `reason`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+pub enum Message<'a> {
+    /// The latest version of a dependency is being looked up.
+    LookupStarted { dependency: &'a str },
+    /// The latest version of a dependency was found.
+    LookupFinished {
+        dependency: &'a str,
+        version: &'a str,
+    },
+    /// Looking up a dependency failed. With `--keep-going` it is left alone, and otherwise
+    /// nothing is changed.
+    LookupFailed {
+        dependency: &'a str,
+        manifest: &'a str,
+        kind: FailureKind,
+        retryable: bool,
+        error: String,
+    },
+    /// A dependency is left alone.
+    DependencySkipped {
+        dependency: &'a str,
+        skip_reason: String,
+    },
+    /// A fresh resolve would lock a dependency at a newer version (with `--check-lock`).
+    LockDrift {
+        package: &'a str,
+        manifest: &'a str,
+        dependency: &'a str,
+        requirement: &'a str,
+        locked: &'a str,
+        resolved: &'a str,
+        level: &'a str,
+    },
+    /// A branch holding the upgrade of a single dependency was created (with `--batch`).
+    BranchCreated {
+        dependency: &'a str,
+        version: &'a str,
+        branch: &'a str,
+        commit: &'a str,
+    },
+    /// A `[patch]` entry was dropped, as the dependency was upgraded past the patched version
+    /// (with `--drop-patches`).
+    PatchDropped {
+        dependency: &'a str,
+        section: &'a str,
+        manifest: &'a str,
+        patched: &'a str,
+        version: &'a str,
+    },
+    /// A manifest was written to disk.
+    ManifestWritten { package: &'a str, manifest: &'a str },
+    /// All the changes made (or, for a dry run, that would have been made), emitted last.
+    Plan {
+        dry_run: bool,
+        upgrades: &'a [PlannedUpgrade],
+    },
+}
+
+impl Message<'_> {
+    /// Print the message as a single line of JSON.
+    pub fn emit(&self) -> Result<()> {
+        let line = serde_json::to_string(self).chain_err(|| "Failed to serialize message")?;
+        println!("{}", line);
+        Ok(())
+    }
+}
@@ -0,0 +1,2811 @@
+//! `cargo upgrade`
+
+use self::align::{Align, Alignments};
+use self::errors::*;
+use self::message::{Message, MessageFormat, PlannedUpgrade};
+use self::report::{write_report, Finding, ReportFormat, ReportedManifest};
+use super::{ClientIdentityArgs, NetArgs};
+use crate::{
+    find, get_features, get_latest_dependency, get_latest_dependency_published_before,
+    get_latest_matching, get_releases, is_fully_yanked, license_allowed, manifest_from_pkgid,
+    notify_of_newer_release, record_for_undo, registry_source, requirement_floor, unified_diff,
+    update_advisory_db, update_registry_index, workspace_members, workspace_root, AdvisoryDb,
+    CrateName, Dependency, Edits, FailureKind, LocalManifest, Lockfile, Manifest, NetConfig,
+    Policy, RegistrySource, StaleFeature, VersionChange, POLICY_ENV,
+};
+use error_chain::bail;
+use failure::Fail;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use structopt::{clap::AppSettings, StructOpt};
+use termcolor::{BufferWriter, Color, ColorChoice, ColorSpec, WriteColor};
+use url::Url;
+
+mod align;
+mod batch;
+mod explain;
+mod message;
+mod report;
+mod scan;
+
+mod errors {
+    error_chain::error_chain! {
+        links {
+            CargoEditLib(crate::Error, crate::ErrorKind);
+        }
+        foreign_links {
+            CargoMetadata(::failure::Compat<::cargo_metadata::Error>);
+        }
+        errors {
+            /// Looking up the crate failed already, for another dependency on it.
+            LookupFailedEarlier(name: String) {
+                description("lookup failed earlier")
+                display("Looking up `{}` failed earlier in this run", name)
+            }
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(bin_name = "cargo")]
+enum Command {
+    /// Upgrade dependencies as specified in the local manifest file (i.e. Cargo.toml).
+    #[structopt(name = "upgrade")]
+    #[structopt(after_help = "\
+This command differs from `cargo update`, which updates the dependency versions recorded in the \
+local lock file (Cargo.lock).
+
+If `<dependency>`(s) are provided, only the specified dependencies will be upgraded. The version \
+to upgrade to for each can be specified with e.g. `docopt@0.8.0` or `serde@>=0.9,<2.0`. A crate \
+depended on more than once, e.g. at two versions with one renamed, is upgraded in every entry, \
+unless the renamed entry is given by its name in the manifest (e.g. `rand07` for \
+`rand07 = { package = \"rand\", version = \"0.7\" }`), which upgrades that entry alone.
+
+A version older than the current one may be given too, e.g. to roll back a bad upgrade. Such \
+changes are reported as downgrades.
+
+Dev, build, and all target dependencies will also be upgraded. Only dependencies from crates.io \
+are supported. Git/path dependencies will be ignored, except that a path dependency which also \
+has a `version` requirement, as publishing needs, has it set to the version of the local crate.
+
+Dependencies required at a prerelease (e.g. `0.12.0-rc.3`) are upgraded to the latest stable \
+release once there is one at or above it, and to the latest prerelease otherwise, even without the \
+'--allow-prerelease' flag.
+
+Dependencies overridden by a `[patch]` or `[replace]` section of the workspace are skipped with a \
+warning, unless they are named explicitly, as their requirement has no effect while the override \
+is there. With the '--drop-patches' flag, a dependency that a `[patch]` entry overrides (e.g. with \
+a git fork) is looked up too, and if the version it would be upgraded to is newer than the version \
+of the patch in `Cargo.lock`, and so likely includes the patched changes, the patch is removed and \
+the dependency upgraded. Otherwise it is skipped as before.
+
+Upgrades that are semver incompatible with the current requirement (e.g. from `1.x` to `2.0`, or \
+from `0.1` to `0.2`) are listed in a warning before any changes are made, as they may need code \
+changes. Use '--skip-compatible' together with '--dry-run' to review just these.
+
+Requirements are rewritten for the new version keeping their operators and precision, e.g. `1.0` \
+becomes `1.2` and `~0.3.1` becomes `~0.4.0`, so one that names the new version as precisely as it \
+can (e.g. `1.0` for 1.0.130) is left as it is.
+
+Wildcard requirements (`*`) are left alone, unless the '--concretize-wildcards' flag is supplied. \
+Then they are replaced with the latest version, like any other requirement, as crates.io doesn't \
+accept packages with wildcard dependencies.
+
+If the '--index' flag is supplied, the latest versions of crates.io dependencies will be looked up \
+in the given registry index instead, e.g. a mirror or a staging registry, or an index on disk given \
+as a `file://` URL, which is read in place. Likewise, the \
+'--local-registry' flag looks them up in a local registry or a directory created by `cargo vendor`, \
+without accessing the network. Such local sources are also used when crates.io is replaced by one \
+in the cargo config.
+
+Dependencies naming an alternative registry (e.g. `registry = \"internal\"`) are always looked up \
+in that registry, as defined in the cargo config, while the rest of the manifest's dependencies \
+come from crates.io. A crate of the same name from each is upgraded separately.
+
+If the registry index can't be updated, or the '--offline' flag is supplied, versions are read from \
+the local copy of the index, or from cargo's index cache if there is no usable copy. A warning is \
+printed if the index couldn't be updated or the cache is read, as these versions may be out of \
+date. Setting `net.offline` in the cargo config (or `CARGO_NET_OFFLINE`) works like \
+'--offline', and failed requests are retried as many times as `net.retry` says, as with cargo.
+
+HTTPS connections also trust the CA bundle set by `http.cainfo` in the cargo config (or the \
+`CARGO_HTTP_CAINFO` environment variable), as cargo does. A client certificate for registries that \
+require one can be given with '--client-cert' and '--client-key'.
+
+A policy file, given with '--policy' or the `CARGO_EDIT_POLICY` environment variable, can ban \
+crates and restrict the versions of others, with a table for each crate: e.g. `[crates.openssl]` \
+with `banned = true` and an optional `reason`, or `[crates.time]` with `minimum = \"0.2.23\"` and \
+`allowed = \"<0.3\"`. Dependencies are upgraded to the latest version the policy allows, with a \
+warning if that isn't the latest one, and upgrades of banned crates or to versions older than the \
+minimum fail.
+
+Dependencies that the RustSec advisory database reports as unmaintained are warned about, along \
+with any replacements the advisory suggests, as are dependencies whose every version has been \
+yanked, and those whose current requirement allows a version with a known vulnerability. The copy \
+of the database that cargo-audit keeps (in `$CARGO_HOME/advisory-db`) is used, and cloned or \
+updated first unless offline; '--advisory-db' reads another copy instead.
+
+With '--report', a report for CI is written to '--report-path' too: `junit` for a JUnit XML test \
+case for each dependency of each manifest, which fails if the dependency is upgraded (or, for a \
+dry run, could be), vulnerable, unmaintained or yanked, or `sarif` for a SARIF result for each of \
+these problems, located at the dependency's entries in the manifests, for code-scanning \
+annotations. With '--dry-run', nothing else is changed.
+
+If looking up a dependency fails, nothing is changed. With the '--keep-going' flag, the other \
+dependencies are upgraded anyway, and the command then fails with a list of the lookups that failed.
+
+With '--min-age', releases are only picked up once they have been out for a while (e.g. `7d`), \
+so a hijacked release has time to be noticed and yanked. Publish times are read from the registry \
+index, or from the crates.io API for crates.io, and versions whose publish time isn't known are \
+never picked. A dependency is left alone if every version newer than its current requirement is \
+too recent.
+
+Upgrades of crates from crates.io are checked against who published each version. If the new \
+version was published by someone who published none of the versions since the current requirement, \
+e.g. because the crate changed hands, a warning is printed. With '--deny-new-publishers', such \
+upgrades fail instead (or are left out, with '--keep-going'). Responses from the crates.io API are \
+cached under `$CARGO_HOME/cargo-edit/http-cache`, and only downloaded again when they have changed.
+
+A warning is also printed if the license of the new version differs from that of the versions the \
+current requirement allows (e.g. from `MIT` to `BUSL-1.1`). Upgrades to versions that can't be used \
+without one of the licenses given with '--deny-license' fail, where a license expression with `OR` \
+only needs one of its alternatives to be allowed.
+
+What the crates.io API reports about each version, i.e. its publish time, publisher and license, \
+is checked against the registry index, which cargo downloads crates by. Versions the index doesn't \
+have are ignored, and a checksum that differs from the index's fails the upgrade, as the response \
+may have been tampered with. The '--no-verify' flag skips this check.
+
+Features that a dependency's entry enables are checked against those of the version it is upgraded \
+to, as published in the registry, with a warning for any the new version doesn't have. With the \
+'--fix-features' flag, those features are removed from the entry instead, or renamed if the new \
+version has one named the same but for case or `-` and `_`.
+
+All packages in the workspace will be upgraded if the `--workspace` flag is supplied. This is also \
+the default when run against a virtual manifest (a workspace root without a package of its own). \
+As with cargo, only the workspace's `default-members` are upgraded if it lists them, unless the \
+`--all-members` flag is supplied, and packages under its `exclude`d paths never are. Changes are \
+reported under each package's name and manifest path, and packages with nothing to upgrade are \
+reported as up to date.
+
+Several manifests that aren't part of one workspace can be upgraded at once by passing \
+'--manifest-path' more than once.
+
+The '--recursive' option upgrades every `Cargo.toml` under a directory, e.g. \
+`cargo upgrade --recursive examples`, whether the manifests are standalone crates or workspaces. \
+`target` directories, hidden directories and paths that git ignores are skipped. Each dependency \
+is looked up once for all of them.
+
+With the '--batch' flag, each dependency is upgraded on a git branch of its own, e.g. \
+`cargo-upgrade/serde-1.0.118`, created from the checked-out commit. The branch holds a single \
+commit with the dependency's new requirements, along with the lock file if the repository tracks \
+one, so each upgrade can be reviewed and merged separately. The working tree must be clean, and the \
+checked-out branch is left as it is. Branches are reported as they are created, as `branch-created` \
+events with '--message-format json-lines'.
+
+If the '--diff' flag is supplied, nothing is written. Instead, a unified diff of the changes to \
+each manifest is printed, which can be reviewed or applied with `patch -p0`.
+
+If '--message-format json-lines' is supplied, progress is printed as one JSON object per line \
+instead, each with a `reason`: `lookup-started` and `lookup-finished` around each version lookup, \
+`dependency-skipped` with a `skip_reason`, and `manifest-written` for each manifest saved. The last \
+line is a `plan` listing every change made, or that would be made for a dry run. A lookup that fails \
+is reported as `lookup-failed`, with the `manifest` declaring the dependency, the `kind` of failure \
+(`not-found`, `network`, `parse` or `other`), and whether it is `retryable`.
+
+If the '--to-lockfile' flag is supplied, all dependencies will be upgraded to the currently locked \
+version as recorded in the Cargo.lock file. This flag requires that the Cargo.lock file is \
+up-to-date. If the lock file is missing, or it needs to be updated, cargo-upgrade will exit with \
+an error. If the '--to-lockfile' flag is supplied then the network won't be accessed. As the \
+locked versions are semver compatible with the requirements, a note is printed for each \
+dependency whose latest release in the local copy of the registry index is not, e.g. \
+`note: serde_yaml v0.8.26 -> v0.9.0 available, not applied (semver incompatible)`, so pending \
+migrations aren't missed. The '--no-notices' flag leaves these out.
+
+If the '--to-precise' flag is supplied, every requirement upgraded is written as an exact \
+`=x.y.z` requirement on its new version, the same in every manifest, for environments that require \
+exact pins in the manifests rather than only in the lock file. This includes `*` requirements. \
+Combined with '--to-lockfile', each dependency is pinned to its locked version instead.
+
+Crates that workspace members require at semver incompatible versions (e.g. `0.2` in one and \
+`0.3` in another) are warned about, as upgrading each manifest on its own, e.g. with \
+'--to-lockfile', keeps them apart. With '--align highest' they are upgraded together as usual, or \
+with '--to-lockfile', set to the highest of the requirements; with '--align lowest' they are set \
+to the lowest of the requirements instead of being upgraded; and with '--align skip' they are \
+left out of the run. Run in a terminal without '--align', the choice is asked for each crate.
+
+If the '--check-lock' flag is supplied, nothing is changed. Instead, every dependency whose \
+requirement allows a newer version than the one in the Cargo.lock file is reported, with the \
+version a fresh resolve (e.g. `cargo generate-lockfile`) would pick and whether that is a `major`, \
+`minor` or `patch` step. With '--message-format json-lines', each is a `lock-drift` event.
+
+The '--explain' flag reports what keeps a dependency from being upgraded, changing nothing, e.g. \
+`cargo upgrade --explain serde`. A copy of the workspace, made next to it, is upgraded for a trial \
+resolution with `cargo update --dry-run`, leaving the manifests themselves untouched. If cargo can't resolve the dependency graph, the \
+requirements elsewhere in it that the new version conflicts with are listed, e.g. an exact \
+`=1.0.100` of another crate, as a graph can only hold one of two semver compatible versions.
+
+Once a day, `cargo add` and `cargo upgrade` check the local copy of the registry index for a newer \
+release of cargo-edit, and print a note if there is one. The '--self' flag installs it with \
+`cargo install cargo-edit`. Setting `CARGO_EDIT_NO_UPDATE_CHECK` turns the check off.")]
+    Upgrade(Args),
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(setting = AppSettings::ColoredHelp)]
+struct Args {
+    /// Crates to be upgraded.
+    dependency: Vec<String>,
+
+    /// Path to the manifest to upgrade. Can be given several times
+    #[structopt(
+        long = "manifest-path",
+        value_name = "path",
+        number_of_values = 1,
+        conflicts_with = "pkgid"
+    )]
+    manifest_path: Vec<PathBuf>,
+
+    /// Upgrade every manifest under a directory, whether or not they are part of one workspace.
+    #[structopt(
+        long = "recursive",
+        value_name = "dir",
+        conflicts_with = "manifest-path",
+        conflicts_with = "pkgid"
+    )]
+    recursive: Option<PathBuf>,
+
+    /// Package id of the crate to add this dependency to.
+    #[structopt(
+        long = "package",
+        short = "p",
+        value_name = "pkgid",
+        conflicts_with = "path",
+        conflicts_with = "all",
+        conflicts_with = "workspace"
+    )]
+    pkgid: Option<String>,
+
+    /// Upgrade all packages in the workspace.
+    #[structopt(
+        long = "all",
+        help = "[deprecated in favor of `--workspace`]",
+        conflicts_with = "workspace",
+        conflicts_with = "pkgid"
+    )]
+    all: bool,
+
+    /// Upgrade all packages in the workspace.
+    #[structopt(long = "workspace", conflicts_with = "all", conflicts_with = "pkgid")]
+    workspace: bool,
+
+    /// Include workspace members left out of the workspace's `default-members`.
+    #[structopt(long = "all-members", conflicts_with = "pkgid")]
+    all_members: bool,
+
+    /// Include prerelease versions when fetching from crates.io (e.g. 0.6.0-alpha').
+    #[structopt(long = "allow-prerelease")]
+    allow_prerelease: bool,
+
+    /// Print changes to be made without making them.
+    #[structopt(long = "dry-run")]
+    dry_run: bool,
+
+    /// Print a unified diff of the changes to each manifest, without making them.
+    #[structopt(long = "diff", conflicts_with = "message-format")]
+    diff: bool,
+
+    /// Only update a dependency if the new version is semver incompatible.
+    #[structopt(long = "skip-compatible", conflicts_with = "to-lockfile")]
+    skip_compatible: bool,
+
+    /// Replace `*` requirements with the latest version, rather than leave them alone.
+    #[structopt(long = "concretize-wildcards")]
+    concretize_wildcards: bool,
+
+    /// Write exact `=x.y.z` requirements on the versions upgraded to, in every manifest.
+    #[structopt(
+        long = "to-precise",
+        conflicts_with = "skip-compatible",
+        conflicts_with = "check-lock",
+        conflicts_with = "explain"
+    )]
+    to_precise: bool,
+
+    #[structopt(flatten)]
+    pub net: NetArgs,
+
+    /// Update cargo-edit itself to its latest release, with `cargo install`.
+    #[structopt(long = "self", conflicts_with = "dependency")]
+    self_update: bool,
+
+    /// Carry on upgrading the other dependencies if looking one up fails.
+    #[structopt(long = "keep-going")]
+    keep_going: bool,
+
+    /// Drop features the new versions no longer have, and follow features that were renamed.
+    #[structopt(long = "fix-features")]
+    fix_features: bool,
+
+    /// Only upgrade to versions published at least this long ago (e.g. `7d`, `12h` or `2w`).
+    #[structopt(
+        long = "min-age",
+        value_name = "age",
+        parse(try_from_str = parse_age),
+        conflicts_with = "to-lockfile"
+    )]
+    min_age: Option<Duration>,
+
+    /// Fail to upgrade crates whose new version was published by someone new to the crate.
+    #[structopt(long = "deny-new-publishers", conflicts_with = "to-lockfile")]
+    deny_new_publishers: bool,
+
+    /// Fail to upgrade crates whose new version can't be used without this license (e.g.
+    /// `BUSL-1.1`), which may be given more than once.
+    #[structopt(
+        long = "deny-license",
+        value_name = "license",
+        number_of_values = 1,
+        conflicts_with = "to-lockfile"
+    )]
+    deny_license: Vec<String>,
+
+    /// Remove `[patch]` entries for dependencies upgraded past the patched version.
+    #[structopt(
+        long = "drop-patches",
+        conflicts_with = "to-lockfile",
+        conflicts_with = "batch",
+        conflicts_with = "check-lock",
+        conflicts_with = "explain"
+    )]
+    drop_patches: bool,
+
+    /// Upgrade all packages to the version in the lockfile.
+    #[structopt(long = "to-lockfile", conflicts_with = "dependency")]
+    pub to_lockfile: bool,
+
+    /// Don't note the semver incompatible releases that '--to-lockfile' leaves alone.
+    #[structopt(long = "no-notices", requires = "to-lockfile")]
+    no_notices: bool,
+
+    /// Use what the crates.io API reports about versions without checking it against the registry
+    /// index.
+    #[structopt(long = "no-verify")]
+    no_verify: bool,
+
+    /// Upgrade each dependency on a git branch of its own, with a commit of just its upgrade.
+    #[structopt(
+        long = "batch",
+        conflicts_with = "frozen",
+        conflicts_with = "dry-run",
+        conflicts_with = "diff",
+        conflicts_with = "to-lockfile",
+        conflicts_with = "check-lock"
+    )]
+    batch: bool,
+
+    /// Report dependencies that a fresh resolve would lock at newer versions, changing nothing.
+    #[structopt(
+        long = "check-lock",
+        conflicts_with = "to-lockfile",
+        conflicts_with = "diff",
+        conflicts_with = "fix-features"
+    )]
+    check_lock: bool,
+
+    /// Report what keeps a dependency from being upgraded, with a trial resolution of the upgraded
+    /// manifests, changing nothing.
+    #[structopt(
+        long = "explain",
+        value_name = "dependency",
+        conflicts_with = "dependency",
+        conflicts_with = "to-lockfile",
+        conflicts_with = "check-lock",
+        conflicts_with = "batch",
+        conflicts_with = "diff",
+        conflicts_with = "message-format"
+    )]
+    explain: Option<String>,
+
+    /// Crates to exclude and not upgrade.
+    #[structopt(long)]
+    exclude: Vec<String>,
+
+    /// Registry index to resolve crates.io dependencies against (e.g. a mirror).
+    #[structopt(long = "index", value_name = "url", parse(try_from_str = Url::parse))]
+    index: Option<Url>,
+
+    /// Resolve crates.io dependencies from a local registry or `cargo vendor` directory.
+    #[structopt(long = "local-registry", value_name = "path", conflicts_with = "index")]
+    local_registry: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    identity: ClientIdentityArgs,
+
+    /// Policy file declaring banned crates and the versions others may be upgraded to.
+    #[structopt(long = "policy", value_name = "path", env = POLICY_ENV)]
+    policy: Option<PathBuf>,
+
+    /// Local copy of the RustSec advisory database to check for unmaintained and vulnerable crates
+    /// in (by default the one cargo-audit keeps, which is updated first).
+    #[structopt(long = "advisory-db", value_name = "path")]
+    advisory_db: Option<PathBuf>,
+
+    /// Output format: `human`, or `json-lines` for a JSON event per line.
+    #[structopt(
+        long = "message-format",
+        value_name = "fmt",
+        default_value = "human",
+        possible_values = MessageFormat::VARIANTS
+    )]
+    message_format: MessageFormat,
+
+    /// Write a report of outdated and vulnerable dependencies for CI to '--report-path': `junit`
+    /// or `sarif`.
+    #[structopt(
+        long = "report",
+        value_name = "fmt",
+        possible_values = ReportFormat::VARIANTS,
+        requires = "report-path",
+        conflicts_with = "check-lock",
+        conflicts_with = "explain"
+    )]
+    report: Option<ReportFormat>,
+
+    /// Where to write the '--report'.
+    #[structopt(long = "report-path", value_name = "path", requires = "report")]
+    report_path: Option<PathBuf>,
+
+    /// How to align crates that members require at incompatible versions: `highest`, `lowest` or
+    /// `skip`.
+    #[structopt(
+        long = "align",
+        value_name = "how",
+        possible_values = Align::VARIANTS,
+        conflicts_with = "batch",
+        conflicts_with = "check-lock",
+        conflicts_with = "explain"
+    )]
+    align: Option<Align>,
+}
+
+/// A collection of manifests.
+struct Manifests(Vec<(LocalManifest, cargo_metadata::Package)>);
+
+/// Helper function to check whether a `cargo_metadata::Dependency` is a version dependency.
+fn is_version_dep(dependency: &cargo_metadata::Dependency) -> bool {
+    match dependency.source {
+        // This is the criterion cargo uses (in `SourceId::from_url`) to decide whether a
+        // dependency has the 'registry' kind.
+        Some(ref s) => s.split('+').next() == Some("registry"),
+        _ => false,
+    }
+}
+
+/// Whether a package in the lock file is the one a dependency resolved to: the same crate from the
+/// same registry, at a version the requirement matches.
+fn is_locked(package: &cargo_metadata::Package, dependency: &cargo_metadata::Dependency) -> bool {
+    package.name == dependency.name
+        && package.source.as_ref().map(|s| s.repr.as_str()) == dependency.source.as_deref()
+        && dependency.req.matches(&package.version)
+}
+
+/// The table a dependency's entry is in, e.g. `["target", "cfg(unix)", "dev-dependencies"]`.
+fn table_path(dependency: &cargo_metadata::Dependency) -> Vec<String> {
+    let kind = match dependency.kind {
+        cargo_metadata::DependencyKind::Development => "dev-dependencies",
+        cargo_metadata::DependencyKind::Build => "build-dependencies",
+        _ => "dependencies",
+    };
+    let mut table_path = Vec::new();
+    if let Some(target) = &dependency.target {
+        table_path.push("target".to_owned());
+        table_path.push(target.to_string());
+    }
+    table_path.push(kind.to_owned());
+    table_path
+}
+
+/// A dependency's entry in its manifest.
+fn dependency_entry<'m>(
+    manifest: &'m LocalManifest,
+    dependency: &cargo_metadata::Dependency,
+) -> Option<&'m toml_edit::Item> {
+    let mut path = table_path(dependency);
+    path.push(
+        dependency
+            .rename
+            .as_ref()
+            .unwrap_or(&dependency.name)
+            .clone(),
+    );
+    manifest.get_item(&path)
+}
+
+/// The version of the local crate in `dir`, as its manifest gives it or inherits it from its
+/// workspace.
+fn local_version(dir: &Path) -> Result<Option<String>> {
+    let path = dir.join("Cargo.toml");
+    let manifest = Manifest::open(&Some(path.clone()))?;
+    let workspace = Manifest::open(&Some(workspace_root(&path)?.join("Cargo.toml")))?;
+    let version = manifest
+        .get_inherited_item(&["package".to_owned(), "version".to_owned()], &workspace)
+        .and_then(|version| version.as_str().map(str::to_owned));
+    Ok(version)
+}
+
+/// The alternative registry that a dependency's entry in its manifest names, if any.
+fn registry_name(
+    manifest: &LocalManifest,
+    dependency: &cargo_metadata::Dependency,
+) -> Option<String> {
+    dependency.registry.as_ref()?;
+    let key = dependency.rename.as_deref().unwrap_or(&dependency.name);
+    let table_path = table_path(dependency);
+
+    let (_, table) = manifest
+        .get_sections()
+        .into_iter()
+        .find(|(path, _)| *path == table_path)?;
+    let entry = table.as_table_like()?.get(key)?;
+    let registry = entry.as_table_like()?.get("registry")?.as_str()?;
+    Some(registry.to_owned())
+}
+
+/// Where to look a dependency up: in the registry its entry names, following the source
+/// replacement configured for it, or else in the index cargo resolved it from, or else in
+/// `default_source`, or else in the registry configured for the manifest declaring it.
+fn dependency_source(
+    dep: &Dependency,
+    index: Option<&str>,
+    manifest_path: &Path,
+    default_source: &Option<RegistrySource>,
+) -> Result<Option<RegistrySource>> {
+    match (dep.registry(), index) {
+        (Some(registry), _) => Ok(Some(registry_source(manifest_path, Some(registry))?)),
+        (None, Some(index)) => Ok(Some(RegistrySource::Remote(
+            Url::parse(index)
+                .map_err(|_| ErrorKind::CargoEditLib(crate::ErrorKind::InvalidCargoConfig))?,
+        ))),
+        (None, None) => match default_source {
+            Some(source) => Ok(Some(source.clone())),
+            None => Ok(Some(registry_source(manifest_path, None)?)),
+        },
+    }
+}
+
+fn deprecated_message(message: &str) -> Result<()> {
+    let bufwtr = BufferWriter::stderr(ColorChoice::Always);
+    let mut buffer = bufwtr.buffer();
+    buffer
+        .set_color(ColorSpec::new().set_fg(Some(Color::Red)).set_bold(true))
+        .chain_err(|| "Failed to set output colour")?;
+    writeln!(&mut buffer, "{}", message).chain_err(|| "Failed to write dry run message")?;
+    buffer
+        .set_color(&ColorSpec::new())
+        .chain_err(|| "Failed to clear output colour")?;
+    bufwtr
+        .print(&buffer)
+        .chain_err(|| "Failed to print dry run message")
+}
+
+/// Warn about features that a dependency's new version doesn't have, and say what is done about
+/// them.
+fn stale_features_message(
+    manifest: &Path,
+    version: &str,
+    stale: &[StaleFeature],
+    fix: bool,
+) -> Result<()> {
+    let bufwtr = BufferWriter::stderr(ColorChoice::Always);
+    let mut buffer = bufwtr.buffer();
+    for stale in stale {
+        buffer
+            .set_color(ColorSpec::new().set_fg(Some(Color::Yellow)).set_bold(true))
+            .chain_err(|| "Failed to set output colour")?;
+        write!(&mut buffer, "WARN: ").chain_err(|| "Failed to write feature message")?;
+        buffer
+            .set_color(&ColorSpec::new())
+            .chain_err(|| "Failed to clear output colour")?;
+        let outcome = match (&stale.renamed_to, fix) {
+            (Some(renamed), true) => format!("renaming it to `{}`", renamed),
+            (None, true) => "removing it".to_owned(),
+            (Some(renamed), false) => format!(
+                "it may have been renamed to `{}` (use --fix-features to rename it)",
+                renamed
+            ),
+            (None, false) => {
+                "it may have been removed (use --fix-features to remove it)".to_owned()
+            }
+        };
+        writeln!(
+            &mut buffer,
+            "`{}` {} has no `{}` feature, which `{}` enables; {}",
+            stale.dependency,
+            version,
+            stale.feature,
+            relative_to_cwd(manifest).display(),
+            outcome
+        )
+        .chain_err(|| "Failed to write feature message")?;
+    }
+    bufwtr
+        .print(&buffer)
+        .chain_err(|| "Failed to print feature message")
+}
+
+/// Warn about upgrades that cross a semver-incompatible boundary.
+fn breaking_message(breaking: &[String]) -> Result<()> {
+    let bufwtr = BufferWriter::stderr(ColorChoice::Always);
+    let mut buffer = bufwtr.buffer();
+    buffer
+        .set_color(ColorSpec::new().set_fg(Some(Color::Red)).set_bold(true))
+        .chain_err(|| "Failed to set output colour")?;
+    writeln!(
+        &mut buffer,
+        "WARN: These upgrades are semver incompatible and may break your build:"
+    )
+    .chain_err(|| "Failed to write breaking upgrade message")?;
+    buffer
+        .set_color(&ColorSpec::new())
+        .chain_err(|| "Failed to clear output colour")?;
+    for upgrade in breaking {
+        writeln!(&mut buffer, "    {}", upgrade)
+            .chain_err(|| "Failed to write breaking upgrade message")?;
+    }
+    bufwtr
+        .print(&buffer)
+        .chain_err(|| "Failed to print breaking upgrade message")
+}
+
+/// A path relative to the current directory, if it is inside it.
+/// Parse an age like `7d`: a number of seconds (`s`), minutes (`m`), hours (`h`), days (`d`) or
+/// weeks (`w`).
+fn parse_age(age: &str) -> Result<Duration> {
+    let split = age.len() - age.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let (number, unit) = age.split_at(split);
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => bail!("Invalid age `{}`, expected e.g. `7d`, `12h` or `2w`", age),
+    };
+    let number: u64 = number
+        .parse()
+        .chain_err(|| format!("Invalid age `{}`, expected e.g. `7d`, `12h` or `2w`", age))?;
+    Ok(Duration::from_secs(number * seconds))
+}
+
+fn relative_to_cwd(path: &Path) -> &Path {
+    std::env::current_dir()
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok())
+        .unwrap_or(path)
+}
+
+/// Print the heading under which the changes to a package are reported, e.g.
+/// `one (one/Cargo.toml):`. Headings after the first are separated by a blank line.
+fn package_heading(package: &cargo_metadata::Package, first: bool) -> Result<()> {
+    let path = relative_to_cwd(&package.manifest_path);
+
+    let bufwtr = BufferWriter::stdout(ColorChoice::Always);
+    let mut buffer = bufwtr.buffer();
+    if !first {
+        writeln!(&mut buffer).chain_err(|| "Failed to write package heading")?;
+    }
+    buffer
+        .set_color(ColorSpec::new().set_bold(true))
+        .chain_err(|| "Failed to set output colour")?;
+    write!(&mut buffer, "{}", package.name).chain_err(|| "Failed to write package heading")?;
+    buffer
+        .set_color(&ColorSpec::new())
+        .chain_err(|| "Failed to clear output colour")?;
+    writeln!(&mut buffer, " ({}):", path.display())
+        .chain_err(|| "Failed to write package heading")?;
+    bufwtr
+        .print(&buffer)
+        .chain_err(|| "Failed to print package heading")
+}
+
+/// Report the changes made (or, for a dry run, that would be made) to a package's manifest, and add
+/// them to the plan.
+fn report_changes(
+    format: MessageFormat,
+    package: &cargo_metadata::Package,
+    changes: Vec<VersionChange>,
+    dry_run: bool,
+    plan: &mut Vec<PlannedUpgrade>,
+) -> Result<()> {
+    let manifest = package.manifest_path.to_string_lossy();
+    match format {
+        MessageFormat::Human => {
+            for change in &changes {
+                change.print()?;
+            }
+            if changes.is_empty() {
+                print_up_to_date()?;
+            }
+        }
+        MessageFormat::JsonLines => {
+            if !changes.is_empty() && !dry_run {
+                Message::ManifestWritten {
+                    package: &package.name,
+                    manifest: &manifest,
+                }
+                .emit()?;
+            }
+        }
+    }
+    plan.extend(changes.into_iter().map(|change| PlannedUpgrade {
+        package: package.name.clone(),
+        manifest: manifest.clone().into_owned(),
+        dependency: change.name,
+        old: change.old,
+        new: change.new,
+    }));
+    Ok(())
+}
+
+/// The underlying reason for an error, e.g. why a lookup failed.
+fn root_cause(err: &Error) -> String {
+    err.iter()
+        .last()
+        .map_or_else(|| err.to_string(), ToString::to_string)
+}
+
+/// Print a unified diff between the manifest on disk at `path` and its upgraded contents.
+fn print_diff(manifest: &Manifest, path: &Path) -> Result<()> {
+    let old = fs::read_to_string(path).chain_err(|| "Failed to read manifest")?;
+    let new = manifest.to_string_like(&old);
+    let path = relative_to_cwd(path).display().to_string();
+    print!("{}", unified_diff(&old, &new, &path, &path));
+    Ok(())
+}
+
+/// Set the requirements of a package's path dependencies to the versions of the local crates, in
+/// `local_versions` by their directories. Entries inherited from the workspace are left to it.
+fn sync_local_versions(
+    manifest: &mut LocalManifest,
+    package: &cargo_metadata::Package,
+    local_versions: &HashMap<PathBuf, String>,
+    skip_compatible: bool,
+) -> Result<Vec<VersionChange>> {
+    let mut changes = Vec::new();
+    for dependency in &package.dependencies {
+        let version = match dependency
+            .path
+            .as_ref()
+            .and_then(|dir| local_versions.get(dir))
+        {
+            Some(version) => version,
+            None => continue,
+        };
+        if skip_compatible
+            && semver::Version::parse(version).is_ok_and(|v| dependency.req.matches(&v))
+        {
+            continue;
+        }
+        let inherited = dependency_entry(manifest, dependency)
+            .and_then(|entry| entry.as_table_like())
+            .and_then(|entry| entry.get("workspace"))
+            .and_then(toml_edit::Item::as_bool)
+            == Some(true);
+        if inherited {
+            continue;
+        }
+        let requirement = if manifest.precise {
+            format!("={}", version)
+        } else {
+            version.clone()
+        };
+        let key = dependency.rename.as_deref().unwrap_or(&dependency.name);
+        changes.extend(manifest.set_requirement(&table_path(dependency), key, &requirement)?);
+    }
+    Ok(changes)
+}
+
+/// How far a fresh resolve would move a locked dependency: `major`, `minor` or `patch`.
+fn drift_level(locked: &semver::Version, resolved: &semver::Version) -> &'static str {
+    if resolved.major != locked.major {
+        "major"
+    } else if resolved.minor != locked.minor {
+        "minor"
+    } else {
+        "patch"
+    }
+}
+
+/// Print a dependency that a fresh resolve would move, e.g.
+/// `Drifting serde v1.0.100 -> v1.0.117 (minor, allowed by ^1.0)`.
+fn print_drift(
+    name: &str,
+    requirement: &str,
+    locked: &semver::Version,
+    resolved: &semver::Version,
+) -> Result<()> {
+    let bufwtr = BufferWriter::stdout(ColorChoice::Always);
+    let mut buffer = bufwtr.buffer();
+    buffer
+        .set_color(ColorSpec::new().set_fg(Some(Color::Yellow)).set_bold(true))
+        .chain_err(|| "Failed to set output colour")?;
+    write!(&mut buffer, "     Drifting ").chain_err(|| "Failed to write drift message")?;
+    buffer
+        .set_color(&ColorSpec::new())
+        .chain_err(|| "Failed to clear output colour")?;
+    writeln!(
+        &mut buffer,
+        "{} v{} -> v{} ({}, allowed by {})",
+        name,
+        locked,
+        resolved,
+        drift_level(locked, resolved),
+        requirement
+    )
+    .chain_err(|| "Failed to write drift message")?;
+    bufwtr
+        .print(&buffer)
+        .chain_err(|| "Failed to print drift message")
+}
+
+/// Print whether upgrading a dependency resolves, and if not, the requirements that block it, e.g.
+/// `Blocked my-prerelease v0.12.0 by:` followed by `helper v0.1.0 requires =0.12.0-rc.3`.
+///
+/// Cargo's own error is printed when none of the requirements can be pointed out.
+fn print_explanation(
+    name: &str,
+    version: &str,
+    blockers: &[explain::Blocker],
+    cargo_error: Option<&str>,
+) -> Result<()> {
+    let bufwtr = BufferWriter::stdout(ColorChoice::Always);
+    let mut buffer = bufwtr.buffer();
+    let (colour, verb) = match cargo_error {
+        None => (Color::Green, "   Resolvable "),
+        Some(_) => (Color::Red, "      Blocked "),
+    };
+    buffer
+        .set_color(ColorSpec::new().set_fg(Some(colour)).set_bold(true))
+        .chain_err(|| "Failed to set output colour")?;
+    write!(&mut buffer, "{}", verb).chain_err(|| "Failed to write explanation")?;
+    buffer
+        .set_color(&ColorSpec::new())
+        .chain_err(|| "Failed to clear output colour")?;
+    match cargo_error {
+        None => writeln!(
+            &mut buffer,
+            "{} v{}, nothing blocks the upgrade",
+            name, version
+        ),
+        Some(_) if !blockers.is_empty() => writeln!(&mut buffer, "{} v{} by:", name, version)
+            .and_then(|_| {
+                blockers.iter().try_for_each(|blocker| {
+                    writeln!(
+                        &mut buffer,
+                        "    {} requires {}",
+                        blocker.package, blocker.requirement
+                    )
+                })
+            }),
+        Some(error) => writeln!(
+            &mut buffer,
+            "{} v{}, as cargo can't resolve the dependency graph:\n    {}",
+            name,
+            version,
+            error.replace('\n', "\n    ")
+        ),
+    }
+    .chain_err(|| "Failed to write explanation")?;
+    bufwtr
+        .print(&buffer)
+        .chain_err(|| "Failed to print explanation")
+}
+
+/// Print the heading under which the changes on a branch created by `--batch` are reported, e.g.
+/// `cargo-upgrade/serde-1.0.118 (0123abc):`.
+fn branch_heading(branch: &str, commit: &str, first: bool) -> Result<()> {
+    let bufwtr = BufferWriter::stdout(ColorChoice::Always);
+    let mut buffer = bufwtr.buffer();
+    if !first {
+        writeln!(&mut buffer).chain_err(|| "Failed to write branch heading")?;
+    }
+    buffer
+        .set_color(ColorSpec::new().set_bold(true))
+        .chain_err(|| "Failed to set output colour")?;
+    write!(&mut buffer, "{}", branch).chain_err(|| "Failed to write branch heading")?;
+    buffer
+        .set_color(&ColorSpec::new())
+        .chain_err(|| "Failed to clear output colour")?;
+    let short = commit.get(..7).unwrap_or(commit);
+    writeln!(&mut buffer, " ({}):", short).chain_err(|| "Failed to write branch heading")?;
+    bufwtr
+        .print(&buffer)
+        .chain_err(|| "Failed to print branch heading")
+}
+
+/// Print a message for a `[patch]` entry that is dropped, as the version upgraded to is newer.
+fn print_dropped_patch(
+    name: &str,
+    section: &str,
+    patched: &semver::Version,
+    version: &str,
+) -> Result<()> {
+    let bufwtr = BufferWriter::stdout(ColorChoice::Always);
+    let mut buffer = bufwtr.buffer();
+    buffer
+        .set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))
+        .chain_err(|| "Failed to set output colour")?;
+    write!(&mut buffer, "     Dropping ").chain_err(|| "Failed to write patch message")?;
+    buffer
+        .set_color(&ColorSpec::new())
+        .chain_err(|| "Failed to clear output colour")?;
+    writeln!(
+        &mut buffer,
+        "[{}] entry for {} v{}, as v{} is newer",
+        section, name, patched, version
+    )
+    .chain_err(|| "Failed to write patch message")?;
+    bufwtr
+        .print(&buffer)
+        .chain_err(|| "Failed to print patch message")
+}
+
+/// Print a message for a manifest that needed no changes.
+fn print_up_to_date() -> Result<()> {
+    let bufwtr = BufferWriter::stdout(ColorChoice::Always);
+    let mut buffer = bufwtr.buffer();
+    buffer
+        .set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))
+        .chain_err(|| "Failed to set output colour")?;
+    write!(&mut buffer, "    Already ").chain_err(|| "Failed to write up to date message")?;
+    buffer
+        .set_color(&ColorSpec::new())
+        .chain_err(|| "Failed to clear output colour")?;
+    writeln!(&mut buffer, "up to date").chain_err(|| "Failed to write up to date message")?;
+    bufwtr
+        .print(&buffer)
+        .chain_err(|| "Failed to print up to date message")
+}
+
+fn dry_run_message() -> Result<()> {
+    let bufwtr = BufferWriter::stdout(ColorChoice::Always);
+    let mut buffer = bufwtr.buffer();
+    buffer
+        .set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bold(true))
+        .chain_err(|| "Failed to set output colour")?;
+    write!(&mut buffer, "Starting dry run. ").chain_err(|| "Failed to write dry run message")?;
+    buffer
+        .set_color(&ColorSpec::new())
+        .chain_err(|| "Failed to clear output colour")?;
+    writeln!(&mut buffer, "Changes will not be saved.")
+        .chain_err(|| "Failed to write dry run message")?;
+    bufwtr
+        .print(&buffer)
+        .chain_err(|| "Failed to print dry run message")
+}
+
+/// Update a registry index. If that fails (e.g. without network access), carry on with the local
+/// copy of the index, but warn that it may be stale.
+fn update_index_or_warn(url: &Url, net: &NetConfig) {
+    if let Err(err) = update_registry_index(url, false, net) {
+        eprintln!(
+            "WARN: Failed to update the '{}' index ({}), using the local copy which may be out \
+             of date",
+            url, err
+        );
+    }
+}
+
+/// Read the RustSec advisory database from `path`, or else from the copy shared with cargo-audit,
+/// which is brought up to date first unless offline. Problems with the shared copy are only warned
+/// about, as it is just used for warnings itself.
+fn load_advisories(path: Option<PathBuf>, net: &NetConfig) -> Result<Option<AdvisoryDb>> {
+    if let Some(path) = path {
+        return Ok(Some(AdvisoryDb::open(&path)?));
+    }
+
+    let path = AdvisoryDb::default_path()?;
+    if !net.offline && std::env::var("CARGO_IS_TEST").is_err() {
+        if let Err(err) = update_advisory_db(&path, false, net) {
+            eprintln!(
+                "WARN: Failed to update the RustSec advisory database ({}), using the local copy \
+                 if there is one",
+                err
+            );
+        }
+    }
+    if !path.exists() {
+        return Ok(None);
+    }
+    match AdvisoryDb::open(&path) {
+        Ok(advisories) => Ok(Some(advisories)),
+        Err(err) => {
+            eprintln!("WARN: {}", err);
+            Ok(None)
+        }
+    }
+}
+
+/// A dependency overridden by a `[patch]` or `[replace]` section of a workspace.
+#[derive(Debug, Clone)]
+struct Override {
+    /// The section overriding it, e.g. `patch.crates-io`.
+    section: String,
+    /// The manifest at the workspace root, which holds the section.
+    root: PathBuf,
+    /// The version of a patch, as locked in `Cargo.lock`, if it is there.
+    patched: Option<semver::Version>,
+}
+
+/// The versions that the lock file at `path` holds of packages that don't come from a registry,
+/// e.g. the git forks or local copies that patches point at.
+fn unregistered_versions(path: &Path) -> Result<HashMap<String, semver::Version>> {
+    Ok(Lockfile::read_or_default(path)?
+        .package
+        .into_iter()
+        .filter(|package| {
+            package
+                .source
+                .as_ref()
+                .is_none_or(|source| source.starts_with("git+"))
+        })
+        .map(|package| (package.name, package.version))
+        .collect())
+}
+
+/// The dependencies overridden by `[patch]` and `[replace]` sections. A workspace's sections
+/// only apply to its own manifests, so they are kept by the manifest at the root of each
+/// workspace, alongside the root of each manifest's workspace.
+#[derive(Debug, Default)]
+struct Overrides {
+    by_root: BTreeMap<PathBuf, BTreeMap<String, Override>>,
+    roots: HashMap<PathBuf, PathBuf>,
+}
+
+impl Overrides {
+    /// Read the overrides of the workspace at `manifest_path`, which `manifests` are members of.
+    fn add(&mut self, manifest_path: &Option<PathBuf>, manifests: &Manifests) -> Result<()> {
+        let (root, overrides) = get_overrides(manifest_path)?;
+        for (manifest, _) in &manifests.0 {
+            self.roots.insert(manifest.path.clone(), root.clone());
+        }
+        self.by_root.insert(root, overrides);
+        Ok(())
+    }
+
+    /// The manifests, of `manifests`, in the workspace at `root` that declare a dependency on
+    /// the crate `name`.
+    fn declaring<'m>(&self, root: &Path, name: &str, manifests: &'m Manifests) -> Vec<&'m Path> {
+        manifests
+            .0
+            .iter()
+            .filter(|(manifest, _)| {
+                self.roots.get(&manifest.path).map(PathBuf::as_path) == Some(root)
+            })
+            .filter(|(_, package)| declares(package, name))
+            .map(|(manifest, _)| manifest.path.as_path())
+            .collect()
+    }
+}
+
+/// Whether a package has a dependency on the crate `name` with a version requirement.
+fn declares(package: &cargo_metadata::Package, name: &str) -> bool {
+    package
+        .dependencies
+        .iter()
+        .any(|dependency| dependency.name == name && is_version_dep(dependency))
+}
+
+/// The dependencies overridden by the `[patch]` and `[replace]` sections of the workspace at
+/// `manifest_path`, and the manifest at its root.
+fn get_overrides(manifest_path: &Option<PathBuf>) -> Result<(PathBuf, BTreeMap<String, Override>)> {
+    let mut cmd = cargo_metadata::MetadataCommand::new();
+    cmd.no_deps();
+    if let Some(path) = manifest_path {
+        cmd.manifest_path(path);
+    }
+    let result = cmd
+        .exec()
+        .map_err(|e| Error::from(e.compat()).chain_err(|| "Failed to get workspace metadata"))?;
+    let root_path = result.workspace_root.join("Cargo.toml");
+    let root = LocalManifest::try_new(&root_path)?;
+    let locked = unregistered_versions(&result.workspace_root.join("Cargo.lock"))?;
+    let overrides = root
+        .get_overrides()
+        .into_iter()
+        .map(|(name, section)| {
+            let patched = if section.starts_with("patch.") {
+                locked.get(&name).cloned()
+            } else {
+                None
+            };
+            let root = root_path.clone();
+            (
+                name,
+                Override {
+                    section,
+                    root,
+                    patched,
+                },
+            )
+        })
+        .collect();
+    Ok((root_path, overrides))
+}
+
+/// Warn that a dependency is overridden, and return why it is skipped, unless it was named
+/// explicitly and is upgraded anyway.
+fn warn_overridden(name: &str, section: &str, explicit: bool) -> Option<String> {
+    if explicit {
+        eprintln!(
+            "WARN: `{}` is overridden by `[{}]`, so upgrading its version requirement may have no \
+             effect",
+            name, section
+        );
+        None
+    } else {
+        eprintln!(
+            "WARN: Not upgrading `{}`, as it is overridden by `[{}]`",
+            name, section
+        );
+        Some(format!("overridden by `[{}]`", section))
+    }
+}
+
+/// Remove the `[patch]` entries of the dependencies upgraded past their patched versions, from the
+/// manifests at the workspace roots.
+fn drop_patches(
+    dropped: &[(String, String, Override)],
+    dry_run: bool,
+    diff: bool,
+    format: MessageFormat,
+    edits: &mut Edits,
+) -> Result<()> {
+    let mut roots: Vec<&Path> = dropped.iter().map(|(_, _, o)| o.root.as_path()).collect();
+    roots.sort();
+    roots.dedup();
+    for root in roots {
+        // The root manifest may have been upgraded already, as a member of the workspace.
+        let mut manifest = match edits.saved(root) {
+            Some(manifest) if !diff => manifest.clone(),
+            _ => Manifest::open(&Some(root.to_owned()))?,
+        };
+        for (name, version, o) in dropped.iter().filter(|(_, _, o)| o.root == root) {
+            let patched = o
+                .patched
+                .as_ref()
+                .expect("only patches with a version are dropped");
+            for section in manifest.remove_patch(name) {
+                match format {
+                    MessageFormat::Human if !diff => {
+                        print_dropped_patch(name, &section, patched, version)?
+                    }
+                    MessageFormat::Human => {}
+                    MessageFormat::JsonLines => Message::PatchDropped {
+                        dependency: name,
+                        section: &section,
+                        manifest: &root.to_string_lossy(),
+                        patched: &patched.to_string(),
+                        version,
+                    }
+                    .emit()?,
+                }
+            }
+        }
+        if diff {
+            print_diff(&manifest, root)?;
+        } else if !dry_run {
+            edits.save(&manifest, root)?;
+        }
+    }
+    Ok(())
+}
+
+impl Manifests {
+    /// Get all manifests in the workspace, or only its default members unless `all_members` is set.
+    fn get_all(manifest_path: &Option<PathBuf>, all_members: bool) -> Result<Self> {
+        let mut cmd = cargo_metadata::MetadataCommand::new();
+        cmd.no_deps();
+        if let Some(path) = manifest_path {
+            cmd.manifest_path(path);
+        }
+        let result = cmd.exec().map_err(|e| {
+            Error::from(e.compat()).chain_err(|| "Failed to get workspace metadata")
+        })?;
+        workspace_members(&result, all_members)?
+            .into_iter()
+            .map(|package| {
+                Ok((
+                    LocalManifest::try_new(Path::new(&package.manifest_path))?,
+                    package,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(Manifests)
+    }
+
+    fn get_pkgid(pkgid: &str) -> Result<Self> {
+        let package = manifest_from_pkgid(pkgid)?;
+        let manifest = LocalManifest::try_new(Path::new(&package.manifest_path))?;
+        Ok(Manifests(vec![(manifest, package)]))
+    }
+
+    /// Get the manifest specified by the manifest path. Try to make an educated guess if no path is
+    /// provided.
+    fn get_local_one(manifest_path: &Option<PathBuf>) -> Result<Self> {
+        let resolved_manifest_path: String = find(manifest_path)?.to_string_lossy().into();
+
+        let manifest = LocalManifest::find(manifest_path)?;
+
+        let mut cmd = cargo_metadata::MetadataCommand::new();
+        cmd.no_deps();
+        if let Some(path) = manifest_path {
+            cmd.manifest_path(path);
+        }
+        let result = cmd
+            .exec()
+            .map_err(|e| Error::from(e.compat()).chain_err(|| "Invalid manifest"))?;
+        let packages = result.packages;
+        let package = packages
+            .iter()
+            .find(|p| p.manifest_path.to_string_lossy() == resolved_manifest_path)
+            // If we have successfully got metadata, but our manifest path does not correspond to a
+            // package, we must have been called against a virtual manifest.
+            .chain_err(|| {
+                "Found virtual manifest, but this command requires running against an \
+                 actual package in this workspace. Try adding `--workspace`."
+            })?;
+
+        Ok(Manifests(vec![(manifest, package.to_owned())]))
+    }
+
+    /// Get the the combined set of dependencies to upgrade. If the user has specified
+    /// per-dependency desired versions, extract those here.
+    fn get_dependencies(
+        &self,
+        only_update: Vec<String>,
+        exclude: Vec<String>,
+    ) -> Result<DesiredUpgrades> {
+        // Map the names of user-specified dependencies to the (optionally) requested version.
+        let selected_dependencies = only_update
+            .into_iter()
+            .map(|name| {
+                if let Some(dependency) = CrateName::new(&name).parse_as_version()? {
+                    Ok((
+                        dependency.name.clone(),
+                        dependency.version().map(String::from),
+                    ))
+                } else {
+                    Ok((name, None))
+                }
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+
+        // Path dependencies with a version requirement (as publishing needs) are kept in step
+        // with the local crate, rather than looked up.
+        let mut local_versions = HashMap::new();
+        let dependencies = self.0.iter().flat_map(|(local, package)| {
+            package
+                .dependencies
+                .iter()
+                .map(move |dependency| (local, dependency))
+        });
+        for (local, dependency) in dependencies {
+            let versioned = dependency_entry(local, dependency)
+                .and_then(|entry| entry.as_table_like())
+                .is_some_and(|entry| entry.get("version").is_some());
+            let dir = match &dependency.path {
+                Some(dir) if versioned => dir,
+                _ => continue,
+            };
+            let key = dependency.rename.as_ref().unwrap_or(&dependency.name);
+            let selected = selected_dependencies.is_empty()
+                || selected_dependencies.contains_key(&dependency.name)
+                || selected_dependencies.contains_key(key);
+            let excluded = exclude.contains(&dependency.name) || exclude.contains(key);
+            if selected && !excluded && !local_versions.contains_key(dir) {
+                if let Some(version) = local_version(dir)? {
+                    local_versions.insert(dir.clone(), version);
+                }
+            }
+        }
+
+        Ok(DesiredUpgrades(
+            self.0
+                .iter()
+                .flat_map(|(local, package)| {
+                    let manifest = package.manifest_path.clone();
+                    package
+                        .dependencies
+                        .clone()
+                        .into_iter()
+                        .map(move |dependency| {
+                            let registry = registry_name(local, &dependency);
+                            (dependency, registry, manifest.clone())
+                        })
+                })
+                .filter(|(dependency, _, _)| is_version_dep(dependency))
+                .filter(|(dependency, _, _)| !exclude.contains(&dependency.name))
+                // Exclude renamed dependecies aswell
+                .filter(|(dependency, _, _)| {
+                    dependency
+                        .rename
+                        .as_ref()
+                        .is_none_or(|rename| !exclude.contains(rename))
+                })
+                .filter_map(|(dependency, registry, manifest)| {
+                    let req = dependency.req.to_string();
+                    let is_prerelease = req.contains('-');
+                    let floor = requirement_floor(&req);
+                    // Crates of the same name from different registries are upgraded apart.
+                    let mut dep = Dependency::new(&dependency.name);
+                    if let Some(registry) = &registry {
+                        dep = dep.set_registry(registry);
+                    }
+                    if selected_dependencies.is_empty() {
+                        // User hasn't asked for any specific dependencies to be upgraded,
+                        // so upgrade all the dependencies.
+                        if let Some(rename) = dependency.rename {
+                            dep = dep.set_rename(&rename);
+                        }
+                        Some((
+                            dep,
+                            UpgradeMetadata {
+                                registry: dependency.registry,
+                                version: None,
+                                is_prerelease,
+                                floor,
+                                manifest,
+                            },
+                        ))
+                    } else {
+                        // User has asked for specific dependencies. Check if this dependency
+                        // was specified, populating the registry from the lockfile metadata.
+                        // A renamed entry can be picked out by its name in the manifest.
+                        let by_rename = dependency
+                            .rename
+                            .as_ref()
+                            .and_then(|rename| selected_dependencies.get(rename));
+                        if by_rename.is_some() {
+                            dep = dep.set_rename(dependency.rename.as_ref().unwrap());
+                        }
+                        match by_rename.or_else(|| selected_dependencies.get(&dependency.name)) {
+                            Some(version) => Some((
+                                dep,
+                                UpgradeMetadata {
+                                    registry: dependency.registry,
+                                    version: version.clone(),
+                                    is_prerelease,
+                                    floor,
+                                    manifest,
+                                },
+                            )),
+                            None => None,
+                        }
+                    }
+                })
+                .fold(HashMap::new(), |mut upgrades, (dep, mut metadata)| {
+                    // A dependency declared more than once is held to its newest requirement.
+                    if let Some(previous) = upgrades.get(&dep) {
+                        metadata.floor = metadata.floor.max(previous.floor.clone());
+                    }
+                    upgrades.insert(dep, metadata);
+                    upgrades
+                }),
+            local_versions,
+        ))
+    }
+
+    /// Describe the upgrades to versions that the current requirements don't match, i.e. that
+    /// cross a major version (or a minor version for `0.x` crates).
+    fn breaking_upgrades(&self, upgraded_deps: &ActualUpgrades) -> Vec<String> {
+        let mut breaking = self
+            .0
+            .iter()
+            .flat_map(|(manifest, package)| {
+                package
+                    .dependencies
+                    .iter()
+                    .filter(|dependency| is_version_dep(dependency))
+                    .filter(move |dependency| {
+                        !upgraded_deps.holds(&manifest.path, &dependency.name)
+                    })
+                    .filter_map(move |dependency| {
+                        let (_, version) = upgraded_deps
+                            .0
+                            .iter()
+                            .find(|(dep, _)| dep.name == dependency.name)?;
+                        // Explicitly requested requirements (e.g. `>=0.9, <2.0`) aren't checked.
+                        let new_version = semver::Version::parse(version).ok()?;
+                        if dependency.req.matches(&new_version) {
+                            return None;
+                        }
+                        Some(format!(
+                            "{} -> {} (in {})",
+                            dependency.name, version, package.name
+                        ))
+                    })
+            })
+            .collect::<Vec<_>>();
+        breaking.sort();
+        breaking.dedup();
+        breaking
+    }
+
+    /// Warn about features that dependencies are upgraded to versions without, and with
+    /// `fix`, drop or rename them (in memory, to be written with the upgrade).
+    fn check_features(
+        &mut self,
+        upgraded_deps: &ActualUpgrades,
+        skip_compatible: bool,
+        fix: bool,
+        dry_run: bool,
+    ) -> Result<()> {
+        for (manifest, _) in &mut self.0 {
+            for (dep, version) in &upgraded_deps.0 {
+                let available = match upgraded_deps.1.get(&dep.name) {
+                    Some(available) if !upgraded_deps.holds(&manifest.path, &dep.name) => available,
+                    _ => continue,
+                };
+                // Only entries whose requirement changes pick up the new version's features.
+                let new_dep = dep.clone().set_version(version);
+                if manifest
+                    .upgrade(&new_dep, true, skip_compatible)?
+                    .is_empty()
+                {
+                    continue;
+                }
+                let stale = manifest.stale_features(&dep.name, available);
+                if stale.is_empty() {
+                    continue;
+                }
+                stale_features_message(&manifest.path, version, &stale, fix)?;
+                if fix && !dry_run {
+                    manifest.fix_features(&stale)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Upgrade the manifests in memory following the previously-determined upgrade schema.
+    fn upgrade(
+        &mut self,
+        upgraded_deps: &ActualUpgrades,
+        dry_run: bool,
+        skip_compatible: bool,
+        diff: bool,
+        format: MessageFormat,
+        plan: &mut Vec<PlannedUpgrade>,
+    ) -> Result<()> {
+        // A diff is printed on its own, so it can be applied with `patch`.
+        let human = format == MessageFormat::Human && !diff;
+        if dry_run && human {
+            dry_run_message()?;
+        }
+
+        let breaking = self.breaking_upgrades(upgraded_deps);
+        if !breaking.is_empty() {
+            breaking_message(&breaking)?;
+        }
+
+        for (i, (manifest, package)) in self.0.iter_mut().enumerate() {
+            if human {
+                package_heading(package, i == 0)?;
+            }
+
+            let mut changes = Vec::new();
+            for (dep, version) in &upgraded_deps.0 {
+                if upgraded_deps.holds(&manifest.path, &dep.name) {
+                    continue;
+                }
+                let new_dep = dep.clone().set_version(version);
+                changes.extend(manifest.upgrade_in_memory(&new_dep, skip_compatible)?);
+            }
+            // Aligned requirements aren't versions to be compatible with, so they are always set.
+            for (dep, requirement) in &upgraded_deps.3 {
+                let aligned = dep.clone().set_version(requirement);
+                changes.extend(manifest.upgrade_in_memory(&aligned, false)?);
+            }
+            changes.extend(sync_local_versions(
+                manifest,
+                package,
+                &upgraded_deps.2,
+                skip_compatible,
+            )?);
+            if diff {
+                print_diff(manifest, &manifest.path)?;
+            } else {
+                report_changes(format, package, changes, dry_run, plan)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Collect the manifests, as they have been upgraded, to be written.
+    fn save(&self, edits: &mut Edits) -> Result<()> {
+        for (manifest, _) in &self.0 {
+            edits.save(manifest, &manifest.path)?;
+        }
+        Ok(())
+    }
+
+    /// Upgrade each dependency on a git branch of its own, created from the checked-out commit,
+    /// with a commit holding just its new requirements (and the lock file, if the repository
+    /// tracks one). The checked-out branch is left as it is.
+    fn upgrade_in_batches(
+        self,
+        upgraded_deps: &ActualUpgrades,
+        skip_compatible: bool,
+        fix_features: bool,
+        offline: bool,
+        format: MessageFormat,
+        plan: &mut Vec<PlannedUpgrade>,
+    ) -> Result<()> {
+        let repo = match self.0.first() {
+            Some((manifest, _)) => batch::Repo::open(&manifest.path)?,
+            None => return Ok(()),
+        };
+        let base = repo.head()?;
+        let lockfile = repo.tracks_lockfile()?;
+
+        let breaking = self.breaking_upgrades(upgraded_deps);
+        if !breaking.is_empty() {
+            breaking_message(&breaking)?;
+        }
+
+        let mut upgrades: Vec<_> = upgraded_deps.0.iter().collect();
+        upgrades.sort_by(|(a, a_version), (b, b_version)| {
+            (&a.name, a_version).cmp(&(&b.name, b_version))
+        });
+        let mut first = true;
+        for (dep, version) in upgrades {
+            let branch = batch::branch_name(&dep.name, version);
+            repo.create_branch(&branch, &base)?;
+            let upgraded = self
+                .upgrade_on_branch(
+                    upgraded_deps,
+                    (dep, version),
+                    skip_compatible,
+                    fix_features,
+                    lockfile,
+                    offline,
+                )
+                .and_then(|changes| match changes {
+                    Some(changes) => {
+                        let message = format!("Upgrade {} to {}", dep.name, version);
+                        Ok(Some((changes, repo.commit_all(&message)?)))
+                    }
+                    None => Ok(None),
+                });
+            repo.checkout(&base)?;
+            let (changes, commit) = match upgraded {
+                Ok(Some(upgraded)) => upgraded,
+                Ok(None) => {
+                    repo.delete_branch(&branch)?;
+                    continue;
+                }
+                Err(err) => {
+                    repo.delete_branch(&branch)?;
+                    return Err(err);
+                }
+            };
+
+            match format {
+                MessageFormat::Human => {
+                    branch_heading(&branch, &commit, first)?;
+                    for (_, change) in &changes {
+                        change.print()?;
+                    }
+                }
+                MessageFormat::JsonLines => Message::BranchCreated {
+                    dependency: &dep.name,
+                    version,
+                    branch: &branch,
+                    commit: &commit,
+                }
+                .emit()?,
+            }
+            first = false;
+            plan.extend(changes.into_iter().map(|(package, change)| PlannedUpgrade {
+                manifest: package.manifest_path.to_string_lossy().into_owned(),
+                package: package.name,
+                dependency: change.name,
+                old: change.old,
+                new: change.new,
+            }));
+        }
+        Ok(())
+    }
+
+    /// Upgrade one dependency in the manifests of the checked-out branch, returning the changes,
+    /// or nothing if there was nothing to change.
+    fn upgrade_on_branch(
+        &self,
+        upgraded_deps: &ActualUpgrades,
+        (dep, version): (&Dependency, &String),
+        skip_compatible: bool,
+        fix_features: bool,
+        lockfile: bool,
+        offline: bool,
+    ) -> Result<Option<Vec<(cargo_metadata::Package, VersionChange)>>> {
+        let single = ActualUpgrades(
+            std::iter::once((dep.clone(), version.clone())).collect(),
+            upgraded_deps.1.clone(),
+            HashMap::new(),
+            Vec::new(),
+            upgraded_deps.4.clone(),
+        );
+        // The manifests are read again, as they are on this branch.
+        let mut manifests = Manifests(
+            self.0
+                .iter()
+                .map(|(manifest, package)| {
+                    let mut reread = LocalManifest::try_new(&manifest.path)?;
+                    reread.concretize_wildcards = manifest.concretize_wildcards;
+                    reread.precise = manifest.precise;
+                    Ok((reread, package.clone()))
+                })
+                .collect::<Result<_>>()?,
+        );
+        manifests.check_features(&single, skip_compatible, fix_features, false)?;
+
+        let new_dep = dep.clone().set_version(version);
+        let mut changes = Vec::new();
+        for (manifest, package) in &mut manifests.0 {
+            if single.holds(&manifest.path, &dep.name) {
+                continue;
+            }
+            let changed = manifest.upgrade_in_memory(&new_dep, skip_compatible)?;
+            changes.extend(changed.into_iter().map(|change| (package.clone(), change)));
+        }
+        let mut edits = Edits::new();
+        manifests.save(&mut edits)?;
+        // Each upgrade is committed on its own branch, so it isn't journaled for `cargo edit undo`.
+        for file in edits.write()? {
+            if lockfile {
+                batch::update_lockfile(&file.path, offline)?;
+            }
+        }
+        if changes.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(changes))
+        }
+    }
+
+    /// The packages from registries and git that the lock file holds.
+    fn locked_packages(&self, net: &NetConfig) -> Result<Vec<cargo_metadata::Package>> {
+        // For workspaces with multiple Cargo.toml files, there is only a single lockfile, so it
+        // suffices to get metadata for any one of Cargo.toml files.
+        let (manifest, _package) = self.0.first().ok_or(ErrorKind::CargoEditLib(
+            crate::ErrorKind::InvalidCargoConfig,
+        ))?;
+        let mut cmd = cargo_metadata::MetadataCommand::new();
+        cmd.manifest_path(manifest.path.clone());
+        cmd.features(cargo_metadata::CargoOpt::AllFeatures);
+        let mut options = net.cargo_flags();
+        options.push("--locked".to_string());
+        cmd.other_options(options);
+
+        let result = cmd
+            .exec()
+            .map_err(|e| Error::from(e.compat()).chain_err(|| "Invalid manifest"))?;
+
+        Ok(result
+            .packages
+            .into_iter()
+            .filter(|p| p.source.is_some()) // Source is none for local packages
+            .collect())
+    }
+
+    /// Report the dependencies whose requirements allow newer versions than the lock file holds,
+    /// which a fresh resolve (e.g. `cargo generate-lockfile`) would move to.
+    fn check_lock(
+        &self,
+        default_source: &Option<RegistrySource>,
+        format: MessageFormat,
+        net: &NetConfig,
+    ) -> Result<()> {
+        let locked = self.locked_packages(net)?;
+        let human = format == MessageFormat::Human;
+
+        for (i, (manifest, package)) in self.0.iter().enumerate() {
+            if human {
+                package_heading(package, i == 0)?;
+            }
+
+            let mut seen = HashSet::new();
+            let mut drifted = false;
+            for dependency in package.dependencies.iter().filter(|d| is_version_dep(d)) {
+                let locked = match locked.iter().find(|p| is_locked(p, dependency)) {
+                    Some(locked) => &locked.version,
+                    None => continue,
+                };
+                if !seen.insert((&dependency.name, locked)) {
+                    continue;
+                }
+                let mut dep = Dependency::new(&dependency.name);
+                if let Some(registry) = registry_name(manifest, dependency) {
+                    dep = dep.set_registry(&registry);
+                }
+                let registry = dependency_source(
+                    &dep,
+                    dependency.registry.as_deref(),
+                    &manifest.path,
+                    default_source,
+                )?;
+                let resolved = match get_latest_matching(
+                    &dependency.name,
+                    &dependency.req,
+                    &manifest.path,
+                    &registry,
+                )? {
+                    Some(resolved) if resolved > *locked => resolved,
+                    _ => continue,
+                };
+
+                drifted = true;
+                let requirement = dependency.req.to_string();
+                match format {
+                    MessageFormat::Human => {
+                        print_drift(&dependency.name, &requirement, locked, &resolved)?
+                    }
+                    MessageFormat::JsonLines => Message::LockDrift {
+                        package: &package.name,
+                        manifest: &package.manifest_path.to_string_lossy(),
+                        dependency: &dependency.name,
+                        requirement: &requirement,
+                        locked: &locked.to_string(),
+                        resolved: &resolved.to_string(),
+                        level: drift_level(locked, &resolved),
+                    }
+                    .emit()?,
+                }
+            }
+            if human && !drifted {
+                print_up_to_date()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Report what keeps each dependency from being upgraded: the requirements elsewhere in the
+    /// dependency graph that conflict with its new version, once a trial resolution with the
+    /// upgraded manifests fails. The trial is run in a copy of the workspace, so that the manifests
+    /// aren't touched.
+    fn explain(self, upgraded_deps: &ActualUpgrades, net: &NetConfig) -> Result<()> {
+        let manifest_path = match self.0.first() {
+            Some((manifest, _)) => manifest.path.clone(),
+            None => return Ok(()),
+        };
+        let mut cmd = cargo_metadata::MetadataCommand::new();
+        cmd.manifest_path(&manifest_path);
+        cmd.features(cargo_metadata::CargoOpt::AllFeatures);
+        cmd.other_options(net.cargo_flags());
+        let metadata = cmd
+            .exec()
+            .map_err(|e| Error::from(e.compat()).chain_err(|| "Invalid manifest"))?;
+        let upgraded: Vec<_> = self
+            .0
+            .iter()
+            .map(|(_, package)| package.id.clone())
+            .collect();
+
+        if upgraded_deps.0.is_empty() {
+            return print_up_to_date();
+        }
+        let trial_workspace = explain::TrialWorkspace::new(&workspace_root(&manifest_path)?)?;
+        let trial_manifest_path = trial_workspace.path_of(&manifest_path)?;
+        for (dep, version) in &upgraded_deps.0 {
+            let new_dep = dep.clone().set_version(version);
+            let trial = self
+                .0
+                .iter()
+                .map(|(manifest, _)| {
+                    // Each dependency is tried on its own, from the manifests as they are on disk.
+                    let mut manifest = LocalManifest::try_new(&manifest.path)?;
+                    manifest.concretize_wildcards = self.0[0].0.concretize_wildcards;
+                    manifest.upgrade_in_memory(&new_dep, false)?;
+                    manifest.save(&trial_workspace.path_of(&manifest.path)?)?;
+                    Ok(())
+                })
+                .collect::<Result<Vec<_>>>()
+                .and_then(|_| explain::trial_resolve(&trial_manifest_path, net.offline));
+
+            let cargo_error = trial?;
+            let blockers = match (&cargo_error, semver::Version::parse(version)) {
+                (Some(_), Ok(version)) => {
+                    explain::blockers(&metadata, &dep.name, &version, &upgraded)
+                }
+                _ => Vec::new(),
+            };
+            print_explanation(&dep.name, version, &blockers, cargo_error.as_deref())?;
+        }
+        Ok(())
+    }
+
+    /// Update dependencies in Cargo.toml file(s) to match the corresponding
+    /// version in Cargo.lock, in memory. Returns the dependencies found in the lock file.
+    fn sync_to_lockfile(
+        &mut self,
+        dry_run: bool,
+        diff: bool,
+        format: MessageFormat,
+        alignments: &Alignments,
+        plan: &mut Vec<PlannedUpgrade>,
+        net: &NetConfig,
+    ) -> Result<Vec<LockedDependency>> {
+        // Get locked dependencies. For workspaces with multiple Cargo.toml
+        // files, there is only a single lockfile, so it suffices to get
+        // metadata for any one of Cargo.toml files.
+        let locked = self.locked_packages(net)?;
+
+        let human = format == MessageFormat::Human && !diff;
+        if dry_run && human {
+            dry_run_message()?;
+        }
+
+        let mut synced = Vec::new();
+        for (i, (manifest, package)) in self.0.iter_mut().enumerate() {
+            if human {
+                package_heading(package, i == 0)?;
+            }
+
+            // Upgrade the manifests one at a time, as multiple manifests may
+            // request the same dependency at differing versions.
+            let mut changes = Vec::new();
+            let locked_deps: Vec<_> = package
+                .dependencies
+                .iter()
+                .filter(|d| is_version_dep(d) && !alignments.excludes(&d.name))
+                .filter_map(|d| {
+                    // The requested dependency may be present in the lock file with different
+                    // versions, but only one will be semver-compatible with the requested version.
+                    let locked = locked.iter().find(|p| is_locked(p, d))?;
+                    let mut dep = Dependency::new(&d.name);
+                    if let Some(registry) = registry_name(manifest, d) {
+                        dep = dep.set_registry(&registry);
+                    }
+                    Some((dep, locked.version.clone(), d.registry.clone()))
+                })
+                .collect();
+            for (dep, version, index) in locked_deps {
+                changes.extend(
+                    manifest
+                        .upgrade_in_memory(&dep.clone().set_version(&version.to_string()), false)?,
+                );
+                synced.push(LockedDependency {
+                    dep,
+                    version,
+                    index,
+                    manifest: manifest.path.clone(),
+                });
+            }
+            for (dep, requirement) in &alignments.requirements {
+                changes.extend(
+                    manifest.upgrade_in_memory(&dep.clone().set_version(requirement), false)?,
+                );
+            }
+            if diff {
+                print_diff(manifest, &manifest.path)?;
+            } else {
+                report_changes(format, package, changes, dry_run, plan)?;
+            }
+        }
+        Ok(synced)
+    }
+}
+
+/// A dependency that '--to-lockfile' synced to the version in the lock file.
+struct LockedDependency {
+    dep: Dependency,
+    version: semver::Version,
+    // The index of its registry, as cargo metadata reports it.
+    index: Option<String>,
+    // The manifest declaring it.
+    manifest: PathBuf,
+}
+
+/// Note the dependencies whose latest release is semver incompatible with the version they are
+/// locked at, and so out of reach of '--to-lockfile'. A failed lookup is treated as there being no
+/// such release, as this is only informational.
+fn note_breaking_releases(
+    locked: &[LockedDependency],
+    default_source: &Option<RegistrySource>,
+) -> Result<()> {
+    let mut noted = HashSet::new();
+    for locked in locked {
+        if !noted.insert(&locked.dep.name) {
+            continue;
+        }
+        let latest = dependency_source(
+            &locked.dep,
+            locked.index.as_deref(),
+            &locked.manifest,
+            default_source,
+        )
+        .and_then(|registry| {
+            get_latest_dependency(&locked.dep.name, false, &locked.manifest, &registry)
+                .map_err(Error::from)
+        })
+        .ok()
+        .and_then(|latest| semver::Version::parse(latest.version()?).ok());
+        let compatible = semver::VersionReq::parse(&format!("^{}", locked.version))
+            .chain_err(|| format!("Invalid locked version of `{}`", locked.dep.name))?;
+        if let Some(latest) = latest.filter(|l| *l > locked.version && !compatible.matches(l)) {
+            eprintln!(
+                "note: {} v{} -> v{} available, not applied (semver incompatible)",
+                locked.dep.name, locked.version, latest
+            );
+        }
+    }
+    Ok(())
+}
+
+// Some metadata about the dependency
+// we're trying to upgrade.
+struct UpgradeMetadata {
+    registry: Option<String>,
+    // `Some` if the user has specified an explicit
+    // version to upgrade to.
+    version: Option<String>,
+    is_prerelease: bool,
+    // The lowest version the current requirement allows.
+    floor: Option<semver::Version>,
+    // A manifest that declares the dependency, whose configuration it is looked up with.
+    manifest: PathBuf,
+}
+
+/// A crate to look up the latest version of: its name, source, and whether prereleases count.
+type LookupKey = (String, Option<RegistrySource>, bool);
+
+/// The set of dependencies to be upgraded, alongside the registries returned from cargo metadata, and
+/// the desired versions, if specified by the user. Path dependencies are given separately, as the
+/// versions of the local crates, by their directories.
+struct DesiredUpgrades(
+    HashMap<Dependency, UpgradeMetadata>,
+    HashMap<PathBuf, String>,
+);
+
+/// The complete specification of the upgrades that will be performed. Map of the dependency names
+/// to the new versions, of the names to the features of the new versions, where they are known,
+/// and of the directories of path dependencies to the versions of the local crates. Then come the
+/// requirements that crates members disagree on are aligned to, with `--align`, and last the names
+/// of the dependencies left alone in particular manifests, as their workspaces override them.
+struct ActualUpgrades(
+    HashMap<Dependency, String>,
+    HashMap<String, BTreeSet<String>>,
+    HashMap<PathBuf, String>,
+    Vec<(Dependency, String)>,
+    HashMap<PathBuf, HashSet<String>>,
+);
+
+impl ActualUpgrades {
+    /// Whether the dependency on the crate `name` is left alone in the manifest at `manifest`.
+    fn holds(&self, manifest: &Path, name: &str) -> bool {
+        self.4
+            .get(manifest)
+            .is_some_and(|names| names.contains(name))
+    }
+}
+
+/// Restrictions on the versions picked, guarding against compromised releases and enforcing the
+/// organization's policy.
+#[derive(Debug, Clone)]
+struct TrustPolicy {
+    /// Only pick versions published by then, and leave dependencies alone if none of those is as
+    /// new as their current requirement.
+    published_before: Option<SystemTime>,
+    /// Fail to upgrade to versions published by someone who published none of the versions since
+    /// the current requirement, rather than only warning.
+    deny_new_publishers: bool,
+    /// Fail to upgrade to versions that can't be used without one of these licenses.
+    denied_licenses: Vec<String>,
+    /// Crates that may not be upgraded, and the versions they may be upgraded to.
+    policy: Policy,
+    /// The network settings to ask the registry about releases with.
+    net: NetConfig,
+}
+
+/// Check what crates.io records about the new version of a crate against the versions since the
+/// one the current requirement starts at: that it was published by someone who published earlier
+/// versions too, and that its license is the same and allowed.
+fn check_release(
+    name: &str,
+    since: Option<&semver::Version>,
+    version: &semver::Version,
+    manifest_path: &Path,
+    registry: &Option<RegistrySource>,
+    trust: &TrustPolicy,
+) -> Result<()> {
+    let strict = trust.deny_new_publishers || !trust.denied_licenses.is_empty();
+    let releases = match get_releases(name, manifest_path, registry, &trust.net) {
+        Ok(Some(releases)) => releases,
+        Ok(None) => return Ok(()),
+        Err(err) if strict => {
+            return Err(err).chain_err(|| format!("Failed to check the releases of `{}`", name))
+        }
+        Err(err) => {
+            eprintln!(
+                "WARN: Failed to check the releases of `{}` {}: {}",
+                name, version, err
+            );
+            return Ok(());
+        }
+    };
+
+    if let Some(license) = releases
+        .0
+        .get(version)
+        .and_then(|release| release.license.as_deref())
+    {
+        if !license_allowed(license, &trust.denied_licenses) {
+            bail!(
+                "`{}` {} is licensed under `{}`, which needs a denied license",
+                name,
+                version,
+                license
+            );
+        }
+    }
+    let since = match since {
+        Some(since) => since,
+        None => return Ok(()),
+    };
+    if let Some((old, new)) = releases.license_change(since, version) {
+        eprintln!(
+            "WARN: The license of `{}` changes from `{}` to `{}` in {}",
+            name, old, new, version
+        );
+    }
+    if let Some(publisher) = releases.new_publisher(since, version) {
+        let message = format!(
+            "`{}` {} was published by `{}`, who published none of its versions since {}",
+            name, version, publisher, since
+        );
+        if trust.deny_new_publishers {
+            bail!(message);
+        }
+        eprintln!(
+            "WARN: {}; check who owns the crate now before trusting it",
+            message
+        );
+    }
+    Ok(())
+}
+
+/// A dependency, the version to upgrade it to, and the features of that version if they are known.
+type Upgrade = (Dependency, String, Option<BTreeSet<String>>);
+
+impl DesiredUpgrades {
+    /// Warn about dependencies that the advisory database says are unmaintained, suggesting the
+    /// replacements it names, or vulnerable, and about those whose every version has been yanked.
+    /// The problems are returned too, for `--report`.
+    fn check_advisories(
+        &self,
+        advisories: Option<&AdvisoryDb>,
+        default_source: &Option<RegistrySource>,
+    ) -> Result<Vec<Finding>> {
+        let mut findings = Vec::new();
+        let mut found = |rule, dependency: &str, message: String| {
+            eprintln!("WARN: {}", message);
+            findings.push(Finding {
+                rule,
+                dependency: dependency.to_owned(),
+                manifest: None,
+                message,
+            });
+        };
+        let mut deps: Vec<_> = self.0.iter().collect();
+        deps.sort_by(|(a, _), (b, _)| a.name.cmp(&b.name));
+        for (
+            dep,
+            UpgradeMetadata {
+                registry,
+                floor,
+                manifest,
+                ..
+            },
+        ) in deps
+        {
+            let version = floor
+                .clone()
+                .unwrap_or_else(|| semver::Version::new(0, 0, 0));
+            if let Some(advisory) = advisories.and_then(|db| db.unmaintained(&dep.name, &version)) {
+                let alternatives: Vec<_> = advisory
+                    .alternatives
+                    .iter()
+                    .map(|name| format!("`{}`", name))
+                    .collect();
+                let suggestion = if alternatives.is_empty() {
+                    String::new()
+                } else {
+                    format!("; consider {} instead", alternatives.join(" or "))
+                };
+                found(
+                    "unmaintained",
+                    &dep.name,
+                    format!(
+                        "`{}` is unmaintained ({}){}",
+                        dep.name, advisory.id, suggestion
+                    ),
+                );
+            }
+            for advisory in advisories
+                .map(|db| db.vulnerabilities(&dep.name, &version))
+                .unwrap_or_default()
+            {
+                found(
+                    "vulnerable",
+                    &dep.name,
+                    format!(
+                        "`{}` v{} has a known vulnerability ({})",
+                        dep.name, version, advisory.id
+                    ),
+                );
+            }
+
+            let registry = dependency_source(dep, registry.as_deref(), manifest, default_source)?;
+            // A failed lookup is reported when looking for the new version.
+            if is_fully_yanked(&dep.name, manifest, &registry).unwrap_or(false) {
+                found(
+                    "yanked",
+                    &dep.name,
+                    format!("Every version of `{}` has been yanked", dep.name),
+                );
+            }
+        }
+        Ok(findings)
+    }
+
+    /// Transform the dependencies into their upgraded forms. If a version is specified, all
+    /// dependencies will get that version. Dependencies without an explicit registry are looked up
+    /// in `default_source`, if given.
+    ///
+    /// With `keep_going`, dependencies whose lookup fails are left out, and returned alongside the
+    /// upgrades with the reason they failed.
+    fn get_upgraded(
+        self,
+        allow_prerelease: bool,
+        default_source: &Option<RegistrySource>,
+        format: MessageFormat,
+        keep_going: bool,
+        trust: TrustPolicy,
+    ) -> Result<(ActualUpgrades, Vec<(String, Error)>)> {
+        // A crate is looked up once per run, however many tables and manifests declare it. A
+        // failed lookup is remembered as `None`, so it isn't retried either.
+        let latest_versions: RefCell<HashMap<LookupKey, Option<String>>> = RefCell::default();
+        let lookup = |(
+            dep,
+            UpgradeMetadata {
+                registry,
+                version,
+                is_prerelease,
+                floor,
+                manifest,
+            },
+        ): (Dependency, UpgradeMetadata)|
+         -> Result<Option<Upgrade>> {
+            trust.policy.check_crate(&dep.name)?;
+            if let Some(v) = version {
+                if let Some(floor) = requirement_floor(&v) {
+                    trust.policy.check_version(&dep.name, &floor)?;
+                }
+                Ok(Some((dep, v, None)))
+            } else {
+                let registry =
+                    dependency_source(&dep, registry.as_deref(), &manifest, default_source)?;
+                if format == MessageFormat::JsonLines {
+                    Message::LookupStarted {
+                        dependency: &dep.name,
+                    }
+                    .emit()?;
+                }
+                let latest = |allow_prerelease| {
+                    let key = (dep.name.clone(), registry.clone(), allow_prerelease);
+                    match latest_versions.borrow().get(&key) {
+                        Some(Some(version)) => return Ok(version.clone()),
+                        Some(None) => bail!(ErrorKind::LookupFailedEarlier(dep.name.clone())),
+                        None => {}
+                    }
+                    let version = match trust.published_before {
+                        Some(time) => get_latest_dependency_published_before(
+                            &dep.name,
+                            allow_prerelease,
+                            time,
+                            &manifest,
+                            &registry,
+                            &trust.net,
+                        ),
+                        None => {
+                            get_latest_dependency(&dep.name, allow_prerelease, &manifest, &registry)
+                        }
+                    }
+                    .map(|new_dep| {
+                        new_dep
+                            .version()
+                            .expect("Invalid dependency type")
+                            .to_string()
+                    })
+                    .chain_err(|| "Failed to get new version");
+                    latest_versions
+                        .borrow_mut()
+                        .insert(key, version.as_ref().ok().cloned());
+                    version
+                };
+                let version = match floor.clone().filter(|floor| floor.is_prerelease()) {
+                    // A prerelease requirement moves to a stable release once there is
+                    // one at or above it, and otherwise to the latest prerelease.
+                    Some(floor) if !allow_prerelease => match latest(false) {
+                        Ok(stable)
+                            if semver::Version::parse(&stable)
+                                .is_ok_and(|stable| stable >= floor) =>
+                        {
+                            stable
+                        }
+                        _ => latest(true)?,
+                    },
+                    _ => latest(allow_prerelease || is_prerelease)?,
+                };
+                let version = match semver::Version::parse(&version) {
+                    Ok(latest) => {
+                        let allowed = trust
+                            .policy
+                            .clamp(&dep.name, &latest, &manifest, &registry)?;
+                        if allowed != latest {
+                            if let Some(floor) = floor.as_ref().filter(|floor| **floor > allowed) {
+                                // Moving below the current requirement isn't an upgrade, so this
+                                // only fails if the requirement is against the policy itself.
+                                trust.policy.check_version(&dep.name, floor)?;
+                                eprintln!(
+                                    "WARN: Not upgrading `{}`, as the policy allows no newer \
+                                     version",
+                                    dep.name
+                                );
+                                if format == MessageFormat::JsonLines {
+                                    Message::DependencySkipped {
+                                        dependency: &dep.name,
+                                        skip_reason: "the policy allows no newer version"
+                                            .to_owned(),
+                                    }
+                                    .emit()?;
+                                }
+                                return Ok(None);
+                            }
+                            eprintln!(
+                                "WARN: Upgrading `{}` to {}, as the policy doesn't allow {}",
+                                dep.name, allowed, latest
+                            );
+                        }
+                        allowed.to_string()
+                    }
+                    Err(_) => version,
+                };
+                if format == MessageFormat::JsonLines {
+                    Message::LookupFinished {
+                        dependency: &dep.name,
+                        version: &version,
+                    }
+                    .emit()?;
+                }
+                // Releases too new for the cooldown may leave only versions older than the
+                // current requirement, which aren't worth moving back to.
+                let parsed = semver::Version::parse(&version).ok();
+                if trust.published_before.is_some() && parsed.is_some() && floor > parsed {
+                    eprintln!(
+                        "WARN: Not upgrading `{}`, as no version it could move to was published \
+                         long enough ago",
+                        dep.name
+                    );
+                    if format == MessageFormat::JsonLines {
+                        Message::DependencySkipped {
+                            dependency: &dep.name,
+                            skip_reason: "no version is old enough".to_owned(),
+                        }
+                        .emit()?;
+                    }
+                    return Ok(None);
+                }
+                if let Some(version) = &parsed {
+                    check_release(
+                        &dep.name,
+                        floor.as_ref(),
+                        version,
+                        &manifest,
+                        &registry,
+                        &trust,
+                    )?;
+                }
+                // Features are only checked on a best-effort basis, so failing to read them
+                // doesn't stop the upgrade.
+                let features = parsed
+                    .and_then(|v| get_features(&dep.name, &v, &manifest, &registry).ok())
+                    .flatten();
+                Ok(Some((dep, version, features)))
+            }
+        };
+
+        let mut upgrades = HashMap::new();
+        let mut features = HashMap::new();
+        let mut failed = Vec::new();
+        for (dep, metadata) in self.0 {
+            let name = dep.name.clone();
+            let manifest = metadata.manifest.clone();
+            match lookup((dep, metadata)) {
+                Ok(None) => {}
+                Ok(Some((dep, version, new_features))) => {
+                    if let Some(new_features) = new_features {
+                        features.insert(name, new_features);
+                    }
+                    upgrades.insert(dep, version);
+                }
+                // The failure was reported for the first dependency on the crate.
+                Err(Error(ErrorKind::LookupFailedEarlier(_), _)) => {}
+                Err(err) => {
+                    if format == MessageFormat::JsonLines {
+                        let kind = FailureKind::of(&err);
+                        Message::LookupFailed {
+                            dependency: &name,
+                            manifest: &relative_to_cwd(&manifest).display().to_string(),
+                            kind,
+                            retryable: kind.is_retryable(),
+                            error: root_cause(&err),
+                        }
+                        .emit()?;
+                    }
+                    if !keep_going {
+                        return Err(err);
+                    }
+                    failed.push((name, err));
+                }
+            }
+        }
+        Ok((
+            ActualUpgrades(upgrades, features, self.1, Vec::new(), HashMap::new()),
+            failed,
+        ))
+    }
+}
+
+/// Install the latest release of cargo-edit over this one.
+fn update_self(offline: bool) -> Result<()> {
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let mut cmd = subprocess::Exec::cmd(cargo).args(&["install", "cargo-edit"]);
+    // Executables installed for each command are replaced along with `cargo-edit`.
+    if cfg!(feature = "separate-bins") {
+        cmd = cmd.args(&["--features", "separate-bins"]);
+    }
+    if offline {
+        cmd = cmd.arg("--offline");
+    }
+    let status = cmd.join().chain_err(|| "Failed to run `cargo install`")?;
+    if !status.success() {
+        bail!("`cargo install cargo-edit` failed");
+    }
+    Ok(())
+}
+
+/// Main processing function. Allows us to return a `Result` so that `main` can print pretty error
+/// messages.
+fn process(args: Args) -> Result<()> {
+    if args.self_update {
+        return update_self(args.net.offline || args.net.frozen);
+    }
+
+    let Args {
+        dependency,
+        manifest_path,
+        recursive,
+        pkgid,
+        all,
+        allow_prerelease,
+        dry_run,
+        diff,
+        skip_compatible,
+        concretize_wildcards,
+        to_precise,
+        keep_going,
+        fix_features,
+        batch,
+        min_age,
+        deny_new_publishers,
+        deny_license,
+        drop_patches: drop_patch_entries,
+        to_lockfile,
+        no_notices,
+        no_verify,
+        check_lock,
+        explain,
+        workspace,
+        all_members,
+        exclude,
+        index,
+        local_registry,
+        message_format,
+        policy,
+        advisory_db,
+        report,
+        report_path,
+        align,
+        net,
+        identity,
+        ..
+    } = args;
+    let mut net = identity.apply(net.net_config()?)?;
+    net.verify_api = !no_verify;
+    let offline = net.offline;
+
+    if all {
+        deprecated_message("The flag `--all` has been deprecated in favor of `--workspace`")?;
+    }
+
+    let all = workspace || all;
+
+    // Without `--manifest-path` or `--recursive`, the manifest is found from the current directory.
+    // Registry configuration is looked up relative to the first manifest.
+    let manifest_path = match recursive {
+        Some(dir) => {
+            let found = scan::find_manifests(&dir)?;
+            if found.is_empty() {
+                bail!("No manifests found under `{}`", dir.display());
+            }
+            found
+        }
+        None => manifest_path,
+    };
+    let manifest_paths: Vec<Option<PathBuf>> = if manifest_path.is_empty() {
+        vec![None]
+    } else {
+        manifest_path.into_iter().map(Some).collect()
+    };
+
+    let default_source = match (local_registry, index) {
+        (Some(path), _) => Some(RegistrySource::from_local_path(&path)),
+        (None, Some(index)) => Some(RegistrySource::from_index_url(index)),
+        (None, None) => None,
+    };
+
+    if !offline && !to_lockfile && std::env::var("CARGO_IS_TEST").is_err() {
+        // Each manifest may be configured with a registry of its own.
+        let mut updated = HashSet::new();
+        for manifest_path in &manifest_paths {
+            let source = match &default_source {
+                Some(source) => source.clone(),
+                None => registry_source(&find(manifest_path)?, None)?,
+            };
+            // Local sources are always up to date.
+            if let RegistrySource::Remote(url) = source {
+                if updated.insert(url.clone()) {
+                    update_index_or_warn(&url, &net);
+                }
+            }
+        }
+    }
+
+    let mut manifests = manifest_paths
+        .iter()
+        .map(|manifest_path| {
+            if all {
+                Manifests::get_all(manifest_path, all_members)
+            } else if let Some(ref pkgid) = pkgid {
+                Manifests::get_pkgid(pkgid)
+            } else if LocalManifest::find(manifest_path)?.is_virtual() {
+                // There is no package to upgrade at a workspace root, so upgrade its members.
+                Manifests::get_all(manifest_path, all_members)
+            } else {
+                Manifests::get_local_one(manifest_path)
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    for (manifest, _) in manifests.iter_mut().flat_map(|manifests| &mut manifests.0) {
+        manifest.concretize_wildcards = concretize_wildcards;
+        manifest.precise = to_precise;
+    }
+
+    if check_lock {
+        for manifests in manifests {
+            manifests.check_lock(&default_source, message_format, &net)?;
+        }
+        return Ok(());
+    }
+
+    let mut reported: Vec<ReportedManifest> = Vec::new();
+    for (_, package) in manifests.iter().flat_map(|manifests| &manifests.0) {
+        if reported.iter().all(|m| m.path != package.manifest_path) {
+            reported.push(ReportedManifest {
+                package: package.name.clone(),
+                path: package.manifest_path.clone(),
+                dependencies: package
+                    .dependencies
+                    .iter()
+                    .filter(|dependency| is_version_dep(dependency))
+                    .map(|dependency| dependency.name.clone())
+                    .collect(),
+            });
+        }
+    }
+
+    let mut plan = Vec::new();
+    let mut failed_lookups = Vec::new();
+    let mut findings = Vec::new();
+    if to_lockfile {
+        let conflicts = align::find_conflicts(manifests.iter().flat_map(|m| &m.0), |_| true);
+        let alignments = align::decide(&conflicts, align, false, to_precise)?;
+
+        // Each manifest is synced to its own lock file.
+        let mut locked = Vec::new();
+        let mut edits = Edits::new();
+        for mut manifests in manifests {
+            locked.extend(manifests.sync_to_lockfile(
+                dry_run,
+                diff,
+                message_format,
+                &alignments,
+                &mut plan,
+                &net,
+            )?);
+            if !dry_run && !diff {
+                manifests.save(&mut edits)?;
+            }
+        }
+        record_for_undo(&edits.write()?)?;
+        if !no_notices {
+            note_breaking_releases(&locked, &default_source)?;
+        }
+    } else {
+        let mut overrides = Overrides::default();
+        for (manifest_path, manifests) in manifest_paths.iter().zip(&manifests) {
+            overrides.add(manifest_path, manifests)?;
+        }
+
+        // Upgrade all the manifests together, so each dependency is only looked up once.
+        let mut seen = HashSet::new();
+        let mut manifests = Manifests(
+            manifests
+                .into_iter()
+                .flat_map(|manifests| manifests.0)
+                .filter(|(manifest, _)| seen.insert(manifest.path.clone()))
+                .collect(),
+        );
+
+        let dependency = match &explain {
+            Some(dep) => vec![dep.clone()],
+            None => dependency,
+        };
+        let explicit = !dependency.is_empty();
+        let mut existing_dependencies = manifests.get_dependencies(dependency, exclude)?;
+
+        // Bumping the requirement of an overridden dependency doesn't change what is built, so
+        // only do it when asked to explicitly. With `--drop-patches`, patched dependencies are
+        // looked up to see if the patch can go.
+        let droppable = |o: &Override| drop_patch_entries && o.patched.is_some();
+        let mut held: HashMap<PathBuf, HashSet<String>> = HashMap::new();
+        let mut skipped = Vec::new();
+        let names: BTreeSet<_> = existing_dependencies
+            .0
+            .keys()
+            .map(|dep| dep.name.clone())
+            .collect();
+        for (root, overridden) in &overrides.by_root {
+            for (name, o) in overridden {
+                if droppable(o) || !names.contains(name) {
+                    continue;
+                }
+                let declaring = overrides.declaring(root, name, &manifests);
+                if declaring.is_empty() {
+                    continue;
+                }
+                if let Some(reason) = warn_overridden(name, &o.section, explicit) {
+                    skipped.push((name.clone(), reason));
+                    for manifest in declaring {
+                        held.entry(manifest.to_owned())
+                            .or_default()
+                            .insert(name.clone());
+                    }
+                }
+            }
+        }
+        let is_held = |held: &HashMap<PathBuf, HashSet<String>>, name: &str| {
+            manifests.0.iter().all(|(manifest, package)| {
+                !declares(package, name)
+                    || held
+                        .get(&manifest.path)
+                        .is_some_and(|names| names.contains(name))
+            })
+        };
+        existing_dependencies
+            .0
+            .retain(|dep, _| !is_held(&held, &dep.name));
+        if message_format == MessageFormat::JsonLines {
+            for (dependency, skip_reason) in &skipped {
+                Message::DependencySkipped {
+                    dependency,
+                    skip_reason: skip_reason.clone(),
+                }
+                .emit()?;
+            }
+        }
+
+        // Crates members disagree on are aligned as asked, apart from the upgrades.
+        let alignments = if batch || explain.is_some() {
+            Alignments::default()
+        } else {
+            let in_run = |name: &str| existing_dependencies.0.keys().any(|dep| dep.name == name);
+            let conflicts = align::find_conflicts(&manifests.0, in_run);
+            align::decide(&conflicts, align, true, to_precise)?
+        };
+        existing_dependencies
+            .0
+            .retain(|dep, _| !alignments.excludes(&dep.name));
+
+        // Update indices for any alternative registries, unless
+        // we're offline.
+        if !offline && std::env::var("CARGO_IS_TEST").is_err() {
+            for registry_url in existing_dependencies
+                .0
+                .values()
+                .filter_map(|UpgradeMetadata { registry, .. }| registry.as_ref())
+                .collect::<HashSet<_>>()
+            {
+                let url = Url::parse(registry_url)
+                    .map_err(|_| ErrorKind::CargoEditLib(crate::ErrorKind::InvalidCargoConfig))?;
+                update_index_or_warn(&url, &net);
+            }
+        }
+
+        let advisories = load_advisories(advisory_db, &net)?;
+        findings = existing_dependencies.check_advisories(advisories.as_ref(), &default_source)?;
+
+        let (mut upgraded_dependencies, failed) = existing_dependencies.get_upgraded(
+            allow_prerelease,
+            &default_source,
+            message_format,
+            keep_going,
+            TrustPolicy {
+                published_before: min_age.map(|age| SystemTime::now() - age),
+                deny_new_publishers,
+                denied_licenses: deny_license,
+                policy: Policy::from_path(policy.as_deref())?,
+                net: net.clone(),
+            },
+        )?;
+        failed_lookups = failed;
+        upgraded_dependencies.3 = alignments.requirements;
+
+        // A patch is only dropped once the release is newer than it, and so likely includes its
+        // changes.
+        let mut dropped = Vec::new();
+        let mut skipped = Vec::new();
+        for (root, overridden) in &overrides.by_root {
+            for (name, o) in overridden.iter().filter(|(_, o)| droppable(o)) {
+                let declaring = overrides.declaring(root, name, &manifests);
+                let versions = upgraded_dependencies
+                    .0
+                    .iter()
+                    .filter(|(dep, _)| &dep.name == name && !declaring.is_empty());
+                for (_, version) in versions {
+                    let patched = o
+                        .patched
+                        .as_ref()
+                        .expect("droppable patches have a version");
+                    if semver::Version::parse(version).is_ok_and(|version| version > *patched) {
+                        dropped.push((name.clone(), version.clone(), o.clone()));
+                        continue;
+                    }
+                    eprintln!(
+                        "WARN: `{}` {} is not newer than the patched {}, so `[{}]` is kept",
+                        name, version, patched, o.section
+                    );
+                    if let Some(reason) = warn_overridden(name, &o.section, explicit) {
+                        skipped.push((name.clone(), reason));
+                        for manifest in &declaring {
+                            held.entry(manifest.to_path_buf())
+                                .or_default()
+                                .insert(name.clone());
+                        }
+                    }
+                }
+            }
+        }
+        upgraded_dependencies
+            .0
+            .retain(|dep, _| !is_held(&held, &dep.name));
+        upgraded_dependencies.4 = held;
+        if message_format == MessageFormat::JsonLines {
+            for (dependency, skip_reason) in &skipped {
+                Message::DependencySkipped {
+                    dependency,
+                    skip_reason: skip_reason.clone(),
+                }
+                .emit()?;
+            }
+        }
+
+        if explain.is_some() {
+            manifests.explain(&upgraded_dependencies, &net)?;
+        } else if batch {
+            manifests.upgrade_in_batches(
+                &upgraded_dependencies,
+                skip_compatible,
+                fix_features,
+                offline,
+                message_format,
+                &mut plan,
+            )?;
+        } else {
+            manifests.check_features(
+                &upgraded_dependencies,
+                skip_compatible,
+                fix_features,
+                dry_run,
+            )?;
+            manifests.upgrade(
+                &upgraded_dependencies,
+                dry_run,
+                skip_compatible,
+                diff,
+                message_format,
+                &mut plan,
+            )?;
+            let mut edits = Edits::new();
+            if !dry_run && !diff {
+                manifests.save(&mut edits)?;
+            }
+            drop_patches(&dropped, dry_run, diff, message_format, &mut edits)?;
+            record_for_undo(&edits.write()?)?;
+        }
+    }
+
+    if message_format == MessageFormat::JsonLines {
+        Message::Plan {
+            dry_run,
+            upgrades: &plan,
+        }
+        .emit()?;
+    } else if let Ok(dir) = std::env::current_dir() {
+        // `cargo upgrade --self` installs with the configuration of the current directory.
+        notify_of_newer_release(&dir.join("Cargo.toml"));
+    }
+
+    if let (Some(format), Some(path)) = (report, report_path) {
+        let written = !dry_run && !diff;
+        findings.extend(
+            plan.iter()
+                .map(|upgrade| Finding::outdated(upgrade, !written)),
+        );
+        write_report(format, &path, &reported, &findings)?;
+    }
+
+    if !failed_lookups.is_empty() {
+        let mut failures = failed_lookups
+            .iter()
+            .map(|(name, err)| format!("{}: {}", name, root_cause(err)))
+            .collect::<Vec<_>>();
+        failures.sort();
+        bail!(
+            "Failed to look up the latest version of these dependencies, which were left \
+             unchanged:\n    {}",
+            failures.join("\n    ")
+        );
+    }
+    Ok(())
+}
+
+/// Run `cargo upgrade` with a command line as cargo passes it, e.g. from
+/// `std::env::args_os`.
+pub fn run(args: impl IntoIterator<Item = OsString>) {
+    let args: Command = Command::from_iter(args);
+    let Command::Upgrade(args) = args;
+
+    if let Err(err) = process(args) {
+        super::exit_with_error(&err);
+    }
+}
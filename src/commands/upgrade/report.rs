@@ -0,0 +1,295 @@
+//! Reports for CI systems, for `--report`: JUnit XML, for outdated and vulnerable dependencies to
+//! show up as failed tests, or SARIF, for them to show up as code-scanning annotations on the
+//! manifests.
+
+use super::errors::*;
+use super::message::PlannedUpgrade;
+use super::relative_to_cwd;
+use crate::{Manifest, Span};
+use error_chain::bail;
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The format of the report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// JUnit XML, with a test case for each dependency of each manifest.
+    Junit,
+    /// SARIF 2.1.0, with a result for each problem, located at the entries in the manifests.
+    Sarif,
+}
+
+impl ReportFormat {
+    /// The accepted values of `--report`.
+    pub const VARIANTS: &'static [&'static str] = &["junit", "sarif"];
+}
+
+impl FromStr for ReportFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "junit" => Ok(ReportFormat::Junit),
+            "sarif" => Ok(ReportFormat::Sarif),
+            _ => bail!("Unknown report format `{}`", s),
+        }
+    }
+}
+
+/// The kinds of problem reported, with a description and their SARIF level.
+const RULES: &[(&str, &str, &str)] = &[
+    (
+        "outdated",
+        "The requirement on a dependency is behind its latest version",
+        "warning",
+    ),
+    (
+        "vulnerable",
+        "The requirement on a dependency allows versions with a known vulnerability",
+        "error",
+    ),
+    (
+        "unmaintained",
+        "A dependency is no longer maintained",
+        "warning",
+    ),
+    (
+        "yanked",
+        "Every version of a dependency has been yanked",
+        "error",
+    ),
+];
+
+/// A problem with a dependency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// The kind of problem, one of `RULES`.
+    pub rule: &'static str,
+    /// Name of the dependency.
+    pub dependency: String,
+    /// The manifest with the problem, or `None` for every manifest depending on the crate.
+    pub manifest: Option<PathBuf>,
+    /// What is wrong.
+    pub message: String,
+}
+
+impl Finding {
+    /// A dependency that is (or, for a dry run, would be) upgraded.
+    pub fn outdated(upgrade: &PlannedUpgrade, dry_run: bool) -> Self {
+        let verb = if dry_run { "can be" } else { "was" };
+        Finding {
+            rule: "outdated",
+            dependency: upgrade.dependency.clone(),
+            manifest: Some(PathBuf::from(&upgrade.manifest)),
+            message: format!(
+                "`{}` {} upgraded from {} to {}",
+                upgrade.dependency,
+                verb,
+                upgrade.old.as_deref().unwrap_or("*"),
+                upgrade.new.as_deref().unwrap_or("*")
+            ),
+        }
+    }
+
+    fn applies_to(&self, manifest: &ReportedManifest) -> bool {
+        self.manifest
+            .as_ref()
+            .map_or(manifest.dependencies.contains(&self.dependency), |path| {
+                *path == manifest.path
+            })
+    }
+}
+
+/// A manifest covered by the report, and the registry dependencies it has.
+#[derive(Debug, Clone)]
+pub struct ReportedManifest {
+    /// Name of its package.
+    pub package: String,
+    /// Its path.
+    pub path: PathBuf,
+    /// The crates it depends on, from registries.
+    pub dependencies: Vec<String>,
+}
+
+/// Write a report of the problems found with the dependencies of some manifests to `path`.
+pub fn write_report(
+    format: ReportFormat,
+    path: &Path,
+    manifests: &[ReportedManifest],
+    findings: &[Finding],
+) -> Result<()> {
+    let report = match format {
+        ReportFormat::Junit => junit(manifests, findings)?,
+        ReportFormat::Sarif => sarif(manifests, findings)?,
+    };
+    fs::write(path, report).chain_err(|| format!("Failed to write `{}`", path.display()))
+}
+
+/// The path of a manifest as reported: relative to the current directory where possible, and with
+/// `/` separators.
+fn report_path(path: &Path) -> String {
+    relative_to_cwd(path).to_string_lossy().replace('\\', "/")
+}
+
+/// Where the entries for a dependency are in a manifest.
+fn spans(manifest: &ReportedManifest, dependency: &str) -> Result<Vec<Span>> {
+    let contents = Manifest::open(&Some(manifest.path.clone()))?;
+    Ok(contents.dependency_spans(dependency))
+}
+
+fn junit(manifests: &[ReportedManifest], findings: &[Finding]) -> Result<String> {
+    let mut suites = String::new();
+    let mut total = (0, 0);
+    for manifest in manifests {
+        let file = report_path(&manifest.path);
+        let mut dependencies = manifest.dependencies.clone();
+        dependencies.sort();
+        dependencies.dedup();
+
+        let mut cases = String::new();
+        let mut failures = 0;
+        for dependency in &dependencies {
+            let found: Vec<_> = findings
+                .iter()
+                .filter(|f| f.dependency == *dependency && f.applies_to(manifest))
+                .collect();
+            let line = spans(manifest, dependency)?
+                .first()
+                .map(|span| span.start_line);
+            let location = line.map(|line| format!(" line=\"{}\"", line));
+            cases.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"{}\" file=\"{}\"{}",
+                escape(dependency),
+                escape(&manifest.package),
+                escape(&file),
+                location.unwrap_or_default()
+            ));
+            if found.is_empty() {
+                cases.push_str("/>\n");
+                continue;
+            }
+            failures += 1;
+            let rules: Vec<_> = found.iter().map(|f| f.rule).collect();
+            let messages: Vec<_> = found.iter().map(|f| f.message.as_str()).collect();
+            let position = line.map(|line| format!(":{}", line)).unwrap_or_default();
+            let details: Vec<_> = messages
+                .iter()
+                .map(|message| format!("{}{}: {}", file, position, message))
+                .collect();
+            cases.push_str(&format!(
+                ">\n      <failure type=\"{}\" message=\"{}\">{}</failure>\n    </testcase>\n",
+                rules.join(","),
+                escape(&messages.join("; ")),
+                escape(&details.join("\n"))
+            ));
+        }
+
+        total = (total.0 + dependencies.len(), total.1 + failures);
+        suites.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">\n{}  </testsuite>\n",
+            escape(&manifest.package),
+            dependencies.len(),
+            failures,
+            cases
+        ));
+    }
+    Ok(format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <testsuites name=\"cargo-upgrade\" tests=\"{}\" failures=\"{}\">\n{}</testsuites>\n",
+        total.0, total.1, suites
+    ))
+}
+
+fn sarif(manifests: &[ReportedManifest], findings: &[Finding]) -> Result<String> {
+    let rules: Vec<_> = RULES
+        .iter()
+        .map(|(id, description, level)| {
+            json!({
+                "id": id,
+                "shortDescription": { "text": description },
+                "defaultConfiguration": { "level": level },
+            })
+        })
+        .collect();
+
+    let mut results = Vec::new();
+    for finding in findings {
+        let level = RULES
+            .iter()
+            .find(|(id, _, _)| *id == finding.rule)
+            .map_or("warning", |(_, _, level)| level);
+        for manifest in manifests.iter().filter(|m| finding.applies_to(m)) {
+            let uri = report_path(&manifest.path);
+            let mut locations: Vec<_> = spans(manifest, &finding.dependency)?
+                .into_iter()
+                .map(|span| {
+                    json!({
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": uri },
+                            "region": {
+                                "startLine": span.start_line,
+                                "startColumn": span.start_column,
+                                "endLine": span.end_line,
+                                "endColumn": span.end_column,
+                                "byteOffset": span.offset,
+                                "byteLength": span.len,
+                            },
+                        },
+                    })
+                })
+                .collect();
+            if locations.is_empty() {
+                locations.push(json!({
+                    "physicalLocation": { "artifactLocation": { "uri": uri } },
+                }));
+            }
+            results.push(json!({
+                "ruleId": finding.rule,
+                "level": level,
+                "message": { "text": finding.message },
+                "locations": locations,
+            }));
+        }
+    }
+
+    let report = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "cargo-upgrade",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": rules,
+                },
+            },
+            "results": results,
+        }],
+    });
+    serde_json::to_string_pretty(&report).chain_err(|| "Failed to serialize report")
+}
+
+/// Escape text for XML attributes and content.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_xml() {
+        assert_eq!(
+            escape("`a` < \"b\" & 'c'"),
+            "`a` &lt; &quot;b&quot; &amp; &apos;c&apos;"
+        );
+    }
+}
@@ -0,0 +1,64 @@
+//! Finding the manifests in a directory tree, for `--recursive`.
+
+use super::errors::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Every `Cargo.toml` under `dir`, in a stable order. `target` directories, hidden directories
+/// such as `.git`, and paths that git ignores are skipped.
+pub fn find_manifests(dir: &Path) -> Result<Vec<PathBuf>> {
+    // Absolute paths, to match the ones cargo reports for the packages.
+    let dir = &fs::canonicalize(dir).chain_err(|| format!("Failed to find `{}`", dir.display()))?;
+    let mut manifests = Vec::new();
+    walk(dir, Path::new(""), &mut manifests)?;
+    let ignored = git_ignored(dir, &manifests);
+    let mut manifests: Vec<_> = manifests
+        .into_iter()
+        .filter(|path| !ignored.contains(path))
+        .map(|path| dir.join(path))
+        .collect();
+    manifests.sort();
+    Ok(manifests)
+}
+
+/// Collect the manifests under `dir.join(relative)`, as paths relative to `dir`.
+fn walk(dir: &Path, relative: &Path, manifests: &mut Vec<PathBuf>) -> Result<()> {
+    let path = dir.join(relative);
+    let entries =
+        fs::read_dir(&path).chain_err(|| format!("Failed to read `{}`", path.display()))?;
+    for entry in entries {
+        let entry = entry.chain_err(|| format!("Failed to read `{}`", path.display()))?;
+        let name = entry.file_name();
+        let file_type = entry
+            .file_type()
+            .chain_err(|| format!("Failed to read `{}`", entry.path().display()))?;
+        if file_type.is_dir() {
+            let skipped = name == "target" || name.to_string_lossy().starts_with('.');
+            if !skipped {
+                walk(dir, &relative.join(&name), manifests)?;
+            }
+        } else if name == "Cargo.toml" {
+            manifests.push(relative.join(name));
+        }
+    }
+    Ok(())
+}
+
+/// Which of `paths`, relative to `dir`, git ignores. Outside a git repository, or without git,
+/// nothing is ignored.
+fn git_ignored(dir: &Path, paths: &[PathBuf]) -> Vec<PathBuf> {
+    let input: String = paths
+        .iter()
+        .map(|path| format!("{}\n", path.to_string_lossy()))
+        .collect();
+    subprocess::Exec::cmd("git")
+        .args(&["check-ignore", "--stdin"])
+        .cwd(dir)
+        .env_remove("GIT_DIR")
+        .stdin(input.as_str())
+        .stdout(subprocess::Redirection::Pipe)
+        .stderr(subprocess::Redirection::Pipe)
+        .capture()
+        .map(|capture| capture.stdout_str().lines().map(PathBuf::from).collect())
+        .unwrap_or_default()
+}
@@ -1,10 +1,12 @@
 //! Crate name parsing.
 use crate::errors::*;
-use crate::Dependency;
 use crate::{get_crate_name_from_github, get_crate_name_from_gitlab, get_crate_name_from_path};
+use crate::{Dependency, NetConfig};
+use std::borrow::Cow;
+use url::Url;
 
 /// A crate specifier. This can be a plain name (e.g. `docopt`), a name and a versionreq (e.g.
-/// `docopt@^0.8`), a URL, or a path.
+/// `docopt@^0.8`), a crates.io or repository URL, a `gh:owner/repo` shorthand, or a path.
 #[derive(Debug)]
 pub struct CrateName<'a>(&'a str);
 
@@ -30,7 +32,15 @@ impl<'a> CrateName<'a> {
     }
 
     fn is_github_url(&self) -> bool {
-        self.0.contains("https://github.com")
+        self.0.contains("https://github.com") || self.0.starts_with("gh:")
+    }
+
+    /// The GitHub repository URL, expanding the `gh:owner/repo` shorthand.
+    fn github_url(&self) -> Cow<'a, str> {
+        match self.0.strip_prefix("gh:") {
+            Some(repo) => Cow::Owned(format!("https://github.com/{}", repo)),
+            None => Cow::Borrowed(self.0),
+        }
     }
 
     fn is_gitlab_url(&self) -> bool {
@@ -42,6 +52,28 @@ impl<'a> CrateName<'a> {
         self.0.contains('.') || self.0.contains('/') || self.0.contains('\\')
     }
 
+    /// If this is a crates.io page URL (e.g. `https://crates.io/crates/serde` or
+    /// `https://crates.io/crates/serde/1.0.0`), the equivalent crate specifier (`serde` or
+    /// `serde@1.0.0`).
+    pub fn crates_io_spec(&self) -> Option<String> {
+        let url = Url::parse(self.0).ok()?;
+        if !matches!(url.host_str()?, "crates.io" | "www.crates.io") {
+            return None;
+        }
+
+        let mut segments = url.path_segments()?.filter(|s| !s.is_empty());
+        if segments.next()? != "crates" {
+            return None;
+        }
+        let name = segments.next()?;
+        match segments.next() {
+            Some(version) if semver::Version::parse(version).is_ok() => {
+                Some(format!("{}@{}", name, version))
+            }
+            _ => Some(name.to_owned()),
+        }
+    }
+
     /// If this crate specifier includes a version (e.g. `docopt@0.8`), extract the name and
     /// version.
     pub fn parse_as_version(&self) -> Result<Option<Dependency>> {
@@ -57,13 +89,14 @@ impl<'a> CrateName<'a> {
     }
 
     /// Will parse this crate name on the assumption that it is a URI.
-    pub fn parse_crate_name_from_uri(&self) -> Result<Dependency> {
+    pub fn parse_crate_name_from_uri(&self, net: &NetConfig) -> Result<Dependency> {
         if self.is_github_url() {
-            if let Ok(ref crate_name) = get_crate_name_from_github(self.0) {
-                return Ok(Dependency::new(crate_name).set_git(self.0, None));
+            let url = self.github_url();
+            if let Ok(ref crate_name) = get_crate_name_from_github(&url, net) {
+                return Ok(Dependency::new(crate_name).set_git(&url, None));
             }
         } else if self.is_gitlab_url() {
-            if let Ok(ref crate_name) = get_crate_name_from_gitlab(self.0) {
+            if let Ok(ref crate_name) = get_crate_name_from_gitlab(self.0, net) {
                 return Ok(Dependency::new(crate_name).set_git(self.0, None));
             }
         } else if self.is_path() {
@@ -75,3 +108,39 @@ impl<'a> CrateName<'a> {
         bail!("Unable to obtain crate informations from `{}`.\n", self.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crates_io_urls() {
+        let spec = |s| CrateName::new(s).crates_io_spec();
+        assert_eq!(
+            spec("https://crates.io/crates/serde"),
+            Some("serde".to_owned())
+        );
+        assert_eq!(
+            spec("https://crates.io/crates/serde/"),
+            Some("serde".to_owned())
+        );
+        assert_eq!(
+            spec("https://crates.io/crates/serde/1.0.0"),
+            Some("serde@1.0.0".to_owned())
+        );
+        assert_eq!(
+            spec("https://crates.io/crates/serde/versions"),
+            Some("serde".to_owned())
+        );
+        assert_eq!(spec("https://crates.io/search?q=serde"), None);
+        assert_eq!(spec("https://github.com/serde-rs/serde"), None);
+        assert_eq!(spec("serde"), None);
+    }
+
+    #[test]
+    fn github_shorthand() {
+        let name = CrateName::new("gh:killercup/cargo-edit");
+        assert!(name.is_url_or_path());
+        assert_eq!(name.github_url(), "https://github.com/killercup/cargo-edit");
+    }
+}
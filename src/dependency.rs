@@ -104,8 +104,7 @@ impl Dependency {
     pub fn set_features(mut self, features: Option<Vec<String>>) -> Dependency {
         self.features = features.map(|f| {
             f.iter()
-                .map(|x| x.split(' ').map(String::from))
-                .flatten()
+                .flat_map(|x| x.split(' ').map(String::from))
                 .filter(|s| !s.is_empty())
                 .collect::<Vec<String>>()
         });
@@ -128,7 +127,7 @@ impl Dependency {
     /// that is, either the alias (rename field if Some),
     /// or the official package name (name field).
     pub fn name_in_manifest(&self) -> &str {
-        &self.rename().unwrap_or(&self.name)
+        self.rename().unwrap_or(&self.name)
     }
 
     /// Set the value of registry for the dependency
@@ -158,6 +157,17 @@ impl Dependency {
         }
     }
 
+    /// Get the alternative registry of the dependency (if any)
+    pub fn registry(&self) -> Option<&str> {
+        match self.source {
+            DependencySource::Version {
+                registry: Some(ref registry),
+                ..
+            } => Some(registry),
+            _ => None,
+        }
+    }
+
     /// Get the alias for the dependency (if any)
     pub fn rename(&self) -> Option<&str> {
         self.rename.as_deref()
@@ -0,0 +1,162 @@
+//! Unified diffs between two versions of a manifest.
+
+use std::fmt::Write;
+
+/// Number of unchanged lines shown around each change.
+const CONTEXT: usize = 3;
+
+enum Line<'a> {
+    Context(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Diff two lists of lines, using their longest common subsequence.
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Line<'a>> {
+    // `lcs[i][j]` is the length of the longest common subsequence of `old[i..]` and `new[j..]`.
+    let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(Line::Context(old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            lines.push(Line::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(Line::Added(new[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old[i..].iter().map(|line| Line::Removed(line)));
+    lines.extend(new[j..].iter().map(|line| Line::Added(line)));
+    lines
+}
+
+/// Format the line range of a hunk, e.g. `3,7`. An empty range refers to the line before it.
+fn hunk_range(start: usize, len: usize) -> String {
+    if len == 0 {
+        format!("{},0", start)
+    } else {
+        format!("{},{}", start + 1, len)
+    }
+}
+
+/// A unified diff between two versions of a file, in the format of `diff -u`, so it can be applied
+/// with `patch`. It is empty if the contents are the same.
+pub fn unified_diff(old: &str, new: &str, old_path: &str, new_path: &str) -> String {
+    let old_lines: Vec<_> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<_> = new.split_inclusive('\n').collect();
+    let lines = diff_lines(&old_lines, &new_lines);
+
+    // Group the changes, along with their context, into hunks of `lines[start..end]`.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (i, _) in lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Line::Context(_)))
+    {
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + 1 + CONTEXT).min(lines.len());
+        match hunks.last_mut() {
+            Some(hunk) if start <= hunk.1 => hunk.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    if hunks.is_empty() {
+        return String::new();
+    }
+
+    // The number of old and new lines before each line of the diff.
+    let mut old_pos = vec![0];
+    let mut new_pos = vec![0];
+    for line in &lines {
+        let (in_old, in_new) = match line {
+            Line::Context(_) => (1, 1),
+            Line::Removed(_) => (1, 0),
+            Line::Added(_) => (0, 1),
+        };
+        old_pos.push(old_pos.last().unwrap() + in_old);
+        new_pos.push(new_pos.last().unwrap() + in_new);
+    }
+
+    let mut diff = format!("--- {}\n+++ {}\n", old_path, new_path);
+    for (start, end) in hunks {
+        let _ = writeln!(
+            diff,
+            "@@ -{} +{} @@",
+            hunk_range(old_pos[start], old_pos[end] - old_pos[start]),
+            hunk_range(new_pos[start], new_pos[end] - new_pos[start]),
+        );
+        for line in &lines[start..end] {
+            let (prefix, text) = match line {
+                Line::Context(text) => (' ', text),
+                Line::Removed(text) => ('-', text),
+                Line::Added(text) => ('+', text),
+            };
+            diff.push(prefix);
+            diff.push_str(text);
+            if !text.ends_with('\n') {
+                diff.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_contents() {
+        assert_eq!(unified_diff("a\nb\n", "a\nb\n", "old", "new"), "");
+    }
+
+    #[test]
+    fn changed_line_with_context() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+        let new = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n";
+        assert_eq!(
+            unified_diff(old, new, "Cargo.toml", "Cargo.toml"),
+            "--- Cargo.toml\n+++ Cargo.toml\n@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+        );
+    }
+
+    #[test]
+    fn separate_hunks() {
+        let old = "a\n1\n2\n3\n4\n5\n6\n7\n8\nb\n";
+        let new = "A\n1\n2\n3\n4\n5\n6\n7\n8\nB\n";
+        let diff = unified_diff(old, new, "old", "new");
+        assert!(diff.contains("@@ -1,4 +1,4 @@\n-a\n+A\n 1\n 2\n 3\n"));
+        assert!(diff.contains("@@ -7,4 +7,4 @@\n 6\n 7\n 8\n-b\n+B\n"));
+    }
+
+    #[test]
+    fn missing_newline_at_end() {
+        assert_eq!(
+            unified_diff("a\nb", "a\nc", "old", "new"),
+            "--- old\n+++ new\n@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+c\n\\ No newline at end of file\n"
+        );
+    }
+
+    #[test]
+    fn insertion_into_empty_file() {
+        assert_eq!(
+            unified_diff("", "a\n", "old", "new"),
+            "--- old\n+++ new\n@@ -0,0 +1,1 @@\n+a\n"
+        );
+    }
+}
@@ -0,0 +1,121 @@
+//! The manifest edits of one command, written together so that the hooks of each workspace run
+//! once for all of them, and so that they are all undone if a hook fails.
+
+use crate::errors::*;
+use crate::hooks::Hooks;
+use crate::journal::JournaledFile;
+use crate::manifest::{workspace_root, Manifest};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The manifests a command has changed, waiting to be written
+#[derive(Debug, Default)]
+pub struct Edits {
+    /// The manifests by their canonical paths, in the order they were first collected
+    manifests: Vec<(PathBuf, Manifest)>,
+}
+
+impl Edits {
+    /// Start collecting the edits of a command
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write a manifest that is the only edit of a command, like `write` does
+    pub fn write_one(manifest: &Manifest, path: &Path) -> Result<Vec<JournaledFile>> {
+        let mut edits = Edits::new();
+        edits.save(manifest, path)?;
+        edits.write()
+    }
+
+    /// Collect a manifest to be written to the file at `path`, instead of what was collected for
+    /// that file before
+    pub fn save(&mut self, manifest: &Manifest, path: &Path) -> Result<()> {
+        let path = path
+            .canonicalize()
+            .chain_err(|| format!("Failed to find `{}`", path.display()))?;
+        match self.manifests.iter_mut().find(|(saved, _)| *saved == path) {
+            Some((_, saved)) => *saved = manifest.clone(),
+            None => self.manifests.push((path, manifest.clone())),
+        }
+        Ok(())
+    }
+
+    /// The manifest collected for the file at `path`, if there is one, e.g. to make further
+    /// changes to it
+    pub fn saved(&self, path: &Path) -> Option<&Manifest> {
+        let path = path.canonicalize().ok()?;
+        self.manifests
+            .iter()
+            .find(|(saved, _)| *saved == path)
+            .map(|(_, manifest)| manifest)
+    }
+
+    /// Write the collected manifests that differ from the files, running the pre-edit hooks of
+    /// each workspace before and the post-edit hooks after, each once with the paths of all the
+    /// manifests written in the workspace (see `Hooks::for_manifest`)
+    ///
+    /// If a pre-edit hook fails, nothing is written. If writing a manifest or a post-edit hook
+    /// fails, the manifests are put back as they were. Returns the manifests written, as they were
+    /// before and after, e.g. for `record_for_undo`.
+    pub fn write(self) -> Result<Vec<JournaledFile>> {
+        let mut changed = Vec::new();
+        for (path, manifest) in self.manifests {
+            let original = fs::read_to_string(&path)
+                .chain_err(|| format!("Failed to read `{}`", path.display()))?;
+            let written = manifest.to_string_like(&original);
+            if written != original {
+                changed.push((path, original, written, manifest));
+            }
+        }
+        let mut workspaces: Vec<(PathBuf, Hooks, Vec<PathBuf>)> = Vec::new();
+        for (path, ..) in &changed {
+            let root = workspace_root(path)?;
+            match workspaces.iter_mut().find(|(dir, ..)| *dir == root) {
+                Some((.., paths)) => paths.push(path.clone()),
+                None => workspaces.push((root, Hooks::for_manifest(path)?, vec![path.clone()])),
+            }
+        }
+
+        for (_, hooks, paths) in &workspaces {
+            hooks.pre_edit(paths)?;
+        }
+        let mut written = Vec::new();
+        let result = (|| -> Result<()> {
+            for (path, original, _, manifest) in &changed {
+                written.push((path, original));
+                manifest.save(path)?;
+            }
+            for (_, hooks, paths) in &workspaces {
+                hooks.post_edit(paths)?;
+            }
+            Ok(())
+        })();
+        let err = match result {
+            Ok(()) => {
+                return Ok(changed
+                    .into_iter()
+                    .map(|(path, original, written, _)| JournaledFile {
+                        path,
+                        original,
+                        written,
+                    })
+                    .collect())
+            }
+            Err(err) => err,
+        };
+        let unrestored: Vec<_> = written
+            .into_iter()
+            .filter_map(|(path, original)| {
+                let err = fs::write(path, original).err()?;
+                Some(format!("`{}` ({})", path.display(), err))
+            })
+            .collect();
+        if unrestored.is_empty() {
+            Err(err)
+        } else {
+            // The failure to restore is added to the error that caused it, rather than hiding it.
+            Err(err).chain_err(|| format!("Failed to restore {}", unrestored.join(", ")))
+        }
+    }
+}
@@ -3,8 +3,8 @@ use std::path::PathBuf;
 error_chain! {
     foreign_links {
         Io(::std::io::Error) #[doc = "An error from the std::io module"];
-        Git(::git2::Error)#[doc = "An error from the git2 crate"];
-        CargoMetadata(::failure::Compat<::cargo_metadata::Error>)#[doc = "An error from the cargo_metadata crate"];
+        Git(::git2::Error)#[cfg(feature = "registry")] #[doc = "An error from the git2 crate"];
+        CargoMetadata(::failure::Compat<::cargo_metadata::Error>)#[cfg(feature = "registry")] #[doc = "An error from the cargo_metadata crate"];
     }
 
     errors {
@@ -32,6 +32,16 @@ error_chain! {
                          --allow-prerelease flag might solve the issue."
             )
         }
+        /// No versions were published long enough ago
+        NoVersionsOldEnough(name: String) {
+            description("No versions were published long enough ago")
+            display("No version of `{}` was published long enough ago", name)
+        }
+        /// The registry doesn't record when versions were published
+        UnknownPublishTimes(name: String) {
+            description("The registry doesn't record when versions were published")
+            display("The registry doesn't record when versions of `{}` were published", name)
+        }
         /// Unable to parse external Cargo.toml
         ParseCargoToml {
             description("Unable to parse external Cargo.toml")
@@ -59,6 +69,18 @@ error_chain! {
             description("non existent dependency")
             display("The dependency `{}` could not be found in `{}`.", name, table)
         }
+        /// A `[package]` field is inherited from the workspace, e.g. `{ workspace = true }`
+        InheritedField(field: String) {
+            description("A field is inherited from the workspace")
+            display("The field `{}` is inherited from the workspace, so it must be set in the \
+                     `[workspace.package]` table of the workspace root", field)
+        }
+        /// A dependency spec matches more than one entry
+        AmbiguousDependency(spec: String, entries: Vec<String>) {
+            description("A dependency spec matches more than one entry")
+            display("`{}` matches more than one dependency: {}. Select one by its name in the \
+                     manifest, or with a version, e.g. `{}@1`", spec, entries.join(", "), spec)
+        }
         /// Config of cargo is invalid
         InvalidCargoConfig {
             description("Invalid cargo config")
@@ -72,6 +94,11 @@ error_chain! {
         NoSuchRegistryFound(name: String) {
             display("The registry '{}' could not be found", name)
         }
+        /// The registry has been replaced by a source on disk
+        LocalRegistrySource(path: PathBuf) {
+            description("The registry has been replaced by a local source")
+            display("The registry has been replaced by the local source at `{}`", path.display())
+        }
         /// Failed to parse a version for a dependency
         ParseVersion(version: String, dep: String) {
             description("Failed to parse a version for a dependency")
@@ -82,6 +109,61 @@ error_chain! {
             description("Missing registry checkout in the cargo registry")
             display("Looks like ({}) is empty", path.display())
         }
+        /// A PEM file holds no usable certificates
+        InvalidCertificate(path: PathBuf) {
+            description("No certificates could be read from a PEM file")
+            display("No certificates could be read from `{}`", path.display())
+        }
+        /// A PEM file holds no usable private key
+        InvalidPrivateKey(path: PathBuf) {
+            description("No private key could be read from a PEM file")
+            display("No private key could be read from `{}`", path.display())
+        }
+        /// Only one of a client certificate and its private key was given
+        IncompleteClientIdentity {
+            description("A client certificate and its private key must be given together")
+        }
+        /// A dependency is against the policy being enforced
+        PolicyViolation(name: String, reason: String) {
+            description("A dependency is against the policy")
+            display("Policy violation for `{}`: {}", name, reason)
+        }
+        /// A hook configured for manifest edits failed
+        HookFailed(hook: String, paths: Vec<PathBuf>) {
+            description("A hook configured for manifest edits failed")
+            display("The hook `{}` failed for {}, so the manifests were left as they were", hook,
+                    paths.iter().map(|path| format!("`{}`", path.display())).collect::<Vec<_>>().join(", "))
+        }
+        /// Manifests were changed after the operation being undone wrote them
+        ChangedSinceOperation(command: String, paths: Vec<PathBuf>) {
+            description("Manifests were changed since the operation being undone")
+            display("{} changed since `{}`, so undoing it would lose those changes (pass \
+                     '--force' to undo it anyway)",
+                    paths.iter().map(|path| format!("`{}`", path.display())).collect::<Vec<_>>().join(", "),
+                    command)
+        }
+        /// A version requirement couldn't be parsed
+        InvalidRequirement(requirement: String) {
+            description("Failed to parse a version requirement")
+            display("The version requirement `{}` couldn't be parsed", requirement)
+        }
+        /// A version requirement couldn't be rewritten to match a version
+        RequirementMismatch(requirement: String, version: String) {
+            description("A version requirement couldn't be rewritten to match a version")
+            display("The version requirement `{}` can't be rewritten to match {}", requirement, version)
+        }
+        /// The crates.io API reports a different checksum for a version than the registry index
+        ApiChecksumMismatch(name: String, version: String, reported: String, indexed: String) {
+            description("The crates.io API reports a different checksum than the registry index")
+            display("The crates.io API reports the checksum `{}` for `{} v{}`, but the registry \
+                     index has `{}`, so its response can't be trusted (pass '--no-verify' to use it \
+                     anyway)", reported, name, version, indexed)
+        }
+        /// A registry lookup panicked on the pool it ran on
+        LookupPanicked(message: String) {
+            description("A registry lookup panicked")
+            display("A registry lookup panicked: {}", message)
+        }
         /// Non Unicode git path
         NonUnicodeGitPath {
             // this is because git2 function takes &str instead of something like AsRef<Path>
@@ -89,3 +171,90 @@ error_chain! {
         }
     }
 }
+
+/// What kind of failure an error is, for tools deciding how to report it or whether to retry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FailureKind {
+    /// The crate, or a version of it, doesn't exist
+    NotFound,
+    /// A registry index or git host couldn't be reached
+    Network,
+    /// A manifest, registry entry or version couldn't be parsed
+    Parse,
+    /// Anything else
+    Other,
+}
+
+impl FailureKind {
+    /// Classify an error by the first error in its chain of causes that is recognised
+    pub fn of(err: &(dyn std::error::Error + 'static)) -> Self {
+        std::iter::successors(Some(err), |err| err.source())
+            .find_map(|err| {
+                if let Some(err) = err.downcast_ref::<Error>() {
+                    Self::of_kind(err.kind())
+                } else if is_git_error(err) || err.is::<::std::io::Error>() {
+                    Some(FailureKind::Network)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(FailureKind::Other)
+    }
+
+    /// Classify an error kind of this crate, if it is recognised
+    pub fn of_kind(kind: &ErrorKind) -> Option<Self> {
+        match kind {
+            ErrorKind::NoCrate(_)
+            | ErrorKind::NoVersionsAvailable
+            | ErrorKind::NoVersionsOldEnough(_) => Some(FailureKind::NotFound),
+            ErrorKind::InvalidSummaryJson
+            | ErrorKind::ParseCargoToml
+            | ErrorKind::InvalidManifest
+            | ErrorKind::ParseVersion(..)
+            | ErrorKind::InvalidRequirement(_) => Some(FailureKind::Parse),
+            ErrorKind::Io(_) => Some(FailureKind::Network),
+            #[cfg(feature = "registry")]
+            ErrorKind::Git(_) => Some(FailureKind::Network),
+            _ => None,
+        }
+    }
+
+    /// Whether trying again later might succeed
+    pub fn is_retryable(self) -> bool {
+        self == FailureKind::Network
+    }
+}
+
+#[cfg(feature = "registry")]
+fn is_git_error(err: &(dyn std::error::Error + 'static)) -> bool {
+    err.is::<::git2::Error>()
+}
+
+#[cfg(not(feature = "registry"))]
+fn is_git_error(_err: &(dyn std::error::Error + 'static)) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_causes() {
+        let err = Error::from(ErrorKind::NoCrate("docopt".into()));
+        assert_eq!(FailureKind::of(&err), FailureKind::NotFound);
+
+        let err = Error::with_chain(err, "Failed to get new version");
+        assert_eq!(FailureKind::of(&err), FailureKind::NotFound);
+
+        let io = std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out");
+        let err = Error::with_chain(io, "Failed to fetch the index");
+        assert_eq!(FailureKind::of(&err), FailureKind::Network);
+        assert!(FailureKind::of(&err).is_retryable());
+
+        let err = Error::from("Unknown message format");
+        assert_eq!(FailureKind::of(&err), FailureKind::Other);
+        assert!(!FailureKind::of(&err).is_retryable());
+    }
+}
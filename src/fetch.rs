@@ -1,11 +1,19 @@
 use crate::errors::*;
-use crate::registry::{registry_path, registry_path_from_url};
+use crate::http_cache::{self, CachedResponse};
+use crate::license::same_license;
+use crate::net::NetConfig;
+use crate::registry::{
+    registry_cache_paths, registry_path_from_url, registry_source, RegistrySource,
+};
+use crate::tls::TlsConfig;
 use crate::{Dependency, Manifest};
 use regex::Regex;
-use std::env;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+use std::{env, fs};
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 use url::Url;
 
@@ -15,12 +23,81 @@ struct CrateVersion {
     #[serde(rename = "vers")]
     version: semver::Version,
     yanked: bool,
+    #[serde(default)]
+    features: BTreeMap<String, Vec<String>>,
+    // Features using newer syntax (`dep:` and `?`), kept apart for older cargo versions
+    #[serde(default)]
+    features2: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    deps: Vec<IndexDependency>,
+    // When the version was published (in RFC 3339 format), in indexes that record it
+    #[serde(default)]
+    pubtime: Option<String>,
+    // The `rust-version` of the version, in indexes that record it
+    #[serde(default)]
+    rust_version: Option<String>,
+    // The SHA-256 checksum of the `.crate` file, which directory sources don't record
+    #[serde(default)]
+    cksum: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct IndexDependency {
+    name: String,
+    #[serde(default)]
+    optional: bool,
+}
+
+impl CrateVersion {
+    /// When the version was published, if that is known
+    fn published(&self) -> Option<SystemTime> {
+        let pubtime = chrono::DateTime::parse_from_rfc3339(self.pubtime.as_ref()?).ok()?;
+        Some(SystemTime::from(pubtime))
+    }
+
+    /// The features of the version, including those implied by optional dependencies
+    fn feature_names(&self) -> BTreeSet<String> {
+        let features = self.features.iter().chain(&self.features2);
+        let explicit_deps: BTreeSet<&str> = features
+            .clone()
+            .flat_map(|(_, entries)| entries)
+            .filter_map(|entry| entry.strip_prefix("dep:"))
+            .collect();
+        let implicit = self
+            .deps
+            .iter()
+            .filter(|dep| dep.optional && !explicit_deps.contains(dep.name.as_str()))
+            .map(|dep| dep.name.clone());
+        features
+            .map(|(name, _)| name.clone())
+            .chain(implicit)
+            .collect()
+    }
+
+    /// The features of the version with their entries, including those implied by optional
+    /// dependencies as `name = ["dep:name"]`
+    fn feature_map(&self) -> BTreeMap<String, Vec<String>> {
+        let mut features: BTreeMap<_, _> = self
+            .features
+            .iter()
+            .chain(&self.features2)
+            .map(|(name, entries)| (name.clone(), entries.clone()))
+            .collect();
+        for name in self.feature_names() {
+            features
+                .entry(name.clone())
+                .or_insert_with(|| vec![format!("dep:{}", name)]);
+        }
+        features
+    }
 }
 
 /// Query latest version from a registry index
 ///
 /// The registry argument must be specified for crates
-/// from alternative registries.
+/// from alternative registries, or to read from a local registry or
+/// vendored directory. Otherwise, crates.io (or whatever source replaces it
+/// in the cargo config) is used.
 ///
 /// The latest version will be returned as a `Dependency`. This will fail, when
 ///
@@ -31,36 +108,258 @@ pub fn get_latest_dependency(
     crate_name: &str,
     flag_allow_prerelease: bool,
     manifest_path: &Path,
-    registry: &Option<Url>,
+    registry: &Option<RegistrySource>,
 ) -> Result<Dependency> {
-    if env::var("CARGO_IS_TEST").is_ok() {
-        // We are in a simulated reality. Nothing is real here.
-        // FIXME: Use actual test handling code.
-        let new_version = if flag_allow_prerelease {
-            format!("{}--PRERELEASE_VERSION_TEST", crate_name)
-        } else {
-            match crate_name {
-                "test_breaking" => "0.2.0".to_string(),
-                "test_nonbreaking" => "0.1.1".to_string(),
-                other => format!("{}--CURRENT_VERSION_TEST", other),
+    let crate_versions = match query_index(crate_name, manifest_path, registry)? {
+        Some(crate_versions) => crate_versions,
+        None => {
+            // We are in a simulated reality. Nothing is real here.
+            // FIXME: Use actual test handling code.
+            let new_version = if flag_allow_prerelease {
+                format!("{}--PRERELEASE_VERSION_TEST", crate_name)
+            } else {
+                match crate_name {
+                    "test_breaking" => "0.2.0".to_string(),
+                    "test_nonbreaking" => "0.1.1".to_string(),
+                    other => format!("{}--CURRENT_VERSION_TEST", other),
+                }
+            };
+            return Ok(Dependency::new(crate_name).set_version(&new_version));
+        }
+    };
+    latest_dependency(crate_name, &crate_versions, flag_allow_prerelease)
+}
+
+/// The source the registry argument of the lookups stands for: itself if given, or else the
+/// default registry of the manifest (crates.io, or whatever source replaces it)
+fn resolve_source(
+    manifest_path: &Path,
+    registry: &Option<RegistrySource>,
+) -> Result<RegistrySource> {
+    match registry {
+        Some(source) => Ok(source.clone()),
+        None => registry_source(manifest_path, None),
+    }
+}
+
+/// Whether lookups in a source are simulated, as they are in cargo-edit's own tests. Local sources
+/// are real even in tests, as they don't need the network.
+fn is_simulated(source: &RegistrySource) -> bool {
+    env::var("CARGO_IS_TEST").is_ok() && !source.is_local()
+}
+
+/// Query the versions of a crate from the source the registry argument stands for, which every
+/// lookup of the index starts with
+///
+/// `None` is returned if the source is simulated, as it has no real versions.
+fn query_index(
+    crate_name: &str,
+    manifest_path: &Path,
+    registry: &Option<RegistrySource>,
+) -> Result<Option<Vec<CrateVersion>>> {
+    let source = resolve_source(manifest_path, registry)?;
+    if is_simulated(&source) {
+        return Ok(None);
+    }
+    query_versions(crate_name, source).map(Some)
+}
+
+/// Query latest version from a registry index, out of those published before a time
+///
+/// This is `get_latest_dependency` with a cooldown on new releases. Versions are only picked if
+/// the registry records when they were published: the index says so in its `pubtime` fields if it
+/// has them, and otherwise the crates.io API is asked for crates from crates.io.
+pub fn get_latest_dependency_published_before(
+    crate_name: &str,
+    flag_allow_prerelease: bool,
+    published_before: SystemTime,
+    manifest_path: &Path,
+    registry: &Option<RegistrySource>,
+    net: &NetConfig,
+) -> Result<Dependency> {
+    let source = resolve_source(manifest_path, registry)?;
+
+    // The simulated registry of the tests has no publish times, so they aren't checked.
+    if is_simulated(&source) {
+        return get_latest_dependency(crate_name, flag_allow_prerelease, manifest_path, registry);
+    }
+
+    let mut crate_versions = query_versions_with_publish_times(crate_name, source, net)?;
+    if crate_versions.iter().all(|v| v.published().is_none()) {
+        return Err(ErrorKind::UnknownPublishTimes(crate_name.to_owned()).into());
+    }
+    crate_versions.retain(|v| v.published().is_some_and(|time| time <= published_before));
+    if crate_versions.is_empty() {
+        return Err(ErrorKind::NoVersionsOldEnough(crate_name.to_owned()).into());
+    }
+    latest_dependency(crate_name, &crate_versions, flag_allow_prerelease)
+}
+
+/// Query the versions of a crate, asking the crates.io API when those from crates.io were published
+/// if the index doesn't say
+fn query_versions_with_publish_times(
+    crate_name: &str,
+    source: RegistrySource,
+    net: &NetConfig,
+) -> Result<Vec<CrateVersion>> {
+    let is_crates_io = source.is_crates_io();
+    let mut crate_versions = query_versions(crate_name, source)?;
+    if is_crates_io && crate_versions.iter().any(|v| v.pubtime.is_none()) {
+        let name = crate_versions
+            .first()
+            .map_or(crate_name, |v| v.name.as_str())
+            .to_owned();
+        let mut pubtimes = crates_io_publish_times(&name, &crate_versions, net)?;
+        for version in &mut crate_versions {
+            if version.pubtime.is_none() {
+                version.pubtime = pubtimes.remove(&version.version.to_string());
             }
-        };
+        }
+    }
+    Ok(crate_versions)
+}
+
+/// A version of a crate that hasn't been yanked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublishedVersion {
+    /// The version itself
+    pub version: semver::Version,
+    /// When it was published, if the registry records that
+    pub published: Option<SystemTime>,
+}
 
-        return Ok(Dependency::new(crate_name).set_version(&new_version));
+/// Query the versions of a crate that haven't been yanked, oldest first, with when each was
+/// published
+///
+/// The registry argument is used as for `get_latest_dependency`, and publish times are found as
+/// for `get_latest_dependency_published_before`.
+pub fn get_published_versions(
+    crate_name: &str,
+    manifest_path: &Path,
+    registry: &Option<RegistrySource>,
+    net: &NetConfig,
+) -> Result<Vec<PublishedVersion>> {
+    let source = resolve_source(manifest_path, registry)?;
+
+    // The simulated registry of the tests has no real versions.
+    if is_simulated(&source) {
+        return Ok(Vec::new());
     }
 
-    if crate_name.is_empty() {
-        return Err(ErrorKind::EmptyCrateName.into());
+    let mut versions: Vec<_> = query_versions_with_publish_times(crate_name, source, net)?
+        .into_iter()
+        .filter(|v| !v.yanked)
+        .map(|v| PublishedVersion {
+            published: v.published(),
+            version: v.version,
+        })
+        .collect();
+    versions.sort_by(|a, b| a.version.cmp(&b.version));
+    Ok(versions)
+}
+
+/// A version of a crate, as the registry index describes it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionInfo {
+    /// The version itself
+    pub version: semver::Version,
+    /// Whether it has been yanked
+    pub yanked: bool,
+    /// Its features with the entries of each, including those implied by optional dependencies
+    /// as `name = ["dep:name"]`
+    pub features: BTreeMap<String, Vec<String>>,
+    /// When it was published, if the index records that
+    pub published: Option<SystemTime>,
+    /// Its minimum supported Rust version (`rust-version`), if the index records one
+    pub rust_version: Option<String>,
+}
+
+/// All versions of a crate, as the registry index describes them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CrateVersions {
+    /// The name of the crate, which may be spelled differently than the one queried for (e.g.
+    /// with `_` for `-`)
+    pub name: String,
+    /// The versions, oldest first, including yanked ones
+    pub versions: Vec<VersionInfo>,
+}
+
+impl CrateVersions {
+    /// The latest version that hasn't been yanked, only picking a prerelease if
+    /// `allow_prerelease` is set
+    pub fn latest(&self, allow_prerelease: bool) -> Option<&VersionInfo> {
+        self.versions
+            .iter()
+            .filter(|v| !v.yanked && (allow_prerelease || !v.version.is_prerelease()))
+            .max_by(|a, b| a.version.cmp(&b.version))
     }
 
-    let registry_path = match registry {
-        Some(url) => registry_path_from_url(url)?,
-        None => registry_path(manifest_path, None)?,
-    };
+    /// A version, if the index has it
+    pub fn get(&self, version: &semver::Version) -> Option<&VersionInfo> {
+        self.versions.iter().find(|v| v.version == *version)
+    }
 
-    let crate_versions = fuzzy_query_registry_index(crate_name, &registry_path)?;
+    /// Whether every published version has been yanked
+    pub fn is_fully_yanked(&self) -> bool {
+        !self.versions.is_empty() && self.versions.iter().all(|v| v.yanked)
+    }
 
-    let dep = read_latest_version(&crate_versions, flag_allow_prerelease)?;
+    /// The latest version that hasn't been yanked and that a requirement matches, which is what
+    /// a fresh resolve would pick
+    pub fn latest_matching(&self, requirement: &semver::VersionReq) -> Option<&VersionInfo> {
+        self.versions
+            .iter()
+            .filter(|v| !v.yanked && requirement.matches(&v.version))
+            .max_by(|a, b| a.version.cmp(&b.version))
+    }
+}
+
+/// Query all versions of a crate, with whether each is yanked, its features, when it was
+/// published and its `rust-version`
+///
+/// The registry argument is used as for `get_latest_dependency`. Unlike
+/// `get_published_versions`, only the index is read, so publish times are only known if it
+/// records them.
+pub fn get_crate_versions(
+    crate_name: &str,
+    manifest_path: &Path,
+    registry: &Option<RegistrySource>,
+) -> Result<CrateVersions> {
+    let crate_versions = match query_index(crate_name, manifest_path, registry)? {
+        Some(crate_versions) => crate_versions,
+        // The simulated registry of the tests has no real versions.
+        None => {
+            return Ok(CrateVersions {
+                name: crate_name.to_owned(),
+                versions: Vec::new(),
+            })
+        }
+    };
+    let name = crate_versions
+        .first()
+        .map_or(crate_name, |v| v.name.as_str())
+        .to_owned();
+    let mut versions: Vec<_> = crate_versions
+        .into_iter()
+        .map(|v| VersionInfo {
+            features: v.feature_map(),
+            published: v.published(),
+            version: v.version,
+            yanked: v.yanked,
+            rust_version: v.rust_version,
+        })
+        .collect();
+    versions.sort_by(|a, b| a.version.cmp(&b.version));
+    Ok(CrateVersions { name, versions })
+}
+
+/// The latest of the versions found for a crate, warning if they are of a similarly-named one
+fn latest_dependency(
+    crate_name: &str,
+    crate_versions: &[CrateVersion],
+    flag_allow_prerelease: bool,
+) -> Result<Dependency> {
+    let dep = read_latest_version(crate_versions, flag_allow_prerelease)?;
 
     if dep.name != crate_name {
         println!("WARN: Added `{}` instead of `{}`", dep.name, crate_name);
@@ -69,6 +368,85 @@ pub fn get_latest_dependency(
     Ok(dep)
 }
 
+/// Query the latest version of a crate that a requirement matches, which is what a fresh resolve
+/// would pick
+///
+/// The registry argument is used as for `get_latest_dependency`. Yanked versions are skipped, and
+/// prereleases only match requirements that name one. `None` is returned if no version matches.
+pub fn get_latest_matching(
+    crate_name: &str,
+    requirement: &semver::VersionReq,
+    manifest_path: &Path,
+    registry: &Option<RegistrySource>,
+) -> Result<Option<semver::Version>> {
+    let versions = get_crate_versions(crate_name, manifest_path, registry)?;
+    Ok(versions
+        .latest_matching(requirement)
+        .map(|v| v.version.clone()))
+}
+
+/// Query whether every published version of a crate has been yanked
+///
+/// The registry argument is used as for `get_latest_dependency`.
+pub fn is_fully_yanked(
+    crate_name: &str,
+    manifest_path: &Path,
+    registry: &Option<RegistrySource>,
+) -> Result<bool> {
+    Ok(get_crate_versions(crate_name, manifest_path, registry)?.is_fully_yanked())
+}
+
+/// Query the features a version of a crate has, including the implicit features of its optional
+/// dependencies
+///
+/// The registry argument is used as for `get_latest_dependency`. `None` is returned if the
+/// version can't be found.
+pub fn get_features(
+    crate_name: &str,
+    version: &semver::Version,
+    manifest_path: &Path,
+    registry: &Option<RegistrySource>,
+) -> Result<Option<BTreeSet<String>>> {
+    let versions = get_crate_versions(crate_name, manifest_path, registry)?;
+    Ok(versions
+        .get(version)
+        .map(|v| v.features.keys().cloned().collect()))
+}
+
+/// Query all versions of a crate (or of a similarly-named one) from a source
+fn query_versions(crate_name: &str, source: RegistrySource) -> Result<Vec<CrateVersion>> {
+    if crate_name.is_empty() {
+        return Err(ErrorKind::EmptyCrateName.into());
+    }
+
+    Ok(match source {
+        RegistrySource::Remote(url) => {
+            match fuzzy_query_registry_index(crate_name, registry_path_from_url(&url)?) {
+                Ok(versions) => versions,
+                // Without a usable index checkout (e.g. when offline), fall back to the index
+                // cache cargo keeps on disk. A crate missing from a usable checkout is missing.
+                Err(err) if FailureKind::of(&err) != FailureKind::Network => return Err(err),
+                Err(err) => match fuzzy_query_registry_cache(crate_name, &url)? {
+                    Some(versions) => {
+                        eprintln!(
+                            "WARN: Read `{}` from the local index cache, its latest version may be \
+                             out of date",
+                            crate_name
+                        );
+                        versions
+                    }
+                    None => return Err(err),
+                },
+            }
+        }
+        RegistrySource::LocalRegistry(path) => {
+            fuzzy_query_local_index(crate_name, path.join("index"))?
+        }
+        RegistrySource::Directory(path) => query_directory_source(crate_name, path)?,
+        RegistrySource::LocalIndex(path) => fuzzy_query_local_index(crate_name, path)?,
+    })
+}
+
 // Checks whether a version object is a stable release
 fn version_is_stable(version: &CrateVersion) -> bool {
     !version.version.is_prerelease()
@@ -92,7 +470,12 @@ fn read_latest_version(
 }
 
 /// update registry index for given project
-pub fn update_registry_index(registry: &Url, quiet: bool) -> Result<()> {
+///
+/// Nothing is fetched when `net.offline` is set, and failed fetches are retried `net.retry` times.
+pub fn update_registry_index(registry: &Url, quiet: bool, net: &NetConfig) -> Result<()> {
+    if net.offline {
+        return Ok(());
+    }
     let registry_path = registry_path_from_url(registry)?;
 
     let colorchoice = if atty::is(atty::Stream::Stdout) {
@@ -126,14 +509,99 @@ pub fn update_registry_index(registry: &Url, quiet: bool) -> Result<()> {
         "refs/heads/{0}:refs/remotes/origin/{0}",
         get_checkout_name(registry_path)?
     );
-    fetch_with_cli(&repo, registry.as_str(), &refspec)?;
+    // Like cargo, retry fetches that fail, as that is mostly down to the network.
+    let mut attempt = 0;
+    loop {
+        match fetch_with_cli(&repo, registry.as_str(), &refspec, &net.tls) {
+            Err(err) if attempt < net.retry => {
+                attempt += 1;
+                eprintln!(
+                    "WARN: Failed to update the '{}' index ({}), retrying ({}/{})",
+                    registry, err, attempt, net.retry
+                );
+            }
+            result => return result,
+        }
+    }
+}
 
-    Ok(())
+/// List the packages in a git repository, from every `Cargo.toml` on a branch (or the default
+/// branch), e.g. the members of a workspace
+///
+/// The repository is fetched into a temporary directory, which fails when `net.offline` is set
+/// (unless it is a local path).
+pub fn get_git_packages(url: &str, branch: Option<&str>, net: &NetConfig) -> Result<Vec<String>> {
+    if net.offline && !Path::new(url).exists() {
+        return Err(format!(
+            "Can't fetch `{}` without accessing the network, which `net.offline` forbids",
+            url
+        )
+        .into());
+    }
+
+    let dir = env::temp_dir().join(format!("cargo-edit-git-{}", std::process::id()));
+    let packages = (|| -> Result<Vec<String>> {
+        let mut opts = git2::RepositoryInitOptions::new();
+        opts.bare(true);
+        let repo = git2::Repository::init_opts(&dir, &opts)?;
+        let refspec = match branch {
+            Some(branch) => format!("refs/heads/{}:refs/remotes/origin/fetched", branch),
+            None => "HEAD:refs/remotes/origin/fetched".to_owned(),
+        };
+        fetch_with_cli(&repo, url, &refspec, &net.tls)?;
+        let tree = repo
+            .find_reference("refs/remotes/origin/fetched")?
+            .peel_to_tree()?;
+
+        let mut packages = Vec::new();
+        let mut error = None;
+        tree.walk(git2::TreeWalkMode::PreOrder, |_, entry| {
+            if entry.name() != Some("Cargo.toml") {
+                return git2::TreeWalkResult::Ok;
+            }
+            let name = entry
+                .to_object(&repo)
+                .and_then(|object| object.peel_to_blob())
+                .map(|blob| {
+                    std::str::from_utf8(blob.content())
+                        .ok()
+                        .and_then(|contents| contents.parse::<toml::Value>().ok())
+                        .and_then(|manifest| {
+                            Some(manifest.get("package")?.get("name")?.as_str()?.to_owned())
+                        })
+                });
+            match name {
+                Ok(name) => {
+                    packages.extend(name);
+                    git2::TreeWalkResult::Ok
+                }
+                Err(err) => {
+                    error = Some(err);
+                    git2::TreeWalkResult::Abort
+                }
+            }
+        })?;
+        if let Some(err) = error {
+            return Err(err.into());
+        }
+        packages.sort();
+        packages.dedup();
+        Ok(packages)
+    })();
+    // The clone is only needed for the list.
+    let _ = fs::remove_dir_all(&dir);
+    packages.chain_err(|| format!("Failed to list the packages in `{}`", url))
 }
 
 // https://github.com/rust-lang/cargo/blob/57986eac7157261c33f0123bade7ccd20f15200f/src/cargo/sources/git/utils.rs#L758
-fn fetch_with_cli(repo: &git2::Repository, url: &str, refspec: &str) -> Result<()> {
-    let cmd = subprocess::Exec::shell("git")
+fn fetch_with_cli(
+    repo: &git2::Repository,
+    url: &str,
+    refspec: &str,
+    tls: &TlsConfig,
+) -> Result<()> {
+    let cmd = subprocess::Exec::cmd("git")
+        .args(&tls.git_options())
         .arg("fetch")
         .arg("--tags") // fetch all tags
         .arg("--force") // handle force pushes
@@ -151,12 +619,17 @@ fn fetch_with_cli(repo: &git2::Repository, url: &str, refspec: &str) -> Result<(
         .env_remove("GIT_INDEX_FILE")
         .env_remove("GIT_OBJECT_DIRECTORY")
         .env_remove("GIT_ALTERNATE_OBJECT_DIRECTORIES")
-        .cwd(repo.path());
+        .cwd(repo.path())
+        .stdout(subprocess::Redirection::Pipe)
+        .stderr(subprocess::Redirection::Pipe);
 
-    let _ = cmd.capture().map_err(|e| match e {
+    let capture = cmd.capture().map_err(|e| match e {
         subprocess::PopenError::IoError(io) => ErrorKind::Io(io),
         _ => unreachable!("expected only io error"),
     })?;
+    if !capture.success() {
+        return Err(format!("`git fetch` failed: {}", capture.stderr_str().trim()).into());
+    }
     Ok(())
 }
 
@@ -262,6 +735,353 @@ fn get_no_latest_version_from_json_when_all_are_yanked() {
     assert!(read_latest_version(&versions, false).is_err());
 }
 
+#[test]
+fn get_features_from_json() {
+    let version: CrateVersion = serde_json::from_str(
+        r#"{
+          "name": "foo",
+          "vers": "0.5.0",
+          "yanked": false,
+          "features": {"default": ["std"], "std": []},
+          "features2": {"tls": ["dep:rustls"]},
+          "deps": [
+            {"name": "rustls", "optional": true},
+            {"name": "serde", "optional": true},
+            {"name": "regex"}
+          ]
+        }"#,
+    )
+    .expect("crate version is correctly parsed");
+
+    let features: Vec<_> = version.feature_names().into_iter().collect();
+    assert_eq!(features, vec!["default", "serde", "std", "tls"]);
+}
+
+#[test]
+fn get_publish_time_from_json() {
+    let version: CrateVersion = serde_json::from_str(
+        r#"{"name": "foo", "vers": "0.5.0", "yanked": false, "pubtime": "2021-03-01T12:00:00Z"}"#,
+    )
+    .expect("crate version is correctly parsed");
+
+    let published = version.published().unwrap();
+    assert_eq!(
+        published.duration_since(SystemTime::UNIX_EPOCH).unwrap(),
+        Duration::from_secs(1_614_600_000)
+    );
+}
+
+#[test]
+fn verify_api_versions_against_index() {
+    let index: Vec<CrateVersion> = serde_json::from_str(
+        r#"[
+          {"name": "foo", "vers": "0.5.0", "yanked": false, "cksum": "aa11"},
+          {"name": "foo", "vers": "0.6.0", "yanked": false, "cksum": "bb22"}
+        ]"#,
+    )
+    .expect("crate versions are correctly parsed");
+    let api = |checksum: &str| -> Vec<ApiVersion> {
+        serde_json::from_str(&format!(
+            r#"[
+              {{"num": "0.5.0", "created_at": "2021-03-01T12:00:00Z", "checksum": "aa11"}},
+              {{"num": "0.6.0", "created_at": "2021-04-01T12:00:00Z", "checksum": "{}"}},
+              {{"num": "0.7.0", "created_at": "2021-05-01T12:00:00Z", "checksum": "cc33"}}
+            ]"#,
+            checksum
+        ))
+        .expect("API versions are correctly parsed")
+    };
+
+    let verified = verify_api_versions("foo", api("BB22"), &index).unwrap();
+    let versions: Vec<_> = verified.iter().map(|v| v.num.as_str()).collect();
+    assert_eq!(versions, vec!["0.5.0", "0.6.0"]);
+
+    let err = match verify_api_versions("foo", api("dd44"), &index) {
+        Ok(_) => panic!("a checksum that differs from the index is refused"),
+        Err(err) => err,
+    };
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::ApiChecksumMismatch(name, version, ..) if name == "foo" && version == "0.6.0"
+    ));
+}
+
+#[test]
+fn finds_suspicious_crates() {
+    let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_614_600_000);
+    let popular = vec!["serde".to_owned(), "rand".to_owned()];
+    let established = CrateStats {
+        created: now - Duration::from_secs(3 * 365 * 24 * 60 * 60),
+        downloads: 5_000_000,
+        owner_crates: vec![1, 20],
+    };
+    assert!(established
+        .suspicions("serde_json", &popular, now)
+        .is_empty());
+    assert!(established.suspicions("serde", &popular, now).is_empty());
+    assert_eq!(
+        established.suspicions("serd", &popular, now),
+        vec!["is named like the popular `serde`"]
+    );
+    assert_eq!(
+        established.suspicions("Ranf", &popular, now),
+        vec!["is named like the popular `rand`"]
+    );
+
+    let fresh = CrateStats {
+        created: now - Duration::from_secs(2 * 24 * 60 * 60 + 60),
+        downloads: 3,
+        owner_crates: vec![1],
+    };
+    assert_eq!(
+        fresh.suspicions("something", &popular, now),
+        vec![
+            "was first published 2 days ago and has 3 downloads",
+            "is owned by accounts that have published nothing else",
+        ]
+    );
+}
+
+#[test]
+fn measures_edit_distance() {
+    assert_eq!(edit_distance("serde", "serde"), 0);
+    assert_eq!(edit_distance("serde", "serd"), 1);
+    assert_eq!(edit_distance("serde", "sedre"), 2);
+    assert_eq!(edit_distance("", "rand"), 4);
+}
+
+#[test]
+fn finds_new_publishers() {
+    let version = |v: &str| semver::Version::parse(v).unwrap();
+    let publishers = Releases(
+        vec![
+            ("1.0.0", "alice"),
+            ("1.1.0", "bob"),
+            ("1.2.0", "alice"),
+            ("1.3.0", "mallory"),
+        ]
+        .into_iter()
+        .map(|(v, login)| {
+            let release = Release {
+                publisher: Some(login.to_owned()),
+                license: None,
+            };
+            (version(v), release)
+        })
+        .collect(),
+    );
+
+    assert_eq!(
+        publishers.new_publisher(&version("1.0.0"), &version("1.3.0")),
+        Some("mallory")
+    );
+    assert_eq!(
+        publishers.new_publisher(&version("1.0.0"), &version("1.2.0")),
+        None
+    );
+    // `bob` published none of the versions before 1.1.0.
+    assert_eq!(
+        publishers.new_publisher(&version("1.0.0"), &version("1.1.0")),
+        Some("bob")
+    );
+    // Nothing is known about versions before 0.9.0.
+    assert_eq!(
+        publishers.new_publisher(&version("0.9.0"), &version("0.9.0")),
+        None
+    );
+}
+
+#[test]
+fn finds_license_changes() {
+    let version = |v: &str| semver::Version::parse(v).unwrap();
+    let releases = Releases(
+        vec![
+            ("1.0.0", Some("MIT/Apache-2.0")),
+            ("1.1.0", Some("MIT OR Apache-2.0")),
+            ("1.2.0", None),
+            ("2.0.0", Some("BUSL-1.1")),
+        ]
+        .into_iter()
+        .map(|(v, license)| {
+            let release = Release {
+                publisher: None,
+                license: license.map(ToOwned::to_owned),
+            };
+            (version(v), release)
+        })
+        .collect(),
+    );
+
+    assert_eq!(
+        releases.license_change(&version("1.0.0"), &version("2.0.0")),
+        Some(("MIT/Apache-2.0", "BUSL-1.1"))
+    );
+    assert_eq!(
+        releases.license_change(&version("1.0.0"), &version("1.1.0")),
+        None
+    );
+    assert_eq!(
+        releases.license_change(&version("1.0.0"), &version("1.2.0")),
+        None
+    );
+}
+
+#[test]
+fn get_latest_version_from_local_registry() {
+    let versions = fuzzy_query_local_index("my_package", "tests/fixtures/local-registry/index")
+        .expect("crate versions are read from the local registry");
+
+    assert_eq!(versions.len(), 3);
+    assert_eq!(
+        read_latest_version(&versions, false)
+            .unwrap()
+            .version()
+            .unwrap(),
+        "0.2.0"
+    );
+}
+
+#[test]
+fn get_latest_matching_from_local_registry() {
+    let registry = Some(RegistrySource::LocalRegistry(
+        env::current_dir()
+            .unwrap()
+            .join("tests/fixtures/local-registry"),
+    ));
+    let latest = |req: &str| {
+        get_latest_matching(
+            "my-package",
+            &semver::VersionReq::parse(req).unwrap(),
+            Path::new("Cargo.toml"),
+            &registry,
+        )
+        .unwrap()
+        .map(|v| v.to_string())
+    };
+
+    assert_eq!(latest("0.1").as_deref(), Some("0.1.0"));
+    // 0.3.0 is yanked.
+    assert_eq!(latest(">=0.1").as_deref(), Some("0.2.0"));
+    assert_eq!(latest("1.0"), None);
+}
+
+#[test]
+fn get_published_versions_from_local_registry() {
+    let registry = Some(RegistrySource::LocalRegistry(
+        env::current_dir()
+            .unwrap()
+            .join("tests/fixtures/local-registry"),
+    ));
+    let versions = get_published_versions(
+        "my-package",
+        Path::new("Cargo.toml"),
+        &registry,
+        &NetConfig::default(),
+    )
+    .expect("versions are read from the local registry");
+
+    // 0.3.0 is yanked, and the index records no publish times.
+    assert_eq!(
+        versions,
+        vec![
+            PublishedVersion {
+                version: semver::Version::parse("0.1.0").unwrap(),
+                published: None,
+            },
+            PublishedVersion {
+                version: semver::Version::parse("0.2.0").unwrap(),
+                published: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn get_crate_versions_from_local_registry() {
+    let registry = Some(RegistrySource::LocalRegistry(
+        env::current_dir()
+            .unwrap()
+            .join("tests/fixtures/local-registry"),
+    ));
+    let versions = get_crate_versions("my_package", Path::new("Cargo.toml"), &registry)
+        .expect("versions are read from the local registry");
+
+    assert_eq!(versions.name, "my-package");
+    let listed: Vec<_> = versions
+        .versions
+        .iter()
+        .map(|v| (v.version.to_string(), v.yanked))
+        .collect();
+    assert_eq!(
+        listed,
+        vec![
+            ("0.1.0".to_owned(), false),
+            ("0.2.0".to_owned(), false),
+            ("0.3.0".to_owned(), true),
+        ]
+    );
+    assert_eq!(versions.latest(false).unwrap().version.to_string(), "0.2.0");
+    let requirement = semver::VersionReq::parse("0.1").unwrap();
+    assert_eq!(
+        versions
+            .latest_matching(&requirement)
+            .unwrap()
+            .version
+            .to_string(),
+        "0.1.0"
+    );
+}
+
+#[test]
+fn get_feature_map_from_json() {
+    let version: CrateVersion = serde_json::from_str(
+        r#"{"name": "foo", "vers": "0.5.0", "yanked": false, "rust_version": "1.60",
+            "features": {"default": ["std"], "std": []},
+            "features2": {"tls": ["dep:rustls"]},
+            "deps": [{"name": "serde", "optional": true}, {"name": "rustls", "optional": true}]}"#,
+    )
+    .expect("crate version is correctly parsed");
+
+    let features = version.feature_map();
+    assert_eq!(features["tls"], vec!["dep:rustls"]);
+    assert_eq!(features["serde"], vec!["dep:serde"]);
+    assert!(!features.contains_key("rustls"));
+    assert_eq!(version.rust_version.as_deref(), Some("1.60"));
+}
+
+#[test]
+fn get_latest_version_from_directory_source() {
+    let versions = query_directory_source("my-package", "tests/fixtures/vendor")
+        .expect("crate versions are read from the vendor directory");
+
+    // `my-package-extra` is a different crate, even though its directory name matches.
+    assert_eq!(versions.len(), 2);
+    assert_eq!(
+        read_latest_version(&versions, false)
+            .unwrap()
+            .version()
+            .unwrap(),
+        "0.2.1"
+    );
+    assert!(query_directory_source("other-package", "tests/fixtures/vendor").is_err());
+}
+
+#[test]
+fn get_latest_version_from_index_cache() {
+    let content = fs::read("tests/fixtures/index-cache/my-package").unwrap();
+    let versions = parse_index_cache(&content).expect("index cache is correctly parsed");
+
+    assert_eq!(versions.len(), 3);
+    assert_eq!(
+        read_latest_version(&versions, false)
+            .unwrap()
+            .version()
+            .unwrap(),
+        "0.4.0"
+    );
+    assert!(parse_index_cache(&[]).is_err());
+}
+
 /// Gets the checkedout branch name of .cargo/registry/index/github.com-*/.git/refs or
 /// .cargo/registry/index/github.com-*/refs for bare git repository
 fn get_checkout_name(registry_path: impl AsRef<Path>) -> Result<String> {
@@ -286,7 +1106,6 @@ fn fuzzy_query_registry_index(
     crate_name: impl Into<String>,
     registry_path: impl AsRef<Path>,
 ) -> Result<Vec<CrateVersion>> {
-    let crate_name = crate_name.into();
     let remotes = PathBuf::from("refs/remotes/origin/");
     let repo = git2::Repository::open(&registry_path)?;
     let tree = repo
@@ -298,6 +1117,60 @@ fn fuzzy_query_registry_index(
         )?
         .peel_to_tree()?;
 
+    let content = fuzzy_read_summary(crate_name.into(), |summary_path| {
+        match tree.get_path(&PathBuf::from(summary_path)) {
+            Ok(x) => Ok(Some(x.to_object(&repo)?.peel_to_blob()?.content().to_vec())),
+            Err(_) => Ok(None),
+        }
+    })?;
+    parse_summary(content)
+}
+
+/// Fuzzy query crate from an index on disk, e.g. that of a `local-registry` source
+fn fuzzy_query_local_index(
+    crate_name: impl Into<String>,
+    index: impl AsRef<Path>,
+) -> Result<Vec<CrateVersion>> {
+    let index = index.as_ref();
+    let content = fuzzy_read_summary(crate_name.into(), |summary_path| {
+        let path = index.join(summary_path);
+        if path.is_file() {
+            Ok(Some(fs::read(path)?))
+        } else {
+            Ok(None)
+        }
+    })?;
+    parse_summary(content)
+}
+
+/// Fuzzy query crate from cargo's on-disk cache of a registry's index
+///
+/// Returns `None` if the crate hasn't been cached.
+fn fuzzy_query_registry_cache(
+    crate_name: &str,
+    registry: &Url,
+) -> Result<Option<Vec<CrateVersion>>> {
+    for cache in registry_cache_paths(registry)? {
+        let content = fuzzy_read_summary(crate_name.to_string(), |summary_path| {
+            let path = cache.join(summary_path);
+            if path.is_file() {
+                Ok(Some(fs::read(path)?))
+            } else {
+                Ok(None)
+            }
+        });
+        if let Ok(content) = content {
+            return parse_index_cache(&content).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+/// Read the summary file of the first similarly-named crate that exists
+fn fuzzy_read_summary<F>(crate_name: String, mut read_summary: F) -> Result<Vec<u8>>
+where
+    F: FnMut(&str) -> Result<Option<Vec<u8>>>,
+{
     let mut names = gen_fuzzy_crate_names(crate_name.clone())?;
     if let Some(index) = names.iter().position(|x| *x == crate_name) {
         // ref: https://github.com/killercup/cargo-edit/pull/317#discussion_r307365704
@@ -305,25 +1178,141 @@ fn fuzzy_query_registry_index(
     }
 
     for the_name in names {
-        let file = match tree.get_path(&PathBuf::from(summary_raw_path(&the_name))) {
-            Ok(x) => x.to_object(&repo)?.peel_to_blob()?,
-            Err(_) => continue,
-        };
-        let content = String::from_utf8(file.content().to_vec())
-            .map_err(|_| ErrorKind::InvalidSummaryJson)?;
-
-        return content
-            .lines()
-            .map(|line: &str| {
-                serde_json::from_str::<CrateVersion>(line)
-                    .map_err(|_| ErrorKind::InvalidSummaryJson.into())
-            })
-            .collect::<Result<Vec<CrateVersion>>>();
+        if let Some(content) = read_summary(&summary_raw_path(&the_name))? {
+            return Ok(content);
+        }
     }
     Err(ErrorKind::NoCrate(crate_name).into())
 }
 
-fn get_crate_name_from_repository<T>(repo: &str, matcher: &Regex, url_template: T) -> Result<String>
+/// Parse a summary file of a registry index, which has one JSON object per line
+fn parse_summary(content: Vec<u8>) -> Result<Vec<CrateVersion>> {
+    let content = String::from_utf8(content).map_err(|_| ErrorKind::InvalidSummaryJson)?;
+
+    content
+        .lines()
+        .map(|line: &str| {
+            serde_json::from_str::<CrateVersion>(line)
+                .map_err(|_| ErrorKind::InvalidSummaryJson.into())
+        })
+        .collect::<Result<Vec<CrateVersion>>>()
+}
+
+/// Parse a summary file from cargo's index cache
+///
+/// The file starts with a cache version byte, followed by the index format version (a `u32`,
+/// since cache version 2) and the index revision. After that, every version of the crate is stored
+/// as its version string and its JSON summary, each terminated by a NUL byte.
+fn parse_index_cache(content: &[u8]) -> Result<Vec<CrateVersion>> {
+    let header_len = match content.first() {
+        Some(1) => 1,
+        Some(_) => 5,
+        None => return Err(ErrorKind::InvalidSummaryJson.into()),
+    };
+    let mut fields = content
+        .get(header_len..)
+        .ok_or(ErrorKind::InvalidSummaryJson)?
+        .split(|&b| b == 0);
+    // Skip the index revision
+    fields.next();
+
+    let mut versions = Vec::new();
+    while let (Some(_version), Some(summary)) = (fields.next(), fields.next()) {
+        versions.push(
+            serde_json::from_slice::<CrateVersion>(summary)
+                .map_err(|_| ErrorKind::InvalidSummaryJson)?,
+        );
+    }
+    Ok(versions)
+}
+
+/// Query crate from a `directory` source, e.g. the output of `cargo vendor`
+///
+/// Vendored crates live in directories named either `<name>` or `<name>-<version>`, each with
+/// its own `Cargo.toml`.
+fn query_directory_source(
+    crate_name: impl Into<String>,
+    directory: impl AsRef<Path>,
+) -> Result<Vec<CrateVersion>> {
+    let crate_name = crate_name.into();
+    let names = gen_fuzzy_crate_names(crate_name.clone())?;
+
+    let mut versions = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        let dir_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        let manifest_path = path.join("Cargo.toml");
+        if !names
+            .iter()
+            .any(|name| dir_name.starts_with(&name.to_ascii_lowercase()))
+            || !manifest_path.is_file()
+        {
+            continue;
+        }
+
+        let manifest = Manifest::open(&Some(manifest_path))?;
+        let package = &manifest.data["package"];
+        if let (Some(name), Some(version)) = (package["name"].as_str(), package["version"].as_str())
+        {
+            if names.iter().any(|x| x == name) {
+                versions.push(CrateVersion {
+                    name: name.to_string(),
+                    version: semver::Version::parse(version).chain_err(|| {
+                        ErrorKind::ParseVersion(version.to_string(), name.to_string())
+                    })?,
+                    yanked: false,
+                    features: manifest_features(&manifest),
+                    features2: BTreeMap::new(),
+                    pubtime: None,
+                    rust_version: manifest.rust_version().map(ToOwned::to_owned),
+                    cksum: None,
+                    deps: manifest
+                        .implicit_features()
+                        .into_iter()
+                        .map(|name| IndexDependency {
+                            name,
+                            optional: true,
+                        })
+                        .collect(),
+                });
+            }
+        }
+    }
+
+    if versions.is_empty() {
+        Err(ErrorKind::NoCrate(crate_name).into())
+    } else {
+        Ok(versions)
+    }
+}
+
+/// The explicit features of a manifest
+fn manifest_features(manifest: &Manifest) -> BTreeMap<String, Vec<String>> {
+    manifest.data["features"]
+        .as_table_like()
+        .into_iter()
+        .flat_map(|features| features.iter())
+        .map(|(name, entries)| {
+            let entries = entries
+                .as_array()
+                .into_iter()
+                .flat_map(toml_edit::Array::iter)
+                .filter_map(|entry| entry.as_str().map(ToOwned::to_owned))
+                .collect();
+            (name.to_owned(), entries)
+        })
+        .collect()
+}
+
+fn get_crate_name_from_repository<T>(
+    repo: &str,
+    matcher: &Regex,
+    url_template: T,
+    net: &NetConfig,
+) -> Result<String>
 where
     T: Fn(&str, &str) -> String,
 {
@@ -333,7 +1322,7 @@ where
         .and_then(|cap| match (cap.get(1), cap.get(2)) {
             (Some(user), Some(repo)) => {
                 let url = url_template(user.as_str(), repo.as_str());
-                let data: Result<Manifest> = get_cargo_toml_from_git_url(&url)
+                let data: Result<Manifest> = get_cargo_toml_from_git_url(&url, net)
                     .and_then(|m| m.parse().chain_err(|| ErrorKind::ParseCargoToml));
                 data.and_then(|ref manifest| get_name_from_manifest(manifest))
             }
@@ -348,16 +1337,21 @@ where
 /// - there is no Internet connection,
 /// - Cargo.toml is not present in the root of the master branch,
 /// - the response from github is an error or in an incorrect format.
-pub fn get_crate_name_from_github(repo: &str) -> Result<String> {
+pub fn get_crate_name_from_github(repo: &str, net: &NetConfig) -> Result<String> {
     let re =
         Regex::new(r"^https://github.com/([-_0-9a-zA-Z]+)/([-_0-9a-zA-Z]+)(/|.git)?$").unwrap();
-    get_crate_name_from_repository(repo, &re, |user, repo| {
-        format!(
-            "https://raw.githubusercontent.com/{user}/{repo}/master/Cargo.toml",
-            user = user,
-            repo = repo
-        )
-    })
+    get_crate_name_from_repository(
+        repo,
+        &re,
+        |user, repo| {
+            format!(
+                "https://raw.githubusercontent.com/{user}/{repo}/master/Cargo.toml",
+                user = user,
+                repo = repo
+            )
+        },
+        net,
+    )
 }
 
 /// Query crate name by accessing a gitlab repo Cargo.toml
@@ -367,16 +1361,21 @@ pub fn get_crate_name_from_github(repo: &str) -> Result<String> {
 /// - there is no Internet connection,
 /// - Cargo.toml is not present in the root of the master branch,
 /// - the response from gitlab is an error or in an incorrect format.
-pub fn get_crate_name_from_gitlab(repo: &str) -> Result<String> {
+pub fn get_crate_name_from_gitlab(repo: &str, net: &NetConfig) -> Result<String> {
     let re =
         Regex::new(r"^https://gitlab.com/([-_0-9a-zA-Z]+)/([-_0-9a-zA-Z]+)(/|.git)?$").unwrap();
-    get_crate_name_from_repository(repo, &re, |user, repo| {
-        format!(
-            "https://gitlab.com/{user}/{repo}/raw/master/Cargo.toml",
-            user = user,
-            repo = repo
-        )
-    })
+    get_crate_name_from_repository(
+        repo,
+        &re,
+        |user, repo| {
+            format!(
+                "https://gitlab.com/{user}/{repo}/raw/master/Cargo.toml",
+                user = user,
+                repo = repo
+            )
+        },
+        net,
+    )
 }
 
 /// Query crate name by accessing Cargo.toml in a local path
@@ -395,12 +1394,488 @@ fn get_name_from_manifest(manifest: &Manifest) -> Result<String> {
         .data
         .as_table()
         .get("package")
-        .and_then(|m| m["name"].as_str().map(std::string::ToString::to_string))
+        .and_then(|m| m["name"].as_str().map(ToString::to_string))
         .ok_or_else(|| ErrorKind::ParseCargoToml.into())
 }
 
-fn get_cargo_toml_from_git_url(url: &str) -> Result<String> {
-    let mut req = ureq::get(url);
+/// A version of a crate, as the crates.io API describes it
+#[derive(Deserialize)]
+struct ApiVersion {
+    num: String,
+    created_at: String,
+    published_by: Option<ApiUser>,
+    license: Option<String>,
+    #[serde(default)]
+    checksum: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiUser {
+    login: String,
+}
+
+/// Ask the crates.io API about every version of a crate, checked against the versions of the
+/// registry index with `verify_api_versions` unless `net.verify_api` is unset
+fn crates_io_versions(
+    crate_name: &str,
+    index: &[CrateVersion],
+    net: &NetConfig,
+) -> Result<Vec<ApiVersion>> {
+    #[derive(Deserialize)]
+    struct Versions {
+        versions: Vec<ApiVersion>,
+    }
+
+    let url = format!("https://crates.io/api/v1/crates/{}/versions", crate_name);
+    let versions: Versions = serde_json::from_str(&http_get(&url, net)?)
+        .chain_err(|| format!("Invalid response from `{}`", url))?;
+    if !net.verify_api {
+        return Ok(versions.versions);
+    }
+    verify_api_versions(crate_name, versions.versions, index)
+}
+
+/// Check what the crates.io API reports against the registry index, which is what cargo trusts
+/// when downloading: versions the index doesn't have are dropped, and a version whose checksum
+/// differs from the index's fails, as the response may have been tampered with.
+fn verify_api_versions(
+    crate_name: &str,
+    versions: Vec<ApiVersion>,
+    index: &[CrateVersion],
+) -> Result<Vec<ApiVersion>> {
+    let checksums: HashMap<String, Option<&str>> = index
+        .iter()
+        .map(|v| (v.version.to_string(), v.cksum.as_deref()))
+        .collect();
+    let mut verified = Vec::new();
+    for version in versions {
+        let checksum = match checksums.get(&version.num) {
+            Some(checksum) => *checksum,
+            None => continue,
+        };
+        if let Some(checksum) = checksum {
+            let reported = version.checksum.as_deref().unwrap_or("");
+            if !reported.eq_ignore_ascii_case(checksum) {
+                return Err(ErrorKind::ApiChecksumMismatch(
+                    crate_name.to_owned(),
+                    version.num,
+                    reported.to_owned(),
+                    checksum.to_owned(),
+                )
+                .into());
+            }
+        }
+        verified.push(version);
+    }
+    Ok(verified)
+}
+
+/// Ask the crates.io API when each version of a crate in the registry index was published
+fn crates_io_publish_times(
+    crate_name: &str,
+    index: &[CrateVersion],
+    net: &NetConfig,
+) -> Result<HashMap<String, String>> {
+    Ok(crates_io_versions(crate_name, index, net)?
+        .into_iter()
+        .map(|v| (v.num, v.created_at))
+        .collect())
+}
+
+/// What crates.io records about a version of a crate
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Release {
+    /// The login of whoever published it, unless it was published before crates.io recorded that
+    pub publisher: Option<String>,
+    /// Its license expression, unless it only has a license file
+    pub license: Option<String>,
+}
+
+/// What crates.io records about each version of a crate
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Releases(pub BTreeMap<semver::Version, Release>);
+
+impl Releases {
+    /// The publisher of `version`, if they published none of the versions from `since` up to it,
+    /// e.g. because the crate changed hands
+    ///
+    /// Nothing is returned if it isn't known who published those versions.
+    pub fn new_publisher(
+        &self,
+        since: &semver::Version,
+        version: &semver::Version,
+    ) -> Option<&str> {
+        let publisher = self.0.get(version)?.publisher.as_ref()?;
+        let mut previous = self
+            .0
+            .range(since..version)
+            .filter_map(|(_, release)| release.publisher.as_ref())
+            .peekable();
+        previous.peek()?;
+        if previous.any(|login| login == publisher) {
+            None
+        } else {
+            Some(publisher)
+        }
+    }
+
+    /// The licenses of the first version from `since` on and of `version`, if they differ
+    ///
+    /// Nothing is returned if either version has no license expression.
+    pub fn license_change(
+        &self,
+        since: &semver::Version,
+        version: &semver::Version,
+    ) -> Option<(&str, &str)> {
+        let old = self.0.range(since..version).next()?.1.license.as_deref()?;
+        let new = self.0.get(version)?.license.as_deref()?;
+        if same_license(old, new) {
+            None
+        } else {
+            Some((old, new))
+        }
+    }
+}
+
+/// Query who published each version of a crate, and under which license
+///
+/// The registry argument is used as for `get_latest_dependency`. Only crates.io records this, so
+/// `None` is returned for crates from other registries.
+pub fn get_releases(
+    crate_name: &str,
+    manifest_path: &Path,
+    registry: &Option<RegistrySource>,
+    net: &NetConfig,
+) -> Result<Option<Releases>> {
+    let source = resolve_source(manifest_path, registry)?;
+    if !source.is_crates_io() || is_simulated(&source) {
+        return Ok(None);
+    }
+
+    let index = query_versions(crate_name, source)?;
+    let name = index.first().map_or(crate_name, |v| v.name.as_str());
+    let releases = crates_io_versions(name, &index, net)?
+        .into_iter()
+        .filter_map(|v| {
+            let version = semver::Version::parse(&v.num).ok()?;
+            let release = Release {
+                publisher: v.published_by.map(|user| user.login),
+                license: v.license,
+            };
+            Some((version, release))
+        })
+        .collect();
+    Ok(Some(Releases(releases)))
+}
+
+/// The description of a crate, as crates.io records it for its latest version
+///
+/// The registry argument is used as for `get_latest_dependency`. Only crates.io records this, so
+/// `None` is returned for crates from other registries.
+pub fn get_description(
+    crate_name: &str,
+    manifest_path: &Path,
+    registry: &Option<RegistrySource>,
+    net: &NetConfig,
+) -> Result<Option<String>> {
+    #[derive(Deserialize)]
+    struct Crate {
+        description: Option<String>,
+    }
+    #[derive(Deserialize)]
+    struct Response {
+        #[serde(rename = "crate")]
+        krate: Crate,
+    }
+
+    if !resolve_source(manifest_path, registry)?.is_crates_io() {
+        return Ok(None);
+    }
+    if env::var("CARGO_IS_TEST").is_ok() {
+        // We are in a simulated reality, where every crate describes itself the same way.
+        return Ok(Some(format!("A crate called {}", crate_name)));
+    }
+
+    let url = format!("https://crates.io/api/v1/crates/{}", crate_name);
+    let response: Response = serde_json::from_str(&http_get(&url, net)?)
+        .chain_err(|| format!("Invalid response from `{}`", url))?;
+    // Descriptions are often wrapped over several lines, which a comment doesn't need.
+    Ok(response
+        .krate
+        .description
+        .map(|description| description.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|description| !description.is_empty()))
+}
+
+/// How new a crate can be and still be suspicious, if it is hardly downloaded
+const NEW_CRATE_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// How many downloads a crate can have and still be suspicious, if it is new
+const FEW_DOWNLOADS: u64 = 100;
+
+/// What crates.io records about a crate that hints at whether it can be trusted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateStats {
+    /// When its first version was published
+    pub created: SystemTime,
+    /// How many times it has been downloaded
+    pub downloads: u64,
+    /// How many crates each of its owners owns, this one included
+    pub owner_crates: Vec<u64>,
+}
+
+impl CrateStats {
+    /// Why a crate called `name` with these stats might not be what it seems, e.g. because its
+    /// name is a typo of one of the `popular` crates
+    ///
+    /// Each reason is a clause, e.g. "was first published 2 days ago and has 3 downloads".
+    pub fn suspicions(&self, name: &str, popular: &[String], now: SystemTime) -> Vec<String> {
+        let mut suspicions = Vec::new();
+        let age = now.duration_since(self.created).unwrap_or_default();
+        if age < NEW_CRATE_AGE && self.downloads < FEW_DOWNLOADS {
+            suspicions.push(format!(
+                "was first published {} ago and has {} downloads",
+                format_age(age),
+                self.downloads
+            ));
+        }
+        if let Some(lookalike) = popular.iter().find(|popular| {
+            edit_distance(&normalize_crate_name(popular), &normalize_crate_name(name)) == 1
+        }) {
+            suspicions.push(format!("is named like the popular `{}`", lookalike));
+        }
+        if !self.owner_crates.is_empty() && self.owner_crates.iter().all(|&crates| crates <= 1) {
+            suspicions.push("is owned by accounts that have published nothing else".to_owned());
+        }
+        suspicions
+    }
+}
+
+/// An age in the largest whole unit, e.g. "3 hours"
+fn format_age(age: Duration) -> String {
+    let hours = age.as_secs() / (60 * 60);
+    let (count, unit) = if hours >= 24 {
+        (hours / 24, "day")
+    } else if hours >= 1 {
+        (hours, "hour")
+    } else {
+        (age.as_secs() / 60, "minute")
+    };
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
+
+/// crates.io treats `-` and `_` in crate names alike, and ignores case.
+fn normalize_crate_name(name: &str) -> String {
+    name.to_lowercase().replace('-', "_")
+}
+
+/// The number of single-character insertions, deletions and substitutions turning `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Query when a crate was first published, how often it has been downloaded and by whom it is owned
+///
+/// The registry argument is used as for `get_latest_dependency`. Only crates.io records this, so
+/// `None` is returned for crates from other registries.
+pub fn get_crate_stats(
+    crate_name: &str,
+    manifest_path: &Path,
+    registry: &Option<RegistrySource>,
+    net: &NetConfig,
+) -> Result<Option<CrateStats>> {
+    #[derive(Deserialize)]
+    struct Crate {
+        created_at: String,
+        downloads: u64,
+    }
+    #[derive(Deserialize)]
+    struct Response {
+        #[serde(rename = "crate")]
+        krate: Crate,
+    }
+    #[derive(Deserialize)]
+    struct Owner {
+        id: u64,
+        kind: String,
+    }
+    #[derive(Deserialize)]
+    struct Owners {
+        users: Vec<Owner>,
+    }
+    #[derive(Deserialize)]
+    struct Meta {
+        total: u64,
+    }
+    #[derive(Deserialize)]
+    struct Crates {
+        meta: Meta,
+    }
+
+    if !resolve_source(manifest_path, registry)?.is_crates_io() {
+        return Ok(None);
+    }
+    if env::var("CARGO_IS_TEST").is_ok() {
+        // We are in a simulated reality, where crates are well established unless they say they
+        // can't be trusted.
+        let untrusted = crate_name.starts_with("untrusted");
+        let age = if untrusted {
+            2 * 24 * 60 * 60
+        } else {
+            5 * 365 * 24 * 60 * 60
+        };
+        return Ok(Some(CrateStats {
+            created: SystemTime::now() - Duration::from_secs(age),
+            downloads: if untrusted { 3 } else { 1_234_567 },
+            owner_crates: vec![if untrusted { 1 } else { 12 }],
+        }));
+    }
+
+    let url = format!("https://crates.io/api/v1/crates/{}", crate_name);
+    let response: Response = serde_json::from_str(&http_get(&url, net)?)
+        .chain_err(|| format!("Invalid response from `{}`", url))?;
+    let created = chrono::DateTime::parse_from_rfc3339(&response.krate.created_at)
+        .chain_err(|| format!("Invalid response from `{}`", url))?;
+
+    let url = format!("https://crates.io/api/v1/crates/{}/owners", crate_name);
+    let owners: Owners = serde_json::from_str(&http_get(&url, net)?)
+        .chain_err(|| format!("Invalid response from `{}`", url))?;
+    // Teams own crates through their members, so only users say anything about the owners.
+    let owner_crates = owners
+        .users
+        .iter()
+        .filter(|owner| owner.kind == "user")
+        .map(|owner| {
+            let url = format!(
+                "https://crates.io/api/v1/crates?user_id={}&per_page=1",
+                owner.id
+            );
+            let crates: Crates = serde_json::from_str(&http_get(&url, net)?)
+                .chain_err(|| format!("Invalid response from `{}`", url))?;
+            Ok(crates.meta.total)
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(Some(CrateStats {
+        created: SystemTime::from(created),
+        downloads: response.krate.downloads,
+        owner_crates,
+    }))
+}
+
+/// The names of the most downloaded crates on crates.io, most downloaded first
+pub fn get_popular_crates(limit: usize, net: &NetConfig) -> Result<Vec<String>> {
+    #[derive(Deserialize)]
+    struct Crate {
+        name: String,
+    }
+    #[derive(Deserialize)]
+    struct Crates {
+        crates: Vec<Crate>,
+    }
+
+    if env::var("CARGO_IS_TEST").is_ok() {
+        // We are in a simulated reality, where few crates are popular.
+        return Ok(["serde", "rand", "regex"]
+            .iter()
+            .take(limit)
+            .map(|name| (*name).to_owned())
+            .collect());
+    }
+
+    let url = format!(
+        "https://crates.io/api/v1/crates?sort=downloads&per_page={}",
+        limit
+    );
+    let crates: Crates = serde_json::from_str(&http_get(&url, net)?)
+        .chain_err(|| format!("Invalid response from `{}`", url))?;
+    Ok(crates.crates.into_iter().map(|krate| krate.name).collect())
+}
+
+/// A crate found by searching crates.io
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CrateSummary {
+    /// Its name
+    pub name: String,
+    /// Its latest version
+    #[serde(rename = "max_version")]
+    pub version: String,
+    /// What it is for, in a sentence or so
+    pub description: Option<String>,
+    /// How many times it has been downloaded
+    pub downloads: u64,
+    /// When it was last published, in RFC 3339 format
+    pub updated_at: String,
+}
+
+/// Search crates.io for crates matching a query, most relevant first
+pub fn search_crates(query: &str, limit: usize, net: &NetConfig) -> Result<Vec<CrateSummary>> {
+    #[derive(Deserialize)]
+    struct Results {
+        crates: Vec<CrateSummary>,
+    }
+
+    if env::var("CARGO_IS_TEST").is_ok() {
+        // We are in a simulated reality, where these crates have known latest versions.
+        return Ok([("test_breaking", "0.2.0"), ("test_nonbreaking", "0.1.1")]
+            .iter()
+            .take(limit)
+            .map(|(name, version)| CrateSummary {
+                name: (*name).to_owned(),
+                version: (*version).to_owned(),
+                description: Some(format!("A crate about {}", query)),
+                downloads: 1_234_567,
+                updated_at: "2021-03-01T12:00:00Z".to_owned(),
+            })
+            .collect());
+    }
+
+    let url = Url::parse_with_params(
+        "https://crates.io/api/v1/crates",
+        &[("q", query), ("per_page", &limit.to_string())],
+    )
+    .chain_err(|| "Invalid search query")?;
+    let results: Results = serde_json::from_str(&http_get(url.as_str(), net)?)
+        .chain_err(|| format!("Invalid response from `{}`", url))?;
+    Ok(results.crates)
+}
+
+fn get_cargo_toml_from_git_url(url: &str, net: &NetConfig) -> Result<String> {
+    http_get(url, net).chain_err(|| "Git response not a valid `String`")
+}
+
+/// The client every request goes through, whichever command makes it, so that connections to a
+/// registry are kept and reused from one request to the next
+fn http_agent() -> &'static ureq::Agent {
+    static AGENT: OnceLock<ureq::Agent> = OnceLock::new();
+    AGENT.get_or_init(ureq::agent)
+}
+
+fn http_get(url: &str, net: &NetConfig) -> Result<String> {
+    if net.offline {
+        return Err(format!(
+            "Can't request `{}` without accessing the network, which `net.offline` forbids",
+            url
+        )
+        .into());
+    }
+
+    let mut req = http_agent().get(url);
+    // crates.io asks API clients to identify themselves.
+    req.set(
+        "User-Agent",
+        concat!("cargo-edit/", env!("CARGO_PKG_VERSION")),
+    );
     req.timeout(get_default_timeout());
     if let Some(proxy) = env_proxy::for_url_str(url)
         .to_url()
@@ -408,7 +1883,28 @@ fn get_cargo_toml_from_git_url(url: &str) -> Result<String> {
     {
         req.set_proxy(proxy);
     }
-    let res = req.call();
+    if let Some(config) = net.tls.rustls_config()? {
+        req.set_tls_config(config);
+    }
+    // Revalidate a cached response rather than downloading it again.
+    let cached = http_cache::load(url);
+    if let Some(cached) = &cached {
+        for (header, value) in cached.conditional_headers() {
+            req.set(header, value);
+        }
+    }
+    let mut res = req.call();
+    // Like cargo, retry requests that failed to connect or hit a server error.
+    for attempt in 1..=net.retry {
+        if res.synthetic_error().is_none() && !res.server_error() {
+            break;
+        }
+        eprintln!(
+            "WARN: Request `{}` failed, retrying ({}/{})",
+            url, attempt, net.retry
+        );
+        res = req.call();
+    }
     if res.error() {
         return Err(format!(
             "HTTP request `{}` failed: {}",
@@ -420,9 +1916,19 @@ fn get_cargo_toml_from_git_url(url: &str) -> Result<String> {
         )
         .into());
     }
+    if let (304, Some(cached)) = (res.status(), cached) {
+        return Ok(cached.body);
+    }
 
-    res.into_string()
-        .chain_err(|| "Git response not a valid `String`")
+    let etag = res.header("ETag").map(str::to_owned);
+    let last_modified = res.header("Last-Modified").map(str::to_owned);
+    let body = res
+        .into_string()
+        .chain_err(|| format!("Response from `{}` not a valid `String`", url))?;
+    if let Some(response) = CachedResponse::new(etag.as_deref(), last_modified.as_deref(), &body) {
+        http_cache::store(url, &response);
+    }
+    Ok(body)
 }
 
 const fn get_default_timeout() -> Duration {
@@ -490,7 +1996,7 @@ fn test_gen_fuzzy_crate_names() {
     );
 }
 
-fn summary_raw_path(crate_name: &str) -> String {
+pub(crate) fn summary_raw_path(crate_name: &str) -> String {
     let crate_name = crate_name.to_ascii_lowercase();
     match crate_name.len() {
         0 => unreachable!("we check that crate_name is not empty here"),
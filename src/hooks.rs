@@ -0,0 +1,170 @@
+//! Commands that a workspace has run before and after the manifest edits of each command, e.g. a
+//! formatter or a policy check.
+//!
+//! They are configured in the manifest at the workspace root (or the package's own, outside a
+//! workspace), each as the program and its arguments:
+//!
+//! ```toml
+//! [workspace.metadata.cargo-edit]
+//! pre-edit = [["./scripts/check-owners.sh"]]
+//! post-edit = [["taplo", "fmt"]]
+//! ```
+//!
+//! Each hook runs once per command, with the paths of all the manifests the command writes
+//! appended to its arguments, so it has to accept any number of them: `taplo fmt a/Cargo.toml
+//! b/Cargo.toml` formats both, where a hook ending in an option like `--manifest-path` would be
+//! given several paths after it.
+//!
+//! As they run whatever the workspace says, they only run in workspaces that the user trusts, by
+//! listing their roots in `cargo-edit.trusted-hooks` in the cargo config of their home directory.
+
+use crate::errors::*;
+use crate::manifest::workspace_root;
+use crate::registry::trusted_hooks;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Environment variable that turns the hooks off, e.g. for the edits the hooks make themselves
+pub const NO_HOOKS_ENV: &str = "CARGO_EDIT_NO_HOOKS";
+
+/// The commands to run around manifest edits
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Hooks {
+    /// Run before the manifests are written, with their paths appended to the arguments. If one
+    /// fails, they aren't written.
+    #[serde(default)]
+    pub pre_edit: Vec<Vec<String>>,
+    /// Run after the manifests are written, with their paths appended to the arguments. If one
+    /// fails, the edits are rolled back.
+    #[serde(default)]
+    pub post_edit: Vec<Vec<String>>,
+    /// Where the hooks are run: the workspace root
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+impl Hooks {
+    /// The hooks configured for the workspace a manifest is in, or none if the user doesn't trust
+    /// it or `CARGO_EDIT_NO_HOOKS` is set
+    ///
+    /// A warning is printed if a workspace that isn't trusted configures hooks.
+    pub fn for_manifest(manifest_path: &Path) -> Result<Self> {
+        if env::var_os(NO_HOOKS_ENV).is_some() {
+            return Ok(Hooks::default());
+        }
+        let hooks = Self::configured(manifest_path)?;
+        if hooks.pre_edit.is_empty() && hooks.post_edit.is_empty() {
+            return Ok(hooks);
+        }
+        if !trusted_hooks()?
+            .iter()
+            .any(|root| is_same_dir(root, &hooks.dir))
+        {
+            eprintln!(
+                "WARN: Not running the hooks configured in `{}`, as the workspace isn't listed in \
+                 `cargo-edit.trusted-hooks` of your cargo config",
+                hooks.dir.join("Cargo.toml").display()
+            );
+            return Ok(Hooks::default());
+        }
+        Ok(hooks)
+    }
+
+    /// The hooks configured for the workspace a manifest is in, whether the user trusts it or not
+    fn configured(manifest_path: &Path) -> Result<Self> {
+        let root = workspace_root(manifest_path)?;
+        let path = root.join("Cargo.toml");
+        let manifest: toml::Value = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| contents.parse().ok())
+            .unwrap_or_else(|| toml::Value::Table(Default::default()));
+        let config = ["workspace", "package"]
+            .iter()
+            .find_map(|table| manifest.get(table)?.get("metadata")?.get("cargo-edit"));
+        let mut hooks: Hooks = match config {
+            Some(config) => config.clone().try_into().chain_err(|| {
+                format!(
+                    "Invalid `metadata.cargo-edit` hooks in `{}`",
+                    path.display()
+                )
+            })?,
+            None => Hooks::default(),
+        };
+        hooks.dir = root;
+        Ok(hooks)
+    }
+
+    /// Run the pre-edit hooks for the manifests about to be written
+    pub fn pre_edit(&self, manifest_paths: &[PathBuf]) -> Result<()> {
+        self.run(&self.pre_edit, manifest_paths)
+    }
+
+    /// Run the post-edit hooks for the manifests that were written
+    pub fn post_edit(&self, manifest_paths: &[PathBuf]) -> Result<()> {
+        self.run(&self.post_edit, manifest_paths)
+    }
+
+    /// Run hooks one after the other in the workspace root, with the manifests' paths as their last
+    /// arguments, stopping at the first that fails.
+    fn run(&self, hooks: &[Vec<String>], manifest_paths: &[PathBuf]) -> Result<()> {
+        for hook in hooks {
+            let command = hook.join(" ");
+            let (program, args) = hook
+                .split_first()
+                .chain_err(|| "An empty hook is configured")?;
+            // The output goes to stderr, to keep stdout to what cargo-edit itself reports.
+            let status = Command::new(program)
+                .args(args)
+                .args(manifest_paths)
+                .current_dir(&self.dir)
+                .env(NO_HOOKS_ENV, "1")
+                .stdout(Stdio::from(io::stderr()))
+                .status()
+                .chain_err(|| format!("Failed to run the hook `{}`", command))?;
+            if !status.success() {
+                return Err(ErrorKind::HookFailed(command, manifest_paths.to_owned()).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether two paths are of the same directory, even if spelled differently
+fn is_same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hooks_are_configured_at_the_workspace_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let member = dir.path().join("member");
+        fs::create_dir(&member).unwrap();
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            r#"[workspace]
+members = ["member"]
+
+[workspace.metadata.cargo-edit]
+post-edit = [["taplo", "fmt"]]
+"#,
+        )
+        .unwrap();
+        fs::write(member.join("Cargo.toml"), "[package]\nname = \"member\"\n").unwrap();
+
+        let hooks = Hooks::configured(&member.join("Cargo.toml")).unwrap();
+        assert!(hooks.pre_edit.is_empty());
+        assert_eq!(hooks.post_edit, vec![vec!["taplo", "fmt"]]);
+        assert_eq!(hooks.dir, dir.path().canonicalize().unwrap());
+    }
+}
@@ -0,0 +1,106 @@
+//! A cache of the responses to HTTP lookups, kept fresh with conditional requests
+//!
+//! Responses that carry an `ETag` or `Last-Modified` header are stored, and the next request for
+//! the same URL sends them back as `If-None-Match` and `If-Modified-Since`. When the server answers
+//! `304 Not Modified`, the stored body is used, so repeat runs transfer almost nothing.
+
+use crate::errors::*;
+use crate::registry::cargo_home;
+use std::fs;
+use std::path::PathBuf;
+
+/// A response body, with the validators to revalidate it with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct CachedResponse {
+    /// The `ETag` header of the response
+    pub etag: Option<String>,
+    /// The `Last-Modified` header of the response
+    pub last_modified: Option<String>,
+    /// The body of the response
+    pub body: String,
+}
+
+impl CachedResponse {
+    /// The cacheable response, if the server sent anything to revalidate it with
+    pub fn new(etag: Option<&str>, last_modified: Option<&str>, body: &str) -> Option<Self> {
+        if etag.is_none() && last_modified.is_none() {
+            return None;
+        }
+        Some(CachedResponse {
+            etag: etag.map(str::to_owned),
+            last_modified: last_modified.map(str::to_owned),
+            body: body.to_owned(),
+        })
+    }
+
+    /// The headers that ask the server to only send the response if it has changed
+    pub fn conditional_headers(&self) -> Vec<(&'static str, &str)> {
+        let mut headers = Vec::new();
+        if let Some(etag) = &self.etag {
+            headers.push(("If-None-Match", etag.as_str()));
+        }
+        if let Some(last_modified) = &self.last_modified {
+            headers.push(("If-Modified-Since", last_modified.as_str()));
+        }
+        headers
+    }
+}
+
+/// The file a URL's response is cached in
+fn cache_path(url: &str) -> Result<PathBuf> {
+    #![allow(deprecated)]
+    use std::hash::{Hash, Hasher, SipHasher};
+
+    let mut hasher = SipHasher::new();
+    url.hash(&mut hasher);
+    let hash = hex::encode(hasher.finish().to_le_bytes());
+    Ok(cargo_home()?
+        .join("cargo-edit")
+        .join("http-cache")
+        .join(hash))
+}
+
+/// The cached response for a URL, if there is a usable one
+pub(crate) fn load(url: &str) -> Option<CachedResponse> {
+    let content = fs::read(cache_path(url).ok()?).ok()?;
+    serde_json::from_slice(&content).ok()
+}
+
+/// Cache the response for a URL
+///
+/// The cache only saves time, so failing to write it isn't an error.
+pub(crate) fn store(url: &str, response: &CachedResponse) {
+    let path = match cache_path(url) {
+        Ok(path) => path,
+        Err(_) => return,
+    };
+    let content = match serde_json::to_vec(response) {
+        Ok(content) => content,
+        Err(_) => return,
+    };
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let _ = fs::write(path, content);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_responses_with_validators_are_cached() {
+        assert_eq!(CachedResponse::new(None, None, "{}"), None);
+
+        let response =
+            CachedResponse::new(Some("\"abc\""), Some("Mon, 01 Mar 2021 12:00:00 GMT"), "{}")
+                .unwrap();
+        assert_eq!(
+            response.conditional_headers(),
+            vec![
+                ("If-None-Match", "\"abc\""),
+                ("If-Modified-Since", "Mon, 01 Mar 2021 12:00:00 GMT"),
+            ]
+        );
+    }
+}
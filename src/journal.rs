@@ -0,0 +1,284 @@
+//! A journal of what manifests looked like before each command changed them, for `cargo edit undo`.
+
+use crate::errors::*;
+use crate::manifest::workspace_root;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Where the journal is kept, relative to the workspace's target directory
+const JOURNAL_DIR: &str = "cargo-edit/journal";
+
+/// How many operations the journal keeps, so that it stays small
+const KEPT_OPERATIONS: usize = 10;
+
+/// The manifests one command changed, as they were before and after it ran
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Operation {
+    /// The command line, e.g. `cargo upgrade --workspace`
+    pub command: String,
+    /// The manifests, in the order they were written
+    pub files: Vec<JournaledFile>,
+}
+
+impl Operation {
+    /// The manifests that have been changed since the operation wrote them, which undoing it
+    /// would lose the changes to
+    pub fn changed_since(&self) -> Vec<&Path> {
+        self.files
+            .iter()
+            .filter(|file| fs::read_to_string(&file.path).ok().as_ref() != Some(&file.written))
+            .map(|file| file.path.as_path())
+            .collect()
+    }
+}
+
+/// A manifest that a command changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournaledFile {
+    /// Its absolute path
+    pub path: PathBuf,
+    /// Its contents before the command changed it
+    pub original: String,
+    /// The contents the command wrote
+    pub written: String,
+}
+
+/// The journal for the workspace a manifest is in, under the target directory that cargo uses for
+/// it, e.g. as set by `CARGO_TARGET_DIR` or `build.target-dir`.
+///
+/// Cargo only reads the workspace's manifests and config for it, without the network or
+/// `Cargo.lock`, so this works offline and with `--frozen`. If cargo can't read the workspace, e.g.
+/// because a manifest is incomplete, it is under `CARGO_TARGET_DIR` or else `target` in the
+/// workspace root.
+fn journal_dir(manifest_path: &Path) -> Result<PathBuf> {
+    let mut cmd = cargo_metadata::MetadataCommand::new();
+    cmd.manifest_path(manifest_path)
+        .no_deps()
+        .other_options(vec!["--offline".to_owned()]);
+    let target_dir = match cmd.exec() {
+        Ok(metadata) => metadata.target_directory,
+        Err(_) => match std::env::var_os("CARGO_TARGET_DIR") {
+            Some(dir) => PathBuf::from(dir),
+            None => workspace_root(manifest_path)?.join("target"),
+        },
+    };
+    Ok(target_dir.join(JOURNAL_DIR))
+}
+
+fn read_operation(path: &Path) -> Result<Operation> {
+    let contents = fs::read_to_string(path)?;
+    toml::from_str(&contents).chain_err(|| format!("Invalid journal entry `{}`", path.display()))
+}
+
+/// The journal entries, oldest first.
+fn entries(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.retain(|path| path.extension().is_some_and(|ext| ext == "toml"));
+    entries.sort();
+    Ok(entries)
+}
+
+/// Record the manifests that the running command wrote, e.g. as returned by `Edits::write`, as
+/// the operation that `cargo edit undo` undoes next in their workspace
+///
+/// Nothing is recorded if no manifest was written.
+pub fn record_for_undo(files: &[JournaledFile]) -> Result<()> {
+    // Manifests from different workspaces, e.g. given to `cargo upgrade` one by one, are recorded
+    // in the journals of their own workspaces. Cargo is asked for the journal of each workspace
+    // once, rather than for each of its manifests.
+    let mut workspaces: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut journals: Vec<(PathBuf, Vec<JournaledFile>)> = Vec::new();
+    for file in files {
+        let dir = (|| -> Result<PathBuf> {
+            let root = workspace_root(&file.path)?;
+            if let Some((_, dir)) = workspaces.iter().find(|(known, _)| *known == root) {
+                return Ok(dir.clone());
+            }
+            let dir = journal_dir(&file.path)?;
+            workspaces.push((root, dir.clone()));
+            Ok(dir)
+        })()
+        .chain_err(|| {
+            format!(
+                "Failed to record `{}` for `cargo edit undo`",
+                file.path.display()
+            )
+        })?;
+        match journals.iter_mut().find(|(journal, _)| *journal == dir) {
+            Some((_, files)) => files.push(file.clone()),
+            None => journals.push((dir, vec![file.clone()])),
+        }
+    }
+
+    // Entries are named so that they sort in the order the commands ran.
+    let since_epoch = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    let name = format!("{:020}-{}.toml", since_epoch.as_nanos(), std::process::id());
+    for (dir, files) in journals {
+        (|| -> Result<()> {
+            fs::create_dir_all(&dir)?;
+            let mut entries = entries(&dir)?;
+            if entries.len() >= KEPT_OPERATIONS {
+                for old in entries.drain(..=entries.len() - KEPT_OPERATIONS) {
+                    fs::remove_file(old)?;
+                }
+            }
+            let operation = Operation {
+                command: command_line(),
+                files,
+            };
+            let contents =
+                toml::to_string(&operation).chain_err(|| "Failed to serialize journal")?;
+            fs::write(dir.join(&name), contents)?;
+            Ok(())
+        })()
+        .chain_err(|| format!("Failed to record the edits in `{}`", dir.display()))?;
+    }
+    Ok(())
+}
+
+/// The command line of the running command, as it would be typed, e.g. `cargo add serde`
+fn command_line() -> String {
+    let mut args: Vec<_> = std::env::args().collect();
+    // Cargo runs `cargo-add add serde` for `cargo add serde`.
+    if let Some(program) = args.first_mut() {
+        *program = "cargo".to_owned();
+    }
+    args.join(" ")
+}
+
+/// The last operation recorded in the journal of the workspace a manifest is in
+pub fn last_operation(manifest_path: &Path) -> Result<Option<Operation>> {
+    let dir = journal_dir(manifest_path)?;
+    match entries(&dir)?.last() {
+        Some(entry) => Ok(Some(read_operation(entry)?)),
+        None => Ok(None),
+    }
+}
+
+/// Restore the manifests that the last operation in the journal changed, and drop it from the
+/// journal, so that the operation before it is undone next
+///
+/// Unless `force` is set, nothing is restored if a manifest has been changed since the operation
+/// wrote it (see `Operation::changed_since`). Returns the operation, or `None` if there is nothing
+/// to undo.
+pub fn undo(manifest_path: &Path, force: bool) -> Result<Option<Operation>> {
+    let dir = journal_dir(manifest_path)?;
+    let entry = match entries(&dir)?.pop() {
+        Some(entry) => entry,
+        None => return Ok(None),
+    };
+    let operation = read_operation(&entry)?;
+    let changed = operation.changed_since();
+    if !force && !changed.is_empty() {
+        return Err(ErrorKind::ChangedSinceOperation(
+            operation.command.clone(),
+            changed.into_iter().map(Path::to_owned).collect(),
+        )
+        .into());
+    }
+    for file in &operation.files {
+        fs::write(&file.path, &file.original)
+            .chain_err(|| format!("Failed to restore `{}`", file.path.display()))?;
+    }
+    fs::remove_file(&entry)?;
+    Ok(Some(operation))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn journal_is_kept_in_the_workspace_target_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let member = dir.path().join("member");
+        fs::create_dir(&member).unwrap();
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[workspace]\nmembers = [\"member\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            member.join("Cargo.toml"),
+            "[package]\nname = \"member\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        fs::create_dir(member.join("src")).unwrap();
+        fs::write(member.join("src/lib.rs"), "").unwrap();
+
+        let journal = journal_dir(&member.join("Cargo.toml")).unwrap();
+        assert!(journal.ends_with(JOURNAL_DIR));
+        assert_eq!(
+            journal,
+            journal_dir(&dir.path().join("Cargo.toml")).unwrap()
+        );
+    }
+
+    #[test]
+    fn records_the_manifests_of_a_workspace_together() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[workspace]\nmembers = [\"a\", \"b\"]\n",
+        )
+        .unwrap();
+        let mut files = Vec::new();
+        for member in &["a", "b"] {
+            let member_dir = dir.path().join(member);
+            fs::create_dir_all(member_dir.join("src")).unwrap();
+            fs::write(member_dir.join("src/lib.rs"), "").unwrap();
+            let contents = format!("[package]\nname = \"{}\"\nversion = \"0.1.0\"\n", member);
+            fs::write(member_dir.join("Cargo.toml"), &contents).unwrap();
+            files.push(JournaledFile {
+                path: member_dir.join("Cargo.toml").canonicalize().unwrap(),
+                original: contents.clone(),
+                written: contents,
+            });
+        }
+        record_for_undo(&files).unwrap();
+
+        let journal = journal_dir(&dir.path().join("Cargo.toml")).unwrap();
+        assert_eq!(entries(&journal).unwrap().len(), 1);
+        let operation = last_operation(&files[1].path).unwrap().unwrap();
+        assert_eq!(operation.files, files);
+    }
+
+    #[test]
+    fn undo_restores_the_last_operation() {
+        let dir = tempfile::tempdir().unwrap();
+        let before = "[package]\nname = \"before\"\nversion = \"0.1.0\"\n";
+        let after = "[package]\nname = \"after\"\nversion = \"0.1.0\"\n";
+        fs::write(dir.path().join("Cargo.toml"), after).unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "").unwrap();
+        let manifest = dir.path().join("Cargo.toml").canonicalize().unwrap();
+        record_for_undo(&[JournaledFile {
+            path: manifest.clone(),
+            original: before.to_owned(),
+            written: after.to_owned(),
+        }])
+        .unwrap();
+
+        let operation = last_operation(&manifest).unwrap().unwrap();
+        assert_eq!(operation.files.len(), 1);
+        assert!(operation.changed_since().is_empty());
+
+        // Changes made since are kept, unless forced.
+        let later = "[package]\nname = \"later\"\nversion = \"0.1.0\"\n";
+        fs::write(&manifest, later).unwrap();
+        assert!(undo(&manifest, false).is_err());
+        assert_eq!(fs::read_to_string(&manifest).unwrap(), later);
+
+        assert!(undo(&manifest, true).unwrap().is_some());
+        assert_eq!(fs::read_to_string(&manifest).unwrap(), before);
+        assert!(undo(&manifest, false).unwrap().is_none());
+    }
+}
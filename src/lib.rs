@@ -1,5 +1,14 @@
 //! Show and Edit Cargo's Manifest Files
-#![recursion_limit = "128"]
+//!
+//! The manifest-editing core (`Manifest`, `LocalManifest`, `Dependency`, `upgrade_requirement`,
+//! `lint`, ...) is always available, and doesn't access the network, git or cargo.
+//!
+//! Everything that does, and what is built on it, needs the `registry` feature: the registry
+//! lookups (`get_latest_dependency`, `update_registry_index`, ...), `RegistrySource`, `CrateName`,
+//! `NetConfig`, `Edits`, `Hooks`, the undo journal, `Lockfile` and the workspace lookups. It is
+//! enabled by the default features; with `default-features = false`, enable it explicitly. The
+//! `async` feature adds the `nonblocking` module on top of it.
+#![recursion_limit = "256"]
 #![cfg_attr(test, allow(dead_code))]
 #![warn(
     missing_docs,
@@ -18,21 +27,94 @@ extern crate error_chain;
 #[macro_use]
 extern crate serde_derive;
 
+#[cfg(feature = "registry")]
+mod advisory;
+#[cfg(feature = "cli")]
+pub mod commands;
+#[cfg(feature = "registry")]
 mod crate_name;
 mod dependency;
+mod diff;
+#[cfg(feature = "registry")]
+mod edits;
 mod errors;
+#[cfg(feature = "registry")]
 mod fetch;
+#[cfg(feature = "registry")]
+mod hooks;
+#[cfg(feature = "registry")]
+mod http_cache;
+#[cfg(feature = "registry")]
+mod journal;
+mod license;
+mod lint;
+#[cfg(feature = "registry")]
+mod lockfile;
 mod manifest;
+#[cfg(feature = "registry")]
 mod metadata;
+#[cfg(feature = "registry")]
+mod net;
+#[cfg(feature = "async")]
+pub mod nonblocking;
+#[cfg(feature = "registry")]
+mod pin;
+#[cfg(feature = "registry")]
+mod policy;
+#[cfg(feature = "registry")]
 mod registry;
+mod requirement;
+#[cfg(feature = "registry")]
+mod self_update;
+#[cfg(all(feature = "registry", any(test, feature = "testing")))]
+pub mod testing;
+#[cfg(feature = "registry")]
+mod tls;
 
+#[cfg(feature = "registry")]
+pub use crate::advisory::{update_advisory_db, Advisory, AdvisoryDb};
+#[cfg(feature = "registry")]
 pub use crate::crate_name::CrateName;
 pub use crate::dependency::Dependency;
+pub use crate::diff::unified_diff;
+#[cfg(feature = "registry")]
+pub use crate::edits::Edits;
 pub use crate::errors::*;
+#[cfg(feature = "registry")]
 pub use crate::fetch::{
     get_crate_name_from_github, get_crate_name_from_gitlab, get_crate_name_from_path,
-    get_latest_dependency, update_registry_index,
+    get_crate_stats, get_crate_versions, get_description, get_features, get_git_packages,
+    get_latest_dependency, get_latest_dependency_published_before, get_latest_matching,
+    get_popular_crates, get_published_versions, get_releases, is_fully_yanked, search_crates,
+    update_registry_index, CrateStats, CrateSummary, CrateVersions, PublishedVersion, Release,
+    Releases, VersionInfo,
 };
-pub use crate::manifest::{find, LocalManifest, Manifest};
-pub use crate::metadata::manifest_from_pkgid;
-pub use crate::registry::registry_url;
+#[cfg(feature = "registry")]
+pub use crate::hooks::{Hooks, NO_HOOKS_ENV};
+#[cfg(feature = "registry")]
+pub use crate::journal::{last_operation, record_for_undo, undo, JournaledFile, Operation};
+pub use crate::license::license_allowed;
+pub use crate::lint::{fix_lints, lint, Lint, LintKind};
+#[cfg(feature = "registry")]
+pub use crate::lockfile::{LockedPackage, Lockfile};
+pub use crate::manifest::{
+    find, requirement_floor, workspace_root, LocalManifest, Manifest, Span, StaleFeature,
+    VersionChange,
+};
+#[cfg(feature = "registry")]
+pub use crate::metadata::{manifest_from_pkgid, workspace_members};
+#[cfg(feature = "registry")]
+pub use crate::net::NetConfig;
+#[cfg(feature = "registry")]
+pub use crate::pin::{pin_dependencies, PinDirection, PinOptions};
+#[cfg(feature = "registry")]
+pub use crate::policy::{CratePolicy, Policy, POLICY_ENV};
+#[cfg(feature = "registry")]
+pub use crate::registry::{
+    add_defaults, describe_dependencies, registry_source, registry_url, AddDefaults, RegistrySource,
+};
+pub use crate::requirement::upgrade_requirement;
+#[cfg(feature = "registry")]
+pub use crate::self_update::{newer_release, notify_of_newer_release, NO_UPDATE_CHECK_ENV};
+#[cfg(feature = "registry")]
+pub use crate::tls::{TlsConfig, CLIENT_CERT_ENV, CLIENT_KEY_ENV};
@@ -0,0 +1,125 @@
+//! SPDX license expressions, as crates give them in the `license` field of their manifest.
+
+/// Split an expression into identifiers, operators and parentheses, reading the old `/`
+/// separator as `OR`.
+fn tokens(expression: &str) -> Vec<String> {
+    expression
+        .replace('(', " ( ")
+        .replace(')', " ) ")
+        .replace('/', " OR ")
+        .split_whitespace()
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+/// Whether two license expressions are written the same, ignoring case, spacing and the old `/`
+/// separator.
+#[cfg_attr(not(feature = "registry"), allow(dead_code))]
+pub(crate) fn same_license(a: &str, b: &str) -> bool {
+    let (a, b) = (tokens(a), tokens(b));
+    a.len() == b.len() && a.iter().zip(&b).all(|(a, b)| a.eq_ignore_ascii_case(b))
+}
+
+/// Whether a crate under a license expression can be used without accepting any of the `denied`
+/// licenses.
+///
+/// Licenses joined by `OR` are a choice, so only one of them has to be allowed, while every
+/// license joined by `AND` applies. Identifiers are compared ignoring case, and exceptions after
+/// `WITH` are ignored.
+pub fn license_allowed(expression: &str, denied: &[String]) -> bool {
+    let mut parser = Parser {
+        tokens: tokens(expression),
+        pos: 0,
+        denied,
+    };
+    parser.any_of()
+}
+
+struct Parser<'a> {
+    tokens: Vec<String>,
+    pos: usize,
+    denied: &'a [String],
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Option<&str> {
+        let token = self.tokens.get(self.pos)?;
+        self.pos += 1;
+        Some(token)
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        let found = self
+            .tokens
+            .get(self.pos)
+            .is_some_and(|next| next.eq_ignore_ascii_case(token));
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn any_of(&mut self) -> bool {
+        let mut allowed = self.all_of();
+        while self.eat("OR") {
+            let next = self.all_of();
+            allowed = allowed || next;
+        }
+        allowed
+    }
+
+    fn all_of(&mut self) -> bool {
+        let mut allowed = self.license();
+        while self.eat("AND") {
+            let next = self.license();
+            allowed = allowed && next;
+        }
+        allowed
+    }
+
+    fn license(&mut self) -> bool {
+        let denied = self.denied;
+        let allowed = match self.next() {
+            Some("(") => {
+                let allowed = self.any_of();
+                self.eat(")");
+                allowed
+            }
+            Some(id) => !denied.iter().any(|denied| denied.eq_ignore_ascii_case(id)),
+            None => true,
+        };
+        if self.eat("WITH") {
+            self.next();
+        }
+        allowed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_expressions() {
+        assert!(same_license("MIT/Apache-2.0", "MIT OR Apache-2.0"));
+        assert!(same_license("(mit)", "( MIT )"));
+        assert!(!same_license("MIT", "BUSL-1.1"));
+        assert!(!same_license("MIT OR Apache-2.0", "MIT"));
+    }
+
+    #[test]
+    fn denies_licenses_without_an_alternative() {
+        let denied = vec!["GPL-3.0".to_owned(), "busl-1.1".to_owned()];
+        assert!(license_allowed("MIT", &denied));
+        assert!(!license_allowed("BUSL-1.1", &denied));
+        assert!(license_allowed("MIT OR GPL-3.0", &denied));
+        assert!(license_allowed("MIT/GPL-3.0", &denied));
+        assert!(!license_allowed("MIT AND GPL-3.0", &denied));
+        assert!(!license_allowed("(MIT OR Apache-2.0) AND GPL-3.0", &denied));
+        assert!(license_allowed("(MIT AND GPL-3.0) OR Apache-2.0", &denied));
+        assert!(!license_allowed(
+            "GPL-3.0 WITH Classpath-exception-2.0",
+            &denied
+        ));
+    }
+}
@@ -0,0 +1,388 @@
+//! Checks for common mistakes in manifests, and fixes for the mechanical ones.
+
+use crate::errors::*;
+use crate::manifest::Manifest;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// A kind of problem in a manifest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LintKind {
+    /// A dev-dependency that is also a normal dependency, with the same entry or a different
+    /// version requirement
+    DuplicateDependency,
+    /// `default-features = true`, which is the default
+    RedundantDefaultFeatures,
+    /// A `*` version requirement, which crates.io rejects
+    WildcardRequirement,
+    /// A path or git dependency of a publishable package without a version requirement, which
+    /// crates.io rejects
+    MissingVersion,
+    /// A `[features]` entry referring to a dependency or feature that doesn't exist
+    UnknownFeatureReference,
+}
+
+impl LintKind {
+    /// The name the lint is reported with, e.g. `wildcard-requirement`
+    pub fn name(self) -> &'static str {
+        match self {
+            LintKind::DuplicateDependency => "duplicate-dependency",
+            LintKind::RedundantDefaultFeatures => "redundant-default-features",
+            LintKind::WildcardRequirement => "wildcard-requirement",
+            LintKind::MissingVersion => "missing-version",
+            LintKind::UnknownFeatureReference => "unknown-feature-reference",
+        }
+    }
+}
+
+/// A problem found in a manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    /// What kind of problem it is
+    pub kind: LintKind,
+    /// The table the problem is in, e.g. `["dev-dependencies"]` or `["features"]`
+    pub table_path: Vec<String>,
+    /// The name of the entry in the table
+    pub name: String,
+    /// What is wrong
+    pub message: String,
+    /// Whether `fix_lints` can fix it
+    pub fixable: bool,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: `{}` in `[{}]` {}",
+            self.kind.name(),
+            self.name,
+            table_name(&self.table_path),
+            self.message
+        )
+    }
+}
+
+/// The dotted name of a table, e.g. `target.'cfg(unix)'.dependencies`
+fn table_name(table_path: &[String]) -> String {
+    let is_bare = |key: &str| {
+        !key.is_empty()
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    };
+    table_path
+        .iter()
+        .map(|key| {
+            if is_bare(key) {
+                key.clone()
+            } else {
+                format!("'{}'", key)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// The version requirement of a dependency entry
+fn requirement(entry: &toml_edit::Item) -> Option<&str> {
+    entry.as_str().or_else(|| entry["version"].as_str())
+}
+
+/// A dependency entry as plain TOML, to compare entries regardless of their formatting
+fn plain(entry: &toml_edit::Item) -> Option<toml::Value> {
+    let document = match entry {
+        toml_edit::Item::Value(value) => format!("entry = {}", value),
+        toml_edit::Item::Table(table) => format!("[entry]\n{}", table),
+        _ => return None,
+    };
+    let mut document: toml::value::Table = toml::from_str(&document).ok()?;
+    document.remove("entry")
+}
+
+/// Whether the package can be published, i.e. `publish` isn't `false` or an empty list
+fn is_publishable(manifest: &Manifest) -> bool {
+    let package = &manifest.data["package"];
+    if !package.is_table_like() {
+        return false;
+    }
+    let publish = &package["publish"];
+    publish.as_bool() != Some(false) && !publish.as_array().is_some_and(|a| a.is_empty())
+}
+
+fn is_dev(table_path: &[String]) -> bool {
+    table_path.last().map(String::as_str) == Some("dev-dependencies")
+}
+
+/// Check a manifest for common mistakes
+pub fn lint(manifest: &Manifest) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    let sections = manifest.get_sections();
+    let publishable = is_publishable(manifest);
+
+    for (table_path, table) in &sections {
+        let table = table.as_table_like().expect("Unexpected non-table");
+        for (name, entry) in table.iter() {
+            let lint = |kind, message: String, fixable| Lint {
+                kind,
+                table_path: table_path.clone(),
+                name: name.to_owned(),
+                message,
+                fixable,
+            };
+
+            for key in &["default-features", "default_features"] {
+                if entry[key].as_bool() == Some(true) {
+                    let message = format!("sets `{} = true`, which is the default", key);
+                    lints.push(lint(LintKind::RedundantDefaultFeatures, message, true));
+                }
+            }
+
+            let requirement = requirement(entry);
+            if requirement.map(str::trim) == Some("*") {
+                let message = "allows any version with `*`, which crates.io rejects".to_owned();
+                lints.push(lint(LintKind::WildcardRequirement, message, false));
+            }
+
+            if publishable && !is_dev(table_path) && requirement.is_none() {
+                for key in &["path", "git"] {
+                    if !entry[key].is_none() {
+                        let message = format!(
+                            "has a `{}` but no `version`, so the package can't be published",
+                            key
+                        );
+                        lints.push(lint(LintKind::MissingVersion, message, false));
+                    }
+                }
+            }
+
+            if is_dev(table_path) {
+                let mut normal_path = table_path.clone();
+                *normal_path.last_mut().unwrap() = "dependencies".to_owned();
+                let normal = sections
+                    .iter()
+                    .find(|(path, _)| *path == normal_path)
+                    .map(|(_, table)| &table[name])
+                    .filter(|normal| !normal.is_none());
+                if let Some(normal) = normal {
+                    let normal_requirement = self::requirement(normal);
+                    if plain(normal).is_some() && plain(normal) == plain(entry) {
+                        let message = format!(
+                            "is the same as in `[{}]`, which tests and examples can already use",
+                            table_name(&normal_path)
+                        );
+                        lints.push(lint(LintKind::DuplicateDependency, message, true));
+                    } else if normal_requirement != requirement {
+                        let message = format!(
+                            "requires `{}`, but `[{}]` requires `{}`",
+                            requirement.unwrap_or("*"),
+                            table_name(&normal_path),
+                            normal_requirement.unwrap_or("*")
+                        );
+                        lints.push(lint(LintKind::DuplicateDependency, message, false));
+                    }
+                }
+            }
+        }
+    }
+
+    lints.extend(lint_features(manifest, &sections));
+    lints
+}
+
+/// Check that `[features]` only refers to dependencies and features that exist
+fn lint_features(manifest: &Manifest, sections: &[(Vec<String>, toml_edit::Item)]) -> Vec<Lint> {
+    let features = match manifest.data["features"].as_table_like() {
+        Some(features) => features,
+        None => return Vec::new(),
+    };
+    // Features can't refer to dev-dependencies.
+    let mut dependencies = BTreeSet::new();
+    let mut optional = BTreeSet::new();
+    for (_, table) in sections.iter().filter(|(path, _)| !is_dev(path)) {
+        for (name, entry) in table.as_table_like().expect("Unexpected non-table").iter() {
+            dependencies.insert(name.to_owned());
+            if entry["optional"].as_bool() == Some(true) {
+                optional.insert(name.to_owned());
+            }
+        }
+    }
+
+    let mut lints = Vec::new();
+    for (feature, entries) in features.iter() {
+        let entries = entries
+            .as_array()
+            .into_iter()
+            .flat_map(toml_edit::Array::iter)
+            .filter_map(toml_edit::Value::as_str);
+        for entry in entries {
+            let problem = if let Some(dependency) = entry.strip_prefix("dep:") {
+                if optional.contains(dependency) {
+                    continue;
+                }
+                format!("`{}` isn't an optional dependency", dependency)
+            } else if let Some((dependency, _)) = entry.split_once('/') {
+                let dependency = dependency.trim_end_matches('?');
+                if dependencies.contains(dependency) {
+                    continue;
+                }
+                format!("`{}` isn't a dependency", dependency)
+            } else {
+                if features.get(entry).is_some() || optional.contains(entry) {
+                    continue;
+                }
+                format!(
+                    "`{}` is neither a feature nor an optional dependency",
+                    entry
+                )
+            };
+            lints.push(Lint {
+                kind: LintKind::UnknownFeatureReference,
+                table_path: vec!["features".to_owned()],
+                name: feature.to_owned(),
+                message: format!("refers to `{}`, but {}", entry, problem),
+                fixable: false,
+            });
+        }
+    }
+    lints
+}
+
+/// Fix the fixable problems among `lints`, which were found in the manifest by `lint`
+///
+/// Returns how many were fixed.
+pub fn fix_lints(manifest: &mut Manifest, lints: &[Lint]) -> Result<usize> {
+    let mut fixed = 0;
+    for lint in lints.iter().filter(|lint| lint.fixable) {
+        let table = manifest.get_table(&lint.table_path)?;
+        match lint.kind {
+            LintKind::RedundantDefaultFeatures => {
+                let entry = &mut table[&lint.name];
+                for key in &["default-features", "default_features"] {
+                    if entry[key].as_bool() == Some(true) {
+                        entry[key] = toml_edit::Item::None;
+                    }
+                }
+                if let Some(entry) = entry.as_inline_table_mut() {
+                    entry.fmt();
+                }
+            }
+            LintKind::DuplicateDependency => {
+                table[&lint.name] = toml_edit::Item::None;
+                if table.as_table_like().is_some_and(|table| table.is_empty()) {
+                    *table = toml_edit::Item::None;
+                }
+            }
+            _ => continue,
+        }
+        fixed += 1;
+    }
+    Ok(fixed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(lints: &[Lint]) -> Vec<(LintKind, &str, bool)> {
+        lints
+            .iter()
+            .map(|lint| (lint.kind, lint.name.as_str(), lint.fixable))
+            .collect()
+    }
+
+    #[test]
+    fn finds_problems() {
+        let manifest: Manifest = r#"
+[package]
+name = "demo"
+version = "0.1.0"
+
+[dependencies]
+serde = { version = "1.0", default-features = true }
+rand = "*"
+local = { path = "../local" }
+image = { version = "0.23", optional = true }
+regex = "1.3"
+
+[dev-dependencies]
+regex = "1.3"
+serde = "1.1"
+helper = { path = "../helper" }
+
+[features]
+graphics = ["dep:image", "dep:regex", "missing/std", "image/png", "extra"]
+"#
+        .parse()
+        .unwrap();
+
+        let lints = lint(&manifest);
+        assert_eq!(
+            kinds(&lints),
+            vec![
+                (LintKind::DuplicateDependency, "regex", true),
+                (LintKind::DuplicateDependency, "serde", false),
+                (LintKind::RedundantDefaultFeatures, "serde", true),
+                (LintKind::WildcardRequirement, "rand", false),
+                (LintKind::MissingVersion, "local", false),
+                (LintKind::UnknownFeatureReference, "graphics", false),
+                (LintKind::UnknownFeatureReference, "graphics", false),
+                (LintKind::UnknownFeatureReference, "graphics", false),
+            ]
+        );
+        assert_eq!(
+            lints[1].to_string(),
+            "duplicate-dependency: `serde` in `[dev-dependencies]` requires `1.1`, but \
+             `[dependencies]` requires `1.0`"
+        );
+        assert_eq!(
+            lints[7].to_string(),
+            "unknown-feature-reference: `graphics` in `[features]` refers to `extra`, but `extra` \
+             is neither a feature nor an optional dependency"
+        );
+    }
+
+    #[test]
+    fn skips_missing_versions_of_unpublished_packages() {
+        let manifest: Manifest = r#"
+[package]
+name = "demo"
+publish = false
+
+[target.'cfg(unix)'.dependencies]
+local = { path = "../local" }
+"#
+        .parse()
+        .unwrap();
+        assert!(lint(&manifest).is_empty());
+    }
+
+    #[test]
+    fn fixes_mechanical_problems() {
+        let mut manifest: Manifest = r#"
+[dependencies]
+serde = { version = "1.0", default-features = true, features = ["derive"] }
+rand = "*"
+
+[dev-dependencies]
+rand = "*"
+"#
+        .parse()
+        .unwrap();
+
+        let lints = lint(&manifest);
+        assert_eq!(fix_lints(&mut manifest, &lints).unwrap(), 2);
+        assert_eq!(
+            manifest.data.to_string(),
+            r#"
+[dependencies]
+serde = { version = "1.0", features = ["derive"] }
+rand = "*"
+"#
+        );
+        assert_eq!(
+            kinds(&lint(&manifest)),
+            vec![(LintKind::WildcardRequirement, "rand", false)]
+        );
+    }
+}
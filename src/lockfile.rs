@@ -0,0 +1,121 @@
+//! Reading what a workspace's `Cargo.lock` holds.
+
+use crate::errors::*;
+use semver::Version;
+use std::fs;
+use std::path::Path;
+
+/// The sources crates.io is recorded as in lock files, through its git and its sparse index.
+const CRATES_IO_SOURCES: &[&str] = &[
+    "registry+https://github.com/rust-lang/crates.io-index",
+    "sparse+https://index.crates.io/",
+];
+
+/// The packages a lock file holds
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Lockfile {
+    /// The packages, in the order of the lock file
+    #[serde(default)]
+    pub package: Vec<LockedPackage>,
+}
+
+/// A package in a lock file
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct LockedPackage {
+    /// Its name
+    pub name: String,
+    /// The version it is locked at
+    pub version: Version,
+    /// Where it comes from, e.g. `registry+https://github.com/rust-lang/crates.io-index`, or
+    /// `None` for a path dependency or workspace member
+    pub source: Option<String>,
+    /// Its dependencies, as `name`, or `name version` (followed by the source) if the name is
+    /// ambiguous
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+}
+
+impl LockedPackage {
+    /// Whether it comes from crates.io
+    pub fn is_from_crates_io(&self) -> bool {
+        self.source
+            .as_deref()
+            .is_some_and(|source| CRATES_IO_SOURCES.contains(&source))
+    }
+}
+
+impl Lockfile {
+    /// Read the lock file at `path`.
+    pub fn read(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .chain_err(|| format!("Failed to read `{}`", path.display()))?;
+        toml::from_str(&contents).chain_err(|| format!("Failed to parse `{}`", path.display()))
+    }
+
+    /// Read the lock file at `path`, or have no packages if there isn't one.
+    pub fn read_or_default(path: &Path) -> Result<Self> {
+        if path.exists() {
+            Self::read(path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+impl std::str::FromStr for Lockfile {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        toml::from_str(input).chain_err(|| "Failed to parse `Cargo.lock`")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_packages() {
+        let lockfile: Lockfile = r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["serde", "rand 0.7.3"]
+
+[[package]]
+name = "serde"
+version = "1.0.130"
+source = "sparse+https://index.crates.io/"
+
+[[package]]
+name = "rand"
+version = "0.7.3"
+source = "git+https://github.com/rust-random/rand#0123456789abcdef"
+"#
+        .parse()
+        .unwrap();
+
+        assert_eq!(lockfile.package.len(), 3);
+        assert_eq!(lockfile.package[0].dependencies, ["serde", "rand 0.7.3"]);
+        let from_crates_io: Vec<_> = lockfile
+            .package
+            .iter()
+            .map(LockedPackage::is_from_crates_io)
+            .collect();
+        assert_eq!(from_crates_io, [false, true, false]);
+        assert_eq!(lockfile.package[1].version, Version::new(1, 0, 130));
+    }
+
+    #[test]
+    fn missing_lockfile_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Cargo.lock");
+        assert!(Lockfile::read(&path).is_err());
+        assert_eq!(
+            Lockfile::read_or_default(&path).unwrap(),
+            Lockfile::default()
+        );
+    }
+}
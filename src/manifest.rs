@@ -1,3 +1,4 @@
+use std::collections::BTreeSet;
 use std::fs::{self, File, OpenOptions};
 use std::io::{Read, Write};
 use std::ops::Deref;
@@ -9,6 +10,7 @@ use termcolor::{BufferWriter, Color, ColorChoice, ColorSpec, WriteColor};
 
 use crate::dependency::Dependency;
 use crate::errors::*;
+use crate::requirement::upgrade_requirement;
 
 const MANIFEST_FILENAME: &str = "Cargo.toml";
 
@@ -27,7 +29,7 @@ pub struct Manifest {
 pub fn find(specified: &Option<PathBuf>) -> Result<PathBuf> {
     match *specified {
         Some(ref path)
-            if fs::metadata(&path)
+            if fs::metadata(path)
                 .chain_err(|| "Failed to get cargo file metadata")?
                 .is_file() =>
         {
@@ -38,6 +40,36 @@ pub fn find(specified: &Option<PathBuf>) -> Result<PathBuf> {
     }
 }
 
+/// The root of the workspace a manifest is in: the outermost directory above the manifest with a
+/// `[workspace]` manifest, or else the manifest's own.
+pub fn workspace_root(manifest_path: &Path) -> Result<PathBuf> {
+    let manifest_path = manifest_path
+        .canonicalize()
+        .chain_err(|| format!("Failed to find `{}`", manifest_path.display()))?;
+    let dir = manifest_path
+        .parent()
+        .chain_err(|| "Manifest has no parent directory")?;
+    let root = dir
+        .ancestors()
+        .filter(|ancestor| {
+            fs::read_to_string(ancestor.join("Cargo.toml"))
+                .ok()
+                .and_then(|contents| contents.parse::<toml::Value>().ok())
+                .is_some_and(|manifest| manifest.get("workspace").is_some())
+        })
+        .last()
+        .unwrap_or(dir);
+    Ok(root.to_owned())
+}
+
+/// The package name in a package id spec, as used for the keys of `[replace]` and
+/// `[profile.<profile>.package]`, e.g. `foo` for `foo:1.0.0` or
+/// `https://example.com/index#foo:1.0.0`.
+fn spec_name(spec: &str) -> &str {
+    let name = spec.rsplit('#').next().unwrap_or(spec);
+    name.split(&[':', '@'][..]).next().unwrap_or(name)
+}
+
 /// Search for Cargo.toml in this directory and recursively up the tree until one is found.
 fn search(dir: &Path) -> Result<PathBuf> {
     let manifest = dir.join(MANIFEST_FILENAME);
@@ -47,7 +79,7 @@ fn search(dir: &Path) -> Result<PathBuf> {
     } else {
         dir.parent()
             .ok_or_else(|| ErrorKind::MissingManifest.into())
-            .and_then(|dir| search(dir))
+            .and_then(search)
     }
 }
 
@@ -61,6 +93,106 @@ fn merge_inline_table(old_dep: &mut toml_edit::Item, new: &toml_edit::Item) {
     }
 }
 
+/// The string entries of a feature in `[features]`.
+fn feature_entries(entries: &toml_edit::Item) -> impl Iterator<Item = &str> {
+    entries
+        .as_array()
+        .into_iter()
+        .flat_map(toml_edit::Array::iter)
+        .filter_map(toml_edit::Value::as_str)
+}
+
+/// How a `[features]` entry changes when the dependency `old` is renamed to `new`, or removed if
+/// `new` is `None`: `Some(Some(entry))` to replace it, `Some(None)` to drop it, and `None` if it
+/// doesn't refer to `old`.
+///
+/// Entries refer to a dependency as `old`, `dep:old`, `old/feature` or `old?/feature`.
+fn rename_feature_entry(entry: &str, old: &str, new: Option<&str>) -> Option<Option<String>> {
+    let (prefix, suffix) = if entry == old {
+        ("", "")
+    } else if entry.strip_prefix("dep:") == Some(old) {
+        ("dep:", "")
+    } else {
+        let rest = entry.strip_prefix(old)?;
+        if !rest.starts_with('/') && !rest.starts_with("?/") {
+            return None;
+        }
+        ("", rest)
+    };
+    Some(new.map(|new| format!("{}{}{}", prefix, new, suffix)))
+}
+
+/// Remove an entry of an array, keeping the formatting of the first entry (e.g. no space after the
+/// `[`) if it is the one removed.
+fn remove_array_entry(entries: &mut toml_edit::Array, i: usize) {
+    let removed = entries.remove(i);
+    if i == 0 && !entries.is_empty() {
+        let first = entries.get(0).unwrap().clone();
+        let first = toml_edit::decorated(first, removed.decor().prefix(), removed.decor().suffix());
+        let _ = entries.replace_formatted(0, first);
+    }
+}
+
+/// The feature among `available` that a missing one was probably renamed to: the only one whose
+/// name differs just in case, or in using `-` rather than `_`.
+fn renamed_feature(feature: &str, available: &BTreeSet<String>) -> Option<String> {
+    let normalize = |name: &str| name.to_lowercase().replace('_', "-");
+    let mut similar = available
+        .iter()
+        .filter(|name| normalize(name) == normalize(feature));
+    match (similar.next(), similar.next()) {
+        (Some(name), None) => Some(name.clone()),
+        _ => None,
+    }
+}
+
+/// Replace the entries of an array that are `old` with `new`, returning whether there were any.
+fn rename_array_entry(entries: &mut toml_edit::Array, old: &str, new: &str) -> bool {
+    let mut renamed = false;
+    for i in 0..entries.len() {
+        if entries.get(i).and_then(toml_edit::Value::as_str) == Some(old) {
+            let _ = entries.replace(i, new);
+            renamed = true;
+        }
+    }
+    renamed
+}
+
+/// The path of the table a `[header]` line starts, or `None` for any other line.
+///
+/// Array of tables headers (`[[bin]]`) start no table that holds dependencies, so they give an
+/// empty path.
+fn table_header_path(line: &str) -> Option<Vec<String>> {
+    let line = line.trim();
+    if line.starts_with("[[") {
+        return Some(Vec::new());
+    }
+    if !line.starts_with('[') {
+        return None;
+    }
+    let header: toml_edit::Document = line.parse().ok()?;
+    let mut path = Vec::new();
+    let mut table = header.as_table();
+    while let Some((key, item)) = table.iter().next() {
+        path.push(key.to_owned());
+        table = item.as_table()?;
+    }
+    Some(path)
+}
+
+/// Whether a line sets the key `key` (e.g. `key = "1.0"` or `"key".version = "1.0"`).
+fn sets_key(line: &str, key: &str) -> bool {
+    let line = line.trim_start();
+    let quoted = format!("\"{}\"", key);
+    let rest = line
+        .strip_prefix(quoted.as_str())
+        .or_else(|| line.strip_prefix(key));
+    rest.is_some_and(|rest| {
+        let rest = rest.trim_start();
+        rest.starts_with('=') || rest.starts_with('.')
+    })
+}
+
 fn str_or_1_len_table(item: &toml_edit::Item) -> bool {
     item.is_str() || item.as_table_like().map(|t| t.len() == 1).unwrap_or(false)
 }
@@ -118,46 +250,221 @@ fn old_version_compatible(dependency: &Dependency, old_version: &str) -> Result<
         None => return Ok(false),
     };
 
-    let current_version = Version::parse(&current_version).chain_err(|| {
+    let current_version = Version::parse(current_version).chain_err(|| {
         ErrorKind::ParseVersion(dependency.name.to_string(), current_version.into())
     })?;
 
     Ok(old_version.matches(&current_version))
 }
 
-/// Print a message if the new dependency version is different from the old one.
-fn print_upgrade_if_necessary(
-    crate_name: &str,
-    old_dep: &toml_edit::Item,
-    new_dep: &toml_edit::Item,
-) -> Result<()> {
-    let old_version = get_version(old_dep)?;
-    let new_version = get_version(new_dep)?;
+/// The dependency with an exact requirement (`=x.y.z`) on the version it is set to, which must be a
+/// single version.
+fn exact_requirement(dependency: &Dependency) -> Result<Dependency> {
+    let version = dependency.version().unwrap_or_default();
+    let exact = Version::parse(version.trim_start_matches('='))
+        .chain_err(|| ErrorKind::ParseVersion(version.to_owned(), dependency.name.to_string()))?;
+    Ok(dependency.clone().set_version(&format!("={}", exact)))
+}
 
-    if let (Some(old_version), Some(new_version)) = (old_version.as_str(), new_version.as_str()) {
-        if old_version == new_version {
-            return Ok(());
+/// The old entry's requirement rewritten for the version `dep` is set to, keeping its operators
+/// and precision (see `upgrade_requirement`), e.g. `1.2` rather than `1.2.3` over `1.0`.
+///
+/// `None` if `dep` is set to a requirement rather than a version (e.g. an exact `=1.2.3`), or the
+/// old requirement is a bare `*` or can't be rewritten, so that `dep`'s version is written as it is.
+fn rewritten_requirement(old_dep: &toml_edit::Item, dep: &Dependency) -> Option<String> {
+    if old_dep.is_none() {
+        return None;
+    }
+    let version = Version::parse(dep.version()?).ok()?;
+    let old = version_str(old_dep)?;
+    if old.trim() == "*" {
+        return None;
+    }
+    upgrade_requirement(&old, &version).ok()
+}
+
+/// Whether merging the new dependency would change the version of the old one.
+fn version_changed(old_dep: &toml_edit::Item, new_dep: &toml_edit::Item) -> bool {
+    match (get_version(old_dep), get_version(new_dep)) {
+        (Ok(old_version), Ok(new_version)) => old_version.as_str() != new_version.as_str(),
+        _ => true,
+    }
+}
+
+/// The first Rust release whose cargo understands `dep:` and `?/` entries in `[features]`.
+const NAMESPACED_FEATURES_RUST_VERSION: &str = "1.60";
+
+/// The lowest version matched by a requirement such as `0.9`, `^1.2.3` or `>=0.8, <2`, if it has
+/// one.
+pub fn requirement_floor(req: &str) -> Option<Version> {
+    let first = req.split(',').next()?.trim();
+    if first.starts_with('<') {
+        return None;
+    }
+    let mut version = first
+        .trim_start_matches(['^', '~', '=', '>'])
+        .trim()
+        .to_owned();
+    for _ in version.split('.').count()..3 {
+        version.push_str(".0");
+    }
+    Version::parse(&version).ok()
+}
+
+/// How the lines of a manifest file end, so that it is written back the same way instead of every
+/// line changing in diffs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LineEndings {
+    /// Whether lines end with `\r\n` rather than `\n`.
+    crlf: bool,
+    /// Whether the last line ends with a line ending too.
+    trailing_newline: bool,
+}
+
+impl LineEndings {
+    /// The line endings most lines of a file have. An empty file gets `\n`, with a trailing one.
+    fn detect(contents: &str) -> Self {
+        let newlines = contents.matches('\n').count();
+        let crlfs = contents.matches("\r\n").count();
+        LineEndings {
+            crlf: crlfs > newlines - crlfs,
+            trailing_newline: contents.is_empty() || contents.ends_with('\n'),
+        }
+    }
+
+    /// Contents with these line endings. Lines ending with `\r\n` are left alone in a file of
+    /// `\n`s, in case a string needs them.
+    fn apply(self, contents: &str) -> String {
+        let mut contents = contents.to_owned();
+        if self.trailing_newline {
+            if !contents.is_empty() && !contents.ends_with('\n') {
+                contents.push('\n');
+            }
+        } else {
+            let len = contents.trim_end_matches(['\r', '\n']).len();
+            contents.truncate(len);
+        }
+        if self.crlf {
+            contents = contents.replace("\r\n", "\n").replace('\n', "\r\n");
+        }
+        contents
+    }
+}
+
+/// The version requirement of a dependency entry, if it is a plain string.
+fn version_str(dep: &toml_edit::Item) -> Option<String> {
+    get_version(dep)
+        .ok()
+        .and_then(|version| version.as_str().map(String::from))
+}
+
+/// A change to the version requirement of a dependency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionChange {
+    /// Name of the dependency.
+    pub name: String,
+    /// The requirement before the change, if there was one.
+    pub old: Option<String>,
+    /// The requirement after the change.
+    pub new: Option<String>,
+}
+
+/// A feature that the entry for a dependency enables, but which the version it is upgraded to
+/// doesn't have.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleFeature {
+    /// Name of the dependency.
+    pub dependency: String,
+    /// The missing feature.
+    pub feature: String,
+    /// The feature of the new version it was probably renamed to, if there is one.
+    pub renamed_to: Option<String>,
+}
+
+/// Where an entry is in the text of a manifest, e.g. `serde = "1.0"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// Offset of its first byte.
+    pub offset: usize,
+    /// Its length in bytes.
+    pub len: usize,
+    /// The line it starts on, counting from 1.
+    pub start_line: usize,
+    /// The column it starts at, in characters counting from 1.
+    pub start_column: usize,
+    /// The line it ends on.
+    pub end_line: usize,
+    /// The column just after its last character.
+    pub end_column: usize,
+}
+
+impl Span {
+    /// The span of `text[start..end]`.
+    fn new(text: &str, start: usize, end: usize) -> Self {
+        let position = |offset: usize| {
+            let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+            (
+                text[..offset].matches('\n').count() + 1,
+                text[line_start..offset].chars().count() + 1,
+            )
+        };
+        let (start_line, start_column) = position(start);
+        let (end_line, end_column) = position(end);
+        Span {
+            offset: start,
+            len: end - start,
+            start_line,
+            start_column,
+            end_line,
+            end_column,
+        }
+    }
+}
+
+impl VersionChange {
+    /// Whether the new requirement only allows older versions than the old one did.
+    pub fn is_downgrade(&self) -> bool {
+        match (
+            self.old.as_deref().and_then(requirement_floor),
+            self.new.as_deref().and_then(requirement_floor),
+        ) {
+            (Some(old), Some(new)) => new < old,
+            _ => false,
         }
+    }
+
+    /// Print the change, e.g. `Upgrading serde v1.0 -> v1.0.117`.
+    pub fn print(&self) -> Result<()> {
+        let (old_version, new_version) = match (&self.old, &self.new) {
+            (Some(old), Some(new)) => (old, new),
+            _ => return Ok(()),
+        };
+        // Moving to an older version is allowed (e.g. to roll back a bad upgrade), but it is
+        // called out so it doesn't go unnoticed.
+        let (colour, verb) = if self.is_downgrade() {
+            (Color::Yellow, "  Downgrading ")
+        } else {
+            (Color::Green, "    Upgrading ")
+        };
         let bufwtr = BufferWriter::stdout(ColorChoice::Always);
         let mut buffer = bufwtr.buffer();
         buffer
-            .set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))
+            .set_color(ColorSpec::new().set_fg(Some(colour)).set_bold(true))
             .chain_err(|| "Failed to set output colour")?;
-        write!(&mut buffer, "    Upgrading ").chain_err(|| "Failed to write upgrade message")?;
+        write!(&mut buffer, "{}", verb).chain_err(|| "Failed to write upgrade message")?;
         buffer
             .set_color(&ColorSpec::new())
             .chain_err(|| "Failed to clear output colour")?;
         writeln!(
             &mut buffer,
             "{} v{} -> v{}",
-            crate_name, old_version, new_version,
+            self.name, old_version, new_version,
         )
         .chain_err(|| "Failed to write upgrade versions")?;
         bufwtr
             .print(&buffer)
-            .chain_err(|| "Failed to print upgrade message")?;
+            .chain_err(|| "Failed to print upgrade message")
     }
-    Ok(())
 }
 
 impl Manifest {
@@ -192,7 +499,7 @@ impl Manifest {
             input: &'a mut toml_edit::Item,
             path: &[String],
         ) -> Result<&'a mut toml_edit::Item> {
-            if let Some(segment) = path.get(0) {
+            if let Some(segment) = path.first() {
                 let value = input[&segment].or_insert(toml_edit::table());
 
                 if value.is_table_like() {
@@ -208,6 +515,248 @@ impl Manifest {
         descend(&mut self.data.root, table_path)
     }
 
+    /// Whether this is a virtual manifest, i.e. a workspace root without a package of its own.
+    pub fn is_virtual(&self) -> bool {
+        self.data["package"].is_none()
+            && self.data["project"].is_none()
+            && !self.data["workspace"].is_none()
+    }
+
+    /// Get the item at a path of keys (e.g. `["package", "description"]`), if there is one.
+    pub fn get_item(&self, path: &[String]) -> Option<&toml_edit::Item> {
+        let item = path
+            .iter()
+            .fold(&self.data.root, |item, key| &item[key.as_str()]);
+        if item.is_none() {
+            None
+        } else {
+            Some(item)
+        }
+    }
+
+    /// Get the item at a path of keys like `get_item`, following fields that the package inherits
+    /// from its workspace (e.g. `version = { workspace = true }`) to their values in `workspace`,
+    /// the manifest at the workspace root.
+    ///
+    /// A dependency inheriting its entry from `[workspace.dependencies]` is given as that entry,
+    /// with the keys its own entry sets (e.g. `optional`) on top, and their `features` combined.
+    pub fn get_inherited_item(
+        &self,
+        path: &[String],
+        workspace: &Manifest,
+    ) -> Option<toml_edit::Item> {
+        let inherits = |i: usize| {
+            self.get_item(&path[..i])
+                .and_then(toml_edit::Item::as_table_like)
+                .is_some_and(|table| {
+                    table.get("workspace").and_then(toml_edit::Item::as_bool) == Some(true)
+                })
+        };
+        let i = match (1..=path.len()).find(|&i| inherits(i)) {
+            Some(i) => i,
+            None => return self.get_item(path).cloned(),
+        };
+        let (field, rest) = path.split_at(i);
+        let is_dependency = i >= 2 && field[i - 2].ends_with("dependencies");
+        if i == 2 && path[0] == "package" {
+            let mut inherited = vec!["workspace".to_owned(), "package".to_owned()];
+            inherited.extend_from_slice(&path[1..]);
+            return workspace.get_item(&inherited).cloned();
+        } else if !is_dependency {
+            return self.get_item(path).cloned();
+        }
+
+        // The entry in `[workspace.dependencies]`, as a table.
+        let mut entry = toml_edit::InlineTable::default();
+        match workspace.get_item(&[
+            "workspace".to_owned(),
+            "dependencies".to_owned(),
+            field[i - 1].clone(),
+        ]) {
+            Some(toml_edit::Item::Value(toml_edit::Value::String(version))) => {
+                entry.get_or_insert("version", version.value().as_str());
+            }
+            Some(item) => {
+                for (key, value) in item.as_table_like()?.iter() {
+                    entry.get_or_insert(key, value.as_value()?.clone());
+                }
+            }
+            None => return None,
+        }
+        let local = self.get_item(field)?.as_table_like()?;
+        for (key, value) in local.iter().filter(|(key, _)| *key != "workspace") {
+            let value = value.as_value()?.clone();
+            match (key, entry.get_mut(key)) {
+                ("features", Some(toml_edit::Value::Array(features))) => {
+                    for feature in value.as_array()?.iter().filter_map(|f| f.as_str()) {
+                        if !features.iter().any(|f| f.as_str() == Some(feature)) {
+                            let _ = features.push(feature);
+                        }
+                    }
+                }
+                (_, Some(existing)) => *existing = value,
+                (_, None) => {
+                    entry.get_or_insert(key, value);
+                }
+            }
+        }
+        entry.fmt();
+
+        let item = rest.iter().fold(toml_edit::value(entry), |item, key| {
+            item[key.as_str()].clone()
+        });
+        Some(item).filter(|item| !item.is_none())
+    }
+
+    /// Set the value at a path of keys (e.g. `["package", "description"]`), creating any missing
+    /// tables along the way.
+    pub fn set_value(&mut self, path: &[String], value: toml_edit::Value) -> Result<()> {
+        let (key, table_path) = path.split_last().chain_err(|| "Empty key")?;
+
+        // Tables created here only hold the new value, so their headers needn't be written out
+        // (e.g. `[profile]` when setting `profile.release.opt-level`).
+        let first_missing =
+            (1..=table_path.len()).find(|&i| self.get_item(&table_path[..i]).is_none());
+        if let Some(first_missing) = first_missing {
+            for i in first_missing..=table_path.len() {
+                if let Some(table) = self.get_table(&table_path[..i])?.as_table_mut() {
+                    table.set_implicit(true);
+                }
+            }
+        }
+
+        let table = self.get_table(table_path)?;
+        table[key.as_str()] = toml_edit::value(value);
+        if let Some(t) = table.as_inline_table_mut() {
+            t.fmt()
+        }
+        Ok(())
+    }
+
+    /// Dependencies overridden by the `[patch]` and `[replace]` sections, along with the section
+    /// overriding each of them (e.g. `patch.crates-io`).
+    pub fn get_overrides(&self) -> Vec<(String, String)> {
+        let mut overrides = Vec::new();
+
+        if let Some(patches) = self.data["patch"].as_table_like() {
+            for (source, patch) in patches.iter() {
+                let patch = match patch.as_table_like() {
+                    Some(patch) => patch,
+                    None => continue,
+                };
+                for (name, item) in patch.iter() {
+                    let name = item
+                        .as_table_like()
+                        .and_then(|t| t.get("package").and_then(|p| p.as_str()))
+                        .unwrap_or(name);
+                    overrides.push((name.to_owned(), format!("patch.{}", source)));
+                }
+            }
+        }
+
+        if let Some(replacements) = self.data["replace"].as_table_like() {
+            for (spec, _) in replacements.iter() {
+                overrides.push((spec_name(spec).to_owned(), "replace".to_owned()));
+            }
+        }
+
+        overrides
+    }
+
+    /// The entries of the `[patch]`, `[replace]` and `[profile.<profile>.package]` tables that
+    /// refer to a package, as the path of their table and their key in it, e.g.
+    /// `(["profile", "release", "package"], "foo")`.
+    pub fn related_overrides(&self, name: &str) -> Vec<(Vec<String>, String)> {
+        let mut entries = Vec::new();
+        let mut find = |table_path: Vec<String>,
+                        matches: &dyn Fn(&str, &toml_edit::Item) -> bool| {
+            let table = table_path
+                .iter()
+                .fold(&self.data.root, |item, key| &item[key.as_str()]);
+            if let Some(table) = table.as_table_like() {
+                for (key, item) in table.iter() {
+                    // Removed entries are left behind as `Item::None`.
+                    if !item.is_none() && matches(key, item) {
+                        entries.push((table_path.clone(), key.to_owned()));
+                    }
+                }
+            }
+        };
+
+        let tables = |key: &str| -> Vec<String> {
+            self.data[key]
+                .as_table_like()
+                .into_iter()
+                .flat_map(|table| table.iter())
+                .map(|(key, _)| key.to_owned())
+                .collect()
+        };
+        for source in tables("patch") {
+            find(vec!["patch".to_owned(), source], &|key, item| {
+                item["package"].as_str().unwrap_or(key) == name
+            });
+        }
+        find(vec!["replace".to_owned()], &|spec, _| {
+            spec_name(spec) == name
+        });
+        for profile in tables("profile") {
+            let table_path = vec!["profile".to_owned(), profile, "package".to_owned()];
+            find(table_path, &|spec, _| spec_name(spec) == name);
+        }
+
+        entries
+    }
+
+    /// Remove an entry found by `related_overrides`, along with the tables left empty.
+    pub fn remove_override(&mut self, table_path: &[String], key: &str) {
+        self.remove_item(table_path, key);
+    }
+
+    /// The `rust-version` declared in the `[package]` table, if any.
+    pub fn rust_version(&self) -> Option<&str> {
+        self.data["package"]["rust-version"].as_str()
+    }
+
+    /// Whether the `[package]` table inherits `field` from the workspace, e.g.
+    /// `rust-version = { workspace = true }`.
+    pub fn inherits_package_field(&self, field: &str) -> bool {
+        self.data["package"][field]
+            .as_table_like()
+            .and_then(|table| table.get("workspace"))
+            .and_then(toml_edit::Item::as_bool)
+            == Some(true)
+    }
+
+    /// The `rust-version` in the `[workspace.package]` table, which members inherit, if any.
+    pub fn workspace_rust_version(&self) -> Option<&str> {
+        self.data["workspace"]["package"]["rust-version"].as_str()
+    }
+
+    /// Set the `rust-version` in the `[workspace.package]` table, which members inherit.
+    pub fn set_workspace_rust_version(&mut self, version: &str) -> Result<()> {
+        let workspace = &mut self.data["workspace"];
+        if !workspace.is_table_like() {
+            return Err(ErrorKind::NonExistentTable("workspace".into()).into());
+        }
+        if workspace["package"].is_none() {
+            workspace["package"] = toml_edit::table();
+        }
+        workspace["package"]["rust-version"] = toml_edit::value(version);
+        Ok(())
+    }
+
+    /// Whether the toolchain of the `rust-version` in the `[package]` table understands manifest
+    /// syntax that cargo supports since Rust `version`, e.g. `1.60`. Without a `rust-version`, or
+    /// with one inherited from the workspace (which needs Rust 1.64 already), it is assumed to.
+    pub fn supports_rust_version(&self, version: &str) -> bool {
+        // `1.56` is read as `1.56.0`, like a requirement.
+        let rust_version = self.rust_version().and_then(requirement_floor);
+        match (rust_version, requirement_floor(version)) {
+            (Some(rust_version), Some(version)) => rust_version >= version,
+            _ => true,
+        }
+    }
+
     /// Get all sections in the manifest that exist and might contain dependencies.
     /// The returned items are always `Table` or `InlineTable`.
     pub fn get_sections(&self) -> Vec<(Vec<String>, toml_edit::Item)> {
@@ -250,17 +799,38 @@ impl Manifest {
         sections
     }
 
+    /// Write the manifest to the file at `path`.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let original = fs::read_to_string(path).chain_err(|| "Failed to read Cargo.toml")?;
+        let mut file = Manifest::find_file(&Some(path.to_owned()))?;
+        self.write_contents(&mut file, &self.to_string_like(&original))
+            .chain_err(|| "Failed to write new manifest contents")
+    }
+
+    /// The manifest as TOML, with the line endings of `original`, the contents it replaces: `\n` or
+    /// `\r\n`, and a trailing one or not.
+    pub fn to_string_like(&self, original: &str) -> String {
+        LineEndings::detect(original).apply(&self.data.to_string_in_original_order())
+    }
+
     /// Overwrite a file with TOML data.
+    ///
+    /// The file is written as-is from its current position; use `save` to keep the line endings
+    /// the file had.
     pub fn write_to_file(&self, file: &mut File) -> Result<()> {
-        if self.data["package"].is_none() && self.data["project"].is_none() {
-            if !self.data["workspace"].is_none() {
-                return Err(ErrorKind::UnexpectedRootManifest.into());
-            } else {
-                return Err(ErrorKind::InvalidManifest.into());
-            }
+        self.write_contents(file, &self.data.to_string_in_original_order())
+    }
+
+    fn write_contents(&self, file: &mut File, s: &str) -> Result<()> {
+        // A virtual manifest, with only a `[workspace]` table, is written e.g. for the
+        // `[workspace.package]` fields its members inherit.
+        if self.data["package"].is_none()
+            && self.data["project"].is_none()
+            && self.data["workspace"].is_none()
+        {
+            return Err(ErrorKind::InvalidManifest.into());
         }
 
-        let s = self.data.to_string_in_original_order();
         let new_contents_bytes = s.as_bytes();
 
         // We need to truncate the file, otherwise the new contents
@@ -274,6 +844,7 @@ impl Manifest {
     /// Add entry to a Cargo.toml.
     pub fn insert_into_table(&mut self, table_path: &[String], dep: &Dependency) -> Result<()> {
         let table = self.get_table(table_path)?;
+        let mut renamed_from = None;
 
         let existing_dep = Self::find_dep(table, &dep.name);
         if let Some((mut dep_name, dep_item)) = existing_dep {
@@ -289,6 +860,7 @@ impl Manifest {
             if let Some(renamed) = dep.rename() {
                 table[renamed] = dep_item.clone();
                 table[&dep_name] = toml_edit::Item::None;
+                renamed_from = Some(dep_name);
                 dep_name = renamed.to_owned();
             } else if dep.name != dep_name {
                 // if `dep` had been renamed in the manifest,
@@ -301,6 +873,7 @@ impl Manifest {
                 table[&dep_name] = toml_edit::Item::None;
                 let (ref name, ref mut new_dependency) = dep.to_toml();
                 table[name] = new_dependency.clone();
+                renamed_from = Some(dep_name);
                 dep_name = dep.name.to_owned();
             }
             merge_dependencies(&mut table[dep_name], dep);
@@ -312,44 +885,146 @@ impl Manifest {
             let (ref name, ref mut new_dependency) = dep.to_toml();
             table[name] = new_dependency.clone();
         }
+
+        if let Some(old_name) = renamed_from {
+            if old_name != dep.name_in_manifest() {
+                self.update_feature_references(&old_name, Some(dep.name_in_manifest()));
+            }
+        }
         Ok(())
     }
 
     /// Update an entry in Cargo.toml.
+    ///
+    /// Returns the change to the entry's version, if there was one (or would have been, for a dry
+    /// run).
     pub fn update_table_entry(
         &mut self,
         table_path: &[String],
         dep: &Dependency,
         dry_run: bool,
-    ) -> Result<()> {
+    ) -> Result<Option<VersionChange>> {
         self.update_table_named_entry(table_path, dep.name_in_manifest(), dep, dry_run)
     }
 
     /// Update an entry with a specified name in Cargo.toml.
+    ///
+    /// If `dep` is set to a version, the entry's requirement is rewritten for it like
+    /// `upgrade_requirement` does, keeping its operators and precision.
+    ///
+    /// Returns the change to the entry's version, if there was one (or would have been, for a dry
+    /// run).
     pub fn update_table_named_entry(
         &mut self,
         table_path: &[String],
         item_name: &str,
         dep: &Dependency,
         dry_run: bool,
-    ) -> Result<()> {
+    ) -> Result<Option<VersionChange>> {
         let table = self.get_table(table_path)?;
+        let rewritten;
+        let dep = match rewritten_requirement(&table[item_name], dep) {
+            Some(requirement) => {
+                rewritten = dep.clone().set_version(&requirement);
+                &rewritten
+            }
+            None => dep,
+        };
         let new_dep = dep.to_toml().1;
 
         // If (and only if) there is an old entry, merge the new one in.
-        if !table[item_name].is_none() {
-            if let Err(e) = print_upgrade_if_necessary(&dep.name, &table[item_name], &new_dep) {
-                eprintln!("Error while displaying upgrade message, {}", e);
-            }
-            if !dry_run {
-                merge_dependencies(&mut table[item_name], dep);
-                if let Some(t) = table.as_inline_table_mut() {
-                    t.fmt()
-                }
+        if table[item_name].is_none() || !version_changed(&table[item_name], &new_dep) {
+            return Ok(None);
+        }
+
+        let change = VersionChange {
+            name: dep.name.clone(),
+            old: version_str(&table[item_name]),
+            new: version_str(&new_dep),
+        };
+        if !dry_run {
+            merge_dependencies(&mut table[item_name], dep);
+            if let Some(t) = table.as_inline_table_mut() {
+                t.fmt()
             }
         }
 
-        Ok(())
+        Ok(Some(change))
+    }
+
+    /// Find where the entry `key` of the table at `table_path` is in the manifest's text, from
+    /// its key to the end of its value. For an entry that is a table of its own, e.g.
+    /// `[dependencies.serde]`, that is its `version` (or else its first value).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///   use cargo_edit::Manifest;
+    ///
+    ///   let manifest: Manifest = "[dependencies]\nserde = \"1.0\"\n".parse().unwrap();
+    ///   let span = manifest.entry_span(&["dependencies".to_owned()], "serde").unwrap();
+    ///   assert_eq!((span.start_line, span.start_column, span.end_column), (2, 1, 14));
+    /// ```
+    pub fn entry_span(&self, table_path: &[String], key: &str) -> Option<Span> {
+        // The parser keeps the formatting, so the manifest prints as it was read. Markers around
+        // the value show where it is in that text.
+        const START: &str = "\u{1}cargo-edit-span-start\u{1}";
+        const END: &str = "\u{1}cargo-edit-span-end\u{1}";
+
+        self.get_item(table_path)?.as_table_like()?.get(key)?;
+        let mut data = self.data.clone();
+        let table = table_path
+            .iter()
+            .fold(&mut data.root, |item, key| &mut item[key.as_str()]);
+        let mut item = &mut table[key];
+        if let Some(entry) = item.as_table() {
+            let value_key = if entry.contains_value("version") {
+                "version".to_owned()
+            } else {
+                entry
+                    .iter()
+                    .find(|(_, item)| item.is_value())
+                    .map(|(key, _)| key.to_owned())?
+            };
+            item = &mut item[value_key.as_str()];
+        }
+        let value = item.as_value()?;
+        let prefix = format!("{}{}", value.decor().prefix(), START);
+        let suffix = format!("{}{}", END, value.decor().suffix());
+        *item = toml_edit::Item::Value(toml_edit::decorated(value.clone(), &prefix, &suffix));
+
+        let marked = data.to_string();
+        let value_start = marked.find(START)?;
+        let end = marked.find(END)? - START.len();
+        let text = format!(
+            "{}{}",
+            &marked[..value_start],
+            &marked[value_start + START.len()..]
+        );
+        let line_start = text[..value_start].rfind('\n').map_or(0, |i| i + 1);
+        let indent =
+            text[line_start..value_start].len() - text[line_start..value_start].trim_start().len();
+        Some(Span::new(&text, line_start + indent, end))
+    }
+
+    /// Find where the entries for a crate are in the manifest's text, in every dependency table,
+    /// whether or not they are renamed.
+    pub fn dependency_spans(&self, name: &str) -> Vec<Span> {
+        let mut spans = Vec::new();
+        for (table_path, table) in self.get_sections() {
+            let table = table.as_table_like().expect("Unexpected non-table");
+            for (key, item) in table.iter() {
+                let package = item
+                    .as_table_like()
+                    .and_then(|t| t.get("package"))
+                    .and_then(|p| p.as_str());
+                if package.unwrap_or(key) == name {
+                    spans.extend(self.entry_span(&table_path, key));
+                }
+            }
+        }
+        spans.sort_by_key(|span| span.offset);
+        spans
     }
 
     /// Remove entry from a Cargo.toml.
@@ -368,68 +1043,711 @@ impl Manifest {
     ///   assert!(manifest.data["dependencies"].is_none());
     /// ```
     pub fn remove_from_table(&mut self, table: &str, name: &str) -> Result<()> {
-        if !self.data[table].is_table_like() {
-            return Err(ErrorKind::NonExistentTable(table.into()).into());
-        } else {
-            {
-                let dep = &mut self.data[table][name];
-                if dep.is_none() {
-                    return Err(ErrorKind::NonExistentDependency(name.into(), table.into()).into());
-                }
-                // remove the dependency
-                *dep = toml_edit::Item::None;
-            }
+        self.remove_from_table_path(&[table.to_owned()], name)
+    }
 
-            // remove table if empty
-            if self.data[table].as_table_like().unwrap().is_empty() {
-                self.data[table] = toml_edit::Item::None;
+    /// Remove entry from a possibly nested table, e.g. `target.'cfg(windows)'.dependencies`.
+    /// Tables left empty are removed too.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///   use cargo_edit::{Dependency, Manifest};
+    ///   use toml_edit;
+    ///
+    ///   let mut manifest = Manifest { data: toml_edit::Document::new() };
+    ///   let table = ["target".to_owned(), "cfg(windows)".to_owned(), "dependencies".to_owned()];
+    ///   let dep = Dependency::new("winapi").set_version("0.3");
+    ///   let _ = manifest.insert_into_table(&table, &dep);
+    ///   assert!(manifest.remove_from_table_path(&table, &dep.name).is_ok());
+    ///   assert!(manifest.data["target"].is_none());
+    /// ```
+    pub fn remove_from_table_path(&mut self, table_path: &[String], name: &str) -> Result<()> {
+        let description = table_path.join(".");
+        match self.get_item(table_path) {
+            Some(table) if table.is_table_like() => {
+                if table[name].is_none() {
+                    return Err(ErrorKind::NonExistentDependency(name.into(), description).into());
+                }
             }
+            _ => return Err(ErrorKind::NonExistentTable(description).into()),
         }
+
+        self.remove_item(table_path, name);
+        self.update_feature_references(name, None);
         Ok(())
     }
 
-    /// Add multiple dependencies to manifest
-    pub fn add_deps(&mut self, table: &[String], deps: &[Dependency]) -> Result<()> {
-        deps.iter()
-            .map(|dep| self.insert_into_table(table, dep))
-            .collect::<Result<Vec<_>>>()?;
+    /// Remove the item `name` from the table at `table_path`, and then each table that is left
+    /// empty.
+    fn remove_item(&mut self, table_path: &[String], name: &str) {
+        let mut keys: Vec<&str> = table_path.iter().map(String::as_str).collect();
+        keys.push(name);
+        while let Some((key, parent_path)) = keys.split_last() {
+            let parent = parent_path
+                .iter()
+                .fold(&mut self.data.root, |item, key| &mut item[*key]);
+            parent[*key] = toml_edit::Item::None;
+            if parent_path.is_empty() || !parent.as_table_like().is_some_and(|t| t.is_empty()) {
+                break;
+            }
+            keys.pop();
+        }
+    }
 
-        Ok(())
+    /// Remove the `[patch]` entries overriding a dependency, along with the tables left empty,
+    /// and return the sections they were in (e.g. `patch.crates-io`).
+    pub fn remove_patch(&mut self, name: &str) -> Vec<String> {
+        let sources: Vec<String> = match self.data["patch"].as_table_like() {
+            Some(patches) => patches
+                .iter()
+                .map(|(source, _)| source.to_owned())
+                .collect(),
+            None => return Vec::new(),
+        };
+        let mut removed = Vec::new();
+        for source in sources {
+            let keys: Vec<String> = self.data["patch"][&source]
+                .as_table_like()
+                .into_iter()
+                .flat_map(|patch| patch.iter())
+                .filter(|(key, item)| item["package"].as_str().unwrap_or(key) == name)
+                .map(|(key, _)| key.to_owned())
+                .collect();
+            for key in keys {
+                self.remove_item(&["patch".to_owned(), source.clone()], &key);
+                removed.push(format!("patch.{}", source));
+            }
+        }
+        removed
     }
 
-    /// Find a dependency by name (matching on package name for renamed deps)
-    pub fn find_dep<'a>(
-        table: &'a mut toml_edit::Item,
-        dep_name: &'a str,
-    ) -> Option<(String, &'a toml_edit::Item)> {
-        table
-            .as_table_like()
-            .unwrap()
-            .iter()
-            .find(|&item| match item {
-                (name, _) if name == dep_name => true,
-                (_alias, toml_edit::Item::Table(table_dep))
-                    if table_dep.contains_key("package") =>
-                {
-                    table_dep.get("package").unwrap().as_str() == Some(dep_name)
-                }
-                (_alias, toml_edit::Item::Value(toml_edit::Value::InlineTable(inline_dep)))
-                    if inline_dep.contains_key("package") =>
-                {
-                    inline_dep.get("package").unwrap().as_str() == Some(dep_name)
-                }
-                _ => false,
+    /// Features that cargo creates for optional dependencies, named after them.
+    ///
+    /// There is none for a dependency that `[features]` refers to with the `dep:` prefix, or that
+    /// is named like an explicit feature.
+    pub fn implicit_features(&self) -> Vec<String> {
+        let features = self.data["features"].as_table_like();
+        let is_explicit = |name: &str| {
+            features.is_some_and(|features| {
+                features.get(name).is_some()
+                    || features.iter().any(|(_, entries)| {
+                        feature_entries(entries).any(|entry| entry == format!("dep:{}", name))
+                    })
             })
-            .map(|dep| (dep.0.into(), dep.1))
+        };
+
+        let mut implicit: Vec<String> = self
+            .get_sections()
+            .into_iter()
+            .filter(|(path, _)| path.last().map(String::as_str) != Some("dev-dependencies"))
+            .flat_map(|(_, table)| {
+                table
+                    .as_table_like()
+                    .unwrap()
+                    .iter()
+                    .filter(|(_, dep)| dep["optional"].as_bool() == Some(true))
+                    .map(|(name, _)| name.to_owned())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|name| !is_explicit(name))
+            .collect();
+        implicit.sort();
+        implicit.dedup();
+        implicit
     }
-}
 
-impl str::FromStr for Manifest {
-    type Err = Error;
+    /// Warn about the implicit features from `implicit_features` (as returned by
+    /// `implicit_features` before the manifest was changed) that are gone, as packages depending on
+    /// this one may enable them.
+    pub fn warn_removed_features(&self, implicit_features: &[String]) {
+        let remaining = self.implicit_features();
+        for feature in implicit_features.iter().filter(|f| !remaining.contains(f)) {
+            eprintln!(
+                "WARN: The `{}` feature, which cargo creates for the optional dependency of that \
+                 name, is gone. Packages that enable it will fail to build unless it is added to \
+                 `[features]`.",
+                feature
+            );
+        }
+    }
 
-    /// Read manifest data from string
-    fn from_str(input: &str) -> ::std::result::Result<Self, Self::Err> {
-        let d: toml_edit::Document = input.parse().chain_err(|| "Manifest not valid TOML")?;
+    /// Have the feature `feature` enable the optional dependency `dependency` with a `dep:` entry,
+    /// adding the feature to `[features]` if it isn't there yet.
+    ///
+    /// As the dependency no longer gets an implicit feature, plain entries naming it in other
+    /// features are turned into `dep:` entries too.
+    ///
+    /// If the package's `rust-version` is older than Rust 1.60, whose cargo would fail to parse
+    /// `dep:` entries, a plain entry naming the dependency's implicit feature is added instead.
+    pub fn enable_in_feature(&mut self, feature: &str, dependency: &str) -> Result<()> {
+        let namespaced = self.supports_rust_version(NAMESPACED_FEATURES_RUST_VERSION);
+        let rust_version = self.rust_version().unwrap_or_default().to_owned();
+        let entry = if namespaced {
+            format!("dep:{}", dependency)
+        } else {
+            dependency.to_owned()
+        };
+        let features = self.get_table(&["features".to_owned()])?;
+        let explicit_feature = features[dependency].is_array();
+        if explicit_feature && !namespaced {
+            bail!(
+                "The feature `{}` hides the implicit feature of the optional dependency, and \
+                 `dep:{}` needs Rust {}, newer than the package's `rust-version` {}",
+                dependency,
+                dependency,
+                NAMESPACED_FEATURES_RUST_VERSION,
+                rust_version
+            );
+        }
+        if !explicit_feature && namespaced {
+            let names: Vec<String> = features
+                .as_table_like()
+                .map(|features| features.iter().map(|(name, _)| name.to_owned()).collect())
+                .unwrap_or_default();
+            for name in names {
+                if let Some(entries) = features[&name].as_array_mut() {
+                    for i in 0..entries.len() {
+                        if entries.get(i).and_then(toml_edit::Value::as_str) == Some(dependency) {
+                            let _ = entries.replace(i, entry.as_str());
+                        }
+                    }
+                }
+            }
+        }
+
+        let entries = features[feature]
+            .or_insert(toml_edit::value(toml_edit::Array::default()))
+            .as_array_mut();
+        let entries = match entries {
+            Some(entries) => entries,
+            None => bail!("The `{}` feature in `[features]` is not a list", feature),
+        };
+        if !entries.iter().any(|e| e.as_str() == Some(entry.as_str())) {
+            let _ = entries.push(entry);
+        }
+        Ok(())
+    }
+
+    /// Add entries to the feature `feature` in `[features]`, adding the feature if it isn't there
+    /// yet. Entries it already has are left alone.
+    ///
+    /// Entries are checked the way cargo would: `dep:name` and `name?/feature` need `name` to be
+    /// an optional dependency, `name/feature` needs it to be a dependency, and a plain entry needs
+    /// a feature of that name. `dep:` entries are added with `Manifest::enable_in_feature`.
+    ///
+    /// A dependency depended on at more than one version can be picked out with a spec such as
+    /// `rand@0.7/std` (see `Manifest::dependency_key`), which is written with its name in the
+    /// manifest.
+    pub fn add_feature_entries(&mut self, feature: &str, entries: &[String]) -> Result<()> {
+        let entries = entries
+            .iter()
+            .map(|entry| self.resolve_feature_entry(entry))
+            .collect::<Result<Vec<_>>>()?;
+        for entry in &entries {
+            self.check_feature_entry(feature, entry)?;
+        }
+        for entry in entries {
+            if let Some(dependency) = entry.strip_prefix("dep:") {
+                self.enable_in_feature(feature, dependency)?;
+                continue;
+            }
+            let features = self.get_table(&["features".to_owned()])?;
+            let existing = features[feature]
+                .or_insert(toml_edit::value(toml_edit::Array::default()))
+                .as_array_mut();
+            let existing = match existing {
+                Some(existing) => existing,
+                None => bail!("The `{}` feature in `[features]` is not a list", feature),
+            };
+            if !existing.iter().any(|e| e.as_str() == Some(entry.as_str())) {
+                let _ = existing.push(entry.as_str());
+            }
+        }
+        Ok(())
+    }
+
+    /// A `[features]` entry with the dependency spec it names, if any, replaced by the name of
+    /// the entry it selects, e.g. `rand07?/std` for `rand@0.7?/std`.
+    fn resolve_feature_entry(&self, entry: &str) -> Result<String> {
+        let (prefix, rest) = match entry.strip_prefix("dep:") {
+            Some(rest) => ("dep:", rest),
+            None => ("", entry),
+        };
+        let end = rest.find(&['?', '/'][..]).unwrap_or(rest.len());
+        let (spec, suffix) = rest.split_at(end);
+        if !spec.contains('@') {
+            return Ok(entry.to_owned());
+        }
+        let key = self.dependency_key(spec, &["dependencies", "build-dependencies"])?;
+        Ok(format!("{}{}{}", prefix, key, suffix))
+    }
+
+    /// Check that a `[features]` entry for the feature `feature` refers to something that exists,
+    /// and that the package's `rust-version` can parse it.
+    fn check_feature_entry(&self, feature: &str, entry: &str) -> Result<()> {
+        // `dep:` entries have an older equivalent, see `Manifest::enable_in_feature`.
+        if entry.contains("?/") && !self.supports_rust_version(NAMESPACED_FEATURES_RUST_VERSION) {
+            bail!(
+                "`{}` needs Rust {}, newer than the package's `rust-version` {}",
+                entry,
+                NAMESPACED_FEATURES_RUST_VERSION,
+                self.rust_version().unwrap_or_default()
+            );
+        }
+
+        let sections = self.get_sections();
+        let dependency = |name: &str| {
+            sections
+                .iter()
+                .filter(|(path, _)| path.last().map(String::as_str) != Some("dev-dependencies"))
+                .map(|(_, table)| &table[name])
+                .find(|dep| !dep.is_none())
+        };
+        let is_optional = |name: &str| {
+            dependency(name).is_some_and(|dep| dep["optional"].as_bool() == Some(true))
+        };
+
+        if let Some(name) = entry.strip_prefix("dep:") {
+            if !is_optional(name) {
+                bail!("`{}` needs `{}` to be an optional dependency", entry, name);
+            }
+        } else if let Some((name, dep_feature)) = entry.split_once('/') {
+            if dep_feature.is_empty() || dep_feature.contains('/') {
+                bail!("`{}` is not a feature of a dependency", entry);
+            }
+            match name.strip_suffix('?') {
+                Some(name) if !is_optional(name) => bail!(
+                    "`{}` needs `{}` to be an optional dependency, as `?` only enables the \
+                     feature if something else enables the dependency",
+                    entry,
+                    name
+                ),
+                None if dependency(name).is_none() => {
+                    bail!("`{}` needs `{}` to be a dependency", entry, name)
+                }
+                _ => {}
+            }
+        } else if entry == feature {
+            bail!("The feature `{}` can't enable itself", feature);
+        } else if !self.data["features"][entry].is_array()
+            && !self.implicit_features().iter().any(|f| f == entry)
+        {
+            bail!("There is no feature `{}` to enable", entry);
+        }
+        Ok(())
+    }
+
+    /// Rename the feature `old` in `[features]` to `new`, along with the references to it in other
+    /// features, in the `required-features` of targets, and in the features docs.rs builds with.
+    ///
+    /// The renamed feature moves to the end of `[features]`, without any comment above it.
+    pub fn rename_feature(&mut self, old: &str, new: &str) -> Result<()> {
+        if self
+            .implicit_features()
+            .iter()
+            .any(|feature| feature == new)
+        {
+            bail!(
+                "The optional dependency `{}` already has a feature of that name",
+                new
+            );
+        }
+        let features = match self.data["features"].as_table_mut() {
+            Some(features) if features.contains_key(old) => features,
+            _ => bail!("The feature `{}` could not be found", old),
+        };
+        if features.contains_key(new) {
+            bail!("There already is a feature called `{}`", new);
+        }
+        let entries = features.remove(old).expect("The feature was just found");
+        *features.entry(new) = entries;
+
+        let names: Vec<String> = features.iter().map(|(name, _)| name.to_owned()).collect();
+        for name in names {
+            if let Some(entries) = features.entry(&name).as_array_mut() {
+                rename_array_entry(entries, old, new);
+            }
+        }
+
+        for kind in &["bin", "example", "test", "bench"] {
+            if let Some(targets) = self.data[kind].as_array_of_tables_mut() {
+                for i in 0..targets.len() {
+                    let target = targets.get_mut(i).expect("The index is in range");
+                    if let Some(entries) = target.entry("required-features").as_array_mut() {
+                        rename_array_entry(entries, old, new);
+                    }
+                }
+            }
+        }
+
+        if self.data["package"]["metadata"]["docs"]["rs"]["features"].is_array() {
+            let docs_rs = &mut self.data["package"]["metadata"]["docs"]["rs"];
+            if let Some(entries) = docs_rs["features"].as_array_mut() {
+                rename_array_entry(entries, old, new);
+            }
+        }
+        Ok(())
+    }
+
+    /// Follow the rename of the feature `old` of the package `dependency` to `new`, in the entries
+    /// for the dependency and in `[features]` entries such as `dependency/old`.
+    ///
+    /// Returns whether the manifest refers to the feature at all.
+    pub fn rename_dependency_feature(
+        &mut self,
+        dependency: &str,
+        old: &str,
+        new: &str,
+    ) -> Result<bool> {
+        let mut renamed = false;
+        let mut names = Vec::new();
+        for (table_path, name, _) in self.dependency_entries(dependency) {
+            let table = self.get_table(&table_path)?;
+            if let Some(entries) = table[&name]["features"].as_array_mut() {
+                renamed |= rename_array_entry(entries, old, new);
+            }
+            names.push(name);
+        }
+
+        let features = match self.data["features"].as_table_mut() {
+            Some(features) => features,
+            None => return Ok(renamed),
+        };
+        let feature_names: Vec<String> = features.iter().map(|(name, _)| name.to_owned()).collect();
+        for feature in feature_names {
+            if let Some(entries) = features.entry(&feature).as_array_mut() {
+                for name in &names {
+                    for separator in &["/", "?/"] {
+                        renamed |= rename_array_entry(
+                            entries,
+                            &format!("{}{}{}", name, separator, old),
+                            &format!("{}{}{}", name, separator, new),
+                        );
+                    }
+                }
+            }
+        }
+        Ok(renamed)
+    }
+
+    /// Update `[features]` after the dependency `old` was renamed to `new`, or removed if `new` is
+    /// `None`, so it doesn't refer to a dependency that no longer exists.
+    ///
+    /// Nothing is changed while another dependency that features can refer to is still called
+    /// `old`.
+    fn update_feature_references(&mut self, old: &str, new: Option<&str>) {
+        let still_exists = self
+            .get_sections()
+            .iter()
+            .filter(|(path, _)| path.last().map(String::as_str) != Some("dev-dependencies"))
+            .any(|(_, table)| !table[old].is_none());
+        if still_exists {
+            return;
+        }
+
+        let features = match self.data["features"].as_table_like() {
+            Some(features) => features,
+            None => return,
+        };
+        // A plain entry naming an explicit feature refers to that, not to the dependency.
+        let explicit_feature = features.get(old).is_some();
+        let names: Vec<String> = features.iter().map(|(name, _)| name.to_owned()).collect();
+        for name in names {
+            let entries = match self.data["features"][&name].as_array_mut() {
+                Some(entries) => entries,
+                None => continue,
+            };
+            let mut i = 0;
+            while i < entries.len() {
+                let entry = entries.get(i).and_then(toml_edit::Value::as_str);
+                let updated = match entry {
+                    Some(entry) if !(explicit_feature && entry == old) => {
+                        rename_feature_entry(entry, old, new)
+                    }
+                    _ => None,
+                };
+                match updated {
+                    Some(Some(entry)) => {
+                        let _ = entries.replace(i, entry);
+                        i += 1;
+                    }
+                    Some(None) => remove_array_entry(entries, i),
+                    None => i += 1,
+                }
+            }
+        }
+    }
+
+    /// The features that the entries for a dependency enable, but which aren't among the
+    /// `available` features of the version it is being upgraded to.
+    pub fn stale_features(
+        &self,
+        dependency: &str,
+        available: &BTreeSet<String>,
+    ) -> Vec<StaleFeature> {
+        let mut stale: Vec<StaleFeature> = self
+            .dependency_entries(dependency)
+            .iter()
+            .flat_map(|(_, _, entry)| {
+                feature_entries(&entry["features"])
+                    .filter(|feature| !available.contains(*feature))
+                    .map(|feature| StaleFeature {
+                        dependency: dependency.to_owned(),
+                        feature: feature.to_owned(),
+                        renamed_to: renamed_feature(feature, available),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        stale.sort_by(|a, b| a.feature.cmp(&b.feature));
+        stale.dedup();
+        stale
+    }
+
+    /// Rename stale features in the entries for their dependency to their new names, and drop
+    /// those that were removed.
+    pub fn fix_features(&mut self, stale: &[StaleFeature]) -> Result<()> {
+        for (table_path, name, _) in self.dependency_entries_of(stale) {
+            let table = self.get_table(&table_path)?;
+            let entries = match table[&name]["features"].as_array_mut() {
+                Some(entries) => entries,
+                None => continue,
+            };
+            let mut i = 0;
+            while i < entries.len() {
+                let fix = entries
+                    .get(i)
+                    .and_then(toml_edit::Value::as_str)
+                    .and_then(|entry| stale.iter().find(|stale| stale.feature == entry));
+                match fix.map(|stale| stale.renamed_to.clone()) {
+                    Some(Some(renamed)) => {
+                        let _ = entries.replace(i, renamed);
+                        i += 1;
+                    }
+                    Some(None) => remove_array_entry(entries, i),
+                    None => i += 1,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The entries for the dependencies that stale features belong to.
+    fn dependency_entries_of(
+        &self,
+        stale: &[StaleFeature],
+    ) -> Vec<(Vec<String>, String, toml_edit::Item)> {
+        let mut dependencies: Vec<&str> = stale.iter().map(|s| s.dependency.as_str()).collect();
+        dependencies.sort_unstable();
+        dependencies.dedup();
+        dependencies
+            .into_iter()
+            .flat_map(|dependency| self.dependency_entries(dependency))
+            .collect()
+    }
+
+    /// The entries for a dependency in every section, with the table they are in and their name
+    /// in it (which differs from the package name if they are renamed).
+    fn dependency_entries(&self, dependency: &str) -> Vec<(Vec<String>, String, toml_edit::Item)> {
+        self.get_sections()
+            .into_iter()
+            .flat_map(|(table_path, table)| {
+                table
+                    .as_table_like()
+                    .expect("Unexpected non-table")
+                    .iter()
+                    .filter(|(name, entry)| entry["package"].as_str().unwrap_or(name) == dependency)
+                    .map(|(name, entry)| (table_path.clone(), name.to_owned(), entry.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// The name in the manifest of the entry that `spec` selects among those in `sections` (e.g.
+    /// `["dependencies"]`, which includes the platform-specific ones).
+    ///
+    /// A spec is the name of an entry, which is used as is, or the name of a package depended on
+    /// once, or `name@version` to select the entry whose requirement starts at that version, e.g.
+    /// `rand@0.7` for `rand07 = { package = "rand", version = "0.7.3" }` beside `rand = "0.8"`.
+    pub fn dependency_key(&self, spec: &str, sections: &[&str]) -> Result<String> {
+        let tables: Vec<_> = self
+            .get_sections()
+            .into_iter()
+            .filter(|(path, _)| {
+                path.last()
+                    .is_some_and(|section| sections.contains(&section.as_str()))
+            })
+            .collect();
+        Self::select_dependency_key(spec, &tables, &sections.join(", "))
+    }
+
+    /// The name in the manifest of the entry that `spec` selects in a single table, e.g.
+    /// `["target", "cfg(windows)", "dependencies"]`, like `dependency_key`.
+    pub fn dependency_key_in_table(&self, spec: &str, table_path: &[String]) -> Result<String> {
+        let tables: Vec<_> = self
+            .get_sections()
+            .into_iter()
+            .filter(|(path, _)| path == table_path)
+            .collect();
+        Self::select_dependency_key(spec, &tables, &table_path.join("."))
+    }
+
+    /// Select the entry that `spec` refers to among those in `tables`, described as `sections` in
+    /// errors.
+    fn select_dependency_key(
+        spec: &str,
+        tables: &[(Vec<String>, toml_edit::Item)],
+        sections: &str,
+    ) -> Result<String> {
+        let (name, version) = match spec.split_once('@') {
+            Some((name, version)) => (name, Some(version)),
+            None => (spec, None),
+        };
+        if tables.is_empty() {
+            return Err(ErrorKind::NonExistentTable(sections.to_owned()).into());
+        }
+        if version.is_none() && tables.iter().any(|(_, table)| !table[name].is_none()) {
+            return Ok(name.to_owned());
+        }
+
+        let mut matches: Vec<(String, String)> = Vec::new();
+        for (_, table) in tables {
+            let entries = table.as_table_like().expect("Unexpected non-table").iter();
+            for (key, entry) in entries {
+                if entry["package"].as_str().unwrap_or(key) != name {
+                    continue;
+                }
+                let requirement = version_str(entry).unwrap_or_else(|| "*".to_owned());
+                let selected = version.is_none_or(|version| {
+                    requirement_floor(&requirement).is_some_and(|floor| {
+                        let floor = [floor.major, floor.minor, floor.patch];
+                        version
+                            .split('.')
+                            .zip(&floor)
+                            .all(|(part, floor)| part.parse() == Ok(*floor))
+                    })
+                });
+                if selected && !matches.iter().any(|(k, _)| k == key) {
+                    matches.push((key.to_owned(), requirement));
+                }
+            }
+        }
+        match matches.len() {
+            0 => Err(ErrorKind::NonExistentDependency(spec.into(), sections.into()).into()),
+            1 => Ok(matches.remove(0).0),
+            _ => {
+                let entries = matches
+                    .into_iter()
+                    .map(|(key, requirement)| format!("`{}` ({})", key, requirement))
+                    .collect();
+                Err(ErrorKind::AmbiguousDependency(spec.into(), entries).into())
+            }
+        }
+    }
+
+    /// Add multiple dependencies to manifest
+    pub fn add_deps(&mut self, table: &[String], deps: &[Dependency]) -> Result<()> {
+        deps.iter()
+            .map(|dep| self.insert_into_table(table, dep))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(())
+    }
+
+    /// Put a `# comment` line above the entry `key` in the table at `table_path`, with a line for
+    /// each line of `comment`.
+    ///
+    /// Nothing is added if the entry already has that comment right above it.
+    pub fn set_comment(&mut self, table_path: &[String], key: &str, comment: &str) -> Result<()> {
+        let comment: Vec<String> = comment
+            .lines()
+            .map(|line| format!("# {}", line).trim_end().to_owned())
+            .collect();
+        let own_header: Vec<String> = table_path
+            .iter()
+            .cloned()
+            .chain(std::iter::once(key.to_owned()))
+            .collect();
+
+        let content = self.data.to_string();
+        let mut lines: Vec<&str> = content.lines().collect();
+        let mut section = Vec::new();
+        let mut found = None;
+        for (i, line) in lines.iter().enumerate() {
+            if let Some(path) = table_header_path(line) {
+                if path == own_header {
+                    found = Some(i);
+                    break;
+                }
+                section = path;
+            } else if section == table_path && sets_key(line, key) {
+                found = Some(i);
+                break;
+            }
+        }
+        let line = found.chain_err(|| {
+            format!(
+                "The table `{}` has no entry `{}` to comment",
+                table_path.join("."),
+                key
+            )
+        })?;
+
+        if line >= comment.len() && lines[line - comment.len()..line] == comment[..] {
+            return Ok(());
+        }
+        let indent_len = lines[line].len() - lines[line].trim_start().len();
+        let indent = &lines[line][..indent_len];
+        let comment: Vec<String> = comment
+            .iter()
+            .map(|comment| format!("{}{}", indent, comment))
+            .collect();
+        for (offset, comment) in comment.iter().enumerate() {
+            lines.insert(line + offset, comment);
+        }
+
+        let mut content = lines.join("\n");
+        content.push('\n');
+        self.data = content
+            .parse()
+            .chain_err(|| "Commenting the dependency made the manifest invalid")?;
+        Ok(())
+    }
+
+    /// Find a dependency by name (matching on package name for renamed deps)
+    pub fn find_dep<'a>(
+        table: &'a mut toml_edit::Item,
+        dep_name: &'a str,
+    ) -> Option<(String, &'a toml_edit::Item)> {
+        table
+            .as_table_like()
+            .unwrap()
+            .iter()
+            .find(|&item| match item {
+                (name, _) if name == dep_name => true,
+                (_alias, toml_edit::Item::Table(table_dep))
+                    if table_dep.contains_key("package") =>
+                {
+                    table_dep.get("package").unwrap().as_str() == Some(dep_name)
+                }
+                (_alias, toml_edit::Item::Value(toml_edit::Value::InlineTable(inline_dep)))
+                    if inline_dep.contains_key("package") =>
+                {
+                    inline_dep.get("package").unwrap().as_str() == Some(dep_name)
+                }
+                _ => false,
+            })
+            .map(|dep| (dep.0.into(), dep.1))
+    }
+}
+
+impl str::FromStr for Manifest {
+    type Err = Error;
+
+    /// Read manifest data from string
+    fn from_str(input: &str) -> ::std::result::Result<Self, Self::Err> {
+        let d: toml_edit::Document = input.parse().chain_err(|| "Manifest not valid TOML")?;
 
         Ok(Manifest { data: d })
     }
@@ -442,6 +1760,11 @@ pub struct LocalManifest {
     pub path: PathBuf,
     /// Manifest contents
     manifest: Manifest,
+    /// Whether upgrades replace `*` requirements with the version upgraded to, rather than leave
+    /// them alone
+    pub concretize_wildcards: bool,
+    /// Whether upgrades write exact requirements (`=x.y.z`) on the version upgraded to
+    pub precise: bool,
 }
 
 impl Deref for LocalManifest {
@@ -466,22 +1789,70 @@ impl LocalManifest {
         Ok(LocalManifest {
             manifest: Manifest::open(&Some(path.clone()))?,
             path,
+            concretize_wildcards: false,
+            precise: false,
         })
     }
 
-    /// Get the `File` corresponding to this manifest.
-    fn get_file(&self) -> Result<File> {
-        Manifest::find_file(&Some(self.path.clone()))
+    /// Write the manifest to disk, like `Manifest::save`.
+    fn write(&self) -> Result<()> {
+        self.save(&self.path)
     }
 
     /// Instruct this manifest to upgrade a single dependency. If this manifest does not have that
-    /// dependency, it does nothing.
+    /// dependency, or it is already at the requested version, it does nothing, and the file on
+    /// disk is left untouched.
+    ///
+    /// Only entries from the dependency's registry are upgraded, so a crate from an alternative
+    /// registry (e.g. `registry = "internal"`) is kept apart from a crates.io crate of the same
+    /// name.
+    ///
+    /// Returns the changes made (or, for a dry run, that would have been made) to the versions of
+    /// the dependency.
     pub fn upgrade(
         &mut self,
         dependency: &Dependency,
         dry_run: bool,
         skip_compatible: bool,
-    ) -> Result<()> {
+    ) -> Result<Vec<VersionChange>> {
+        let changes = self.upgrade_entries(dependency, dry_run, skip_compatible)?;
+        if !changes.is_empty() && !dry_run {
+            self.write()?;
+        }
+        Ok(changes)
+    }
+
+    /// Drop the `[patch]` entries overriding a dependency, like `Manifest::remove_patch`, only in
+    /// memory. Returns the sections the entries were in.
+    pub fn drop_patch(&mut self, name: &str) -> Vec<String> {
+        self.manifest.remove_patch(name)
+    }
+
+    /// Upgrade a single dependency like `upgrade`, but only in memory, leaving the file on disk
+    /// untouched (e.g. to show the difference).
+    pub fn upgrade_in_memory(
+        &mut self,
+        dependency: &Dependency,
+        skip_compatible: bool,
+    ) -> Result<Vec<VersionChange>> {
+        self.upgrade_entries(dependency, false, skip_compatible)
+    }
+
+    /// Update every entry for the dependency, without writing to disk.
+    fn upgrade_entries(
+        &mut self,
+        dependency: &Dependency,
+        dry_run: bool,
+        skip_compatible: bool,
+    ) -> Result<Vec<VersionChange>> {
+        let exact;
+        let new_dependency = if self.precise {
+            exact = exact_requirement(dependency)?;
+            &exact
+        } else {
+            dependency
+        };
+        let mut changes = Vec::new();
         for (table_path, table) in self.get_sections() {
             let table_like = table.as_table_like().expect("Unexpected non-table");
             for (name, toml_item) in table_like.iter() {
@@ -489,7 +1860,17 @@ impl LocalManifest {
                     .as_table_like()
                     .and_then(|t| t.get("package").and_then(|p| p.as_str()))
                     .unwrap_or(name);
-                if dep_name == dependency.name {
+                let registry = toml_item
+                    .as_table_like()
+                    .and_then(|t| t.get("registry").and_then(|r| r.as_str()));
+                // A renamed dependency is only upgraded in the entry of that name.
+                let selected = dependency.rename().is_none_or(|rename| rename == name);
+                if dep_name == dependency.name && registry == dependency.registry() && selected {
+                    let wildcard = get_version(toml_item)
+                        .is_ok_and(|version| version.as_str().map(str::trim) == Some("*"));
+                    if wildcard && !self.concretize_wildcards && !self.precise {
+                        continue;
+                    }
                     if skip_compatible {
                         if let Some(old_version) = get_version(toml_item)?.as_str() {
                             if old_version_compatible(dependency, old_version)? {
@@ -497,19 +1878,80 @@ impl LocalManifest {
                             }
                         }
                     }
-                    self.manifest.update_table_named_entry(
+                    changes.extend(self.manifest.update_table_named_entry(
                         &table_path,
-                        &name,
-                        dependency,
+                        name,
+                        new_dependency,
                         dry_run,
-                    )?;
+                    )?);
                 }
             }
         }
+        Ok(changes)
+    }
 
-        let mut file = self.get_file()?;
-        self.write_to_file(&mut file)
-            .chain_err(|| "Failed to write new manifest contents")
+    /// Fix stale features like `Manifest::fix_features`, only in memory. They are written along
+    /// with the next upgrade that changes the manifest.
+    pub fn fix_features(&mut self, stale: &[StaleFeature]) -> Result<()> {
+        self.manifest.fix_features(stale)
+    }
+
+    /// Set the `rust-version` in the `[package]` table, only in memory.
+    ///
+    /// Fails if the package inherits its `rust-version` from the workspace, which is set with
+    /// `Manifest::set_workspace_rust_version` in the workspace root instead.
+    pub fn set_rust_version(&mut self, version: &str) -> Result<()> {
+        if self.manifest.inherits_package_field("rust-version") {
+            return Err(ErrorKind::InheritedField("rust-version".into()).into());
+        }
+        let package = &mut self.manifest.data["package"];
+        if !package.is_table_like() {
+            return Err(ErrorKind::UnexpectedRootManifest.into());
+        }
+        package["rust-version"] = toml_edit::value(version);
+        Ok(())
+    }
+
+    /// Replace the version requirement of the dependency `name` in the table at `table_path`,
+    /// keeping the rest of its entry, only in memory.
+    ///
+    /// Returns the change, or `None` if the entry already has that requirement.
+    pub fn set_requirement(
+        &mut self,
+        table_path: &[String],
+        name: &str,
+        requirement: &str,
+    ) -> Result<Option<VersionChange>> {
+        let table = self.manifest.get_table(table_path)?;
+        let entry = &mut table[name];
+        if entry.is_none() {
+            let table = table_path.join(".");
+            return Err(ErrorKind::NonExistentDependency(name.into(), table).into());
+        }
+        let old = version_str(entry);
+        if old.as_deref() == Some(requirement) {
+            return Ok(None);
+        }
+        let package = entry
+            .as_table_like()
+            .and_then(|t| t.get("package").and_then(|p| p.as_str()))
+            .unwrap_or(name)
+            .to_owned();
+
+        if entry.is_str() {
+            *entry = toml_edit::value(requirement);
+        } else {
+            entry["version"] = toml_edit::value(requirement);
+            if let Some(t) = entry.as_inline_table_mut() {
+                t.fmt()
+            }
+        }
+
+        Ok(Some(VersionChange {
+            name: package,
+            old,
+            new: Some(requirement.to_owned()),
+        }))
     }
 }
 
@@ -517,7 +1959,111 @@ impl LocalManifest {
 mod tests {
     use super::*;
     use crate::dependency::Dependency;
-    use toml_edit;
+
+    #[test]
+    fn keeps_line_endings() {
+        let crlf = LineEndings::detect("[package]\r\nname = \"a\"\r\n");
+        assert_eq!(
+            crlf,
+            LineEndings {
+                crlf: true,
+                trailing_newline: true
+            }
+        );
+        assert_eq!(
+            crlf.apply("[package]\r\nname = \"a\"\n\n[dependencies]\nb = \"1\""),
+            "[package]\r\nname = \"a\"\r\n\r\n[dependencies]\r\nb = \"1\"\r\n"
+        );
+
+        let lf = LineEndings::detect("[package]\nname = \"a\"");
+        assert_eq!(
+            lf,
+            LineEndings {
+                crlf: false,
+                trailing_newline: false
+            }
+        );
+        assert_eq!(
+            lf.apply("[package]\nname = \"a\"\nb = \"\"\"x\r\ny\"\"\"\n"),
+            "[package]\nname = \"a\"\nb = \"\"\"x\r\ny\"\"\""
+        );
+
+        assert_eq!(LineEndings::detect("").apply("a = 1"), "a = 1\n");
+    }
+
+    #[test]
+    fn feature_entries_for_old_rust_version() {
+        let mut manifest: Manifest = r#"
+[package]
+name = "a"
+version = "0.1.0"
+rust-version = "1.56"
+
+[dependencies]
+b = { version = "0.1", optional = true }
+c = { version = "0.1", optional = true }
+
+[features]
+c = []
+"#
+        .parse()
+        .unwrap();
+        assert!(!manifest.supports_rust_version("1.60"));
+        assert!(manifest.supports_rust_version("1.56"));
+
+        manifest
+            .add_feature_entries("extra", &["dep:b".to_owned()])
+            .unwrap();
+        assert_eq!(manifest.data["features"]["extra"][0].as_str(), Some("b"));
+        assert!(manifest
+            .add_feature_entries("extra", &["b?/std".to_owned()])
+            .is_err());
+        // A plain `c` entry would enable the feature rather than the dependency.
+        assert!(manifest.enable_in_feature("extra", "c").is_err());
+    }
+
+    #[test]
+    fn related_overrides() {
+        let mut manifest: Manifest = r#"
+[replace]
+"foo:1.0.0" = { path = "foo" }
+"https://example.com/index#bar:0.1.0" = { path = "bar" }
+
+[patch.crates-io]
+foo2 = { package = "foo", path = "foo2" }
+
+[profile.release.package.foo]
+opt-level = 3
+"#
+        .parse()
+        .unwrap();
+        let entries = manifest.related_overrides("foo");
+        assert_eq!(
+            entries,
+            vec![
+                (
+                    vec!["patch".to_owned(), "crates-io".to_owned()],
+                    "foo2".to_owned()
+                ),
+                (vec!["replace".to_owned()], "foo:1.0.0".to_owned()),
+                (
+                    vec![
+                        "profile".to_owned(),
+                        "release".to_owned(),
+                        "package".to_owned()
+                    ],
+                    "foo".to_owned()
+                ),
+            ]
+        );
+        for (table_path, key) in entries {
+            manifest.remove_override(&table_path, &key);
+        }
+        assert!(manifest.related_overrides("foo").is_empty());
+        assert!(manifest.data["patch"].is_none());
+        assert!(manifest.data["profile"].is_none());
+        assert!(!manifest.data["replace"]["https://example.com/index#bar:0.1.0"].is_none());
+    }
 
     #[test]
     fn add_remove_dependency() {
@@ -568,6 +2114,274 @@ mod tests {
         assert_eq!(manifest.data.to_string(), original.data.to_string());
     }
 
+    #[test]
+    fn rename_dependency_in_features() {
+        let mut manifest: Manifest = r#"
+[dependencies]
+a = { version = "0.1", optional = true }
+
+[features]
+default = ["a", "a/std"]
+extra = ["dep:a", "a?/alloc"]
+"#
+        .parse()
+        .unwrap();
+        let dep = Dependency::new("a").set_version("0.1").set_rename("b");
+        manifest
+            .insert_into_table(&["dependencies".to_owned()], &dep)
+            .unwrap();
+
+        let toml = manifest.data.to_string();
+        assert!(toml.contains(r#"b = { version = "0.1", optional = true, package = "a" }"#));
+        assert!(toml.contains(r#"default = ["b", "b/std"]"#));
+        assert!(toml.contains(r#"extra = ["dep:b", "b?/alloc"]"#));
+    }
+
+    #[test]
+    fn enable_dependency_in_feature() {
+        let mut manifest: Manifest = r#"
+[dependencies]
+a = { version = "0.1", optional = true }
+
+[features]
+default = ["a", "a/std"]
+"#
+        .parse()
+        .unwrap();
+        manifest.enable_in_feature("extra", "a").unwrap();
+        manifest.enable_in_feature("extra", "a").unwrap();
+
+        let toml = manifest.data.to_string();
+        assert!(toml.contains(r#"default = ["dep:a", "a/std"]"#));
+        assert!(toml.contains(r#"extra = ["dep:a"]"#));
+        assert!(manifest.implicit_features().is_empty());
+
+        let mut manifest: Manifest = "[features]\nextra = true\n".parse().unwrap();
+        assert!(manifest.enable_in_feature("extra", "a").is_err());
+    }
+
+    #[test]
+    fn add_weak_feature_entries() {
+        let mut manifest: Manifest = r#"
+[dependencies]
+a = { version = "0.1", optional = true }
+b = "0.1"
+
+[dev-dependencies]
+c = "0.1"
+
+[features]
+default = ["a"]
+"#
+        .parse()
+        .unwrap();
+        let entries =
+            |entries: &[&str]| entries.iter().map(|e| (*e).to_owned()).collect::<Vec<_>>();
+        manifest
+            .add_feature_entries("std", &entries(&["a?/std", "b/std", "default"]))
+            .unwrap();
+        manifest
+            .add_feature_entries("std", &entries(&["dep:a", "a?/std"]))
+            .unwrap();
+
+        let toml = manifest.data.to_string();
+        assert!(toml.contains(r#"default = ["dep:a"]"#));
+        assert!(toml.contains(r#"std = ["a?/std", "b/std", "default", "dep:a"]"#));
+
+        for entry in &["b?/std", "dep:b", "c/std", "missing", "std", "a/"] {
+            assert!(
+                manifest
+                    .add_feature_entries("std", &entries(&[entry]))
+                    .is_err(),
+                "{}",
+                entry
+            );
+        }
+    }
+
+    #[test]
+    fn rename_feature() {
+        let mut manifest: Manifest = r#"
+[package]
+name = "demo"
+
+[package.metadata.docs.rs]
+features = ["old"]
+
+[dependencies]
+a = { version = "0.1", optional = true }
+
+[features]
+default = ["old"]
+old = ["a"]
+other = ["old", "a/old"]
+
+[[bin]]
+name = "tool"
+required-features = ["old", "other"]
+"#
+        .parse()
+        .unwrap();
+        manifest.rename_feature("old", "new").unwrap();
+
+        let toml = manifest.data.to_string();
+        assert!(!toml.contains(r#""old""#));
+        assert!(toml.contains(r#"default = ["new"]"#));
+        assert!(toml.contains(r#"other = ["new", "a/old"]"#));
+        assert!(toml.contains(r#"new = ["a"]"#));
+        assert!(toml.contains(r#"required-features = ["new", "other"]"#));
+        assert!(toml.contains(r#"features = ["new"]"#));
+
+        assert!(manifest.rename_feature("old", "newer").is_err());
+        assert!(manifest.rename_feature("new", "other").is_err());
+        assert!(manifest.rename_feature("new", "a").is_err());
+    }
+
+    #[test]
+    fn set_comment() {
+        let mut manifest: Manifest = r#"
+[package]
+name = "demo"
+
+[dev-dependencies]
+a = "0.1"
+
+[dependencies]
+a = "0.1"
+"b" = { version = "0.2" }
+
+[dependencies.c]
+version = "0.3"
+"#
+        .parse()
+        .unwrap();
+        let deps = vec!["dependencies".to_owned()];
+        manifest.set_comment(&deps, "a", "Does a").unwrap();
+        manifest
+            .set_comment(&deps, "b", "Does b\nand more")
+            .unwrap();
+        manifest.set_comment(&deps, "c", "Does c").unwrap();
+        manifest.set_comment(&deps, "a", "Does a").unwrap();
+        assert!(manifest.set_comment(&deps, "d", "Does d").is_err());
+
+        assert_eq!(
+            manifest.data.to_string(),
+            r#"
+[package]
+name = "demo"
+
+[dev-dependencies]
+a = "0.1"
+
+[dependencies]
+# Does a
+a = "0.1"
+# Does b
+# and more
+"b" = { version = "0.2" }
+
+# Does c
+[dependencies.c]
+version = "0.3"
+"#
+        );
+    }
+
+    #[test]
+    fn rename_dependency_feature() {
+        let mut manifest: Manifest = r#"
+[dependencies]
+renamed = { package = "a", version = "0.1", features = ["old"] }
+b = { version = "0.1", features = ["old"] }
+
+[features]
+extra = ["renamed/old", "renamed?/old", "b/old"]
+"#
+        .parse()
+        .unwrap();
+        assert!(manifest
+            .rename_dependency_feature("a", "old", "new")
+            .unwrap());
+
+        let toml = manifest.data.to_string();
+        assert!(
+            toml.contains(r#"renamed = { package = "a", version = "0.1", features = ["new"] }"#)
+        );
+        assert!(toml.contains(r#"b = { version = "0.1", features = ["old"] }"#));
+        assert!(toml.contains(r#"extra = ["renamed/new", "renamed?/new", "b/old"]"#));
+        assert!(!manifest
+            .rename_dependency_feature("c", "old", "new")
+            .unwrap());
+    }
+
+    #[test]
+    fn stale_features() {
+        let mut manifest: Manifest = r#"
+[dependencies]
+a = { version = "0.1", features = ["std", "serde_json", "old"] }
+
+[target.'cfg(unix)'.dependencies]
+b = { version = "0.1", package = "a", features = ["old"] }
+"#
+        .parse()
+        .unwrap();
+        let available = ["std", "serde-json"]
+            .iter()
+            .map(|&f| f.to_owned())
+            .collect();
+
+        let stale = manifest.stale_features("a", &available);
+        assert_eq!(
+            stale,
+            vec![
+                StaleFeature {
+                    dependency: "a".into(),
+                    feature: "old".into(),
+                    renamed_to: None,
+                },
+                StaleFeature {
+                    dependency: "a".into(),
+                    feature: "serde_json".into(),
+                    renamed_to: Some("serde-json".into()),
+                },
+            ]
+        );
+
+        manifest.fix_features(&stale).unwrap();
+        let toml = manifest.data.to_string();
+        assert!(toml.contains(r#"a = { version = "0.1", features = ["std", "serde-json"] }"#));
+        assert!(toml.contains(r#"b = { version = "0.1", package = "a", features = [] }"#));
+        assert!(manifest.stale_features("a", &available).is_empty());
+    }
+
+    #[test]
+    fn remove_dependency_from_features() {
+        let mut manifest: Manifest = r#"
+[dependencies]
+a = { version = "0.1", optional = true }
+b = "0.1"
+
+[target.'cfg(unix)'.dependencies]
+c = { version = "0.1", optional = true }
+
+[features]
+a = []
+default = ["a", "a/std", "b"]
+"#
+        .parse()
+        .unwrap();
+        assert_eq!(manifest.implicit_features(), vec!["c"]);
+
+        // `a` is an explicit feature, so only the reference to the dependency's feature goes.
+        manifest.remove_from_table("dependencies", "a").unwrap();
+        assert!(manifest
+            .data
+            .to_string()
+            .contains(r#"default = ["a", "b"]"#));
+        manifest.remove_from_table("dependencies", "b").unwrap();
+        assert!(manifest.data.to_string().contains(r#"default = ["a"]"#));
+    }
+
     #[test]
     fn remove_dependency_no_section() {
         let mut manifest = Manifest {
@@ -616,4 +2430,372 @@ mod tests {
         assert!(old_version_compatible(&bad_version, "1").is_err());
         assert!(old_version_compatible(&good_version, "CAKE CAKE").is_err());
     }
+
+    #[test]
+    fn overrides() {
+        let manifest = Manifest {
+            data: r#"
+[patch.crates-io]
+foo = { path = "../foo" }
+renamed = { path = "../bar", package = "bar" }
+
+[patch."https://github.com/example/baz"]
+baz = { git = "https://github.com/me/baz" }
+
+[replace]
+"qux:0.1.0" = { path = "../qux" }
+"https://github.com/rust-lang/crates.io-index#quux:1.0.0" = { path = "../quux" }
+"#
+            .parse()
+            .unwrap(),
+        };
+        let overrides = manifest.get_overrides();
+        let find = |name| {
+            overrides
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, section)| section.as_str())
+        };
+        assert_eq!(find("foo"), Some("patch.crates-io"));
+        assert_eq!(find("bar"), Some("patch.crates-io"));
+        assert_eq!(find("renamed"), None);
+        assert_eq!(find("baz"), Some("patch.https://github.com/example/baz"));
+        assert_eq!(find("qux"), Some("replace"));
+        assert_eq!(find("quux"), Some("replace"));
+    }
+
+    #[test]
+    fn removes_patches() {
+        let mut manifest = Manifest {
+            data: r#"[dependencies]
+foo = "1.0"
+
+[patch.crates-io]
+foo = { git = "https://github.com/me/foo" }
+renamed = { path = "../bar", package = "bar" }
+
+[patch."https://github.com/example/baz"]
+foo = { git = "https://github.com/me/foo" }
+"#
+            .parse()
+            .unwrap(),
+        };
+        assert_eq!(
+            manifest.remove_patch("foo"),
+            vec!["patch.crates-io", "patch.https://github.com/example/baz"]
+        );
+        assert_eq!(manifest.remove_patch("renamed"), Vec::<String>::new());
+        assert_eq!(manifest.remove_patch("bar"), vec!["patch.crates-io"]);
+        assert!(manifest.data["patch"].is_none());
+        assert_eq!(manifest.data["dependencies"]["foo"].as_str(), Some("1.0"));
+    }
+
+    #[test]
+    fn finds_dependency_spans() {
+        let text = r#"[package]
+name = "demo"
+
+[dependencies]
+serde = "1.0"
+old-rand = { version = "0.7", package = "rand" }
+
+[dependencies.rand]
+features = ["std"]
+version = "0.8"
+
+[target.'cfg(unix)'.dev-dependencies]
+  rand =   "0.8"
+"#;
+        let manifest: Manifest = text.parse().unwrap();
+        let snippets = |name| {
+            manifest
+                .dependency_spans(name)
+                .into_iter()
+                .map(|span| {
+                    let snippet = &text[span.offset..span.offset + span.len];
+                    (snippet.to_owned(), span.start_line, span.start_column)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            snippets("serde"),
+            vec![("serde = \"1.0\"".to_owned(), 5, 1)]
+        );
+        assert_eq!(
+            snippets("rand"),
+            vec![
+                (
+                    "old-rand = { version = \"0.7\", package = \"rand\" }".to_owned(),
+                    6,
+                    1
+                ),
+                ("version = \"0.8\"".to_owned(), 10, 1),
+                ("rand =   \"0.8\"".to_owned(), 13, 3),
+            ]
+        );
+        assert!(snippets("toml").is_empty());
+    }
+
+    #[test]
+    fn requirement_floors() {
+        let floor = |req| requirement_floor(req).map(|v| v.to_string());
+        assert_eq!(floor("0.9"), Some("0.9.0".to_owned()));
+        assert_eq!(floor("^1.2.3"), Some("1.2.3".to_owned()));
+        assert_eq!(floor(">= 0.8, <2"), Some("0.8.0".to_owned()));
+        assert_eq!(floor("1.0.0-alpha.1"), Some("1.0.0-alpha.1".to_owned()));
+        assert_eq!(floor("<2"), None);
+        assert_eq!(floor("*"), None);
+        assert!(requirement_floor("0.9") < requirement_floor("1"));
+    }
+
+    #[test]
+    fn upgrade_keeps_registries_apart() {
+        let mut manifest = LocalManifest {
+            path: PathBuf::from("Cargo.toml"),
+            manifest: r#"
+[dependencies]
+serde = "1.0"
+
+[dev-dependencies]
+serde = { version = "0.9", registry = "internal" }
+"#
+            .parse()
+            .unwrap(),
+            concretize_wildcards: false,
+            precise: false,
+        };
+
+        let internal = Dependency::new("serde")
+            .set_version("0.10.0")
+            .set_registry("internal");
+        let changes = manifest.upgrade_in_memory(&internal, false).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].new.as_deref(), Some("0.10"));
+        assert_eq!(manifest.data["dependencies"]["serde"].as_str(), Some("1.0"));
+
+        let crates_io = Dependency::new("serde").set_version("1.1.0");
+        let changes = manifest.upgrade_in_memory(&crates_io, false).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(manifest.data["dependencies"]["serde"].as_str(), Some("1.1"));
+        let dev = &manifest.data["dev-dependencies"]["serde"];
+        assert_eq!(dev["version"].as_str(), Some("0.10"));
+        assert_eq!(dev["registry"].as_str(), Some("internal"));
+    }
+
+    #[test]
+    fn get_items_inherited_from_the_workspace() {
+        let workspace: Manifest = r#"
+[workspace.package]
+version = "1.2.3"
+authors = ["Someone"]
+
+[workspace.dependencies]
+rand = "0.8"
+serde = { version = "1.0", features = ["derive"] }
+"#
+        .parse()
+        .unwrap();
+        let manifest: Manifest = r#"
+[package]
+name = "member"
+version = { workspace = true }
+authors = { workspace = true }
+
+[dependencies]
+rand = { workspace = true }
+serde = { workspace = true, optional = true, features = ["rc"] }
+"#
+        .parse()
+        .unwrap();
+        let get = |key: &str| {
+            let path: Vec<_> = key.split('.').map(String::from).collect();
+            manifest
+                .get_inherited_item(&path, &workspace)
+                .map(|item| item.as_value().unwrap().to_string().trim().to_owned())
+        };
+
+        assert_eq!(get("package.name").as_deref(), Some(r#""member""#));
+        assert_eq!(get("package.version").as_deref(), Some(r#""1.2.3""#));
+        assert_eq!(get("package.authors").as_deref(), Some(r#"["Someone"]"#));
+        assert_eq!(
+            get("dependencies.rand.version").as_deref(),
+            Some(r#""0.8""#)
+        );
+        assert_eq!(
+            get("dependencies.serde").as_deref(),
+            Some(r#"{ version = "1.0", features = ["derive", "rc"], optional = true }"#)
+        );
+        assert_eq!(get("dependencies.serde.optional").as_deref(), Some("true"));
+        assert_eq!(get("package.license"), None);
+        assert_eq!(get("dependencies.rand.features"), None);
+    }
+
+    #[test]
+    fn select_dependency_entries_by_spec() {
+        let manifest: Manifest = r#"
+[dependencies]
+rand = "0.8"
+rand07 = { package = "rand", version = "0.7.3" }
+serde1 = { package = "serde", version = "1.0" }
+
+[target.'cfg(unix)'.dependencies]
+libc = "0.2"
+"#
+        .parse()
+        .unwrap();
+        let key = |spec| manifest.dependency_key(spec, &["dependencies"]);
+
+        assert_eq!(key("rand").unwrap(), "rand");
+        assert_eq!(key("rand07").unwrap(), "rand07");
+        assert_eq!(key("rand@0.7").unwrap(), "rand07");
+        assert_eq!(key("rand@0.8").unwrap(), "rand");
+        assert_eq!(key("serde").unwrap(), "serde1");
+        assert_eq!(key("libc@0.2").unwrap(), "libc");
+        assert!(key("rand@0.6").is_err());
+        assert!(key("rand@0").is_err());
+        assert!(key("serde1@1").is_err());
+
+        let mut manifest = manifest;
+        let entries = vec!["rand@0.7/std".to_owned(), "serde@1/derive".to_owned()];
+        manifest.add_feature_entries("std", &entries).unwrap();
+        let toml = manifest.data.to_string();
+        assert!(toml.contains(r#"std = ["rand07/std", "serde1/derive"]"#));
+    }
+
+    #[test]
+    fn upgrade_renamed_entry_alone() {
+        let mut manifest = LocalManifest {
+            path: PathBuf::from("Cargo.toml"),
+            manifest: r#"
+[dependencies]
+rand = "0.8"
+rand07 = { package = "rand", version = "0.7.3" }
+"#
+            .parse()
+            .unwrap(),
+            concretize_wildcards: false,
+            precise: false,
+        };
+
+        let rand07 = Dependency::new("rand")
+            .set_version("0.7.4")
+            .set_rename("rand07");
+        let changes = manifest.upgrade_in_memory(&rand07, false).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(manifest.data["dependencies"]["rand"].as_str(), Some("0.8"));
+        let rand07 = &manifest.data["dependencies"]["rand07"];
+        assert_eq!(rand07["version"].as_str(), Some("0.7.4"));
+    }
+
+    #[test]
+    fn upgrade_leaves_wildcards_alone() {
+        let mut manifest = LocalManifest {
+            path: PathBuf::from("Cargo.toml"),
+            manifest: r#"
+[dependencies]
+serde = "*"
+
+[dev-dependencies]
+serde = "0.9"
+"#
+            .parse()
+            .unwrap(),
+            concretize_wildcards: false,
+            precise: false,
+        };
+
+        let serde = Dependency::new("serde").set_version("1.0.130");
+        let changes = manifest.upgrade_in_memory(&serde, false).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(manifest.data["dependencies"]["serde"].as_str(), Some("*"));
+
+        manifest.concretize_wildcards = true;
+        let changes = manifest.upgrade_in_memory(&serde, false).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            manifest.data["dependencies"]["serde"].as_str(),
+            Some("1.0.130")
+        );
+    }
+
+    #[test]
+    fn upgrade_to_precise_versions() {
+        let mut manifest = LocalManifest {
+            path: PathBuf::from("Cargo.toml"),
+            manifest: r#"
+[dependencies]
+serde = "*"
+rand = { version = "0.7", features = ["small_rng"] }
+
+[dev-dependencies]
+serde = "1.0.130"
+"#
+            .parse()
+            .unwrap(),
+            concretize_wildcards: false,
+            precise: true,
+        };
+
+        let serde = Dependency::new("serde").set_version("1.0.130");
+        let changes = manifest.upgrade_in_memory(&serde, false).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(
+            manifest.data["dependencies"]["serde"].as_str(),
+            Some("=1.0.130")
+        );
+        assert_eq!(
+            manifest.data["dev-dependencies"]["serde"].as_str(),
+            Some("=1.0.130")
+        );
+
+        let rand = Dependency::new("rand").set_version("0.8.4");
+        manifest.upgrade_in_memory(&rand, false).unwrap();
+        assert_eq!(
+            manifest.data["dependencies"]["rand"]["version"].as_str(),
+            Some("=0.8.4")
+        );
+
+        let partial = Dependency::new("rand").set_version("0.8");
+        assert!(manifest.upgrade_in_memory(&partial, false).is_err());
+    }
+
+    #[test]
+    fn sets_requirements() {
+        let mut manifest = LocalManifest {
+            path: PathBuf::from("Cargo.toml"),
+            manifest: r#"
+[dependencies]
+serde = "1.0"
+internal = { package = "serde", version = "0.9", registry = "internal" }
+"#
+            .parse()
+            .unwrap(),
+            concretize_wildcards: false,
+            precise: false,
+        };
+        let dependencies = ["dependencies".to_owned()];
+
+        let change = manifest
+            .set_requirement(&dependencies, "internal", "=0.9.3")
+            .unwrap()
+            .unwrap();
+        assert_eq!(change.name, "serde");
+        assert_eq!(change.old.as_deref(), Some("0.9"));
+        let internal = &manifest.data["dependencies"]["internal"];
+        assert_eq!(internal["version"].as_str(), Some("=0.9.3"));
+        assert_eq!(internal["registry"].as_str(), Some("internal"));
+
+        let change = manifest.set_requirement(&dependencies, "serde", "=1.0.130");
+        assert!(change.unwrap().is_some());
+        assert_eq!(
+            manifest.data["dependencies"]["serde"].as_str(),
+            Some("=1.0.130")
+        );
+        let unchanged = manifest.set_requirement(&dependencies, "serde", "=1.0.130");
+        assert!(unchanged.unwrap().is_none());
+        assert!(manifest
+            .set_requirement(&dependencies, "missing", "1.0")
+            .is_err());
+    }
 }
@@ -1,6 +1,9 @@
 use crate::errors::*;
-use cargo_metadata::Package;
+use crate::manifest::Manifest;
+use cargo_metadata::{Metadata, Package};
 use failure::Fail;
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
 
 /// Takes a pkgid and attempts to find the path to it's `Cargo.toml`, using `cargo`'s metadata
 pub fn manifest_from_pkgid(pkgid: &str) -> Result<Package> {
@@ -19,3 +22,54 @@ pub fn manifest_from_pkgid(pkgid: &str) -> Result<Package> {
         })?;
     Ok(package)
 }
+
+/// The members of a workspace to operate on when acting on the whole workspace. Like cargo, only
+/// the `default-members` of the root manifest are included unless `all_members` is set, and
+/// packages under an `exclude`d path never are.
+pub fn workspace_members(metadata: &Metadata, all_members: bool) -> Result<Vec<Package>> {
+    let root = &metadata.workspace_root;
+    let manifest = Manifest::open(&Some(root.join("Cargo.toml")))?;
+    let paths = |key: &str| -> Vec<PathBuf> {
+        manifest.data["workspace"][key]
+            .as_array()
+            .map(|paths| {
+                paths
+                    .iter()
+                    .filter_map(|path| path.as_str())
+                    .map(|path| normalize(&root.join(path)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let default_members = paths("default-members");
+    let excluded = paths("exclude");
+
+    let members: HashSet<_> = metadata.workspace_members.iter().collect();
+    Ok(metadata
+        .packages
+        .iter()
+        .filter(|package| members.contains(&package.id))
+        .filter(|package| {
+            let dir = normalize(package.manifest_path.parent().unwrap_or(root));
+            let is_default =
+                all_members || default_members.is_empty() || default_members.contains(&dir);
+            is_default && !excluded.iter().any(|path| dir.starts_with(path))
+        })
+        .cloned()
+        .collect())
+}
+
+/// Resolve `.` and `..` in a path without touching the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
@@ -0,0 +1,134 @@
+use crate::errors::*;
+use crate::registry::net_settings;
+use crate::tls::TlsConfig;
+use std::env;
+use std::path::Path;
+
+/// How many times cargo retries a failed network request, unless `net.retry` says otherwise
+const DEFAULT_RETRY: u32 = 3;
+
+/// Network settings, as cargo reads them from its config
+///
+/// Everything that accesses the network takes these, so settings given on the command line (e.g.
+/// a client certificate) reach it without going through the environment.
+///
+/// ref: https://doc.rust-lang.org/cargo/reference/config.html#net
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetConfig {
+    /// Whether to work without accessing the network
+    pub offline: bool,
+    /// Whether `Cargo.lock` must be left as it is, as with cargo's `--frozen`
+    pub frozen: bool,
+    /// How many times to retry a request that failed for a reason that may be temporary
+    pub retry: u32,
+    /// The certificates used for HTTPS
+    pub tls: TlsConfig,
+    /// Whether to check what the crates.io API reports about versions against the registry
+    /// index, which is what cargo trusts when downloading
+    pub verify_api: bool,
+}
+
+impl Default for NetConfig {
+    fn default() -> Self {
+        NetConfig {
+            offline: false,
+            frozen: false,
+            retry: DEFAULT_RETRY,
+            tls: TlsConfig::default(),
+            verify_api: true,
+        }
+    }
+}
+
+impl NetConfig {
+    /// Read the settings that apply to a directory
+    ///
+    /// They are taken from `CARGO_NET_OFFLINE` and `CARGO_NET_RETRY`, or else `net.offline` and
+    /// `net.retry` in the cargo config, like cargo does. The TLS settings are read with
+    /// `TlsConfig::from_env`.
+    pub fn from_env(dir: &Path) -> Result<Self> {
+        let (offline, retry) = net_settings(dir)?;
+        let offline = match env::var("CARGO_NET_OFFLINE") {
+            Ok(offline) => parse_env("CARGO_NET_OFFLINE", &offline)?,
+            Err(_) => offline.unwrap_or(false),
+        };
+        let retry = match env::var("CARGO_NET_RETRY") {
+            Ok(retry) => parse_env("CARGO_NET_RETRY", &retry)?,
+            Err(_) => retry.unwrap_or(DEFAULT_RETRY),
+        };
+        Ok(NetConfig {
+            offline,
+            frozen: false,
+            retry,
+            tls: TlsConfig::from_env(dir)?,
+            verify_api: true,
+        })
+    }
+
+    /// Read the settings that apply to the current directory
+    pub fn from_current_dir() -> Result<Self> {
+        Self::from_env(&env::current_dir()?)
+    }
+
+    /// Apply cargo's `--offline` and `--frozen` flags: both keep cargo-edit and the cargo
+    /// commands it runs off the network, and `--frozen` also keeps them from changing
+    /// `Cargo.lock`.
+    pub fn with_cargo_flags(mut self, offline: bool, frozen: bool) -> Self {
+        self.offline |= offline || frozen;
+        self.frozen |= frozen;
+        self
+    }
+
+    /// The flags to pass on to the cargo commands that are run, e.g. `cargo metadata`
+    pub fn cargo_flags(&self) -> Vec<String> {
+        if self.frozen {
+            vec!["--frozen".to_owned()]
+        } else if self.offline {
+            vec!["--offline".to_owned()]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+fn parse_env<T: std::str::FromStr>(name: &str, value: &str) -> Result<T> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid value `{}` for `{}`", value, name).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn cargo_flags() {
+        let net = NetConfig::default();
+        assert!(net.cargo_flags().is_empty());
+        assert_eq!(
+            net.clone().with_cargo_flags(true, false).cargo_flags(),
+            ["--offline"]
+        );
+        let frozen = net.with_cargo_flags(false, true);
+        assert!(frozen.offline);
+        assert_eq!(frozen.cargo_flags(), ["--frozen"]);
+    }
+
+    #[test]
+    fn settings_from_cargo_config() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(".cargo")).unwrap();
+        fs::write(
+            dir.path().join(".cargo/config.toml"),
+            "[net]\noffline = true\nretry = 5\n",
+        )
+        .unwrap();
+        let member = dir.path().join("member");
+        fs::create_dir_all(member.join(".cargo")).unwrap();
+        fs::write(member.join(".cargo/config.toml"), "[net]\nretry = 1\n").unwrap();
+
+        assert_eq!(net_settings(&member).unwrap(), (Some(true), Some(1)));
+    }
+}
@@ -0,0 +1,377 @@
+//! The registry lookups as futures, for embedding cargo-edit in services, with the `async`
+//! feature.
+//!
+//! These run the synchronous lookups of the crate root, like `get_latest_dependency`, which read
+//! the index and call the registry's API with blocking I/O, on a small pool of threads shared by
+//! the whole process, so an executor's threads are never blocked and no thread is spawned per
+//! lookup. The futures work with any executor.
+//!
+//! Everything a lookup needs is passed to it, so concurrent lookups with different settings
+//! don't interfere. A lookup that panics resolves to an error instead of taking its thread down.
+//!
+//! ```
+//! # #[cfg(feature = "testing")] {
+//! use cargo_edit::nonblocking::{block_on, get_latest_dependency};
+//! use cargo_edit::testing::{MockRegistry, MockVersion, TempManifest};
+//!
+//! let registry = MockRegistry::new().unwrap();
+//! registry.publish(&MockVersion::new("my-crate", "0.2.0")).unwrap();
+//! let manifest = TempManifest::new("[package]\nname = \"demo\"\nversion = \"0.1.0\"\n").unwrap();
+//!
+//! let lookup = get_latest_dependency(
+//!     "my-crate",
+//!     false,
+//!     manifest.path().to_owned(),
+//!     Some(registry.source()),
+//! );
+//! assert_eq!(block_on(lookup).unwrap().version(), Some("0.2.0"));
+//! # }
+//! ```
+
+use crate::dependency::Dependency;
+use crate::errors::*;
+use crate::fetch::{self, CrateVersions, Releases};
+use crate::manifest::{LocalManifest, VersionChange};
+use crate::net::NetConfig;
+use crate::registry::{registry_source, RegistrySource};
+use semver::{Version, VersionReq};
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread;
+
+/// The most lookups that run at once
+const MAX_WORKERS: usize = 8;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// The queue of the shared pool, whose threads are started on the first lookup
+fn pool() -> &'static Mutex<Sender<Job>> {
+    static POOL: OnceLock<Mutex<Sender<Job>>> = OnceLock::new();
+    POOL.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = thread::available_parallelism()
+            .map_or(2, |n| n.get())
+            .clamp(2, MAX_WORKERS);
+        for i in 0..workers {
+            let receiver = Arc::clone(&receiver);
+            thread::Builder::new()
+                .name(format!("cargo-edit-lookup-{}", i))
+                .spawn(move || loop {
+                    let job = match receiver.lock().unwrap().recv() {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    job();
+                })
+                .expect("Failed to start a lookup thread");
+        }
+        Mutex::new(sender)
+    })
+}
+
+#[derive(Debug)]
+struct State<T> {
+    result: Option<Result<T>>,
+    waker: Option<Waker>,
+}
+
+/// A lookup running on the shared pool, which resolves to its result.
+///
+/// The lookup starts as soon as this is created, so several lookups run concurrently even when
+/// they are awaited one after another.
+#[derive(Debug)]
+pub struct Lookup<T> {
+    state: Arc<Mutex<State<T>>>,
+}
+
+impl<T: Send + 'static> Lookup<T> {
+    fn spawn(lookup: impl FnOnce() -> Result<T> + Send + 'static) -> Self {
+        let state = Arc::new(Mutex::new(State {
+            result: None,
+            waker: None,
+        }));
+        let shared = Arc::clone(&state);
+        let job: Job = Box::new(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(lookup)).unwrap_or_else(|panic| {
+                let message = panic
+                    .downcast_ref::<&str>()
+                    .map(|message| (*message).to_owned())
+                    .or_else(|| panic.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                Err(ErrorKind::LookupPanicked(message).into())
+            });
+            let mut state = shared.lock().unwrap();
+            state.result = Some(result);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+        pool()
+            .lock()
+            .unwrap()
+            .send(job)
+            .expect("The lookup threads have stopped");
+        Lookup { state }
+    }
+}
+
+impl<T> Future for Lookup<T> {
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<T>> {
+        let mut state = self.state.lock().unwrap();
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Run a future to completion on the current thread.
+///
+/// This serves callers that have no executor but want to use `plan_upgrades`.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    struct ThreadWaker(thread::Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let mut future = Box::pin(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        thread::park();
+    }
+}
+
+/// Query the latest version of a crate, as `get_latest_dependency` does
+pub fn get_latest_dependency(
+    crate_name: &str,
+    flag_allow_prerelease: bool,
+    manifest_path: PathBuf,
+    registry: Option<RegistrySource>,
+) -> Lookup<Dependency> {
+    let crate_name = crate_name.to_owned();
+    Lookup::spawn(move || {
+        fetch::get_latest_dependency(
+            &crate_name,
+            flag_allow_prerelease,
+            &manifest_path,
+            &registry,
+        )
+    })
+}
+
+/// Query all versions of a crate, as `get_crate_versions` does
+pub fn get_crate_versions(
+    crate_name: &str,
+    manifest_path: PathBuf,
+    registry: Option<RegistrySource>,
+) -> Lookup<CrateVersions> {
+    let crate_name = crate_name.to_owned();
+    Lookup::spawn(move || fetch::get_crate_versions(&crate_name, &manifest_path, &registry))
+}
+
+/// Query the latest version of a crate that a requirement matches, as `get_latest_matching` does
+pub fn get_latest_matching(
+    crate_name: &str,
+    requirement: VersionReq,
+    manifest_path: PathBuf,
+    registry: Option<RegistrySource>,
+) -> Lookup<Option<Version>> {
+    let crate_name = crate_name.to_owned();
+    Lookup::spawn(move || {
+        fetch::get_latest_matching(&crate_name, &requirement, &manifest_path, &registry)
+    })
+}
+
+/// Query whether every published version of a crate has been yanked, as `is_fully_yanked` does
+pub fn is_fully_yanked(
+    crate_name: &str,
+    manifest_path: PathBuf,
+    registry: Option<RegistrySource>,
+) -> Lookup<bool> {
+    let crate_name = crate_name.to_owned();
+    Lookup::spawn(move || fetch::is_fully_yanked(&crate_name, &manifest_path, &registry))
+}
+
+/// Query the features a version of a crate has, as `get_features` does
+pub fn get_features(
+    crate_name: &str,
+    version: Version,
+    manifest_path: PathBuf,
+    registry: Option<RegistrySource>,
+) -> Lookup<Option<BTreeSet<String>>> {
+    let crate_name = crate_name.to_owned();
+    Lookup::spawn(move || fetch::get_features(&crate_name, &version, &manifest_path, &registry))
+}
+
+/// Query who published each version of a crate and under which license, as `get_releases` does
+pub fn get_releases(
+    crate_name: &str,
+    manifest_path: PathBuf,
+    registry: Option<RegistrySource>,
+    net: NetConfig,
+) -> Lookup<Option<Releases>> {
+    let crate_name = crate_name.to_owned();
+    Lookup::spawn(move || fetch::get_releases(&crate_name, &manifest_path, &registry, &net))
+}
+
+/// The changes that upgrading every registry dependency of a manifest to its latest version
+/// would make, like `cargo upgrade --dry-run`.
+///
+/// Dependencies on git repositories or paths are left alone, and those naming an alternative
+/// registry are looked up in it rather than in `registry`. The latest versions are looked up
+/// concurrently, and nothing is written to disk.
+pub async fn plan_upgrades(
+    manifest_path: PathBuf,
+    allow_prerelease: bool,
+    registry: Option<RegistrySource>,
+) -> Result<Vec<VersionChange>> {
+    let mut manifest = LocalManifest::try_new(&manifest_path)?;
+
+    let mut deps = BTreeSet::new();
+    for (_, table) in manifest.get_sections() {
+        let table = table.as_table_like().expect("Unexpected non-table");
+        for (name, dep) in table.iter() {
+            if dep.is_str() {
+                deps.insert((name.to_owned(), None));
+            } else if let Some(dep) = dep.as_table_like() {
+                if dep.get("git").is_none() && dep.get("path").is_none() {
+                    let package = dep.get("package").and_then(|p| p.as_str());
+                    let alternative = dep.get("registry").and_then(|r| r.as_str());
+                    deps.insert((
+                        package.unwrap_or(name).to_owned(),
+                        alternative.map(ToOwned::to_owned),
+                    ));
+                }
+            }
+        }
+    }
+
+    let mut lookups = Vec::new();
+    for (name, alternative) in deps {
+        let source = match &alternative {
+            Some(alternative) => Some(registry_source(&manifest_path, Some(alternative))?),
+            None => registry.clone(),
+        };
+        let lookup = get_latest_dependency(&name, allow_prerelease, manifest_path.clone(), source);
+        lookups.push((lookup, alternative));
+    }
+
+    let mut changes = Vec::new();
+    for (lookup, alternative) in lookups {
+        let mut dependency = lookup.await?;
+        if let Some(alternative) = &alternative {
+            dependency = dependency.set_registry(alternative);
+        }
+        changes.extend(manifest.upgrade_in_memory(&dependency, false)?);
+    }
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockRegistry, MockVersion, TempManifest};
+
+    #[test]
+    fn looks_up_concurrently() {
+        let registry = MockRegistry::new().unwrap();
+        for (name, version) in &[("one", "1.0.0"), ("two", "2.0.0"), ("two", "2.1.0")] {
+            registry.publish(&MockVersion::new(name, version)).unwrap();
+        }
+        let manifest =
+            TempManifest::new("[package]\nname = \"demo\"\nversion = \"0.1.0\"\n").unwrap();
+        let path = manifest.path().to_owned();
+        let source = Some(registry.source());
+
+        let lookups: Vec<_> = ["one", "two", "three"]
+            .iter()
+            .map(|name| get_latest_dependency(name, false, path.clone(), source.clone()))
+            .collect();
+        let versions: Vec<_> = lookups
+            .into_iter()
+            .map(|lookup| {
+                block_on(lookup)
+                    .ok()
+                    .map(|dep| dep.version().unwrap().to_owned())
+            })
+            .collect();
+        assert_eq!(
+            versions,
+            vec![Some("1.0.0".to_owned()), Some("2.1.0".to_owned()), None]
+        );
+
+        let requirement = VersionReq::parse("<2.1").unwrap();
+        let matching = block_on(get_latest_matching("two", requirement, path, source)).unwrap();
+        assert_eq!(matching, Some(Version::parse("2.0.0").unwrap()));
+    }
+
+    #[test]
+    fn panicking_lookups_fail() {
+        let lookups: Vec<Lookup<()>> = (0..MAX_WORKERS * 2)
+            .map(|_| Lookup::spawn(|| panic!("no index")))
+            .collect();
+        for lookup in lookups {
+            match block_on(lookup) {
+                Err(Error(ErrorKind::LookupPanicked(message), _)) => {
+                    assert_eq!(message, "no index")
+                }
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        // The pool still has its threads
+        assert_eq!(block_on(Lookup::spawn(|| Ok(1))).unwrap(), 1);
+    }
+
+    #[test]
+    fn plans_upgrades() {
+        let registry = MockRegistry::new().unwrap();
+        registry.publish(&MockVersion::new("one", "1.2.0")).unwrap();
+        registry.publish(&MockVersion::new("two", "0.3.0")).unwrap();
+        let manifest = TempManifest::new(
+            r#"[package]
+name = "demo"
+version = "0.1.0"
+
+[dependencies]
+one = "1.0"
+renamed = { package = "two", version = "0.2" }
+local = { path = "../local" }
+"#,
+        )
+        .unwrap();
+        let before = manifest.read().unwrap();
+
+        let changes = block_on(plan_upgrades(
+            manifest.path().to_owned(),
+            false,
+            Some(registry.source()),
+        ))
+        .unwrap();
+        let changes: Vec<_> = changes
+            .iter()
+            .map(|c| (c.name.as_str(), c.new.as_deref()))
+            .collect();
+        assert_eq!(changes, vec![("one", Some("1.2")), ("two", Some("0.3"))]);
+        assert_eq!(manifest.read().unwrap(), before);
+    }
+}
@@ -0,0 +1,352 @@
+//! Pinning dependencies to the exact versions they resolve to and turning them back into caret
+//! requirements, for `cargo pin` and `cargo unpin`.
+
+use crate::edits::Edits;
+use crate::errors::*;
+use crate::fetch::{get_latest_matching, update_registry_index};
+use crate::journal::JournaledFile;
+use crate::lockfile::Lockfile;
+use crate::manifest::{find, LocalManifest};
+use crate::metadata::workspace_members;
+use crate::net::NetConfig;
+use crate::registry::{registry_source, RegistrySource};
+use failure::Fail;
+use semver::{Version, VersionReq};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+use url::Url;
+
+/// Which way `pin_dependencies` changes requirements
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinDirection {
+    /// Replace registry dependencies' requirements by exact `=x.y.z` requirements on the
+    /// versions they resolve to
+    Pin,
+    /// Replace exact `=x.y.z` requirements by the caret requirements `x.y.z`
+    Unpin,
+}
+
+impl PinDirection {
+    /// What is printed for each changed requirement
+    fn verb(self) -> &'static str {
+        match self {
+            PinDirection::Pin => "Pinning",
+            PinDirection::Unpin => "Unpinning",
+        }
+    }
+}
+
+/// The manifests and dependencies `pin_dependencies` changes
+#[derive(Debug, Clone)]
+pub struct PinOptions {
+    /// Dependencies to change, by entry or crate name (all of them if empty)
+    pub dependencies: Vec<String>,
+    /// The manifest to change, or the one in the current directory
+    pub manifest_path: Option<PathBuf>,
+    /// The package in the workspace to change instead
+    pub pkgid: Option<String>,
+    /// Change all packages in the workspace, as is also done for a virtual manifest
+    pub workspace: bool,
+    /// Include workspace members left out of the workspace's `default-members`
+    pub all_members: bool,
+    /// Print the changes without making them
+    pub dry_run: bool,
+    /// How the network is accessed, and the flags passed to cargo
+    pub net: NetConfig,
+}
+
+/// A dependency in a manifest with a version requirement.
+#[derive(Debug)]
+struct Entry {
+    table_path: Vec<String>,
+    /// The name of the entry in its table, which differs from the crate's if it is renamed.
+    key: String,
+    name: String,
+    requirement: String,
+    /// The alternative registry the crate is from, if any.
+    registry: Option<String>,
+}
+
+/// The dependencies of a manifest with a version requirement that `direction` applies to, which
+/// `selected` names by entry or crate name unless it is empty. Only registry dependencies are
+/// pinned.
+fn entries(manifest: &LocalManifest, selected: &[String], direction: PinDirection) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    for (table_path, table) in manifest.get_sections() {
+        let table = table.as_table_like().expect("Unexpected non-table");
+        for (key, item) in table.iter() {
+            let (name, requirement, registry) = match item.as_table_like() {
+                Some(t)
+                    if direction == PinDirection::Pin
+                        && (t.get("git").is_some() || t.get("path").is_some()) =>
+                {
+                    continue
+                }
+                Some(t) => (
+                    t.get("package").and_then(|p| p.as_str()).unwrap_or(key),
+                    t.get("version").and_then(|v| v.as_str()),
+                    t.get("registry").and_then(|r| r.as_str()),
+                ),
+                None => (key, item.as_str(), None),
+            };
+            let requirement = match requirement {
+                Some(requirement) => requirement,
+                None => continue,
+            };
+            if selected.is_empty() || selected.iter().any(|s| s == key || s == name) {
+                entries.push(Entry {
+                    table_path: table_path.clone(),
+                    key: key.to_owned(),
+                    name: name.to_owned(),
+                    requirement: requirement.to_owned(),
+                    registry: registry.map(ToOwned::to_owned),
+                });
+            }
+        }
+    }
+    entries
+}
+
+/// The caret requirement for a requirement on a single exact version, e.g. `1.2.3` for `=1.2.3`.
+fn unpinned(requirement: &str) -> Option<&str> {
+    let version = requirement.trim().strip_prefix('=')?.trim();
+    let is_version = !version.is_empty()
+        && version
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || ".-+".contains(c));
+    if is_version {
+        Some(version)
+    } else {
+        None
+    }
+}
+
+/// Looks versions up in registries, updating each registry's index once.
+struct Registries<'a> {
+    net: &'a NetConfig,
+    updated: HashSet<Url>,
+}
+
+impl Registries<'_> {
+    /// The newest version of a crate in its registry that a requirement matches.
+    fn latest_matching(
+        &mut self,
+        entry: &Entry,
+        requirement: &VersionReq,
+        manifest_path: &Path,
+    ) -> Result<Version> {
+        let source = registry_source(manifest_path, entry.registry.as_deref())?;
+        if let RegistrySource::Remote(url) = &source {
+            let testing = std::env::var("CARGO_IS_TEST").is_ok();
+            if !self.net.offline && !testing && self.updated.insert(url.clone()) {
+                if let Err(err) = update_registry_index(url, false, self.net) {
+                    eprintln!(
+                        "WARN: Failed to update the registry index, its local copy may be out \
+                         of date: {}",
+                        err
+                    );
+                }
+            }
+        }
+        get_latest_matching(&entry.name, requirement, manifest_path, &Some(source))?.chain_err(
+            || {
+                format!(
+                    "No version of `{}` matches the requirement `{}`",
+                    entry.name, entry.requirement
+                )
+            },
+        )
+    }
+}
+
+/// The exact requirement on the version a dependency resolves to: the newest in `Cargo.lock` that
+/// its requirement matches, or else the newest in its registry, as a fresh resolve would pick.
+fn pinned(
+    entry: &Entry,
+    locked: &Lockfile,
+    registries: &mut Registries<'_>,
+    manifest_path: &Path,
+) -> Result<String> {
+    // Read as cargo does, where `1.0` means `^1.0` rather than `~1.0`.
+    let requirement = entry.requirement.trim();
+    let requirement = if requirement.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("^{}", requirement)
+    } else {
+        requirement.to_owned()
+    };
+    let requirement = VersionReq::parse(&requirement).chain_err(|| {
+        format!(
+            "Invalid requirement `{}` for `{}`",
+            entry.requirement, entry.name
+        )
+    })?;
+    let locked = locked
+        .package
+        .iter()
+        .filter(|package| {
+            package.name == entry.name
+                && package.source.is_some()
+                && package.is_from_crates_io() == entry.registry.is_none()
+                && requirement.matches(&package.version)
+        })
+        .map(|package| package.version.clone())
+        .max();
+    let version = match locked {
+        Some(version) => version,
+        None => registries.latest_matching(entry, &requirement, manifest_path)?,
+    };
+    Ok(format!("={}", version))
+}
+
+fn dry_run_message() -> Result<()> {
+    let colorchoice = if atty::is(atty::Stream::Stdout) {
+        ColorChoice::Auto
+    } else {
+        ColorChoice::Never
+    };
+    let mut output = StandardStream::stdout(colorchoice);
+    output.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bold(true))?;
+    write!(output, "Starting dry run. ")?;
+    output.reset()?;
+    writeln!(output, "Changes will not be saved.")?;
+    Ok(())
+}
+
+fn print_msg(verb: &str, name: &str, old: &str, new: &str) -> Result<()> {
+    let colorchoice = if atty::is(atty::Stream::Stdout) {
+        ColorChoice::Auto
+    } else {
+        ColorChoice::Never
+    };
+    let mut output = StandardStream::stdout(colorchoice);
+    output.set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))?;
+    write!(output, "{:>12}", verb)?;
+    output.reset()?;
+    writeln!(output, " {} {} -> {}", name, old, new)?;
+    Ok(())
+}
+
+/// Pin or unpin the dependencies of the packages that `options` selects, as `cargo pin` and
+/// `cargo unpin` do, printing each requirement that is changed. Returns the manifests written,
+/// e.g. for `record_for_undo`.
+///
+/// A dependency that is already as `direction` would leave it is left alone. Unpinning only
+/// changes requirements on a single exact version, and looks nothing up.
+pub fn pin_dependencies(
+    direction: PinDirection,
+    options: &PinOptions,
+) -> Result<Vec<JournaledFile>> {
+    let mut cmd = cargo_metadata::MetadataCommand::new();
+    cmd.no_deps();
+    cmd.other_options(options.net.cargo_flags());
+    if let Some(path) = &options.manifest_path {
+        cmd.manifest_path(path);
+    }
+    let metadata = cmd
+        .exec()
+        .map_err(|e| Error::from(e.compat()).chain_err(|| "Failed to get workspace metadata"))?;
+    let members: HashSet<_> = metadata.workspace_members.iter().collect();
+
+    let packages = metadata
+        .packages
+        .iter()
+        .filter(|package| members.contains(&package.id));
+    let workspace = options.workspace
+        || (options.pkgid.is_none() && LocalManifest::find(&options.manifest_path)?.is_virtual());
+    let packages: Vec<_> = if workspace {
+        workspace_members(&metadata, options.all_members)?
+    } else if let Some(pkgid) = &options.pkgid {
+        vec![packages
+            .clone()
+            .find(|package| &package.name == pkgid)
+            .chain_err(|| format!("Package `{}` not found in the workspace", pkgid))?
+            .clone()]
+    } else {
+        let manifest_path = fs::canonicalize(find(&options.manifest_path)?)?;
+        vec![packages
+            .clone()
+            .find(|package| package.manifest_path == manifest_path)
+            // If we have successfully got metadata, but our manifest path does not correspond to a
+            // package, we must have been called against a virtual manifest.
+            .chain_err(|| {
+                "Found virtual manifest, but this command requires running against an \
+                 actual package in this workspace. Try adding `--workspace`."
+            })?
+            .clone()]
+    };
+
+    let mut manifests = Vec::new();
+    for package in packages {
+        let manifest = LocalManifest::try_new(Path::new(&package.manifest_path))?;
+        let entries = entries(&manifest, &options.dependencies, direction);
+        manifests.push((manifest, entries));
+    }
+    for name in &options.dependencies {
+        let found = manifests
+            .iter()
+            .flat_map(|(_, entries)| entries)
+            .any(|entry| &entry.key == name || &entry.name == name);
+        if !found {
+            return Err(format!("The dependency `{}` could not be found", name).into());
+        }
+    }
+
+    let locked = match direction {
+        PinDirection::Pin => {
+            Lockfile::read_or_default(&metadata.workspace_root.join("Cargo.lock"))?
+        }
+        PinDirection::Unpin => Lockfile::default(),
+    };
+    let mut registries = Registries {
+        net: &options.net,
+        updated: HashSet::new(),
+    };
+    if options.dry_run {
+        dry_run_message()?;
+    }
+    let mut edits = Edits::new();
+    for (mut manifest, entries) in manifests {
+        let mut changed = false;
+        for entry in entries {
+            let requirement = match direction {
+                PinDirection::Pin => pinned(&entry, &locked, &mut registries, &manifest.path)?,
+                PinDirection::Unpin => match unpinned(&entry.requirement) {
+                    Some(caret) => caret.to_owned(),
+                    None => continue,
+                },
+            };
+            if let Some(change) =
+                manifest.set_requirement(&entry.table_path, &entry.key, &requirement)?
+            {
+                print_msg(
+                    direction.verb(),
+                    &change.name,
+                    &entry.requirement,
+                    &requirement,
+                )?;
+                changed = true;
+            }
+        }
+        if changed && !options.dry_run {
+            edits.save(&manifest, &manifest.path)?;
+        }
+    }
+    edits.write()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_exact_requirements_are_unpinned() {
+        assert_eq!(unpinned("=1.2.3"), Some("1.2.3"));
+        assert_eq!(unpinned(" = 1.0.0-beta.1 "), Some("1.0.0-beta.1"));
+        assert_eq!(unpinned("1.2.3"), None);
+        assert_eq!(unpinned("=1.2, <1.5"), None);
+        assert_eq!(unpinned("="), None);
+    }
+}
@@ -0,0 +1,198 @@
+//! Organization policies on which crates, and which versions of them, may be depended on.
+//!
+//! A policy file is TOML, with a table for each crate it restricts:
+//!
+//! ```toml
+//! [crates.openssl]
+//! banned = true
+//! reason = "use rustls instead"
+//!
+//! [crates.time]
+//! minimum = "0.2.23"
+//! allowed = "<0.3"
+//! ```
+
+use crate::errors::*;
+use crate::fetch::get_latest_matching;
+use crate::registry::RegistrySource;
+use semver::{Version, VersionReq};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Environment variable naming the policy file to enforce
+pub const POLICY_ENV: &str = "CARGO_EDIT_POLICY";
+
+/// What a policy says about a crate
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CratePolicy {
+    /// The crate may not be depended on at all
+    #[serde(default)]
+    pub banned: bool,
+    /// Why, shown when the policy is violated
+    pub reason: Option<String>,
+    /// The oldest version that may be depended on
+    pub minimum: Option<Version>,
+    /// The versions that may be depended on
+    pub allowed: Option<VersionReq>,
+}
+
+/// Restrictions on dependencies, by crate name
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    /// The restrictions on each crate
+    #[serde(default)]
+    pub crates: BTreeMap<String, CratePolicy>,
+}
+
+impl Policy {
+    /// Read a policy file
+    pub fn open(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .chain_err(|| format!("Failed to read the policy file `{}`", path.display()))?;
+        toml::from_str(&contents)
+            .chain_err(|| format!("Failed to parse the policy file `{}`", path.display()))
+    }
+
+    /// Read the policy file at `path` if one is given, and otherwise enforce no policy
+    pub fn from_path(path: Option<&Path>) -> Result<Self> {
+        path.map_or_else(|| Ok(Policy::default()), Policy::open)
+    }
+
+    fn violation(&self, name: &str, problem: String) -> Error {
+        let reason = match self.crates.get(name).and_then(|c| c.reason.as_ref()) {
+            Some(reason) => format!("{} ({})", problem, reason),
+            None => problem,
+        };
+        ErrorKind::PolicyViolation(name.to_owned(), reason).into()
+    }
+
+    /// Check that a crate isn't banned
+    pub fn check_crate(&self, name: &str) -> Result<()> {
+        if self.crates.get(name).is_some_and(|c| c.banned) {
+            return Err(self.violation(name, "the crate is banned".to_owned()));
+        }
+        Ok(())
+    }
+
+    /// Check that a crate may be depended on at a version
+    pub fn check_version(&self, name: &str, version: &Version) -> Result<()> {
+        self.check_crate(name)?;
+        let policy = match self.crates.get(name) {
+            Some(policy) => policy,
+            None => return Ok(()),
+        };
+        if let Some(minimum) = policy.minimum.as_ref().filter(|min| version < *min) {
+            let problem = format!("{} is older than the minimum of {}", version, minimum);
+            return Err(self.violation(name, problem));
+        }
+        if let Some(allowed) = policy.allowed.as_ref().filter(|req| !req.matches(version)) {
+            let problem = format!("{} is outside the allowed range `{}`", version, allowed);
+            return Err(self.violation(name, problem));
+        }
+        Ok(())
+    }
+
+    /// The latest version of a crate, up to `latest`, that may be depended on
+    ///
+    /// The registry argument is used as for `get_latest_dependency`, to find a version in the
+    /// allowed range if `latest` is outside it.
+    pub fn clamp(
+        &self,
+        name: &str,
+        latest: &Version,
+        manifest_path: &Path,
+        registry: &Option<RegistrySource>,
+    ) -> Result<Version> {
+        let allowed = match self.crates.get(name).and_then(|c| c.allowed.as_ref()) {
+            Some(allowed) if !allowed.matches(latest) => allowed,
+            _ => {
+                self.check_version(name, latest)?;
+                return Ok(latest.clone());
+            }
+        };
+
+        let requirement = VersionReq::parse(&format!("{}, <={}", allowed, latest))
+            .chain_err(|| format!("Invalid allowed range `{}` for `{}`", allowed, name))?;
+        let version = get_latest_matching(name, &requirement, manifest_path, registry)?
+            .ok_or_else(|| {
+                let problem = format!(
+                    "no version up to {} is in the allowed range `{}`",
+                    latest, allowed
+                );
+                self.violation(name, problem)
+            })?;
+        self.check_version(name, &version)?;
+        Ok(version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockRegistry, MockVersion, TempManifest};
+
+    fn policy() -> Policy {
+        toml::from_str(
+            r#"
+[crates.openssl]
+banned = true
+reason = "use rustls instead"
+
+[crates.time]
+minimum = "0.2.23"
+allowed = "<0.3"
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn checks_crates_and_versions() {
+        let policy = policy();
+        let version = |v: &str| Version::parse(v).unwrap();
+
+        let err = policy.check_crate("openssl").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Policy violation for `openssl`: the crate is banned (use rustls instead)"
+        );
+        assert!(policy.check_crate("time").is_ok());
+        assert!(policy.check_version("time", &version("0.2.23")).is_ok());
+        assert!(policy.check_version("time", &version("0.2.22")).is_err());
+        assert!(policy.check_version("time", &version("0.3.0")).is_err());
+        assert!(policy.check_version("rand", &version("0.1.0")).is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        assert!(toml::from_str::<Policy>("[crates.time]\nmaximum = \"0.3\"\n").is_err());
+    }
+
+    #[test]
+    fn clamps_to_allowed_range() {
+        let registry = MockRegistry::new().unwrap();
+        for version in &["0.2.22", "0.2.25", "0.3.0"] {
+            registry
+                .publish(&MockVersion::new("time", version))
+                .unwrap();
+        }
+        let manifest =
+            TempManifest::new("[package]\nname = \"demo\"\nversion = \"0.1.0\"\n").unwrap();
+        let source = Some(registry.source());
+        let version = |v: &str| Version::parse(v).unwrap();
+
+        let clamped = policy()
+            .clamp("time", &version("0.3.0"), manifest.path(), &source)
+            .unwrap();
+        assert_eq!(clamped, version("0.2.25"));
+        assert!(Policy::default()
+            .clamp("time", &version("0.3.0"), manifest.path(), &source)
+            .is_ok());
+        assert!(policy()
+            .clamp("openssl", &version("1.0.0"), manifest.path(), &source)
+            .is_err());
+    }
+}
@@ -6,10 +6,7 @@ use url::Url;
 
 const CRATES_IO_INDEX: &str = "https://github.com/rust-lang/crates.io-index";
 const CRATES_IO_REGISTRY: &str = "crates-io";
-
-pub fn registry_path(manifest_path: &Path, registry: Option<&str>) -> Result<PathBuf> {
-    registry_path_from_url(&registry_url(manifest_path, registry)?)
-}
+const CRATES_IO_SPARSE_HOST: &str = "index.crates.io";
 
 pub fn registry_path_from_url(registry: &Url) -> Result<PathBuf> {
     Ok(cargo_home()?
@@ -18,11 +15,101 @@ pub fn registry_path_from_url(registry: &Url) -> Result<PathBuf> {
         .join(short_name(registry)))
 }
 
+/// The place the versions of a registry's crates are read from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RegistrySource {
+    /// A remote registry, whose git index is checked out under `$CARGO_HOME`.
+    Remote(Url),
+    /// A `local-registry` source: a directory holding an `index` and the `.crate` files.
+    LocalRegistry(PathBuf),
+    /// A `directory` source, as produced by `cargo vendor`.
+    Directory(PathBuf),
+    /// A registry index on disk, read in place, e.g. a test index given as a `file://` URL.
+    LocalIndex(PathBuf),
+}
+
+impl RegistrySource {
+    /// Treat a directory on disk as a source, telling local registries (which contain an `index`)
+    /// apart from vendored directories.
+    pub fn from_local_path(path: &Path) -> Self {
+        if path.join("index").is_dir() {
+            RegistrySource::LocalRegistry(path.to_path_buf())
+        } else {
+            RegistrySource::Directory(path.to_path_buf())
+        }
+    }
+
+    /// The source for a registry index URL: an index on disk if it is a `file://` URL of a
+    /// directory holding an index (with its `config.json`), or else a remote registry.
+    pub fn from_index_url(url: Url) -> Self {
+        match url.to_file_path() {
+            Ok(path) if url.scheme() == "file" && path.join("config.json").is_file() => {
+                RegistrySource::LocalIndex(path)
+            }
+            _ => RegistrySource::Remote(url),
+        }
+    }
+
+    /// Is this source available without accessing the network?
+    pub fn is_local(&self) -> bool {
+        !matches!(self, RegistrySource::Remote(_))
+    }
+
+    /// Is this crates.io itself, rather than a mirror or another registry?
+    pub fn is_crates_io(&self) -> bool {
+        match self {
+            RegistrySource::Remote(url) => {
+                url.as_str().trim_end_matches('/') == CRATES_IO_INDEX
+                    || url.host_str() == Some(CRATES_IO_SPARSE_HOST)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl From<Url> for RegistrySource {
+    fn from(url: Url) -> Self {
+        RegistrySource::Remote(url)
+    }
+}
+
+/// Directories that may hold cargo's on-disk cache of a registry's index
+///
+/// Besides the cache of the registry's own index checkout, crates.io's sparse index cache is
+/// considered, as that is what recent versions of cargo populate.
+pub fn registry_cache_paths(registry: &Url) -> Result<Vec<PathBuf>> {
+    let index_dir = cargo_home()?.join("registry").join("index");
+    let mut hosts = vec![registry.host_str().unwrap_or("").to_string()];
+    if registry.as_str() == CRATES_IO_INDEX {
+        hosts.push(CRATES_IO_SPARSE_HOST.to_string());
+    }
+
+    let mut paths = vec![registry_path_from_url(registry)?.join(".cache")];
+    if let Ok(entries) = std::fs::read_dir(&index_dir) {
+        for entry in entries {
+            let path = entry?.path();
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let host = name.rsplitn(2, '-').last().unwrap_or("");
+            let cache = path.join(".cache");
+            if hosts.iter().any(|h| h == host) && cache.is_dir() && !paths.contains(&cache) {
+                paths.push(cache);
+            }
+        }
+    }
+    Ok(paths.into_iter().filter(|path| path.is_dir()).collect())
+}
+
 #[derive(Debug, Deserialize)]
 struct Source {
     #[serde(rename = "replace-with")]
     replace_with: Option<String>,
     registry: Option<String>,
+    #[serde(rename = "local-registry")]
+    local_registry: Option<PathBuf>,
+    directory: Option<PathBuf>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -30,15 +117,53 @@ struct Registry {
     index: Option<String>,
 }
 
+#[derive(Debug, Default, Deserialize)]
+struct Http {
+    cainfo: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Net {
+    offline: Option<bool>,
+    retry: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CargoEdit {
+    #[serde(rename = "describe-dependencies")]
+    describe_dependencies: Option<bool>,
+    #[serde(rename = "add-defaults", default)]
+    add_defaults: HashMap<String, AddDefaults>,
+    #[serde(rename = "trusted-hooks", default)]
+    trusted_hooks: Vec<PathBuf>,
+}
+
+/// How `cargo add` adds a crate unless told otherwise, as `cargo-edit.add-defaults.<crate>` in the
+/// cargo config sets it, e.g. `serde = { features = ["derive"] }`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct AddDefaults {
+    /// The features to enable
+    pub features: Option<Vec<String>>,
+    /// Whether to enable the default features
+    #[serde(rename = "default-features")]
+    pub default_features: Option<bool>,
+}
+
 #[derive(Debug, Deserialize)]
 struct CargoConfig {
     #[serde(default)]
     registries: HashMap<String, Registry>,
     #[serde(default)]
     source: HashMap<String, Source>,
+    #[serde(default)]
+    http: Http,
+    #[serde(default)]
+    net: Net,
+    #[serde(rename = "cargo-edit", default)]
+    cargo_edit: CargoEdit,
 }
 
-fn cargo_home() -> Result<PathBuf> {
+pub(crate) fn cargo_home() -> Result<PathBuf> {
     let default_cargo_home = dirs_next::home_dir()
         .map(|x| x.join(".cargo"))
         .chain_err(|| ErrorKind::ReadHomeDirFailure)?;
@@ -48,21 +173,130 @@ fn cargo_home() -> Result<PathBuf> {
     Ok(cargo_home)
 }
 
+/// The cargo config files that apply to a directory, most specific first
+///
+/// ref: https://doc.rust-lang.org/cargo/reference/config.html#hierarchical-structure
+fn cargo_config_paths(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut dirs: Vec<_> = dir.ancestors().map(|dir| dir.join(".cargo")).collect();
+    dirs.push(cargo_home()?);
+    Ok(dirs
+        .into_iter()
+        .filter_map(|dir| {
+            let config_path = dir.join("config");
+            if config_path.is_file() {
+                Some(config_path)
+            } else {
+                let config_path = dir.join("config.toml");
+                Some(config_path).filter(|path| path.is_file())
+            }
+        })
+        .collect())
+}
+
+fn read_cargo_config(path: &Path) -> Result<CargoConfig> {
+    let content = std::fs::read(path)?;
+    toml::from_slice::<CargoConfig>(&content).map_err(|_| ErrorKind::InvalidCargoConfig.into())
+}
+
+/// Find the CA bundle set by `http.cainfo` in the cargo config that applies to a directory
+pub fn http_cainfo(dir: &Path) -> Result<Option<PathBuf>> {
+    for path in cargo_config_paths(dir)? {
+        if let Some(cainfo) = read_cargo_config(&path)?.http.cainfo {
+            // Like other paths in the config, it is relative to the directory containing `.cargo`.
+            let base = path.parent().and_then(Path::parent).unwrap_or(&path);
+            return Ok(Some(base.join(cainfo)));
+        }
+    }
+    Ok(None)
+}
+
+/// Find `net.offline` and `net.retry` in the cargo config that applies to a directory
+///
+/// Each is taken from the most specific config file that sets it.
+pub fn net_settings(dir: &Path) -> Result<(Option<bool>, Option<u32>)> {
+    let (mut offline, mut retry) = (None, None);
+    for path in cargo_config_paths(dir)? {
+        let net = read_cargo_config(&path)?.net;
+        offline = offline.or(net.offline);
+        retry = retry.or(net.retry);
+    }
+    Ok((offline, retry))
+}
+
+/// Find `cargo-edit.describe-dependencies` in the cargo config that applies to a directory, which
+/// has `cargo add` put the registry description of a crate in a comment above it
+pub fn describe_dependencies(dir: &Path) -> Result<bool> {
+    for path in cargo_config_paths(dir)? {
+        if let Some(describe) = read_cargo_config(&path)?.cargo_edit.describe_dependencies {
+            return Ok(describe);
+        }
+    }
+    Ok(false)
+}
+
+/// The roots of the workspaces whose edit hooks may run, as `cargo-edit.trusted-hooks` lists them
+///
+/// Only the cargo config in `$CARGO_HOME` is read, as the config files in a workspace come with it.
+pub fn trusted_hooks() -> Result<Vec<PathBuf>> {
+    let home = cargo_home()?;
+    match ["config", "config.toml"]
+        .iter()
+        .map(|name| home.join(name))
+        .find(|path| path.is_file())
+    {
+        Some(path) => Ok(read_cargo_config(&path)?.cargo_edit.trusted_hooks),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Find `cargo-edit.add-defaults.<crate>` in the cargo config that applies to a directory
+///
+/// Each setting is taken from the most specific config file that sets it.
+pub fn add_defaults(dir: &Path, name: &str) -> Result<AddDefaults> {
+    let mut defaults = AddDefaults::default();
+    for path in cargo_config_paths(dir)? {
+        if let Some(config) = read_cargo_config(&path)?
+            .cargo_edit
+            .add_defaults
+            .remove(name)
+        {
+            defaults.features = defaults.features.or(config.features);
+            defaults.default_features = defaults.default_features.or(config.default_features);
+        }
+    }
+    Ok(defaults)
+}
+
 /// Find the URL of a registry
+///
+/// Fails if the registry has been replaced by a local source, see `registry_source`.
 pub fn registry_url(manifest_path: &Path, registry: Option<&str>) -> Result<Url> {
-    // TODO support local registry sources, directory sources, git sources: https://doc.rust-lang.org/cargo/reference/source-replacement.html?highlight=replace-with#source-replacement
-    fn read_config(registries: &mut HashMap<String, Source>, path: impl AsRef<Path>) -> Result<()> {
-        // TODO unit test for source replacement
-        let content = std::fs::read(path)?;
-        let config =
-            toml::from_slice::<CargoConfig>(&content).map_err(|_| ErrorKind::InvalidCargoConfig)?;
+    match registry_source(manifest_path, registry)? {
+        RegistrySource::Remote(url) => Ok(url),
+        RegistrySource::LocalRegistry(path)
+        | RegistrySource::Directory(path)
+        | RegistrySource::LocalIndex(path) => Err(ErrorKind::LocalRegistrySource(path).into()),
+    }
+}
+
+/// Find the source a registry's crates should be read from, following source replacement
+pub fn registry_source(manifest_path: &Path, registry: Option<&str>) -> Result<RegistrySource> {
+    // TODO support git sources: https://doc.rust-lang.org/cargo/reference/source-replacement.html?highlight=replace-with#source-replacement
+    fn read_config(registries: &mut HashMap<String, Source>, path: &Path) -> Result<()> {
+        let config = read_cargo_config(path)?;
         for (key, value) in config.registries {
             registries.entry(key).or_insert(Source {
                 registry: value.index,
                 replace_with: None,
+                local_registry: None,
+                directory: None,
             });
         }
-        for (key, value) in config.source {
+        // Paths of local sources are relative to the directory containing `.cargo`.
+        let base = path.parent().and_then(Path::parent).unwrap_or(path);
+        for (key, mut value) in config.source {
+            value.local_registry = value.local_registry.map(|p| base.join(p));
+            value.directory = value.directory.map(|p| base.join(p));
             registries.entry(key).or_insert(value);
         }
         Ok(())
@@ -71,33 +305,11 @@ pub fn registry_url(manifest_path: &Path, registry: Option<&str>) -> Result<Url>
     // it's looks like a singly linked list
     // put relations in this map.
     let mut registries: HashMap<String, Source> = HashMap::new();
-    // ref: https://doc.rust-lang.org/cargo/reference/config.html#hierarchical-structure
-    for work_dir in manifest_path
+    let work_dir = manifest_path
         .parent()
-        .expect("there must be a parent directory")
-        .ancestors()
-    {
-        let work_cargo_dir = work_dir.join(".cargo");
-        let config_path = work_cargo_dir.join("config");
-        if config_path.is_file() {
-            read_config(&mut registries, config_path)?;
-        } else {
-            let config_path = work_cargo_dir.join("config.toml");
-            if config_path.is_file() {
-                read_config(&mut registries, config_path)?;
-            }
-        }
-    }
-
-    let default_cargo_home = cargo_home()?;
-    let default_config_path = default_cargo_home.join("config");
-    if default_config_path.is_file() {
-        read_config(&mut registries, default_config_path)?;
-    } else {
-        let default_config_path = default_cargo_home.join("config.toml");
-        if default_config_path.is_file() {
-            read_config(&mut registries, default_config_path)?;
-        }
+        .expect("there must be a parent directory");
+    for config_path in cargo_config_paths(work_dir)? {
+        read_config(&mut registries, &config_path)?;
     }
 
     // find head of the relevant linked list
@@ -108,6 +320,8 @@ pub fn registry_url(manifest_path: &Path, registry: Option<&str>) -> Result<Url>
                 .unwrap_or_else(|| Source {
                     replace_with: None,
                     registry: Some(CRATES_IO_INDEX.to_string()),
+                    local_registry: None,
+                    directory: None,
                 })
         }
         Some(r) => registries
@@ -122,12 +336,19 @@ pub fn registry_url(manifest_path: &Path, registry: Option<&str>) -> Result<Url>
             .chain_err(|| ErrorKind::NoSuchSourceFound(replace_with.to_string()))?;
     }
 
+    if let Some(path) = source.local_registry {
+        return Ok(RegistrySource::LocalRegistry(path));
+    }
+    if let Some(path) = source.directory {
+        return Ok(RegistrySource::Directory(path));
+    }
+
     let registry_url = source
         .registry
         .and_then(|x| Url::parse(&x).ok())
         .chain_err(|| ErrorKind::InvalidCargoConfig)?;
 
-    Ok(registry_url)
+    Ok(RegistrySource::Remote(registry_url))
 }
 
 fn short_name(registry: &Url) -> String {
@@ -145,6 +366,42 @@ fn short_name(registry: &Url) -> String {
     format!("{}-{}", ident, hash)
 }
 
+#[test]
+fn test_add_defaults() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join(".cargo")).unwrap();
+    std::fs::write(
+        dir.path().join(".cargo/config.toml"),
+        "[cargo-edit.add-defaults]\n\
+         serde = { features = [\"derive\"], default-features = false }\n\
+         openssl = { default-features = false }\n",
+    )
+    .unwrap();
+    let member = dir.path().join("member");
+    std::fs::create_dir_all(member.join(".cargo")).unwrap();
+    std::fs::write(
+        member.join(".cargo/config.toml"),
+        "[cargo-edit.add-defaults.serde]\nfeatures = [\"derive\", \"rc\"]\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        add_defaults(&member, "serde").unwrap(),
+        AddDefaults {
+            features: Some(vec!["derive".to_owned(), "rc".to_owned()]),
+            default_features: Some(false),
+        }
+    );
+    assert_eq!(
+        add_defaults(&member, "openssl").unwrap().default_features,
+        Some(false)
+    );
+    assert_eq!(
+        add_defaults(&member, "libc").unwrap(),
+        AddDefaults::default()
+    );
+}
+
 #[cfg_attr(target_pointer_width = "64", test)]
 fn test_short_name() {
     fn test_helper(url: &str, name: &str) {
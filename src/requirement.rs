@@ -0,0 +1,190 @@
+//! Rewriting version requirements for a new version, keeping the style they are written in.
+
+use crate::errors::*;
+use semver::{Version, VersionReq};
+
+/// Rewrite a version requirement to one on `version`, keeping its operators and precision
+///
+/// - Bare, `^`, `~` and `=` requirements and `>=` bounds are moved to the new version, with as
+///   many components as before: `^1.0` becomes `^1.2` for 1.2.3, and `~0.3.1` becomes `~0.4.0`
+///   for 0.4.0.
+/// - Wildcards keep their stars: `1.*` becomes `2.*` for 2.0.1, and `*` is left as it is.
+/// - `>` bounds are kept if the new version is above them, and become `>=` bounds on it
+///   otherwise.
+/// - `<` and `<=` bounds are kept if the new version is below them, and are otherwise raised just
+///   enough to let it in, with as many components as before: `>=1.0, <2` becomes `>=2.1, <3` for
+///   2.1.0.
+/// - A prerelease is written out in full (e.g. `^2.0.0-rc.1`), as a requirement only matches the
+///   prereleases of the version it names.
+///
+/// This touches no files and asks no registry. An error is returned for requirements that can't
+/// be parsed, or that still wouldn't match `version` after the rewrite (e.g. an upper bound
+/// letting in a prerelease).
+pub fn upgrade_requirement(requirement: &str, version: &Version) -> Result<String> {
+    let invalid = || Error::from(ErrorKind::InvalidRequirement(requirement.to_owned()));
+    VersionReq::parse(requirement).map_err(|_| invalid())?;
+
+    let comparators = requirement
+        .split(',')
+        .map(|comparator| upgrade_comparator(comparator.trim(), version).ok_or_else(invalid))
+        .collect::<Result<Vec<_>>>()?;
+    let upgraded = comparators.join(", ");
+
+    let matches = VersionReq::parse(&upgraded).is_ok_and(|req| req.matches(version));
+    if !matches {
+        return Err(
+            ErrorKind::RequirementMismatch(requirement.to_owned(), version.to_string()).into(),
+        );
+    }
+    Ok(upgraded)
+}
+
+/// The operators a comparator can start with, longest first so that `>=` isn't read as `>`.
+const OPERATORS: [&str; 7] = [">=", "<=", ">", "<", "=", "^", "~"];
+
+/// Rewrite a single comparator, e.g. `^1.2` or `<2`, or `None` if it can't be parsed.
+fn upgrade_comparator(comparator: &str, version: &Version) -> Option<String> {
+    let op = OPERATORS
+        .iter()
+        .find(|op| comparator.starts_with(*op))
+        .copied()
+        .unwrap_or("");
+    let bound = comparator[op.len()..].trim();
+    // Build metadata plays no part in matching.
+    let bound = bound.split('+').next()?;
+    let (core, pre) = match bound.split_once('-') {
+        Some((core, pre)) => (core, Some(pre)),
+        None => (bound, None),
+    };
+    let parts: Vec<Option<u64>> = core
+        .split('.')
+        .map(|part| match part {
+            "*" | "x" | "X" => Some(None),
+            part => part.parse().ok().map(Some),
+        })
+        .collect::<Option<_>>()?;
+    if parts.is_empty() || parts.len() > 3 {
+        return None;
+    }
+    let precision = parts.len();
+    let numbers = [version.major, version.minor, version.patch];
+
+    let upgraded = match op {
+        ">" | "<" | "<=" => {
+            let mut padded: Vec<_> = parts.iter().map(|part| part.unwrap_or(0)).collect();
+            padded.resize(3, 0);
+            let mut current = format!("{}.{}.{}", padded[0], padded[1], padded[2]);
+            if let Some(pre) = pre {
+                current = format!("{}-{}", current, pre);
+            }
+            let current = Version::parse(&current).ok()?;
+            let prefix = &numbers[..precision];
+            let kept = match op {
+                ">" => *version > current,
+                "<" => *version < current,
+                _ => prefix <= &padded[..precision],
+            };
+            if kept {
+                comparator.to_owned()
+            } else if op == ">" {
+                format!(">={}", version)
+            } else if op == "<" {
+                let mut raised = prefix.to_vec();
+                *raised.last_mut()? += 1;
+                format!("<{}", join(&raised))
+            } else {
+                format!("<={}", join(prefix))
+            }
+        }
+        _ if parts.iter().all(Option::is_none) => comparator.to_owned(),
+        _ if version.is_prerelease() => format!("{}{}", op, version_without_build(version)),
+        _ => {
+            let parts: Vec<String> = parts
+                .iter()
+                .zip(&numbers)
+                .map(|(part, number)| match part {
+                    Some(_) => number.to_string(),
+                    None => "*".to_owned(),
+                })
+                .collect();
+            format!("{}{}", op, parts.join("."))
+        }
+    };
+    Some(upgraded)
+}
+
+fn join(numbers: &[u64]) -> String {
+    numbers
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn version_without_build(version: &Version) -> Version {
+    let mut version = version.clone();
+    version.build.clear();
+    version
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upgrade(requirement: &str, version: &str) -> Result<String> {
+        upgrade_requirement(requirement, &Version::parse(version).unwrap())
+    }
+
+    #[test]
+    fn keeps_operator_and_precision() {
+        assert_eq!(upgrade("1.0", "1.2.3").unwrap(), "1.2");
+        assert_eq!(upgrade("^1.0", "1.2.3").unwrap(), "^1.2");
+        assert_eq!(upgrade("1", "2.0.1").unwrap(), "2");
+        assert_eq!(upgrade("0.3.1", "0.4.0").unwrap(), "0.4.0");
+        assert_eq!(upgrade("~0.3.1", "0.4.0").unwrap(), "~0.4.0");
+        assert_eq!(upgrade("=1.0.0", "1.2.3").unwrap(), "=1.2.3");
+        assert_eq!(upgrade(">=1.0", "1.2.3").unwrap(), ">=1.2");
+        assert_eq!(upgrade("= 1.0.0", "1.2.3").unwrap(), "=1.2.3");
+    }
+
+    #[test]
+    fn keeps_wildcards() {
+        assert_eq!(upgrade("*", "2.0.1").unwrap(), "*");
+        assert_eq!(upgrade("1.*", "2.0.1").unwrap(), "2.*");
+        assert_eq!(upgrade("1.2.*", "1.3.0").unwrap(), "1.3.*");
+    }
+
+    #[test]
+    fn moves_bounds_only_as_needed() {
+        assert_eq!(upgrade(">=1.0, <2", "1.5.0").unwrap(), ">=1.5, <2");
+        assert_eq!(upgrade(">=1.0, <2", "2.1.0").unwrap(), ">=2.1, <3");
+        assert_eq!(upgrade(">=1.0, <2.0", "2.1.0").unwrap(), ">=2.1, <2.2");
+        assert_eq!(upgrade(">=1.0, <=1.4", "1.4.9").unwrap(), ">=1.4, <=1.4");
+        assert_eq!(upgrade(">=1.0, <=1.4", "1.6.0").unwrap(), ">=1.6, <=1.6");
+        assert_eq!(upgrade(">1.0.0", "1.2.0").unwrap(), ">1.0.0");
+        assert_eq!(upgrade(">1.2.0", "1.2.0").unwrap(), ">=1.2.0");
+    }
+
+    #[test]
+    fn writes_prereleases_in_full() {
+        assert_eq!(upgrade("1.0", "2.0.0-rc.1").unwrap(), "2.0.0-rc.1");
+        assert_eq!(upgrade("^1", "2.0.0-rc.1+build").unwrap(), "^2.0.0-rc.1");
+        assert_eq!(upgrade("=2.0.0-rc.1", "2.0.0").unwrap(), "=2.0.0");
+        assert_eq!(upgrade("~1.0", "1.1.0+build").unwrap(), "~1.1");
+    }
+
+    #[test]
+    fn rejects_what_it_cannot_rewrite() {
+        match upgrade("not a requirement", "1.0.0") {
+            Err(Error(ErrorKind::InvalidRequirement(req), _)) => {
+                assert_eq!(req, "not a requirement")
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        // Only a requirement naming a prerelease of the same version matches it.
+        assert!(matches!(
+            upgrade("<2", "1.5.0-rc.1"),
+            Err(Error(ErrorKind::RequirementMismatch(..), _))
+        ));
+    }
+}
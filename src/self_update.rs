@@ -0,0 +1,86 @@
+//! Notes about newer releases of cargo-edit itself
+
+use crate::errors::*;
+use crate::fetch::get_latest_dependency;
+use crate::registry::cargo_home;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use std::{env, fs};
+
+/// Environment variable that turns off the check for newer releases of cargo-edit when set
+pub const NO_UPDATE_CHECK_ENV: &str = "CARGO_EDIT_NO_UPDATE_CHECK";
+
+/// How long to wait after a check before checking again
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The file whose modification time records the last check
+fn stamp_path() -> Result<PathBuf> {
+    Ok(cargo_home()?.join("cargo-edit").join("last-update-check"))
+}
+
+/// Whether a check is due, given when the last one was
+fn is_due(last_check: Option<SystemTime>, now: SystemTime) -> bool {
+    match last_check {
+        // A clock that went backwards doesn't hold up the check.
+        Some(last_check) => now
+            .duration_since(last_check)
+            .map_or(true, |elapsed| elapsed >= CHECK_INTERVAL),
+        None => true,
+    }
+}
+
+/// The release of cargo-edit that is newer than this one, if there is one
+///
+/// The check reads the local copy of the registry index that applies to the manifest, so it
+/// doesn't access the network. It happens at most once a day, and never if
+/// `CARGO_EDIT_NO_UPDATE_CHECK` is set.
+pub fn newer_release(manifest_path: &Path) -> Result<Option<semver::Version>> {
+    if env::var_os(NO_UPDATE_CHECK_ENV).is_some() || env::var("CARGO_IS_TEST").is_ok() {
+        return Ok(None);
+    }
+    let stamp = stamp_path()?;
+    let last_check = fs::metadata(&stamp).and_then(|m| m.modified()).ok();
+    if !is_due(last_check, SystemTime::now()) {
+        return Ok(None);
+    }
+    if let Some(dir) = stamp.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&stamp, "")?;
+
+    let latest = get_latest_dependency(env!("CARGO_PKG_NAME"), false, manifest_path, &None)?;
+    let latest = semver::Version::parse(latest.version().unwrap_or_default())
+        .chain_err(|| "Invalid version of cargo-edit in the registry")?;
+    let current = semver::Version::parse(env!("CARGO_PKG_VERSION"))
+        .chain_err(|| "Invalid version of cargo-edit")?;
+    Ok(Some(latest).filter(|latest| *latest > current))
+}
+
+/// Print a note if there is a newer release of cargo-edit, see `newer_release`
+///
+/// The check is only a courtesy, so it failing is ignored.
+pub fn notify_of_newer_release(manifest_path: &Path) {
+    if let Ok(Some(latest)) = newer_release(manifest_path) {
+        eprintln!(
+            "note: cargo-edit {} is available, this is {}. Run `cargo upgrade --self` to update, \
+             or set {} to stop checking.",
+            latest,
+            env!("CARGO_PKG_VERSION"),
+            NO_UPDATE_CHECK_ENV
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_at_most_once_a_day() {
+        let now = SystemTime::now();
+        assert!(is_due(None, now));
+        assert!(!is_due(Some(now - Duration::from_secs(60 * 60)), now));
+        assert!(is_due(Some(now - CHECK_INTERVAL), now));
+        assert!(is_due(Some(now + Duration::from_secs(60)), now));
+    }
+}
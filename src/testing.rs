@@ -0,0 +1,222 @@
+//! Fixtures for testing tools built on cargo-edit without accessing the network.
+//!
+//! This module needs the `testing` feature.
+//!
+//! ```
+//! use cargo_edit::testing::{MockRegistry, MockVersion, TempManifest};
+//! use cargo_edit::get_latest_dependency;
+//!
+//! let registry = MockRegistry::new().unwrap();
+//! registry.publish(&MockVersion::new("my-crate", "0.1.0")).unwrap();
+//! registry
+//!     .publish(&MockVersion::new("my-crate", "0.2.0").set_feature("std", &[]))
+//!     .unwrap();
+//!
+//! let manifest = TempManifest::new("[package]\nname = \"demo\"\nversion = \"0.1.0\"\n").unwrap();
+//! let dep = get_latest_dependency("my-crate", false, manifest.path(), &Some(registry.source()))
+//!     .unwrap();
+//! assert_eq!(dep.version(), Some("0.2.0"));
+//! ```
+
+use crate::errors::*;
+use crate::fetch::summary_raw_path;
+use crate::manifest::Manifest;
+use crate::registry::RegistrySource;
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// A version of a crate to publish to a `MockRegistry`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockVersion {
+    name: String,
+    version: String,
+    features: BTreeMap<String, Vec<String>>,
+    yanked: bool,
+    published: Option<String>,
+}
+
+impl MockVersion {
+    /// A version of a crate, without features.
+    pub fn new(name: &str, version: &str) -> Self {
+        MockVersion {
+            name: name.into(),
+            version: version.into(),
+            features: BTreeMap::new(),
+            yanked: false,
+            published: None,
+        }
+    }
+
+    /// Add a feature, which enables the given features and dependencies.
+    pub fn set_feature(mut self, name: &str, enables: &[&str]) -> Self {
+        self.features.insert(
+            name.into(),
+            enables.iter().map(|&feature| feature.into()).collect(),
+        );
+        self
+    }
+
+    /// Mark the version as yanked.
+    pub fn set_yanked(mut self, yanked: bool) -> Self {
+        self.yanked = yanked;
+        self
+    }
+
+    /// Record when the version was published, in RFC 3339 format (e.g. `2021-03-01T12:00:00Z`).
+    pub fn set_published(mut self, time: &str) -> Self {
+        self.published = Some(time.into());
+        self
+    }
+
+    /// The line for this version in the registry index.
+    fn to_index_line(&self) -> String {
+        let mut line = serde_json::json!({
+            "name": self.name,
+            "vers": self.version,
+            "deps": [],
+            "cksum": "0".repeat(64),
+            "features": self.features,
+            "yanked": self.yanked,
+        });
+        if let Some(published) = &self.published {
+            line["pubtime"] = published.as_str().into();
+        }
+        line.to_string()
+    }
+}
+
+/// A local registry in a temporary directory, which is removed when this is dropped.
+///
+/// Pass `source()` to functions taking a registry, or `path()` to the `--local-registry` flag of
+/// the binaries.
+#[derive(Debug)]
+pub struct MockRegistry {
+    dir: TempDir,
+}
+
+impl MockRegistry {
+    /// An empty registry.
+    pub fn new() -> Result<Self> {
+        let dir = tempfile::tempdir().chain_err(|| "Failed to create a temporary directory")?;
+        fs::create_dir(dir.path().join("index"))?;
+        Ok(MockRegistry { dir })
+    }
+
+    /// The directory of the registry.
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// The registry as a source to look versions up in.
+    pub fn source(&self) -> RegistrySource {
+        RegistrySource::LocalRegistry(self.path().to_owned())
+    }
+
+    /// Add a version of a crate to the index.
+    pub fn publish(&self, version: &MockVersion) -> Result<()> {
+        let path = self
+            .path()
+            .join("index")
+            .join(summary_raw_path(&version.name));
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .chain_err(|| format!("Failed to open `{}`", path.display()))?;
+        writeln!(file, "{}", version.to_index_line())?;
+        Ok(())
+    }
+}
+
+/// A package with the given manifest, and an empty library, in a temporary directory which is
+/// removed when this is dropped.
+#[derive(Debug)]
+pub struct TempManifest {
+    dir: TempDir,
+    path: PathBuf,
+}
+
+impl TempManifest {
+    /// Write the manifest, and an empty `src/lib.rs`.
+    pub fn new(contents: &str) -> Result<Self> {
+        let dir = tempfile::tempdir().chain_err(|| "Failed to create a temporary directory")?;
+        let path = dir.path().join("Cargo.toml");
+        fs::write(&path, contents)?;
+        fs::create_dir(dir.path().join("src"))?;
+        fs::write(dir.path().join("src/lib.rs"), "")?;
+        Ok(TempManifest { dir, path })
+    }
+
+    /// The directory of the package.
+    pub fn dir(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// The path to `Cargo.toml`.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The current contents of the manifest.
+    pub fn read(&self) -> Result<String> {
+        Ok(fs::read_to_string(&self.path)?)
+    }
+
+    /// The current manifest, parsed.
+    pub fn manifest(&self) -> Result<Manifest> {
+        Manifest::open(&Some(self.path.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::get_latest_dependency;
+
+    #[test]
+    fn publishes_versions() {
+        let registry = MockRegistry::new().unwrap();
+        registry
+            .publish(&MockVersion::new("my-crate", "0.1.0").set_feature("std", &["alloc"]))
+            .unwrap();
+        registry
+            .publish(&MockVersion::new("my-crate", "0.2.0").set_yanked(true))
+            .unwrap();
+
+        let index = fs::read_to_string(registry.path().join("index/my/-c/my-crate")).unwrap();
+        let lines: Vec<serde_json::Value> = index
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["features"]["std"][0], "alloc");
+        assert_eq!(lines[1]["yanked"], true);
+    }
+
+    #[test]
+    fn looks_up_latest_version() {
+        let registry = MockRegistry::new().unwrap();
+        for version in &["0.1.0", "0.3.0-alpha.1", "0.2.0"] {
+            registry
+                .publish(&MockVersion::new("my-crate", version))
+                .unwrap();
+        }
+        let manifest =
+            TempManifest::new("[package]\nname = \"demo\"\nversion = \"0.1.0\"\n").unwrap();
+
+        let dep =
+            get_latest_dependency("my-crate", false, manifest.path(), &Some(registry.source()))
+                .unwrap();
+        assert_eq!(dep.version(), Some("0.2.0"));
+        assert_eq!(
+            manifest.manifest().unwrap().data["package"]["name"].as_str(),
+            Some("demo")
+        );
+    }
+}
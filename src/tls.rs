@@ -0,0 +1,180 @@
+use crate::errors::*;
+use crate::registry::http_cainfo;
+use std::env;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Environment variable the `--client-cert` flag of the commands is read from, naming a PEM client
+/// certificate to present to registries and git hosts
+pub const CLIENT_CERT_ENV: &str = "CARGO_EDIT_CLIENT_CERT";
+/// Environment variable the `--client-key` flag of the commands is read from, naming the PEM
+/// private key of the client certificate
+pub const CLIENT_KEY_ENV: &str = "CARGO_EDIT_CLIENT_KEY";
+
+/// Certificates used for HTTPS, beyond the default trusted roots
+///
+/// This is for registries and mirrors that use an internal CA, or require clients to authenticate
+/// with a certificate (mutual TLS).
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+    /// A bundle of CA certificates to trust, in addition to the default roots
+    pub cainfo: Option<PathBuf>,
+    /// A client certificate and its private key
+    pub client_identity: Option<(PathBuf, PathBuf)>,
+}
+
+impl TlsConfig {
+    /// Read the settings that apply to a directory
+    ///
+    /// The CA bundle is taken from `CARGO_HTTP_CAINFO`, or else `http.cainfo` in the cargo config,
+    /// like cargo does. Cargo has no client certificate setting, so there is none until one is
+    /// set with `with_client_identity`.
+    pub fn from_env(dir: &Path) -> Result<Self> {
+        let cainfo = match env::var_os("CARGO_HTTP_CAINFO") {
+            Some(cainfo) => Some(PathBuf::from(cainfo)),
+            None => http_cainfo(dir)?,
+        };
+        Ok(TlsConfig {
+            cainfo,
+            client_identity: None,
+        })
+    }
+
+    /// Present a client certificate, given with its private key, e.g. from the `--client-cert`
+    /// and `--client-key` flags
+    pub fn with_client_identity(
+        mut self,
+        cert: Option<PathBuf>,
+        key: Option<PathBuf>,
+    ) -> Result<Self> {
+        self.client_identity = match (cert, key) {
+            (Some(cert), Some(key)) => Some((cert, key)),
+            (None, None) => None,
+            _ => return Err(ErrorKind::IncompleteClientIdentity.into()),
+        };
+        Ok(self)
+    }
+
+    /// The `rustls` configuration for HTTP requests, if the defaults need changing
+    pub fn rustls_config(&self) -> Result<Option<Arc<rustls::ClientConfig>>> {
+        if *self == TlsConfig::default() {
+            return Ok(None);
+        }
+
+        let mut config = rustls::ClientConfig::new();
+        config
+            .root_store
+            .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+        if let Some(cainfo) = &self.cainfo {
+            match config.root_store.add_pem_file(&mut open(cainfo)?) {
+                Ok((valid, _)) if valid > 0 => {}
+                _ => return Err(ErrorKind::InvalidCertificate(cainfo.clone()).into()),
+            }
+        }
+        if let Some((cert, key)) = &self.client_identity {
+            let certs = rustls::internal::pemfile::certs(&mut open(cert)?)
+                .ok()
+                .filter(|certs| !certs.is_empty())
+                .chain_err(|| ErrorKind::InvalidCertificate(cert.clone()))?;
+            // Keys may be in PKCS #8 or the older RSA format.
+            let private_key = rustls::internal::pemfile::pkcs8_private_keys(&mut open(key)?)
+                .ok()
+                .and_then(|keys| keys.into_iter().next())
+                .or_else(|| {
+                    let keys = rustls::internal::pemfile::rsa_private_keys(&mut open(key).ok()?);
+                    keys.ok()?.into_iter().next()
+                })
+                .chain_err(|| ErrorKind::InvalidPrivateKey(key.clone()))?;
+            config
+                .set_single_client_cert(certs, private_key)
+                .chain_err(|| ErrorKind::InvalidPrivateKey(key.clone()))?;
+        }
+        Ok(Some(Arc::new(config)))
+    }
+
+    /// `git -c` options with the same settings, for fetching registry indices
+    pub fn git_options(&self) -> Vec<String> {
+        let mut options = Vec::new();
+        if let Some(cainfo) = &self.cainfo {
+            options.push(format!("http.sslCAInfo={}", cainfo.display()));
+        }
+        if let Some((cert, key)) = &self.client_identity {
+            options.push(format!("http.sslCert={}", cert.display()));
+            options.push(format!("http.sslKey={}", key.display()));
+        }
+        options
+            .into_iter()
+            .flat_map(|option| vec!["-c".to_owned(), option])
+            .collect()
+    }
+}
+
+fn open(path: &Path) -> Result<BufReader<File>> {
+    File::open(path)
+        .map(BufReader::new)
+        .chain_err(|| format!("Failed to open `{}`", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn defaults_need_no_config() {
+        assert!(TlsConfig::default().rustls_config().unwrap().is_none());
+        assert!(TlsConfig::default().git_options().is_empty());
+    }
+
+    #[test]
+    fn git_options() {
+        let config = TlsConfig {
+            cainfo: Some("/etc/ca.pem".into()),
+            client_identity: Some(("/etc/client.pem".into(), "/etc/client.key".into())),
+        };
+        assert_eq!(
+            config.git_options(),
+            vec![
+                "-c",
+                "http.sslCAInfo=/etc/ca.pem",
+                "-c",
+                "http.sslCert=/etc/client.pem",
+                "-c",
+                "http.sslKey=/etc/client.key",
+            ]
+        );
+    }
+
+    #[test]
+    fn cainfo_from_cargo_config() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(".cargo")).unwrap();
+        fs::write(
+            dir.path().join(".cargo/config.toml"),
+            "[http]\ncainfo = \"certs/ca.pem\"\n",
+        )
+        .unwrap();
+        let member = dir.path().join("member");
+        fs::create_dir_all(&member).unwrap();
+
+        assert_eq!(
+            http_cainfo(&member).unwrap(),
+            Some(dir.path().join("certs/ca.pem"))
+        );
+    }
+
+    #[test]
+    fn invalid_ca_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let cainfo = dir.path().join("ca.pem");
+        fs::write(&cainfo, "not a certificate").unwrap();
+
+        let config = TlsConfig {
+            cainfo: Some(cainfo),
+            client_identity: None,
+        };
+        assert!(config.rustls_config().is_err());
+    }
+}
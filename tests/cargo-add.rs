@@ -52,6 +52,147 @@ fn adds_prerelease_dependency() {
     assert_eq!(val.as_str().unwrap(), "my-package--PRERELEASE_VERSION_TEST");
 }
 
+#[test]
+fn adds_dependency_with_comment() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(
+        &["add", "my-package", "--comment", "For the demo"],
+        &manifest,
+    );
+
+    let content = std::fs::read_to_string(&manifest).unwrap();
+    assert!(content.contains("# For the demo\nmy-package = "));
+
+    execute_bad_command(
+        &["add", "my-package1", "my-package2", "--comment", "Both"],
+        &manifest,
+    );
+}
+
+#[test]
+fn adds_dependency_with_description() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    std::fs::create_dir(tmpdir.path().join(".cargo")).unwrap();
+    std::fs::write(
+        tmpdir.path().join(".cargo").join("config"),
+        "[cargo-edit]\ndescribe-dependencies = true\n",
+    )
+    .unwrap();
+
+    execute_command(&["add", "my-package"], &manifest);
+    execute_command(
+        &["add", "versioned-package", "--comment", "Mine"],
+        &manifest,
+    );
+
+    let content = std::fs::read_to_string(&manifest).unwrap();
+    assert!(content.contains("# A crate called my-package\nmy-package = "));
+    assert!(content.contains("# Mine\nversioned-package = "));
+}
+
+#[test]
+fn adds_dependency_with_config_defaults() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    std::fs::create_dir(tmpdir.path().join(".cargo")).unwrap();
+    std::fs::write(
+        tmpdir.path().join(".cargo").join("config"),
+        "[cargo-edit.add-defaults]\n\
+         my-package1 = { features = [\"derive\"] }\n\
+         my-package2 = { default-features = false }\n",
+    )
+    .unwrap();
+
+    execute_command(&["add", "my-package1", "my-package2"], &manifest);
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["my-package1"];
+    assert_eq!(val["features"].as_array().unwrap().len(), 1);
+    assert_eq!(val["features"][0].as_str(), Some("derive"));
+    assert!(val["default-features"].is_none());
+    let val = &toml["dependencies"]["my-package2"];
+    assert!(val["features"].is_none());
+    assert_eq!(val["default-features"].as_bool(), Some(false));
+
+    execute_command(&["add", "my-package1", "--features", "rc"], &manifest);
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["my-package1"];
+    assert_eq!(val["features"].as_array().unwrap().len(), 1);
+    assert_eq!(val["features"][0].as_str(), Some("rc"));
+}
+
+#[test]
+fn warns_about_suspicious_crates() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        get_command_path("add").as_str(),
+        "add",
+        "untrusted-package",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .and()
+    .stderr()
+    .is(
+        "WARN: `untrusted-package` looks suspicious: it was first published 2 days ago and has 3 \
+         downloads, and it is owned by accounts that have published nothing else",
+    )
+    .unwrap();
+
+    assert_cli::Assert::command(&[
+        get_command_path("add").as_str(),
+        "add",
+        "serd",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .and()
+    .stderr()
+    .is("WARN: `serd` looks suspicious: it is named like the popular `serde`")
+    .unwrap();
+
+    let toml = get_toml(&manifest);
+    assert!(!toml["dependencies"]["untrusted-package"].is_none());
+    assert!(!toml["dependencies"]["serd"].is_none());
+}
+
+#[test]
+fn denies_suspicious_crates() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        get_command_path("add").as_str(),
+        "add",
+        "untrusted-package",
+        "--deny-suspicious",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .fails_with(1)
+    .and()
+    .stderr()
+    .contains("Refusing to add `untrusted-package`, which looks suspicious.")
+    .unwrap();
+    assert!(get_toml(&manifest)["dependencies"].is_none());
+
+    assert_cli::Assert::command(&[
+        get_command_path("add").as_str(),
+        "add",
+        "untrusted-package",
+        "--force",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .and()
+    .stderr()
+    .is("")
+    .unwrap();
+    assert!(!get_toml(&manifest)["dependencies"]["untrusted-package"].is_none());
+}
+
 fn upgrade_test_helper(upgrade_method: &str, expected_prefix: &str) {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
 
@@ -96,7 +237,7 @@ fn adds_dependency_with_upgrade_bad() {
     let toml = get_toml(&manifest);
     assert!(toml["dependencies"].is_none());
 
-    let upgrade_arg = format!("--upgrade=an_invalid_string",);
+    let upgrade_arg = "--upgrade=an_invalid_string".to_string();
     execute_bad_command(&["add", "my-package", upgrade_arg.as_str()], &manifest);
 }
 
@@ -207,7 +348,7 @@ fn adds_dev_build_dependency() {
 
     // cannot run with both --dev and --build at the same time
     let call = process::Command::new(get_command_path("add").as_str())
-        .args(&["add", BOGUS_CRATE_NAME, "--dev", "--build"])
+        .args(["add", BOGUS_CRATE_NAME, "--dev", "--build"])
         .arg(format!("--manifest-path={}", &manifest))
         .output()
         .unwrap();
@@ -280,7 +421,7 @@ fn adds_specified_version() {
 
     // cannot run with both --dev and --build at the same time
     let call = process::Command::new(get_command_path("add").as_str())
-        .args(&["add", BOGUS_CRATE_NAME, "--vers", "invalid version string"])
+        .args(["add", BOGUS_CRATE_NAME, "--vers", "invalid version string"])
         .arg(format!("--manifest-path={}", &manifest))
         .output()
         .unwrap();
@@ -442,6 +583,95 @@ fn adds_git_branch_using_flag() {
     assert_eq!(val["branch"].as_str(), Some("master"));
 }
 
+/// A git repository holding a workspace of two packages, `foo-core` and `foo-cli`.
+fn git_workspace() -> tempfile::TempDir {
+    let repo = tempfile::tempdir().unwrap();
+    let root = repo.path();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"core\", \"cli\"]\n",
+    )
+    .unwrap();
+    for (dir, name) in &[("core", "foo-core"), ("cli", "foo-cli")] {
+        std::fs::create_dir(root.join(dir)).unwrap();
+        std::fs::write(
+            root.join(dir).join("Cargo.toml"),
+            format!("[package]\nname = \"{}\"\nversion = \"0.1.0\"\n", name),
+        )
+        .unwrap();
+    }
+    for args in &[
+        &["init", "--quiet"][..],
+        &["add", "."],
+        &[
+            "-c",
+            "user.name=test",
+            "-c",
+            "user.email=test@example.com",
+            "commit",
+            "--quiet",
+            "-m",
+            "init",
+        ],
+    ] {
+        let status = std::process::Command::new("git")
+            .args(*args)
+            .current_dir(root)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+    repo
+}
+
+#[test]
+fn adds_git_package_from_workspace() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let repo = git_workspace();
+    let url = repo.path().to_str().unwrap();
+
+    execute_command(
+        &["add", "foo", "--git", url, "--git-package", "foo-core"],
+        &manifest,
+    );
+    execute_command(
+        &["add", "foo-cli", "--git", url, "--git-package", "foo-cli"],
+        &manifest,
+    );
+
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["foo"];
+    assert_eq!(val["git"].as_str(), Some(url));
+    assert_eq!(val["package"].as_str(), Some("foo-core"));
+    let val = &toml["dependencies"]["foo-cli"];
+    assert_eq!(val["git"].as_str(), Some(url));
+    assert!(val["package"].is_none());
+}
+
+#[test]
+fn fails_to_add_missing_git_package() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let repo = git_workspace();
+    let url = repo.path().to_str().unwrap();
+
+    assert_cli::Assert::command(&[
+        get_command_path("add").as_str(),
+        "add",
+        "foo",
+        "--git",
+        url,
+        "--git-package",
+        "foo-gui",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .fails_with(1)
+    .and()
+    .stderr()
+    .contains("has no package `foo-gui`. Its packages are: foo-cli, foo-core")
+    .unwrap();
+}
+
 #[test]
 fn adds_local_source_using_flag() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
@@ -675,9 +905,9 @@ fn git_and_version_flags_are_mutually_exclusive() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
 
     let call = process::Command::new(get_command_path("add").as_str())
-        .args(&["add", BOGUS_CRATE_NAME])
-        .args(&["--vers", "0.4.3"])
-        .args(&["--git", "git://git.git"])
+        .args(["add", BOGUS_CRATE_NAME])
+        .args(["--vers", "0.4.3"])
+        .args(["--git", "git://git.git"])
         .arg(format!("--manifest-path={}", &manifest))
         .output()
         .unwrap();
@@ -691,8 +921,8 @@ fn git_flag_and_inline_version_are_mutually_exclusive() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
 
     let call = process::Command::new(get_command_path("add").as_str())
-        .args(&["add", &format!("{}@0.4.3", BOGUS_CRATE_NAME)])
-        .args(&["--git", "git://git.git"])
+        .args(["add", &format!("{}@0.4.3", BOGUS_CRATE_NAME)])
+        .args(["--git", "git://git.git"])
         .arg(format!("--manifest-path={}", &manifest))
         .output()
         .unwrap();
@@ -706,9 +936,9 @@ fn git_and_path_are_mutually_exclusive() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
 
     let call = process::Command::new(get_command_path("add").as_str())
-        .args(&["add", BOGUS_CRATE_NAME])
-        .args(&["--git", "git://git.git"])
-        .args(&["--path", "/path/here"])
+        .args(["add", BOGUS_CRATE_NAME])
+        .args(["--git", "git://git.git"])
+        .args(["--path", "/path/here"])
         .arg(format!("--manifest-path={}", &manifest))
         .output()
         .unwrap();
@@ -722,9 +952,9 @@ fn git_and_registry_are_mutually_exclusive() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
 
     let call = process::Command::new(get_command_path("add").as_str())
-        .args(&["add", BOGUS_CRATE_NAME])
-        .args(&["--git", "git://git.git"])
-        .args(&["--registry", "alternative"])
+        .args(["add", BOGUS_CRATE_NAME])
+        .args(["--git", "git://git.git"])
+        .args(["--registry", "alternative"])
         .arg(format!("--manifest-path={}", &manifest))
         .output()
         .unwrap();
@@ -738,9 +968,9 @@ fn registry_and_path_are_mutually_exclusive() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
 
     let call = process::Command::new(get_command_path("add").as_str())
-        .args(&["add", BOGUS_CRATE_NAME])
-        .args(&["--registry", "alternative"])
-        .args(&["--path", "/path/here"])
+        .args(["add", BOGUS_CRATE_NAME])
+        .args(["--registry", "alternative"])
+        .args(["--path", "/path/here"])
         .arg(format!("--manifest-path={}", &manifest))
         .output()
         .unwrap();
@@ -774,6 +1004,75 @@ fn adds_optional_dependency() {
     assert_eq!(val.as_bool().expect("optional not a bool"), true);
 }
 
+#[test]
+fn adds_optional_dependency_to_feature() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(
+        &["add", "image", "--optional", "--feature-of", "graphics"],
+        &manifest,
+    );
+    execute_command(
+        &["add", "png", "--optional", "--feature-of", "graphics"],
+        &manifest,
+    );
+
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["image"]["optional"];
+    assert_eq!(val.as_bool().expect("optional not a bool"), true);
+    let features: Vec<_> = toml["features"]["graphics"]
+        .as_array()
+        .expect("graphics not an array")
+        .iter()
+        .map(|entry| entry.as_str().unwrap().to_owned())
+        .collect();
+    assert_eq!(features, vec!["dep:image", "dep:png"]);
+}
+
+#[test]
+fn adds_optional_dependency_to_feature_for_old_rust_version() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let contents = std::fs::read_to_string(&manifest).unwrap().replacen(
+        "[package]\n",
+        "[package]\nrust-version = \"1.56\"\n",
+        1,
+    );
+    std::fs::write(&manifest, contents).unwrap();
+
+    execute_command(
+        &["add", "image", "--optional", "--feature-of", "graphics"],
+        &manifest,
+    );
+
+    // Cargo before 1.60 can't parse `dep:` entries.
+    let toml = get_toml(&manifest);
+    let features: Vec<_> = toml["features"]["graphics"]
+        .as_array()
+        .expect("graphics not an array")
+        .iter()
+        .map(|entry| entry.as_str().unwrap().to_owned())
+        .collect();
+    assert_eq!(features, vec!["image"]);
+}
+
+#[test]
+fn feature_of_requires_optional() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        get_command_path("add").as_str(),
+        "add",
+        "image",
+        "--feature-of",
+        "graphics",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .fails()
+    .unwrap();
+
+    assert!(no_manifest_failures(&get_toml(&manifest).root));
+}
+
 #[test]
 fn adds_multiple_optional_dependencies() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
@@ -908,6 +1207,231 @@ fn adds_multiple_alternative_registry_dependencies() {
     );
 }
 
+#[test]
+fn adds_dependency_from_crates_io_url() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(
+        &[
+            "add",
+            "https://crates.io/crates/my-package",
+            "https://crates.io/crates/versioned-package/0.3.1",
+        ],
+        &manifest,
+    );
+
+    let toml = get_toml(&manifest);
+    let dependencies = &toml["dependencies"];
+    assert_eq!(
+        dependencies["my-package"].as_str(),
+        Some("my-package--CURRENT_VERSION_TEST")
+    );
+    assert_eq!(dependencies["versioned-package"].as_str(), Some("0.3.1"));
+}
+
+#[test]
+fn adds_dependency_from_custom_index() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let index = std::env::current_dir()
+        .unwrap()
+        .join("tests/fixtures/local-registry/index");
+    let index = url::Url::from_directory_path(index).unwrap();
+
+    execute_command(&["add", "my-package", "--index", index.as_str()], &manifest);
+
+    // The index is only used for the lookup; the entry still refers to crates.io.
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["my-package"];
+    assert_eq!(val.as_str().unwrap(), "0.2.0");
+}
+
+#[test]
+fn fails_to_add_dependency_from_invalid_index() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_bad_command(&["add", "my-package", "--index", "not a url"], &manifest);
+    execute_bad_command(
+        &[
+            "add",
+            "my-package",
+            "--index",
+            "https://example.com/index",
+            "--registry",
+            "alternative",
+        ],
+        &manifest,
+    );
+}
+
+#[test]
+fn adds_dependency_from_local_registry() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let registry = std::env::current_dir()
+        .unwrap()
+        .join("tests/fixtures/local-registry");
+
+    execute_command(
+        &[
+            "add",
+            "my-package",
+            "--local-registry",
+            registry.to_str().unwrap(),
+        ],
+        &manifest,
+    );
+
+    // The yanked 0.3.0 is skipped.
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dependencies"]["my-package"].as_str(), Some("0.2.0"));
+}
+
+#[test]
+fn adds_dependency_from_vendor_directory() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let vendor = std::env::current_dir()
+        .unwrap()
+        .join("tests/fixtures/vendor");
+
+    execute_command(
+        &[
+            "add",
+            "my-package",
+            "--local-registry",
+            vendor.to_str().unwrap(),
+        ],
+        &manifest,
+    );
+
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dependencies"]["my-package"].as_str(), Some("0.2.1"));
+}
+
+#[test]
+fn adds_dependency_from_index_cache_when_offline() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    // A cargo home without an index checkout, but with cargo's cache of the sparse index.
+    let cargo_home = tmpdir.path().join("cargo-home");
+    let cache = cargo_home.join("registry/index/index.crates.io-1949cf8c6b5b557f/.cache/my/-p");
+    std::fs::create_dir_all(&cache).unwrap();
+    std::fs::copy(
+        "tests/fixtures/index-cache/my-package",
+        cache.join("my-package"),
+    )
+    .unwrap();
+
+    assert_cli::Assert::command(&[
+        get_command_path("add").as_str(),
+        "add",
+        "my-package",
+        "--offline",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_HOME", &cargo_home))
+    .succeeds()
+    .and()
+    .stderr()
+    .contains("its latest version may be out of date")
+    .unwrap();
+
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dependencies"]["my-package"].as_str(), Some("0.4.0"));
+}
+
+#[test]
+fn adds_locked_version_when_offline() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    std::fs::write(
+        tmpdir.path().join("Cargo.lock"),
+        r#"[[package]]
+name = "my-package"
+version = "0.3.1"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "my-package"
+version = "0.4.0-rc.1"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "my-package"
+version = "0.5.0"
+source = "git+https://github.com/me/my-package#0123456789abcdef"
+"#,
+    )
+    .unwrap();
+
+    assert_cli::Assert::command(&[
+        get_command_path("add").as_str(),
+        "add",
+        "my-package",
+        "--offline",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .and()
+    .stderr()
+    .is("note: Adding `my-package` at 0.3.1, the version in `Cargo.lock`, as cargo is offline")
+    .unwrap();
+
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dependencies"]["my-package"].as_str(), Some("0.3.1"));
+
+    // Crates the lock file doesn't have are looked up in the local copy of the index.
+    assert_cli::Assert::command(&[
+        get_command_path("add").as_str(),
+        "add",
+        "other-package",
+        "--offline",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .and()
+    .stderr()
+    .contains("the latest version in the local copy of the registry index")
+    .unwrap();
+}
+
+#[test]
+fn adds_lockfile_version() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    std::fs::write(
+        tmpdir.path().join("Cargo.lock"),
+        r#"[[package]]
+name = "my-package"
+version = "0.3.1"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#,
+    )
+    .unwrap();
+
+    assert_cli::Assert::command(&[
+        get_command_path("add").as_str(),
+        "add",
+        "my-package",
+        "--lockfile-version",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .and()
+    .stderr()
+    .is("note: Adding `my-package` at 0.3.1, the version in `Cargo.lock`")
+    .unwrap();
+
+    // Crates the lock file doesn't have get the latest version.
+    execute_command(&["add", "other-package", "--lockfile-version"], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dependencies"]["my-package"].as_str(), Some("0.3.1"));
+    assert_eq!(
+        toml["dependencies"]["other-package"].as_str(),
+        Some("other-package--CURRENT_VERSION_TEST")
+    );
+}
+
 #[test]
 fn adds_dependency_with_target_triple() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
@@ -1351,7 +1875,7 @@ fn add_prints_message() {
         "--vers=0.6.0",
         &format!("--manifest-path={}", manifest),
     ])
-    .with_env(&[("CARGO_IS_TEST", "1")])
+    .with_env([("CARGO_IS_TEST", "1")])
     .succeeds()
     .and()
     .stdout()
@@ -1372,7 +1896,7 @@ fn add_prints_message_with_section() {
         "--vers=0.1.0",
         &format!("--manifest-path={}", manifest),
     ])
-    .with_env(&[("CARGO_IS_TEST", "1")])
+    .with_env([("CARGO_IS_TEST", "1")])
     .succeeds()
     .and()
     .stdout()
@@ -1393,7 +1917,7 @@ fn add_prints_message_for_dev_deps() {
         "0.8.0",
         &format!("--manifest-path={}", manifest),
     ])
-    .with_env(&[("CARGO_IS_TEST", "1")])
+    .with_env([("CARGO_IS_TEST", "1")])
     .succeeds()
     .and()
     .stdout()
@@ -1414,7 +1938,7 @@ fn add_prints_message_for_build_deps() {
         "0.1.0",
         &format!("--manifest-path={}", manifest),
     ])
-    .with_env(&[("CARGO_IS_TEST", "1")])
+    .with_env([("CARGO_IS_TEST", "1")])
     .succeeds()
     .and()
     .stdout()
@@ -1534,7 +2058,7 @@ fn add_prints_message_for_features_deps() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
 
     assert_cli::Assert::command(&[
-        "target/debug/cargo-add",
+        get_command_path("add").as_str(),
         "add",
         "hello-world",
         "--vers",
@@ -1549,3 +2073,86 @@ fn add_prints_message_for_features_deps() {
     .contains(r#"Adding hello-world v0.1.0 to dependencies with features: ["jui"]"#)
     .unwrap();
 }
+
+#[test]
+fn add_picks_crate_from_search() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        get_command_path("add").as_str(),
+        "add",
+        "--search",
+        "http client",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env([("CARGO_IS_TEST", "1")])
+    .stdin("2\n")
+    .succeeds()
+    .and()
+    .stdout()
+    .contains("2. test_nonbreaking 0.1.1 - A crate about http client")
+    .and()
+    .stdout()
+    .contains("Adding test_nonbreaking v0.1.1 to dependencies")
+    .unwrap();
+
+    let toml = get_toml(&manifest);
+    assert_eq!(
+        toml["dependencies"]["test_nonbreaking"].as_str(),
+        Some("0.1.1")
+    );
+
+    // Leaving the choice empty adds nothing.
+    assert_cli::Assert::command(&[
+        get_command_path("add").as_str(),
+        "add",
+        "--search",
+        "http client",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env([("CARGO_IS_TEST", "1")])
+    .stdin("\n")
+    .succeeds()
+    .unwrap();
+    assert!(get_toml(&manifest)["dependencies"]["test_breaking"].is_none());
+}
+
+#[test]
+fn add_matching_workspace_requirement() {
+    let (tmpdir, _root_manifest, workspace_manifests) = copy_workspace_test();
+    let one = &workspace_manifests[0];
+    let mut contents = std::fs::read_to_string(one).unwrap();
+    contents.push_str("\nserde = { version = \"1.0\", features = [\"derive\"] }\n");
+    std::fs::write(one, contents).unwrap();
+
+    execute_command_for_pkg(&["add", "serde", "--match-workspace"], "four", &tmpdir);
+    let four = get_toml(&workspace_manifests[3]);
+    let serde = &four["dependencies"]["serde"];
+    assert_eq!(serde["version"].as_str(), Some("1.0"));
+    assert_eq!(serde["features"].as_array().unwrap().len(), 1);
+
+    // `one` requires `0.3` and `two` `0.2`: the tie goes to the newer one.
+    assert_cli::Assert::command(&[
+        get_command_path("add").as_str(),
+        "add",
+        "rand",
+        "--match-workspace",
+        &format!("--manifest-path={}", workspace_manifests[3]),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .and()
+    .stderr()
+    .contains("WARN: Workspace members depend on `rand` differently (0.3 in one; 0.2 in two)")
+    .unwrap();
+    let four = get_toml(&workspace_manifests[3]);
+    assert_eq!(four["dependencies"]["rand"].as_str(), Some("0.3"));
+
+    // Crates no member depends on get the latest version.
+    execute_command_for_pkg(&["add", "toml", "--match-workspace"], "four", &tmpdir);
+    let four = get_toml(&workspace_manifests[3]);
+    assert_eq!(
+        four["dependencies"]["toml"].as_str(),
+        Some("toml--CURRENT_VERSION_TEST")
+    );
+}
@@ -0,0 +1,122 @@
+#[macro_use]
+extern crate pretty_assertions;
+
+use std::fs;
+use std::path::Path;
+use std::process;
+
+mod utils;
+use crate::utils::get_command_path;
+
+/// Write a workspace whose members pull in two versions of `syn`.
+fn write_workspace(root: &Path) {
+    let member = |name: &str, dependencies: &str| {
+        let dir = root.join(name);
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/lib.rs"), "").unwrap();
+        fs::write(
+            dir.join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"{}\"\nversion = \"0.1.0\"\n\n[dependencies]\n{}",
+                name, dependencies
+            ),
+        )
+        .unwrap();
+    };
+    fs::write(
+        root.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"one\", \"two\"]\n",
+    )
+    .unwrap();
+    member("one", "serde_derive = \"1.0\"\nsyn = \"2.0\"\n");
+    member("two", "syn = \"1.0\"\n");
+
+    let registry = "(registry+https://github.com/rust-lang/crates.io-index)";
+    fs::write(
+        root.join("Cargo.lock"),
+        format!(
+            r#"version = 3
+
+[[package]]
+name = "one"
+version = "0.1.0"
+dependencies = [
+ "serde_derive",
+ "syn 2.0.48",
+]
+
+[[package]]
+name = "serde_derive"
+version = "1.0.100"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = [
+ "syn 1.0.109 {0}",
+]
+
+[[package]]
+name = "syn"
+version = "1.0.109"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "syn"
+version = "2.0.48"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "two"
+version = "0.1.0"
+dependencies = [
+ "syn 1.0.109 {0}",
+]
+"#,
+            registry
+        ),
+    )
+    .unwrap();
+}
+
+fn duplicates(dir: &Path, args: &[&str]) -> process::Output {
+    process::Command::new(get_command_path("duplicates"))
+        .arg("duplicates")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn reports_duplicates_with_suggestions() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    write_workspace(tmpdir.path());
+
+    let output = duplicates(tmpdir.path(), &[]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "\
+syn 1.0.109, 2.0.48
+    1.0.109 is pulled in by:
+        one 0.1.0 -> serde_derive 1.0.100 -> syn 1.0.109
+        two 0.1.0 -> syn 1.0.109
+      - one/Cargo.toml: upgrade `serde_derive` to a release that depends on syn 2.0.48 (e.g. with `cargo upgrade serde_derive`)
+      - two/Cargo.toml: change the `syn` requirement from \"1.0\" to \"2.0.48\"
+"
+    );
+}
+
+#[test]
+fn reports_named_crates() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    write_workspace(tmpdir.path());
+
+    let output = duplicates(tmpdir.path(), &["serde_derive"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "No crates are locked at more than one version\n"
+    );
+
+    let output = duplicates(tmpdir.path(), &["rand"]);
+    assert!(!output.status.success());
+}
@@ -0,0 +1,168 @@
+mod utils;
+use crate::utils::{clone_out_test, execute_command, get_command_path, get_toml};
+
+#[test]
+fn undoes_the_last_command() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let original = std::fs::read_to_string(&manifest).unwrap();
+
+    execute_command(&["add", "my-package"], &manifest);
+    let added = std::fs::read_to_string(&manifest).unwrap();
+    execute_command(&["rm", "my-package"], &manifest);
+
+    assert_cli::Assert::command(&[
+        get_command_path("edit").as_str(),
+        "edit",
+        "undo",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .succeeds()
+    .and()
+    .stdout()
+    .contains("Undoing `cargo rm my-package --manifest-path=")
+    .unwrap();
+    assert_eq!(std::fs::read_to_string(&manifest).unwrap(), added);
+
+    execute_command(&["edit", "undo"], &manifest);
+    assert_eq!(std::fs::read_to_string(&manifest).unwrap(), original);
+
+    assert_cli::Assert::command(&[
+        get_command_path("edit").as_str(),
+        "edit",
+        "undo",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .succeeds()
+    .and()
+    .stdout()
+    .is("Nothing to undo")
+    .unwrap();
+}
+
+#[test]
+fn dry_run_restores_nothing() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(&["add", "my-package"], &manifest);
+    let added = std::fs::read_to_string(&manifest).unwrap();
+
+    execute_command(&["edit", "undo", "--dry-run"], &manifest);
+    assert_eq!(std::fs::read_to_string(&manifest).unwrap(), added);
+}
+
+#[test]
+fn runs_other_commands() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        get_command_path("edit").as_str(),
+        "edit",
+        "add",
+        "my-package",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .unwrap();
+
+    let toml = get_toml(&manifest);
+    assert_eq!(
+        toml["dependencies"]["my-package"].as_str(),
+        Some("my-package--CURRENT_VERSION_TEST")
+    );
+
+    assert_cli::Assert::command(&[get_command_path("edit").as_str(), "edit", "frobnicate"])
+        .fails()
+        .unwrap();
+}
+
+#[test]
+fn links_commands_to_itself() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let bin_dir = tempfile::tempdir().unwrap();
+
+    assert_cli::Assert::command(&[
+        get_command_path("edit").as_str(),
+        "edit",
+        "link",
+        "--dir",
+        bin_dir.path().to_str().unwrap(),
+    ])
+    .succeeds()
+    .and()
+    .stdout()
+    .contains("Linking")
+    .unwrap();
+
+    let add = bin_dir
+        .path()
+        .join(format!("cargo-add{}", std::env::consts::EXE_SUFFIX));
+    assert_cli::Assert::command(&[
+        add.to_str().unwrap(),
+        "add",
+        "my-package",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .unwrap();
+    let toml = get_toml(&manifest);
+    assert!(toml["dependencies"]["my-package"].as_str().is_some());
+
+    // Existing executables are left alone.
+    std::fs::remove_file(&add).unwrap();
+    std::fs::write(&add, "not a link").unwrap();
+    assert_cli::Assert::command(&[
+        get_command_path("edit").as_str(),
+        "edit",
+        "link",
+        "--dir",
+        bin_dir.path().to_str().unwrap(),
+    ])
+    .succeeds()
+    .and()
+    .stdout()
+    .contains("Skipping")
+    .unwrap();
+    assert_eq!(std::fs::read_to_string(&add).unwrap(), "not a link");
+}
+
+#[test]
+fn undo_keeps_changes_made_since() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let target_dir = tempfile::tempdir().unwrap();
+    let command = |args: &[&str]| {
+        let mut command = vec![get_command_path(args[0])];
+        command.extend(args.iter().map(|arg| arg.to_string()));
+        command.push(format!("--manifest-path={}", manifest));
+        assert_cli::Assert::command(&command).with_env(
+            assert_cli::Environment::inherit()
+                .insert("CARGO_IS_TEST", "1")
+                .insert("CARGO_TARGET_DIR", target_dir.path()),
+        )
+    };
+
+    let original = std::fs::read_to_string(&manifest).unwrap();
+    command(&["add", "my-package"]).succeeds().unwrap();
+    // The journal is kept in the target directory cargo uses for the workspace.
+    assert!(target_dir.path().join("cargo-edit/journal").is_dir());
+
+    let changed = std::fs::read_to_string(&manifest).unwrap() + "\n# changed\n";
+    std::fs::write(&manifest, &changed).unwrap();
+    command(&["edit", "undo", "--dry-run"])
+        .succeeds()
+        .and()
+        .stdout()
+        .contains("(changed since)")
+        .unwrap();
+    command(&["edit", "undo"])
+        .fails_with(1)
+        .and()
+        .stderr()
+        .contains("pass '--force' to undo it anyway")
+        .unwrap();
+    assert_eq!(std::fs::read_to_string(&manifest).unwrap(), changed);
+
+    command(&["edit", "undo", "--force"]).succeeds().unwrap();
+    assert_eq!(std::fs::read_to_string(&manifest).unwrap(), original);
+}
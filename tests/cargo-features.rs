@@ -0,0 +1,195 @@
+#[macro_use]
+extern crate pretty_assertions;
+
+use std::fs;
+use std::path::Path;
+
+mod utils;
+use crate::utils::get_command_path;
+
+/// Write a workspace whose members enable different features of the same dependency.
+fn write_workspace(root: &Path) {
+    let member = |name: &str, manifest: &str| {
+        let dir = root.join(name);
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/lib.rs"), "").unwrap();
+        fs::write(dir.join("Cargo.toml"), manifest).unwrap();
+    };
+    fs::write(
+        root.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"one\", \"two\"]\n",
+    )
+    .unwrap();
+    member(
+        "one",
+        r#"[package]
+name = "one"
+version = "0.1.0"
+
+[dependencies]
+tokio = { version = "1.0", features = ["rt"] }
+serde = "1.0"
+
+[features]
+default = ["full"]
+full = ["tokio/full"]
+"#,
+    );
+    member(
+        "two",
+        r#"[package]
+name = "two"
+version = "0.1.0"
+
+[dependencies]
+runtime = { package = "tokio", version = "1.0", default-features = false, features = ["rt"] }
+serde = { version = "1.0", features = ["derive"] }
+"#,
+    );
+}
+
+#[test]
+fn reports_features_by_member() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    write_workspace(tmpdir.path());
+
+    let output = std::process::Command::new(get_command_path("features"))
+        .args(["features"])
+        .current_dir(tmpdir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "\
+serde (used by one, two)
+    default  one, two
+  * derive   two
+tokio (used by one, two)
+  * default  one
+  * full     one (default -> full)
+    rt       one, two
+
+* Enabled for every member that uses the dependency, though only some of them ask for it.
+"
+    );
+}
+
+#[test]
+fn reports_named_dependencies() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    write_workspace(tmpdir.path());
+
+    let output = std::process::Command::new(get_command_path("features"))
+        .args(["features", "serde"])
+        .current_dir(tmpdir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("serde (used by one, two)\n"));
+    assert!(!stdout.contains("tokio"));
+
+    let output = std::process::Command::new(get_command_path("features"))
+        .args(["features", "rand"])
+        .current_dir(tmpdir.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn renames_features_across_the_workspace() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    write_workspace(tmpdir.path());
+    let two = tmpdir.path().join("two/Cargo.toml");
+    let manifest = fs::read_to_string(&two).unwrap();
+    fs::write(
+        &two,
+        format!(
+            "{}one = {{ path = \"../one\", features = [\"full\"] }}\n\n[features]\nall = [\"one/full\"]\n",
+            manifest
+        ),
+    )
+    .unwrap();
+
+    let output = std::process::Command::new(get_command_path("features"))
+        .args(["features", "rename", "full", "complete", "--package", "one"])
+        .current_dir(tmpdir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "    Renaming feature `full` of one to `complete`\n    Updating references to it in two\n"
+    );
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("Packages outside the workspace"));
+
+    let one = fs::read_to_string(tmpdir.path().join("one/Cargo.toml")).unwrap();
+    assert!(one.contains("default = [\"complete\"]\ncomplete = [\"tokio/full\"]\n"));
+    let two = fs::read_to_string(&two).unwrap();
+    assert!(two.contains("one = { path = \"../one\", features = [\"complete\"] }"));
+    assert!(two.contains("all = [\"one/complete\"]"));
+
+    let output = std::process::Command::new(get_command_path("features"))
+        .args(["features", "rename", "full", "complete", "--package", "one"])
+        .current_dir(tmpdir.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn adds_weak_feature_entries() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    write_workspace(tmpdir.path());
+    let two = tmpdir.path().join("two/Cargo.toml");
+    let manifest = fs::read_to_string(&two).unwrap();
+    fs::write(
+        &two,
+        manifest.replace(
+            "serde = { version = \"1.0\", features = [\"derive\"] }",
+            "serde = { version = \"1.0\", features = [\"derive\"], optional = true }\n\n[features]\ndefault = [\"serde\"]",
+        ),
+    )
+    .unwrap();
+
+    let output = std::process::Command::new(get_command_path("features"))
+        .args([
+            "features",
+            "add",
+            "std",
+            "serde?/std",
+            "dep:serde",
+            "--package",
+            "two",
+        ])
+        .current_dir(tmpdir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "      Adding `serde?/std`, `dep:serde` to feature `std` of two\n"
+    );
+    let manifest = fs::read_to_string(&two).unwrap();
+    assert!(manifest.contains("default = [\"dep:serde\"]\nstd = [\"serde?/std\", \"dep:serde\"]\n"));
+
+    // `?` only applies to optional dependencies.
+    let output = std::process::Command::new(get_command_path("features"))
+        .args([
+            "features",
+            "add",
+            "std",
+            "runtime?/full",
+            "--package",
+            "two",
+        ])
+        .current_dir(tmpdir.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert_eq!(fs::read_to_string(&two).unwrap(), manifest);
+}
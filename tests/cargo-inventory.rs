@@ -0,0 +1,104 @@
+use std::fs;
+
+mod utils;
+use crate::utils::{clone_out_test, get_command_path};
+
+/// A package with a path dependency, which depends on a crate from the local registry.
+fn inventory_test() -> (tempfile::TempDir, String) {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/inventory/Cargo.toml.source");
+    let registry = std::env::current_dir()
+        .unwrap()
+        .join("tests/fixtures/local-registry");
+    fs::create_dir(tmpdir.path().join("helper")).unwrap();
+    fs::copy(
+        "tests/fixtures/inventory/helper/Cargo.toml",
+        tmpdir.path().join("helper").join("Cargo.toml"),
+    )
+    .unwrap();
+    fs::write(tmpdir.path().join("dummy.rs"), "").unwrap();
+    fs::create_dir(tmpdir.path().join(".cargo")).unwrap();
+    fs::write(
+        tmpdir.path().join(".cargo").join("config"),
+        format!(
+            "[source.crates-io]\nreplace-with = \"fixture\"\n\n\
+             [source.fixture]\nlocal-registry = {:?}\n",
+            registry.to_str().unwrap()
+        ),
+    )
+    .unwrap();
+    (tmpdir, manifest)
+}
+
+fn helper_source(tmpdir: &tempfile::TempDir) -> String {
+    format!(
+        "path+{}",
+        url::Url::from_file_path(tmpdir.path().join("helper")).unwrap()
+    )
+}
+
+#[test]
+fn exports_direct_dependencies_as_json() {
+    let (tmpdir, manifest) = inventory_test();
+
+    assert_cli::Assert::command(&[
+        get_command_path("inventory").as_str(),
+        "inventory",
+        "--offline",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .current_dir(tmpdir.path())
+    .succeeds()
+    .and()
+    .stdout()
+    .is(format!(
+        r#"[
+  {{
+    "name": "helper",
+    "requirement": "*",
+    "version": "0.1.0",
+    "license": "MIT",
+    "source": "{}",
+    "repository": null,
+    "direct": true
+  }}
+]"#,
+        helper_source(&tmpdir)
+    )
+    .as_str())
+    .unwrap();
+}
+
+#[test]
+fn exports_transitive_dependencies_as_csv() {
+    let (tmpdir, manifest) = inventory_test();
+
+    assert_cli::Assert::command(&[
+        get_command_path("inventory").as_str(),
+        "inventory",
+        "--offline",
+        "--transitive",
+        "--format",
+        "csv",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .current_dir(tmpdir.path())
+    .succeeds()
+    .and()
+    .stdout()
+    .is(format!(
+        "\
+name,requirement,version,license,source,repository,direct
+helper,*,0.1.0,MIT,{},,true
+my-prerelease,,0.12.0-rc.3,MIT OR Apache-2.0,registry+https://github.com/rust-lang/crates.io-index,\
+https://github.com/example/my-prerelease,false",
+        helper_source(&tmpdir)
+    )
+    .as_str())
+    .unwrap();
+
+    // Nothing but the lock file is written.
+    assert_eq!(
+        fs::read_to_string(&manifest).unwrap(),
+        fs::read_to_string("tests/fixtures/inventory/Cargo.toml.source").unwrap()
+    );
+}
@@ -0,0 +1,265 @@
+#[macro_use]
+extern crate pretty_assertions;
+
+mod utils;
+use crate::utils::{
+    clone_out_test, execute_bad_command, execute_command, get_command_path, get_toml,
+};
+
+#[test]
+fn sets_typed_values() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(
+        &[
+            "manifest",
+            "set",
+            "package.description",
+            "A command line tool",
+        ],
+        &manifest,
+    );
+    execute_command(
+        &["manifest", "set", "package.categories", r#"["cli"]"#],
+        &manifest,
+    );
+    execute_command(&["manifest", "set", "package.publish", "false"], &manifest);
+    execute_command(
+        &["manifest", "set", "profile.release.opt-level", "3"],
+        &manifest,
+    );
+    execute_command(
+        &["manifest", "set", "package.version", "1.0", "--string"],
+        &manifest,
+    );
+
+    let toml = get_toml(&manifest);
+    let package = &toml["package"];
+    assert_eq!(package["description"].as_str(), Some("A command line tool"));
+    let categories = package["categories"].as_array().unwrap();
+    assert_eq!(
+        categories.iter().map(|c| c.as_str()).collect::<Vec<_>>(),
+        vec![Some("cli")]
+    );
+    assert_eq!(package["publish"].as_bool(), Some(false));
+    assert_eq!(package["version"].as_str(), Some("1.0"));
+    assert_eq!(
+        toml["profile"]["release"]["opt-level"].as_integer(),
+        Some(3)
+    );
+    assert!(!toml.to_string().contains("[profile]"));
+}
+
+#[test]
+fn keeps_string_fields_strings() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(&["manifest", "set", "package.version", "1.0"], &manifest);
+    execute_command(&["manifest", "set", "package.edition", "2021"], &manifest);
+    execute_command(
+        &["manifest", "set", "package.rust-version", "1.70"],
+        &manifest,
+    );
+
+    let toml = get_toml(&manifest);
+    let package = &toml["package"];
+    assert_eq!(package["version"].as_str(), Some("1.0"));
+    assert_eq!(package["edition"].as_str(), Some("2021"));
+    assert_eq!(package["rust-version"].as_str(), Some("1.70"));
+}
+
+#[test]
+fn sets_quoted_keys_in_inline_tables() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(
+        &[
+            "manifest",
+            "set",
+            r#"dependencies."my.crate""#,
+            r#"{ version = "1.0" }"#,
+        ],
+        &manifest,
+    );
+    execute_command(
+        &[
+            "manifest",
+            "set",
+            r#"dependencies."my.crate".features"#,
+            r#"["derive"]"#,
+        ],
+        &manifest,
+    );
+
+    let toml = get_toml(&manifest);
+    let dep = &toml["dependencies"]["my.crate"];
+    assert_eq!(dep["version"].as_str(), Some("1.0"));
+    assert_eq!(dep["features"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn gets_values() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    execute_command(
+        &["manifest", "set", "package.categories", r#"["cli"]"#],
+        &manifest,
+    );
+
+    assert_cli::Assert::command(&[
+        get_command_path("manifest").as_str(),
+        "manifest",
+        "get",
+        "package.name",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .succeeds()
+    .and()
+    .stdout()
+    .is("cargo-list-test-fixture")
+    .unwrap();
+
+    assert_cli::Assert::command(&[
+        get_command_path("manifest").as_str(),
+        "manifest",
+        "get",
+        "package.categories",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .succeeds()
+    .and()
+    .stdout()
+    .is(r#"["cli"]"#)
+    .unwrap();
+}
+
+#[test]
+fn gets_values_as_json() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    execute_command(
+        &["manifest", "set", "package.categories", r#"["cli"]"#],
+        &manifest,
+    );
+
+    assert_cli::Assert::command(&[
+        get_command_path("manifest").as_str(),
+        "manifest",
+        "get",
+        "package.categories",
+        "--output",
+        "json",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .succeeds()
+    .and()
+    .stdout()
+    .is("[\n  \"cli\"\n]")
+    .unwrap();
+}
+
+#[test]
+fn gets_values_inherited_from_the_workspace() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let member = tmpdir.path().join("member");
+    std::fs::create_dir_all(member.join("src")).unwrap();
+    std::fs::write(member.join("src/lib.rs"), "").unwrap();
+    std::fs::write(
+        tmpdir.path().join("Cargo.toml"),
+        r#"[workspace]
+members = ["member"]
+
+[workspace.package]
+version = "1.2.3"
+
+[workspace.dependencies]
+serde = { version = "1.0", features = ["derive"] }
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        member.join("Cargo.toml"),
+        r#"[package]
+name = "member"
+version = { workspace = true }
+
+[dependencies]
+serde = { workspace = true, features = ["rc"] }
+"#,
+    )
+    .unwrap();
+
+    let get = |key: &str| {
+        let output = std::process::Command::new(get_command_path("manifest"))
+            .args(["manifest", "get", key, "--output", "json", "-p", "member"])
+            .current_dir(tmpdir.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    assert_eq!(get("package.version"), "\"1.2.3\"\n");
+    assert_eq!(
+        get("dependencies.serde.features"),
+        "[\n  \"derive\",\n  \"rc\"\n]\n"
+    );
+}
+
+#[test]
+fn fails_to_get_missing_values() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_bad_command(&["manifest", "get", "package.description"], &manifest);
+    execute_bad_command(&["manifest", "get", "package..name"], &manifest);
+}
+
+#[test]
+fn fails_to_set_below_non_table() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_bad_command(&["manifest", "set", "package.name.first", "x"], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert_eq!(
+        toml["package"]["name"].as_str(),
+        Some("cargo-list-test-fixture")
+    );
+}
+
+#[test]
+fn lints_and_fixes_manifests() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let contents = std::fs::read_to_string(&manifest).unwrap();
+    std::fs::write(
+        &manifest,
+        format!(
+            "{}\n[dependencies]\nserde = {{ version = \"1.0\", default-features = true }}\nrand = \"*\"\n",
+            contents
+        ),
+    )
+    .unwrap();
+
+    execute_bad_command(&["manifest", "lint"], &manifest);
+    assert_cli::Assert::command(&[
+        get_command_path("manifest").as_str(),
+        "manifest",
+        "lint",
+        "--fix",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .fails()
+    .and()
+    .stdout()
+    .is(
+        "fixed redundant-default-features: `serde` in `[dependencies]` sets `default-features = \
+         true`, which is the default\nwildcard-requirement: `rand` in `[dependencies]` allows any \
+         version with `*`, which crates.io rejects",
+    )
+    .unwrap();
+
+    let toml = get_toml(&manifest);
+    assert!(toml["dependencies"]["serde"]["default-features"].is_none());
+
+    let manifest = tmpdir.path().join("Cargo.toml");
+    let contents = std::fs::read_to_string(&manifest).unwrap();
+    std::fs::write(&manifest, contents.replace("\"*\"", "\"0.8\"")).unwrap();
+    execute_command(&["manifest", "lint"], manifest.to_str().unwrap());
+}
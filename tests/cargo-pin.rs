@@ -0,0 +1,79 @@
+#[macro_use]
+extern crate pretty_assertions;
+
+use std::fs;
+
+mod utils;
+use crate::utils::{
+    clone_out_test, execute_bad_command, execute_command, get_command_path, get_toml,
+};
+
+/// A package with dependencies, and a lock file for them.
+fn pin_test() -> (tempfile::TempDir, String) {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/pin/Cargo.toml.source");
+    fs::copy(
+        "tests/fixtures/pin/Cargo.lock",
+        tmpdir.path().join("Cargo.lock"),
+    )
+    .unwrap();
+    (tmpdir, manifest)
+}
+
+#[test]
+fn pins_to_locked_versions() {
+    let (_tmpdir, manifest) = pin_test();
+
+    execute_command(&["pin"], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dependencies"]["serde"].as_str(), Some("=1.0.118"));
+    let docopt = &toml["dependencies"]["docopt"];
+    assert_eq!(docopt["version"].as_str(), Some("=0.8.3"));
+    assert_eq!(docopt["features"][0].as_str(), Some("unstable"));
+    assert!(toml["dependencies"]["my-git"]["version"].is_none());
+    assert_eq!(toml["dev-dependencies"]["regex"].as_str(), Some("=1.3.0"));
+    // Renamed dependencies are pinned to the version of the crate their requirement matches.
+    assert_eq!(
+        toml["dev-dependencies"]["old-serde"]["version"].as_str(),
+        Some("=0.9.15")
+    );
+}
+
+#[test]
+fn pins_selected_dependencies() {
+    let (_tmpdir, manifest) = pin_test();
+
+    execute_command(&["pin", "docopt"], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert_eq!(
+        toml["dependencies"]["docopt"]["version"].as_str(),
+        Some("=0.8.3")
+    );
+    assert_eq!(toml["dependencies"]["serde"].as_str(), Some("1.0"));
+}
+
+#[test]
+fn dry_run_leaves_requirements_unchanged() {
+    let (_tmpdir, manifest) = pin_test();
+
+    assert_cli::Assert::command(&[
+        get_command_path("pin").as_str(),
+        "pin",
+        "--dry-run",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .stdout()
+    .contains("Pinning serde 1.0 -> =1.0.118")
+    .unwrap();
+
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dependencies"]["serde"].as_str(), Some("1.0"));
+}
+
+#[test]
+fn fails_to_pin_missing_dependency() {
+    let (_tmpdir, manifest) = pin_test();
+
+    execute_bad_command(&["pin", "failure"], &manifest);
+}
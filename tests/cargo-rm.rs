@@ -242,3 +242,484 @@ fn rm_dependency_from_workspace_member() {
 
     assert!(one["dependencies"]["libc"].as_str().is_none());
 }
+
+#[test]
+fn rm_optional_dependency_updates_features() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+    std::fs::write(
+        &manifest,
+        r#"[package]
+name = "cargo-rm-test-fixture"
+version = "0.1.0"
+
+[dependencies]
+serde = { version = "1.0", optional = true }
+semver = "0.7"
+
+[features]
+default = ["serde", "std"]
+derive = ["serde/derive", "semver/serde"]
+std = ["semver?/std"]
+"#,
+    )
+    .unwrap();
+
+    assert_cli::Assert::command(&[
+        get_command_path("rm").as_str(),
+        "rm",
+        "serde",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .succeeds()
+    .and()
+    .stderr()
+    .contains("The `serde` feature, which cargo creates for the optional dependency")
+    .unwrap();
+
+    let toml = get_toml(&manifest);
+    let entries = |feature: &str| -> Vec<String> {
+        toml["features"][feature]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry.as_str().unwrap().to_owned())
+            .collect()
+    };
+    assert_eq!(entries("default"), vec!["std"]);
+    assert_eq!(entries("derive"), vec!["semver/serde"]);
+    assert_eq!(entries("std"), vec!["semver?/std"]);
+}
+
+#[test]
+fn rm_dependency_by_version() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+    std::fs::write(
+        &manifest,
+        r#"[package]
+name = "cargo-rm-test-fixture"
+version = "0.1.0"
+
+[dependencies]
+rand08 = { package = "rand", version = "0.8" }
+rand07 = { package = "rand", version = "0.7.3" }
+"#,
+    )
+    .unwrap();
+
+    assert_cli::Assert::command(&[
+        get_command_path("rm").as_str(),
+        "rm",
+        "rand",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .fails_with(1)
+    .and()
+    .stderr()
+    .contains("`rand` matches more than one dependency: `rand08` (0.8), `rand07` (0.7.3).")
+    .unwrap();
+
+    execute_command(&["rm", "rand@0.7"], &manifest);
+    let toml = get_toml(&manifest);
+    assert!(toml["dependencies"]["rand07"].is_none());
+    assert!(!toml["dependencies"]["rand08"].is_none());
+
+    // With one entry left, the package name selects it.
+    execute_command(&["rm", "rand"], &manifest);
+    assert!(get_toml(&manifest)["dependencies"].is_none());
+}
+
+#[test]
+fn rm_dependency_from_target() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+    std::fs::write(
+        &manifest,
+        r#"[package]
+name = "cargo-rm-test-fixture"
+version = "0.1.0"
+
+[dependencies]
+winapi = "0.3"
+
+[target.'cfg(windows)'.dependencies]
+winapi = "0.3"
+
+[target.'cfg(unix)'.dependencies]
+libc = "0.2"
+winapi = "0.3"
+"#,
+    )
+    .unwrap();
+
+    assert_cli::Assert::command(&[
+        get_command_path("rm").as_str(),
+        "rm",
+        "winapi",
+        "--target",
+        "cfg(windows)",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .succeeds()
+    .and()
+    .stdout()
+    .is("Removing winapi from dependencies for target `cfg(windows)`")
+    .unwrap();
+
+    // The emptied target table is gone, and the other sections are untouched.
+    let toml = get_toml(&manifest);
+    assert!(toml["target"]["cfg(windows)"].is_none());
+    assert!(!toml["target"]["cfg(unix)"]["dependencies"]["winapi"].is_none());
+    assert!(!toml["dependencies"]["winapi"].is_none());
+
+    assert_cli::Assert::command(&[
+        get_command_path("rm").as_str(),
+        "rm",
+        "libc",
+        "--target",
+        "cfg(windows)",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .fails_with(1)
+    .and()
+    .stderr()
+    .contains("The table `target.cfg(windows).dependencies` could not be found.")
+    .unwrap();
+
+    execute_command(&["rm", "winapi", "--target", "cfg(unix)"], &manifest);
+    let toml = get_toml(&manifest);
+    assert!(toml["target"]["cfg(unix)"]["dependencies"]["winapi"].is_none());
+    assert!(!toml["target"]["cfg(unix)"]["dependencies"]["libc"].is_none());
+}
+
+#[test]
+fn rm_runs_edit_hooks() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+    let write_manifest = |hooks: &str| {
+        std::fs::write(
+            &manifest,
+            format!(
+                r#"[package]
+name = "cargo-rm-test-fixture"
+version = "0.1.0"
+
+[package.metadata.cargo-edit]
+{}
+
+[dependencies]
+docopt = "0.6"
+semver = "0.7"
+"#,
+                hooks
+            ),
+        )
+        .unwrap();
+    };
+    let cargo_home = tempfile::tempdir().unwrap();
+    let rm = |dependency: &str| {
+        assert_cli::Assert::command(&[
+            get_command_path("rm").as_str(),
+            "rm",
+            dependency,
+            &format!("--manifest-path={}", manifest),
+        ])
+        .with_env(
+            assert_cli::Environment::inherit()
+                .insert("CARGO_IS_TEST", "1")
+                .insert("CARGO_HOME", cargo_home.path()),
+        )
+    };
+
+    // Hooks only run in workspaces that the user trusts.
+    write_manifest(r#"post-edit = [["sh", "-c", "echo edited $0 > hooks.log"]]"#);
+    rm("docopt")
+        .succeeds()
+        .and()
+        .stderr()
+        .contains("Not running the hooks configured in")
+        .unwrap();
+    assert!(get_toml(&manifest)["dependencies"]["docopt"].is_none());
+    assert!(!tmpdir.path().join("hooks.log").exists());
+
+    std::fs::write(
+        cargo_home.path().join("config.toml"),
+        format!(
+            "[cargo-edit]\ntrusted-hooks = [{:?}]\n",
+            tmpdir.path().to_str().unwrap()
+        ),
+    )
+    .unwrap();
+
+    // Hooks get the manifest's path as their last argument, `$0` for `sh -c`.
+    write_manifest(r#"post-edit = [["sh", "-c", "echo edited $0 > hooks.log"]]"#);
+    rm("docopt").succeeds().unwrap();
+    assert!(get_toml(&manifest)["dependencies"]["docopt"].is_none());
+    let log = std::fs::read_to_string(tmpdir.path().join("hooks.log")).unwrap();
+    assert!(log.starts_with("edited "));
+    assert!(log.trim_end().ends_with("Cargo.toml"));
+
+    for hooks in &[
+        r#"pre-edit = [["sh", "-c", "exit 1"]]"#,
+        r#"post-edit = [["sh", "-c", "exit 1"]]"#,
+    ] {
+        write_manifest(hooks);
+        let original = std::fs::read_to_string(&manifest).unwrap();
+        rm("docopt")
+            .fails_with(1)
+            .and()
+            .stderr()
+            .contains("The hook `sh -c exit 1` failed for")
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&manifest).unwrap(), original);
+    }
+}
+
+#[test]
+fn rm_unused_dependencies() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+    let src = tmpdir.path().join("src");
+    std::fs::create_dir(&src).unwrap();
+    std::fs::write(
+        src.join("main.rs"),
+        "extern crate docopt;\nuse rustc_serialize::json;\n\nfn main() {\n    let _ = toml::Parser::new(\"\");\n}\n",
+    )
+    .unwrap();
+    std::fs::write(tmpdir.path().join("build.rs"), "use semver::Version;\n").unwrap();
+    std::fs::write(src.join("tests.rs"), "use regex::Regex;\n").unwrap();
+
+    // Unused dependencies are only listed unless '--yes' is supplied. Offline, cargo can't resolve
+    // the fixture's dependencies, so they are looked for by their names in the manifest.
+    let original = std::fs::read_to_string(&manifest).unwrap();
+    assert_cli::Assert::command(&[
+        get_command_path("rm").as_str(),
+        "rm",
+        "--unused",
+        "--offline",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .succeeds()
+    .and()
+    .stdout()
+    .contains("Unused pad in dependencies")
+    .and()
+    .stderr()
+    .contains("remove them with '--unused --yes'")
+    .unwrap();
+    assert_eq!(std::fs::read_to_string(&manifest).unwrap(), original);
+
+    execute_command(&["rm", "--unused", "--yes", "--offline"], &manifest);
+    let toml = get_toml(&manifest);
+    // The build script refers to semver, which is kept in every section it is in.
+    for dep in &["docopt", "rustc-serialize", "toml", "semver"] {
+        assert!(!toml["dependencies"][dep].is_none());
+    }
+    for dep in &["pad", "clippy"] {
+        assert!(toml["dependencies"][dep].is_none());
+    }
+    assert!(!toml["build-dependencies"]["semver"].is_none());
+    assert!(!toml["dev-dependencies"]["regex"].is_none());
+    assert!(toml["dev-dependencies"]["serde"].is_none());
+}
+
+#[test]
+fn rm_unused_resolves_library_names() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let package = |dir: &str, manifest: &str, lib: &str| {
+        let dir = tmpdir.path().join(dir);
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("Cargo.toml"), manifest).unwrap();
+        std::fs::write(dir.join("src/lib.rs"), lib).unwrap();
+        dir.join("Cargo.toml")
+    };
+    package(
+        "fuzzy-matcher",
+        "[package]\nname = \"fuzzy-matcher\"\nversion = \"0.1.0\"\n\n[lib]\nname = \"fuzzy\"\n",
+        "",
+    );
+    package(
+        "native",
+        "[package]\nname = \"native\"\nversion = \"0.1.0\"\nlinks = \"native\"\nbuild = \"build.rs\"\n",
+        "",
+    );
+    std::fs::write(tmpdir.path().join("native/build.rs"), "fn main() {}\n").unwrap();
+    package(
+        "unused",
+        "[package]\nname = \"unused\"\nversion = \"0.1.0\"\n",
+        "",
+    );
+    let manifest = package(
+        "app",
+        r#"[package]
+name = "app"
+version = "0.1.0"
+
+[dependencies]
+fuzzy-matcher = { path = "../fuzzy-matcher" }
+native = { path = "../native" }
+unused = { path = "../unused" }
+"#,
+        "use fuzzy::Matcher;\n",
+    );
+    let manifest = manifest.to_str().unwrap();
+
+    execute_command(&["rm", "--unused", "--yes", "--offline"], manifest);
+    let toml = get_toml(manifest);
+    assert!(!toml["dependencies"]["fuzzy-matcher"].is_none());
+    assert!(!toml["dependencies"]["native"].is_none());
+    assert!(toml["dependencies"]["unused"].is_none());
+}
+
+#[test]
+fn rm_prunes_lockfile() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let package = |dir: &str, name: &str, deps: &str| {
+        let dir = tmpdir.path().join(dir);
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(
+            dir.join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"{}\"\nversion = \"0.1.0\"\n\n[dependencies]\n{}",
+                name, deps
+            ),
+        )
+        .unwrap();
+        std::fs::write(dir.join("src/lib.rs"), "").unwrap();
+        dir.join("Cargo.toml")
+    };
+    let manifest = package(
+        "",
+        "demo",
+        "helper = { path = \"helper\" }\nkept = { path = \"kept\" }\n",
+    );
+    package("helper", "helper", "leaf = { path = \"../leaf\" }\n");
+    package("leaf", "leaf", "");
+    package("kept", "kept", "");
+    let manifest = manifest.to_str().unwrap();
+
+    assert_cli::Assert::command(&["cargo", "generate-lockfile", "--offline"])
+        .current_dir(tmpdir.path())
+        .succeeds()
+        .unwrap();
+
+    assert_cli::Assert::command(&[
+        get_command_path("rm").as_str(),
+        "rm",
+        "helper",
+        "--prune-lockfile",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .succeeds()
+    .and()
+    .stdout()
+    .contains("Pruning helper v0.1.0 from Cargo.lock")
+    .and()
+    .stdout()
+    .contains("Pruning leaf v0.1.0 from Cargo.lock")
+    .unwrap();
+
+    let lockfile = std::fs::read_to_string(tmpdir.path().join("Cargo.lock")).unwrap();
+    assert!(!lockfile.contains("name = \"helper\""));
+    assert!(!lockfile.contains("name = \"leaf\""));
+    assert!(lockfile.contains("name = \"kept\""));
+
+    // Pruning changes the lock file, which `--frozen` rules out.
+    assert_cli::Assert::command(&[
+        get_command_path("rm").as_str(),
+        "rm",
+        "kept",
+        "--prune-lockfile",
+        "--frozen",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .fails_with(1)
+    .unwrap();
+    assert!(get_toml(manifest)["dependencies"]["kept"].is_table_like());
+}
+
+#[test]
+fn rm_prunes_overrides() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let package = |dir: &str, contents: &str| {
+        let dir = tmpdir.path().join(dir);
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("Cargo.toml"), contents).unwrap();
+        std::fs::write(dir.join("src/lib.rs"), "").unwrap();
+        dir.join("Cargo.toml").to_str().unwrap().to_owned()
+    };
+    let root = package(
+        "",
+        r#"[package]
+name = "demo"
+version = "0.1.0"
+
+[workspace]
+members = ["member"]
+
+[dependencies]
+regex = "1.0"
+serde = "1.0"
+
+[patch.crates-io]
+regex = { git = "https://example.com/regex" }
+serde = { git = "https://example.com/serde" }
+
+[profile.release.package.regex]
+opt-level = 3
+
+[profile.dev.package.serde]
+opt-level = 1
+"#,
+    );
+    let member = package(
+        "member",
+        "[package]\nname = \"member\"\nversion = \"0.1.0\"\n\n[dependencies]\nserde = \"1.0\"\n",
+    );
+
+    // Without the flag, the entries of crates nothing depends on anymore are only pointed out.
+    assert_cli::Assert::command(&[
+        get_command_path("rm").as_str(),
+        "rm",
+        "regex",
+        "serde",
+        "--dry-run",
+        &format!("--manifest-path={}", root),
+    ])
+    .succeeds()
+    .and()
+    .stderr()
+    .contains("note: `regex` in `[patch.crates-io]` refers to `regex`")
+    .and()
+    .stderr()
+    .contains("note: `regex` in `[profile.release.package]` refers to `regex`")
+    .and()
+    .stderr()
+    .doesnt_contain("refers to `serde`")
+    .unwrap();
+
+    execute_command(&["rm", "regex", "serde", "--prune-overrides"], &root);
+    let toml = get_toml(&root);
+    assert!(toml["dependencies"].is_none());
+    assert!(toml["patch"]["crates-io"]["regex"].is_none());
+    assert!(toml["profile"]["release"].is_none());
+    // The member still depends on serde.
+    assert!(!toml["patch"]["crates-io"]["serde"].is_none());
+    assert!(!toml["profile"]["dev"]["package"]["serde"].is_none());
+
+    // Removing it from the member prunes the root manifest's entries.
+    assert_cli::Assert::command(&[
+        get_command_path("rm").as_str(),
+        "rm",
+        "serde",
+        "--prune-overrides",
+        &format!("--manifest-path={}", member),
+    ])
+    .succeeds()
+    .and()
+    .stdout()
+    .contains("Removing serde from patch.crates-io")
+    .and()
+    .stdout()
+    .contains("Removing serde from profile.dev.package")
+    .unwrap();
+    let toml = get_toml(&root);
+    assert!(toml["patch"].is_none());
+    assert!(toml["profile"].is_none());
+}
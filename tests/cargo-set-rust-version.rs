@@ -0,0 +1,315 @@
+#[macro_use]
+extern crate pretty_assertions;
+
+use std::fs;
+use std::process;
+
+mod utils;
+use crate::utils::{
+    clone_out_test, copy_workspace_test, execute_bad_command, execute_command,
+    execute_command_in_dir, get_toml,
+};
+
+#[test]
+fn sets_rust_version() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(&["set-rust-version", "1.56"], &manifest);
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["package"]["rust-version"].as_str(), Some("1.56"));
+
+    // An existing version is replaced, whether it is higher or lower.
+    execute_command(&["set-rust-version", "1.51.0"], &manifest);
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["package"]["rust-version"].as_str(), Some("1.51.0"));
+}
+
+#[test]
+fn sets_rust_version_of_current_toolchain() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(&["set-rust-version", "--current-toolchain"], &manifest);
+
+    let rustc = process::Command::new("rustc")
+        .arg("--version")
+        .output()
+        .unwrap();
+    let rustc = String::from_utf8(rustc.stdout).unwrap();
+    let installed = rustc.split_whitespace().nth(1).unwrap();
+
+    let toml = get_toml(&manifest);
+    let version = toml["package"]["rust-version"].as_str().unwrap();
+    assert!(installed.starts_with(&format!("{}.", version)));
+}
+
+#[test]
+fn dry_run_leaves_rust_version_unchanged() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(&["set-rust-version", "1.56", "--dry-run"], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert!(toml["package"]["rust-version"].is_none());
+}
+
+#[test]
+fn fails_to_set_invalid_rust_version() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_bad_command(&["set-rust-version", "1.x"], &manifest);
+    execute_bad_command(&["set-rust-version", "1.56.0-nightly"], &manifest);
+    execute_bad_command(&["set-rust-version"], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert!(toml["package"]["rust-version"].is_none());
+}
+
+#[test]
+fn sets_rust_version_across_workspace() {
+    let (tmpdir, root_manifest, workspace_manifests) = copy_workspace_test();
+
+    execute_command(
+        &[
+            "set-rust-version",
+            "1.56",
+            "--workspace",
+            "--skip-dependency-check",
+        ],
+        &root_manifest,
+    );
+
+    for manifest in &workspace_manifests {
+        let toml = get_toml(manifest);
+        assert_eq!(toml["package"]["rust-version"].as_str(), Some("1.56"));
+    }
+
+    // The virtual manifest is left alone, and targeting it updates all members.
+    assert!(get_toml(&root_manifest)["package"].is_none());
+    let call = process::Command::new(utils::get_command_path("set-rust-version"))
+        .args(["set-rust-version", "1.60", "--skip-dependency-check"])
+        .current_dir(tmpdir.path())
+        .output()
+        .unwrap();
+    assert!(call.status.success());
+
+    for manifest in workspace_manifests {
+        let toml = get_toml(&manifest);
+        assert_eq!(toml["package"]["rust-version"].as_str(), Some("1.60"));
+    }
+}
+
+#[test]
+fn sets_inherited_rust_version_in_workspace() {
+    let tmpdir = tempfile::tempdir().expect("failed to construct temporary directory");
+    let member = tmpdir.path().join("member");
+    fs::create_dir(&member).unwrap();
+    fs::write(member.join("lib.rs"), "").unwrap();
+    fs::write(
+        tmpdir.path().join("Cargo.toml"),
+        r#"[workspace]
+members = ["member"]
+
+[workspace.package]
+rust-version = "1.56"
+"#,
+    )
+    .unwrap();
+    fs::write(
+        member.join("Cargo.toml"),
+        r#"[package]
+name = "member"
+version = "0.1.0"
+rust-version = { workspace = true }
+
+[lib]
+path = "lib.rs"
+"#,
+    )
+    .unwrap();
+    let root_manifest = tmpdir.path().join("Cargo.toml");
+    let root_manifest = root_manifest.to_str().unwrap();
+    let manifest = member.join("Cargo.toml");
+    let manifest = manifest.to_str().unwrap();
+
+    let assert_inherited = |version| {
+        let toml = get_toml(manifest);
+        assert_eq!(
+            toml["package"]["rust-version"]["workspace"].as_bool(),
+            Some(true)
+        );
+        let root = get_toml(root_manifest);
+        assert_eq!(
+            root["workspace"]["package"]["rust-version"].as_str(),
+            Some(version)
+        );
+    };
+
+    execute_command(
+        &["set-rust-version", "1.60", "--skip-dependency-check"],
+        manifest,
+    );
+    assert_inherited("1.60");
+
+    execute_command(
+        &[
+            "set-rust-version",
+            "1.64",
+            "--workspace",
+            "--skip-dependency-check",
+        ],
+        root_manifest,
+    );
+    assert_inherited("1.64");
+}
+
+#[test]
+fn runs_workspace_hooks_once() {
+    let (tmpdir, root_manifest, workspace_manifests) = copy_workspace_test();
+    let mut root = fs::read_to_string(&root_manifest).unwrap();
+    root.push_str(
+        r#"
+[workspace.metadata.cargo-edit]
+post-edit = [["sh", "-c", "echo \"$@\" >> hooks.log", "hook"]]
+"#,
+    );
+    fs::write(&root_manifest, root).unwrap();
+    let cargo_home = tempfile::tempdir().unwrap();
+    fs::write(
+        cargo_home.path().join("config.toml"),
+        format!(
+            "[cargo-edit]\ntrusted-hooks = [{:?}]\n",
+            tmpdir.path().to_str().unwrap()
+        ),
+    )
+    .unwrap();
+
+    let call = process::Command::new(utils::get_command_path("set-rust-version"))
+        .args(["set-rust-version", "1.56", "--workspace"])
+        .arg("--skip-dependency-check")
+        .arg(format!("--manifest-path={}", root_manifest))
+        .env("CARGO_IS_TEST", "1")
+        .env("CARGO_HOME", cargo_home.path())
+        .output()
+        .unwrap();
+    assert!(call.status.success(), "{:?}", call);
+
+    let log = fs::read_to_string(tmpdir.path().join("hooks.log")).unwrap();
+    assert_eq!(log.lines().count(), 1);
+    assert_eq!(
+        log.split_whitespace().count(),
+        workspace_manifests.len(),
+        "{}",
+        log
+    );
+}
+
+#[test]
+fn checks_rust_version_of_dependencies() {
+    let tmpdir = tempfile::tempdir().expect("failed to construct temporary directory");
+    let package = tmpdir.path().join("package");
+    let dependency = tmpdir.path().join("dependency");
+    for dir in &[&package, &dependency] {
+        fs::create_dir(dir).unwrap();
+        fs::write(dir.join("lib.rs"), "").unwrap();
+    }
+    fs::write(
+        package.join("Cargo.toml"),
+        r#"[package]
+name = "package"
+version = "0.1.0"
+
+[lib]
+path = "lib.rs"
+
+[dependencies]
+dependency = { path = "../dependency" }
+"#,
+    )
+    .unwrap();
+    fs::write(
+        dependency.join("Cargo.toml"),
+        r#"[package]
+name = "dependency"
+version = "0.1.0"
+rust-version = "1.60"
+
+[lib]
+path = "lib.rs"
+"#,
+    )
+    .unwrap();
+    let manifest = package.join("Cargo.toml");
+    let manifest = manifest.to_str().unwrap();
+
+    // The dependency needs a newer Rust.
+    execute_bad_command(&["set-rust-version", "1.56"], manifest);
+    assert!(get_toml(manifest)["package"]["rust-version"].is_none());
+
+    // Unless the check is skipped.
+    execute_command(
+        &["set-rust-version", "1.56", "--skip-dependency-check"],
+        manifest,
+    );
+    assert_eq!(
+        get_toml(manifest)["package"]["rust-version"].as_str(),
+        Some("1.56")
+    );
+
+    execute_command_in_dir(&["set-rust-version", "1.60"], &package);
+    assert_eq!(
+        get_toml(manifest)["package"]["rust-version"].as_str(),
+        Some("1.60")
+    );
+}
+
+#[test]
+fn frozen_leaves_lockfile_alone() {
+    let tmpdir = tempfile::tempdir().expect("failed to construct temporary directory");
+    let package = tmpdir.path().join("package");
+    let dependency = tmpdir.path().join("dependency");
+    for dir in &[&package, &dependency] {
+        fs::create_dir(dir).unwrap();
+        fs::write(dir.join("lib.rs"), "").unwrap();
+    }
+    fs::write(
+        package.join("Cargo.toml"),
+        r#"[package]
+name = "package"
+version = "0.1.0"
+
+[lib]
+path = "lib.rs"
+
+[dependencies]
+dependency = { path = "../dependency" }
+"#,
+    )
+    .unwrap();
+    fs::write(
+        dependency.join("Cargo.toml"),
+        r#"[package]
+name = "dependency"
+version = "0.1.0"
+
+[lib]
+path = "lib.rs"
+"#,
+    )
+    .unwrap();
+    let manifest = package.join("Cargo.toml");
+    let manifest = manifest.to_str().unwrap();
+
+    // Checking the dependencies would write the missing lock file.
+    execute_bad_command(&["set-rust-version", "1.56", "--frozen"], manifest);
+    assert!(!package.join("Cargo.lock").exists());
+    assert!(get_toml(manifest)["package"]["rust-version"].is_none());
+
+    execute_command(&["set-rust-version", "1.56", "--offline"], manifest);
+    assert!(package.join("Cargo.lock").exists());
+
+    execute_command(&["set-rust-version", "1.60", "--frozen"], manifest);
+    assert_eq!(
+        get_toml(manifest)["package"]["rust-version"].as_str(),
+        Some("1.60")
+    );
+}
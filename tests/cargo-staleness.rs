@@ -0,0 +1,81 @@
+use std::fs;
+
+mod utils;
+use crate::utils::{clone_out_test, execute_bad_command, get_command_path};
+
+/// A package with dependencies from the local registry, and a lock file for them.
+fn staleness_test() -> (tempfile::TempDir, String) {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/staleness/Cargo.toml.source");
+    fs::copy(
+        "tests/fixtures/staleness/Cargo.lock",
+        tmpdir.path().join("Cargo.lock"),
+    )
+    .unwrap();
+    (tmpdir, manifest)
+}
+
+fn local_registry() -> String {
+    std::env::current_dir()
+        .unwrap()
+        .join("tests/fixtures/local-registry")
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+#[test]
+fn reports_locked_allowed_and_latest_versions() {
+    let (_tmpdir, manifest) = staleness_test();
+
+    assert_cli::Assert::command(&[
+        get_command_path("staleness").as_str(),
+        "staleness",
+        "--local-registry",
+        &local_registry(),
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .and()
+    .stdout()
+    .is("\
+name           requirement  locked  allowed  latest  behind  age
+my-package     0.1          0.1.0   0.1.0    0.2.0   1       -
+my-prerelease  >=0.11       0.11.0  0.12.0   0.12.0  1       -  lock is a minor step behind
+
+Releases behind: 2 in all, 1.0 per dependency, with 0 of 2 dependencies up to date
+1 of 2 dependencies are locked a minor or major step behind what their requirement allows, \
+which `cargo update` would pick up")
+    .unwrap();
+}
+
+#[test]
+fn reports_selected_dependencies() {
+    let (_tmpdir, manifest) = staleness_test();
+
+    assert_cli::Assert::command(&[
+        get_command_path("staleness").as_str(),
+        "staleness",
+        "my-package",
+        "--local-registry",
+        &local_registry(),
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .and()
+    .stdout()
+    .is("\
+name        requirement  locked  allowed  latest  behind  age
+my-package  0.1          0.1.0   0.1.0    0.2.0   1       -
+
+Releases behind: 1 in all, 1.0 per dependency, with 0 of 1 dependencies up to date")
+    .unwrap();
+}
+
+#[test]
+fn fails_to_report_on_missing_dependency() {
+    let (_tmpdir, manifest) = staleness_test();
+
+    execute_bad_command(&["staleness", "failure"], &manifest);
+}
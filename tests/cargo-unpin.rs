@@ -0,0 +1,38 @@
+#[macro_use]
+extern crate pretty_assertions;
+
+mod utils;
+use crate::utils::{clone_out_test, execute_bad_command, execute_command, get_toml};
+
+#[test]
+fn unpins_exact_requirements() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/pin/Cargo.toml.source");
+
+    execute_command(&["unpin"], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dev-dependencies"]["regex"].as_str(), Some("1.3.0"));
+    // Requirements that aren't pins are left alone.
+    assert_eq!(toml["dependencies"]["serde"].as_str(), Some("1.0"));
+    assert_eq!(
+        toml["dependencies"]["docopt"]["version"].as_str(),
+        Some("0.8")
+    );
+}
+
+#[test]
+fn dry_run_leaves_pins_unchanged() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/pin/Cargo.toml.source");
+
+    execute_command(&["unpin", "regex", "--dry-run"], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dev-dependencies"]["regex"].as_str(), Some("=1.3.0"));
+}
+
+#[test]
+fn fails_to_unpin_missing_dependency() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/pin/Cargo.toml.source");
+
+    execute_bad_command(&["unpin", "failure"], &manifest);
+}
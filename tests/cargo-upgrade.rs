@@ -3,8 +3,9 @@ extern crate pretty_assertions;
 
 mod utils;
 use crate::utils::{
-    clone_out_test, copy_workspace_test, execute_command, execute_command_for_pkg,
-    execute_command_in_dir, get_command_path, get_toml, setup_alt_registry_config,
+    clone_out_test, copy_workspace_test, execute_bad_command, execute_command,
+    execute_command_for_pkg, execute_command_in_dir, get_command_path, get_toml,
+    setup_alt_registry_config,
 };
 
 // Verify that an upgraded Cargo.toml matches what we expect.
@@ -148,6 +149,181 @@ fn upgrade_specified_only() {
     assert_eq!(dependencies["env_proxy"].as_str(), Some("0.1.1"));
 }
 
+#[test]
+fn upgrade_multiple_manifests() {
+    let (_tmpdir, first) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let (_tmpdir2, second) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(&["add", "docopt", "--vers", "0.8"], &first);
+    execute_command(&["add", "docopt", "--vers", "0.6"], &second);
+    execute_command(&["add", "pad", "--vers", "0.1"], &second);
+
+    execute_command(&["upgrade", "--manifest-path", first.as_str()], &second);
+
+    for manifest in &[&first, &second] {
+        assert_eq!(
+            get_toml(manifest)["dependencies"]["docopt"].as_str(),
+            Some("docopt--CURRENT_VERSION_TEST")
+        );
+    }
+    assert_eq!(
+        get_toml(&second)["dependencies"]["pad"].as_str(),
+        Some("pad--CURRENT_VERSION_TEST")
+    );
+}
+
+#[test]
+fn upgrade_multiple_manifests_keeps_overrides_apart() {
+    let (_tmpdir, patched) = clone_out_test("tests/fixtures/upgrade/Cargo.toml.overridden");
+    let (_tmpdir2, other) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    execute_command(&["add", "docopt", "--vers", "0.8"], &other);
+
+    assert_cli::Assert::command(&[
+        get_command_path("upgrade").as_str(),
+        "upgrade",
+        "--manifest-path",
+        &patched,
+        "--manifest-path",
+        &other,
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .and()
+    .stderr()
+    .contains("Not upgrading `docopt`, as it is overridden by `[patch.crates-io]`")
+    .unwrap();
+
+    // Only the workspace with the patch keeps its requirement.
+    assert_eq!(
+        get_toml(&patched)["dependencies"]["docopt"].as_str(),
+        Some("0.8")
+    );
+    assert_eq!(
+        get_toml(&other)["dependencies"]["docopt"].as_str(),
+        Some("docopt--CURRENT_VERSION_TEST")
+    );
+}
+
+#[test]
+fn upgrade_multiple_manifests_with_their_own_registries() {
+    let (_tmpdir, first) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let (tmpdir, second) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    execute_command(&["add", "docopt", "--vers", "0.8"], &first);
+    execute_command(&["add", "my-package", "--vers", "0.1"], &second);
+    // Only the second manifest looks crates up in the local registry.
+    let registry = std::env::current_dir()
+        .unwrap()
+        .join("tests/fixtures/local-registry");
+    std::fs::create_dir(tmpdir.path().join(".cargo")).unwrap();
+    std::fs::write(
+        tmpdir.path().join(".cargo").join("config"),
+        format!(
+            "[source.crates-io]\nreplace-with = \"fixture\"\n\n\
+             [source.fixture]\nlocal-registry = {:?}\n",
+            registry.to_str().unwrap()
+        ),
+    )
+    .unwrap();
+
+    execute_command(&["upgrade", "--manifest-path", first.as_str()], &second);
+
+    assert_eq!(
+        get_toml(&first)["dependencies"]["docopt"].as_str(),
+        Some("docopt--CURRENT_VERSION_TEST")
+    );
+    assert_eq!(
+        get_toml(&second)["dependencies"]["my-package"].as_str(),
+        Some("0.2")
+    );
+}
+
+#[test]
+fn upgrade_recursive() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let root = tmpdir.path();
+    let status = std::process::Command::new("git")
+        .args(["init", "--quiet"])
+        .current_dir(root)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    std::fs::write(root.join(".gitignore"), "/vendored\n").unwrap();
+
+    let dirs = ["first", "samples/second", "target/built", "vendored"];
+    for dir in &dirs {
+        std::fs::create_dir_all(root.join(dir)).unwrap();
+        let manifest = root.join(dir).join("Cargo.toml");
+        std::fs::copy("tests/fixtures/add/Cargo.toml.sample", &manifest).unwrap();
+        execute_command(
+            &["add", "docopt", "--vers", "0.8"],
+            manifest.to_str().unwrap(),
+        );
+    }
+
+    execute_command_in_dir(&["upgrade", "--recursive", "."], root);
+
+    // Manifests in `target` and git-ignored directories are left alone.
+    for (dir, expected) in dirs.iter().zip(&[
+        "docopt--CURRENT_VERSION_TEST",
+        "docopt--CURRENT_VERSION_TEST",
+        "0.8",
+        "0.8",
+    ]) {
+        let manifest = root.join(dir).join("Cargo.toml");
+        assert_eq!(
+            get_toml(manifest.to_str().unwrap())["dependencies"]["docopt"].as_str(),
+            Some(*expected),
+            "{}",
+            dir
+        );
+    }
+}
+
+#[test]
+fn upgrade_syncs_path_dependencies_with_local_versions() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let root = tmpdir.path();
+    let write_crate = |dir: &str, manifest: &str| {
+        std::fs::create_dir_all(root.join(dir).join("src")).unwrap();
+        std::fs::write(root.join(dir).join("src/lib.rs"), "").unwrap();
+        std::fs::write(root.join(dir).join("Cargo.toml"), manifest).unwrap();
+    };
+    write_crate(
+        "local",
+        "[package]\nname = \"local\"\nversion = \"0.2.3\"\n",
+    );
+    write_crate(
+        "unversioned",
+        "[package]\nname = \"unversioned\"\nversion = \"1.0.0\"\n",
+    );
+    write_crate(
+        "app",
+        r#"[package]
+name = "app"
+version = "0.1.0"
+
+[dependencies]
+docopt = "0.8"
+local = { path = "../local", version = "0.1" }
+unversioned = { path = "../unversioned" }
+"#,
+    );
+    let manifest = root.join("app/Cargo.toml");
+    let manifest = manifest.to_str().unwrap();
+
+    execute_command(&["upgrade"], manifest);
+
+    // The local crate's version is used, not the registry's.
+    let dependencies = &get_toml(manifest)["dependencies"];
+    assert_eq!(
+        dependencies["docopt"].as_str(),
+        Some("docopt--CURRENT_VERSION_TEST")
+    );
+    assert_eq!(dependencies["local"]["version"].as_str(), Some("0.2.3"));
+    assert_eq!(dependencies["local"]["path"].as_str(), Some("../local"));
+    assert!(dependencies["unversioned"]["version"].is_none());
+}
+
 #[test]
 fn upgrade_skip_compatible() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
@@ -159,10 +335,353 @@ fn upgrade_skip_compatible() {
 
     // Verify that `test_breaking` was upgraded, but not `test_nonbreaking`
     let dependencies = &get_toml(&manifest)["dependencies"];
-    assert_eq!(dependencies["test_breaking"].as_str(), Some("0.2.0"));
+    assert_eq!(dependencies["test_breaking"].as_str(), Some("0.2"));
     assert_eq!(dependencies["test_nonbreaking"].as_str(), Some("0.1"));
 }
 
+#[test]
+fn upgrade_concretize_wildcards() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(&["add", "test_breaking", "--vers", "*"], &manifest);
+    execute_command(&["add", "test_nonbreaking", "--vers", "0.1"], &manifest);
+
+    // Wildcards are left alone by default.
+    execute_command(&["upgrade"], &manifest);
+    let dependencies = &get_toml(&manifest)["dependencies"];
+    assert_eq!(dependencies["test_breaking"].as_str(), Some("*"));
+    assert_eq!(dependencies["test_nonbreaking"].as_str(), Some("0.1"));
+
+    execute_command(&["upgrade", "--concretize-wildcards"], &manifest);
+    let dependencies = &get_toml(&manifest)["dependencies"];
+    assert_eq!(dependencies["test_breaking"].as_str(), Some("0.2.0"));
+}
+
+/// A package depending on `my-prerelease` from the local registry, alongside a path dependency
+/// that requires an exact version of it. Cargo reads the source replacement from the directory it
+/// is run in.
+fn explain_test() -> (tempfile::TempDir, String) {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/explain/Cargo.toml.source");
+    let registry = std::env::current_dir()
+        .unwrap()
+        .join("tests/fixtures/local-registry");
+    std::fs::create_dir(tmpdir.path().join("helper")).unwrap();
+    std::fs::copy(
+        "tests/fixtures/explain/helper/Cargo.toml",
+        tmpdir.path().join("helper").join("Cargo.toml"),
+    )
+    .unwrap();
+    std::fs::write(tmpdir.path().join("dummy.rs"), "").unwrap();
+    std::fs::create_dir(tmpdir.path().join(".cargo")).unwrap();
+    std::fs::write(
+        tmpdir.path().join(".cargo").join("config"),
+        format!(
+            "[source.crates-io]\nreplace-with = \"fixture\"\n\n\
+             [source.fixture]\nlocal-registry = {:?}\n",
+            registry.to_str().unwrap()
+        ),
+    )
+    .unwrap();
+    (tmpdir, manifest)
+}
+
+#[test]
+fn upgrade_explain_reports_blocking_requirement() {
+    let (tmpdir, manifest) = explain_test();
+    let original = std::fs::read_to_string(&manifest).unwrap();
+    let modified = std::fs::metadata(&manifest).unwrap().modified().unwrap();
+
+    assert_cli::Assert::command(&[
+        get_command_path("upgrade").as_str(),
+        "upgrade",
+        "--explain",
+        "my-prerelease",
+        "--offline",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .current_dir(tmpdir.path())
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .and()
+    .stdout()
+    .contains("my-prerelease v0.12.0 by:\n    helper v0.1.0 requires =0.12.0-rc.3")
+    .unwrap();
+
+    // Nothing is changed, or even written.
+    assert_eq!(std::fs::read_to_string(&manifest).unwrap(), original);
+    let metadata = std::fs::metadata(&manifest).unwrap();
+    assert_eq!(metadata.modified().unwrap(), modified);
+}
+
+#[test]
+fn upgrade_explain_resolvable() {
+    let (tmpdir, manifest) = explain_test();
+
+    assert_cli::Assert::command(&[
+        get_command_path("upgrade").as_str(),
+        "upgrade",
+        "--explain",
+        "my-prerelease@0.11.0",
+        "--offline",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .current_dir(tmpdir.path())
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .and()
+    .stdout()
+    .contains("my-prerelease v0.11.0, nothing blocks the upgrade")
+    .unwrap();
+}
+
+#[test]
+fn upgrade_skips_overridden_dependencies() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/upgrade/Cargo.toml.overridden");
+
+    assert_cli::Assert::command(&[
+        get_command_path("upgrade").as_str(),
+        "upgrade",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .and()
+    .stderr()
+    .contains("Not upgrading `docopt`, as it is overridden by `[patch.crates-io]`")
+    .unwrap();
+
+    let dependencies = &get_toml(&manifest)["dependencies"];
+    assert_eq!(dependencies["docopt"].as_str(), Some("0.8"));
+    assert_eq!(
+        dependencies["pad"].as_str(),
+        Some("pad--CURRENT_VERSION_TEST")
+    );
+
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/upgrade/Cargo.toml.replaced");
+
+    assert_cli::Assert::command(&[
+        get_command_path("upgrade").as_str(),
+        "upgrade",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .and()
+    .stderr()
+    .contains("Not upgrading `serde_json`, as it is overridden by `[replace]`")
+    .unwrap();
+
+    let dependencies = &get_toml(&manifest)["dependencies"];
+    assert_eq!(dependencies["serde_json"].as_str(), Some("1.0"));
+    assert_eq!(
+        dependencies["docopt"].as_str(),
+        Some("docopt--CURRENT_VERSION_TEST")
+    );
+}
+
+#[test]
+fn upgrade_explicit_overridden_dependency() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/upgrade/Cargo.toml.overridden");
+
+    assert_cli::Assert::command(&[
+        get_command_path("upgrade").as_str(),
+        "upgrade",
+        "docopt",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .and()
+    .stderr()
+    .contains("`docopt` is overridden by `[patch.crates-io]`")
+    .unwrap();
+
+    let dependencies = &get_toml(&manifest)["dependencies"];
+    assert_eq!(
+        dependencies["docopt"].as_str(),
+        Some("docopt--CURRENT_VERSION_TEST")
+    );
+}
+
+#[test]
+fn downgrade_to_specified_version() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(&["add", "docopt", "--vers", "1.0"], &manifest);
+
+    assert_cli::Assert::command(&[
+        get_command_path("upgrade").as_str(),
+        "upgrade",
+        "docopt@0.9",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .and()
+    .stdout()
+    .contains("docopt v1.0 -> v0.9")
+    .and()
+    .stdout()
+    .contains("Downgrading")
+    .unwrap();
+
+    let dependencies = &get_toml(&manifest)["dependencies"];
+    assert_eq!(dependencies["docopt"].as_str(), Some("0.9"));
+}
+
+#[test]
+fn upgrade_leaves_up_to_date_manifest_untouched() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(&["add", "test_nonbreaking", "--vers", "0.1.1"], &manifest);
+    let modified = std::fs::metadata(&manifest).unwrap().modified().unwrap();
+
+    assert_cli::Assert::command(&[
+        get_command_path("upgrade").as_str(),
+        "upgrade",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .and()
+    .stdout()
+    .contains("up to date")
+    .unwrap();
+
+    // The manifest was not rewritten.
+    assert_eq!(
+        std::fs::metadata(&manifest).unwrap().modified().unwrap(),
+        modified
+    );
+    let dependencies = &get_toml(&manifest)["dependencies"];
+    assert_eq!(dependencies["test_nonbreaking"].as_str(), Some("0.1.1"));
+}
+
+#[test]
+fn upgrade_warns_about_breaking_upgrades() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(&["add", "test_breaking", "--vers", "0.1"], &manifest);
+    execute_command(&["add", "test_nonbreaking", "--vers", "0.1"], &manifest);
+
+    assert_cli::Assert::command(&[
+        get_command_path("upgrade").as_str(),
+        "upgrade",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .and()
+    .stderr()
+    .contains("semver incompatible")
+    .and()
+    .stderr()
+    .contains("test_breaking -> 0.2.0 (in cargo-list-test-fixture)")
+    .and()
+    .stderr()
+    .doesnt_contain("test_nonbreaking")
+    .unwrap();
+}
+
+#[test]
+fn upgrade_prints_diff() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/upgrade/Cargo.toml.source");
+    let original = std::fs::read_to_string(&manifest).unwrap();
+
+    assert_cli::Assert::command(&[
+        get_command_path("upgrade").as_str(),
+        "upgrade",
+        "docopt",
+        "--diff",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .and()
+    .stdout()
+    .contains(
+        "[dependencies]\n-docopt = \"0.8\"\n+docopt = \"docopt--CURRENT_VERSION_TEST\"\n pad = \"0.1\"",
+    )
+    .and()
+    .stdout()
+    .doesnt_contain("Upgrading")
+    .unwrap();
+
+    assert_eq!(std::fs::read_to_string(&manifest).unwrap(), original);
+}
+
+/// Run `cargo upgrade --message-format json-lines` and parse the events it prints.
+fn upgrade_events(args: &[&str], manifest: &str) -> Vec<serde_json::Value> {
+    let output = std::process::Command::new(get_command_path("upgrade"))
+        .args(["upgrade", "--message-format", "json-lines"])
+        .args(args)
+        .arg(format!("--manifest-path={}", manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("every line is a JSON event"))
+        .collect()
+}
+
+#[test]
+fn upgrade_emits_json_lines() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/upgrade/Cargo.toml.overridden");
+
+    let events = upgrade_events(&[], &manifest);
+    let reasons: Vec<_> = events
+        .iter()
+        .map(|e| e["reason"].as_str().unwrap())
+        .collect();
+
+    assert_eq!(reasons[0], "dependency-skipped");
+    assert_eq!(events[0]["dependency"], "docopt");
+    assert_eq!(
+        reasons.iter().filter(|r| **r == "lookup-started").count(),
+        2
+    );
+    assert_eq!(
+        reasons.iter().filter(|r| **r == "lookup-finished").count(),
+        2
+    );
+    assert_eq!(reasons[reasons.len() - 2], "manifest-written");
+
+    let plan = events.last().unwrap();
+    assert_eq!(plan["reason"], "plan");
+    assert_eq!(plan["dry_run"], false);
+    let mut upgraded: Vec<_> = plan["upgrades"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|u| {
+            (
+                u["dependency"].as_str().unwrap(),
+                u["old"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    upgraded.sort();
+    assert_eq!(upgraded, vec![("pad", "0.1"), ("serde_json", "1.0")]);
+}
+
+#[test]
+fn upgrade_json_lines_dry_run() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/upgrade/Cargo.toml.source");
+    let original = std::fs::read_to_string(&manifest).unwrap();
+
+    let events = upgrade_events(&["docopt", "--dry-run"], &manifest);
+
+    assert!(events.iter().all(|e| e["reason"] != "manifest-written"));
+    let plan = events.last().unwrap();
+    assert_eq!(plan["dry_run"], true);
+    assert_eq!(plan["upgrades"][0]["dependency"], "docopt");
+    assert_eq!(plan["upgrades"][0]["new"], "docopt--CURRENT_VERSION_TEST");
+    assert_eq!(std::fs::read_to_string(&manifest).unwrap(), original);
+}
+
 #[test]
 fn fails_to_upgrade_missing_dependency() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
@@ -232,103 +751,538 @@ fn upgrade_renamed_dependency_with_exclude() {
 fn upgrade_renamed_dependency_all() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/upgrade/Cargo.toml.renamed_dep");
 
-    execute_command(&["upgrade"], &manifest);
+    execute_command(&["upgrade"], &manifest);
+
+    let toml = get_toml(&manifest);
+
+    let dep1 = &toml["dependencies"]["te"];
+    assert_eq!(
+        dep1["version"].as_str(),
+        Some("toml_edit--CURRENT_VERSION_TEST")
+    );
+
+    let dep2 = &toml["dependencies"]["rx"];
+    assert_eq!(
+        dep2["version"].as_str(),
+        Some("regex--CURRENT_VERSION_TEST")
+    );
+}
+
+#[test]
+fn upgrade_renamed_dependency_inline_specified_only() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/upgrade/Cargo.toml.renamed_dep");
+
+    execute_command(&["upgrade", "toml_edit"], &manifest);
+
+    let toml = get_toml(&manifest);
+    let dep = &toml["dependencies"]["te"];
+    assert_eq!(
+        dep["version"].as_str(),
+        Some("toml_edit--CURRENT_VERSION_TEST")
+    );
+}
+
+#[test]
+fn upgrade_renamed_dependency_table_specified_only() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/upgrade/Cargo.toml.renamed_dep");
+
+    execute_command(&["upgrade", "regex"], &manifest);
+
+    let toml = get_toml(&manifest);
+    let dep = &toml["dependencies"]["rx"];
+    assert_eq!(dep["version"].as_str(), Some("regex--CURRENT_VERSION_TEST"));
+}
+
+#[test]
+fn upgrade_renamed_dependency_entry_only() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/upgrade/Cargo.toml.renamed_dep");
+    let contents = std::fs::read_to_string(&manifest).unwrap();
+    std::fs::write(
+        &manifest,
+        contents.replace("[dependencies]\n", "[dependencies]\nregex = \"0.1\"\n"),
+    )
+    .unwrap();
+
+    // `regex` would select both entries for the regex crate, `rx` only the renamed one.
+    execute_command(&["upgrade", "rx"], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dependencies"]["regex"].as_str(), Some("0.1"));
+    let dep = &toml["dependencies"]["rx"];
+    assert_eq!(dep["version"].as_str(), Some("regex--CURRENT_VERSION_TEST"));
+}
+
+#[test]
+fn upgrade_alt_registry_dependency_all() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/upgrade/Cargo.toml.alt_registry");
+    setup_alt_registry_config(tmpdir.path());
+
+    // The alternative registry test commands are run
+    // from the test directory, as cargo metadata probes for
+    // cargo config relative to the invocation directory, not
+    // the manifest path.
+    execute_command_in_dir(&["upgrade"], tmpdir.path());
+
+    let toml = get_toml(&manifest);
+
+    let dep1 = &toml["dependencies"]["toml_edit"];
+    assert_eq!(
+        dep1["version"].as_str(),
+        Some("toml_edit--CURRENT_VERSION_TEST")
+    );
+    assert_eq!(dep1["registry"].as_str(), Some("alternative"));
+
+    let dep2 = &toml["dependencies"]["regex"];
+    assert_eq!(
+        dep2["version"].as_str(),
+        Some("regex--CURRENT_VERSION_TEST")
+    );
+    assert_eq!(dep2["registry"].as_str(), Some("alternative"));
+}
+
+#[test]
+fn upgrade_alt_registry_dependency_inline_specified_only() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/upgrade/Cargo.toml.alt_registry");
+    setup_alt_registry_config(tmpdir.path());
+
+    execute_command_in_dir(&["upgrade", "toml_edit"], tmpdir.path());
+
+    let toml = get_toml(&manifest);
+    let dep = &toml["dependencies"]["toml_edit"];
+    assert_eq!(
+        dep["version"].as_str(),
+        Some("toml_edit--CURRENT_VERSION_TEST")
+    );
+    assert_eq!(dep["registry"].as_str(), Some("alternative"));
+}
+
+#[test]
+fn upgrade_alt_registry_dependency_table_specified_only() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/upgrade/Cargo.toml.alt_registry");
+    setup_alt_registry_config(tmpdir.path());
+
+    execute_command_in_dir(&["upgrade", "regex"], tmpdir.path());
+
+    let toml = get_toml(&manifest);
+    let dep = &toml["dependencies"]["regex"];
+    assert_eq!(dep["version"].as_str(), Some("regex--CURRENT_VERSION_TEST"));
+    assert_eq!(dep["registry"].as_str(), Some("alternative"));
+}
+
+#[test]
+fn upgrade_from_custom_index() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let index = std::env::current_dir()
+        .unwrap()
+        .join("tests/fixtures/local-registry/index");
+    let index = url::Url::from_directory_path(index).unwrap();
+
+    execute_command(&["add", "my-package", "--vers", "0.1"], &manifest);
+    execute_command(&["upgrade", "--index", index.as_str()], &manifest);
+
+    assert_eq!(
+        get_toml(&manifest)["dependencies"]["my-package"].as_str(),
+        Some("0.2")
+    );
+}
+
+#[test]
+fn upgrade_keeps_requirement_precision() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let registry = std::env::current_dir()
+        .unwrap()
+        .join("tests/fixtures/local-registry");
+
+    execute_command(&["add", "my-package", "--vers", "0.1"], &manifest);
+    execute_command(
+        &["add", "my-package", "--vers", "~0.1.0", "--dev"],
+        &manifest,
+    );
+    execute_command(
+        &["upgrade", "--local-registry", registry.to_str().unwrap()],
+        &manifest,
+    );
+
+    // The requirements name the new version as precisely as before, with the same operator.
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dependencies"]["my-package"].as_str(), Some("0.2"));
+    assert_eq!(
+        toml["dev-dependencies"]["my-package"].as_str(),
+        Some("~0.2.0")
+    );
+}
+
+#[test]
+fn upgrade_from_local_registry() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let registry = std::env::current_dir()
+        .unwrap()
+        .join("tests/fixtures/local-registry");
+
+    execute_command(&["add", "my-package", "--vers", "0.1"], &manifest);
+    execute_command(
+        &["upgrade", "--local-registry", registry.to_str().unwrap()],
+        &manifest,
+    );
+
+    assert_eq!(
+        get_toml(&manifest)["dependencies"]["my-package"].as_str(),
+        Some("0.2")
+    );
+}
+
+#[test]
+fn upgrade_keep_going_after_failed_lookup() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let registry = std::env::current_dir()
+        .unwrap()
+        .join("tests/fixtures/local-registry");
+
+    // `docopt` isn't in the local registry, so looking it up fails.
+    execute_command(&["add", "my-package", "--vers", "0.1"], &manifest);
+    execute_command(&["add", "docopt", "--vers", "0.8"], &manifest);
+
+    assert_cli::Assert::command(&[
+        get_command_path("upgrade").as_str(),
+        "upgrade",
+        "--keep-going",
+        "--local-registry",
+        registry.to_str().unwrap(),
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .fails()
+    .and()
+    .stderr()
+    .contains("docopt: The crate `docopt` could not be found in registry index.")
+    .unwrap();
+
+    let dependencies = &get_toml(&manifest)["dependencies"];
+    assert_eq!(dependencies["my-package"].as_str(), Some("0.2"));
+    assert_eq!(dependencies["docopt"].as_str(), Some("0.8"));
+}
+
+#[test]
+fn upgrade_looks_each_crate_up_once() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let registry = std::env::current_dir()
+        .unwrap()
+        .join("tests/fixtures/local-registry");
 
-    let toml = get_toml(&manifest);
+    // The renamed entry is a dependency of its own, but the same crate to look up.
+    execute_command(&["add", "docopt", "--vers", "0.8"], &manifest);
+    execute_command(
+        &[
+            "add",
+            "docopt",
+            "--vers",
+            "0.8",
+            "--dev",
+            "--rename",
+            "old-docopt",
+        ],
+        &manifest,
+    );
 
-    let dep1 = &toml["dependencies"]["te"];
+    let output = std::process::Command::new(get_command_path("upgrade"))
+        .args(["upgrade", "--keep-going", "--local-registry"])
+        .arg(&registry)
+        .arg(format!("--manifest-path={}", manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let failures: Vec<_> = stderr
+        .lines()
+        .filter(|line| line.trim_start().starts_with("docopt:"))
+        .collect();
     assert_eq!(
-        dep1["version"].as_str(),
-        Some("toml_edit--CURRENT_VERSION_TEST")
+        failures,
+        ["    docopt: The crate `docopt` could not be found in registry index."]
     );
 
-    let dep2 = &toml["dependencies"]["rx"];
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dependencies"]["docopt"].as_str(), Some("0.8"));
     assert_eq!(
-        dep2["version"].as_str(),
-        Some("regex--CURRENT_VERSION_TEST")
+        toml["dev-dependencies"]["old-docopt"]["version"].as_str(),
+        Some("0.8")
     );
 }
 
 #[test]
-fn upgrade_renamed_dependency_inline_specified_only() {
-    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/upgrade/Cargo.toml.renamed_dep");
+fn upgrade_in_batches() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let registry = std::env::current_dir()
+        .unwrap()
+        .join("tests/fixtures/local-registry");
+
+    execute_command(&["add", "my-package", "--vers", "0.1"], &manifest);
+    execute_command(&["add", "my-prerelease", "--vers", "0.11"], &manifest);
+
+    let git = |args: &[&str]| {
+        let output = std::process::Command::new("git")
+            .args(args)
+            .current_dir(tmpdir.path())
+            .env("GIT_AUTHOR_NAME", "test")
+            .env("GIT_AUTHOR_EMAIL", "test@example.com")
+            .env("GIT_COMMITTER_NAME", "test")
+            .env("GIT_COMMITTER_EMAIL", "test@example.com")
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?} failed", args);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    git(&["init", "--quiet", "--initial-branch=main"]);
+    git(&["add", "Cargo.toml"]);
+    git(&["commit", "--quiet", "--message", "Initial commit"]);
+
+    let output = std::process::Command::new(get_command_path("upgrade"))
+        .args(["upgrade", "--batch", "--message-format", "json-lines"])
+        .arg("--local-registry")
+        .arg(&registry)
+        .arg(format!("--manifest-path={}", manifest))
+        .env("CARGO_IS_TEST", "1")
+        .env("GIT_AUTHOR_NAME", "test")
+        .env("GIT_AUTHOR_EMAIL", "test@example.com")
+        .env("GIT_COMMITTER_NAME", "test")
+        .env("GIT_COMMITTER_EMAIL", "test@example.com")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let branches: Vec<serde_json::Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .filter(|event: &serde_json::Value| event["reason"] == "branch-created")
+        .collect();
+    assert_eq!(branches.len(), 2);
+    assert_eq!(branches[0]["dependency"], "my-package");
+    assert_eq!(branches[0]["branch"], "cargo-upgrade/my-package-0.2.0");
+    assert_eq!(branches[1]["branch"], "cargo-upgrade/my-prerelease-0.12.0");
+
+    // The checked-out branch is untouched.
+    assert_eq!(git(&["symbolic-ref", "--short", "HEAD"]).trim(), "main");
+    let dependencies = &get_toml(&manifest)["dependencies"];
+    assert_eq!(dependencies["my-package"].as_str(), Some("0.1"));
+    assert_eq!(dependencies["my-prerelease"].as_str(), Some("0.11"));
+
+    // Each branch holds a single commit, upgrading only its dependency.
+    for (branch, upgraded, untouched) in &[
+        (
+            "cargo-upgrade/my-package-0.2.0",
+            "my-package",
+            "my-prerelease",
+        ),
+        (
+            "cargo-upgrade/my-prerelease-0.12.0",
+            "my-prerelease",
+            "my-package",
+        ),
+    ] {
+        let commits = git(&["rev-list", "--count", &format!("main..{}", branch)]);
+        assert_eq!(commits.trim(), "1");
+        let diff = git(&["diff", &format!("main..{}", branch)]);
+        assert!(diff.contains(&format!("+{} = ", upgraded)));
+        assert!(!diff.contains(&format!("+{} = ", untouched)));
+    }
+}
 
-    execute_command(&["upgrade", "toml_edit"], &manifest);
+#[test]
+fn upgrade_reports_failed_lookup_as_json() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let registry = std::env::current_dir()
+        .unwrap()
+        .join("tests/fixtures/local-registry");
 
-    let toml = get_toml(&manifest);
-    let dep = &toml["dependencies"]["te"];
+    execute_command(&["add", "docopt", "--vers", "0.8"], &manifest);
+
+    let output = std::process::Command::new(get_command_path("upgrade"))
+        .args([
+            "upgrade",
+            "--message-format",
+            "json-lines",
+            "--local-registry",
+        ])
+        .arg(&registry)
+        .arg(format!("--manifest-path={}", manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    let failure: serde_json::Value = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .find(|event: &serde_json::Value| event["reason"] == "lookup-failed")
+        .expect("a lookup-failed event");
+    assert_eq!(failure["dependency"], "docopt");
+    assert_eq!(failure["kind"], "not-found");
+    assert_eq!(failure["retryable"], false);
+    assert!(failure["manifest"]
+        .as_str()
+        .unwrap()
+        .ends_with("Cargo.toml"));
     assert_eq!(
-        dep["version"].as_str(),
-        Some("toml_edit--CURRENT_VERSION_TEST")
+        get_toml(&manifest)["dependencies"]["docopt"].as_str(),
+        Some("0.8")
     );
 }
 
 #[test]
-fn upgrade_renamed_dependency_table_specified_only() {
-    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/upgrade/Cargo.toml.renamed_dep");
-
-    execute_command(&["upgrade", "regex"], &manifest);
+fn upgrade_requires_client_key_with_client_cert() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
 
-    let toml = get_toml(&manifest);
-    let dep = &toml["dependencies"]["rx"];
-    assert_eq!(dep["version"].as_str(), Some("regex--CURRENT_VERSION_TEST"));
+    assert_cli::Assert::command(&[
+        get_command_path("upgrade").as_str(),
+        "upgrade",
+        "--client-cert",
+        "client.pem",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .fails()
+    .and()
+    .stderr()
+    .contains("--client-key <path>")
+    .unwrap();
 }
 
 #[test]
-fn upgrade_alt_registry_dependency_all() {
-    let (tmpdir, manifest) = clone_out_test("tests/fixtures/upgrade/Cargo.toml.alt_registry");
-    setup_alt_registry_config(tmpdir.path());
-
-    // The alternative registry test commands are run
-    // from the test directory, as cargo metadata probes for
-    // cargo config relative to the invocation directory, not
-    // the manifest path.
-    execute_command_in_dir(&["upgrade"], tmpdir.path());
-
-    let toml = get_toml(&manifest);
+fn upgrade_graduates_prerelease_to_stable() {
+    let registry = std::env::current_dir()
+        .unwrap()
+        .join("tests/fixtures/local-registry");
 
-    let dep1 = &toml["dependencies"]["toml_edit"];
+    // `0.12.0` has been released since `0.12.0-rc.3`, so that is preferred over `0.13.0-alpha.1`.
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    execute_command(
+        &["add", "my-prerelease", "--vers", "0.12.0-rc.3"],
+        &manifest,
+    );
+    execute_command(
+        &["upgrade", "--local-registry", registry.to_str().unwrap()],
+        &manifest,
+    );
     assert_eq!(
-        dep1["version"].as_str(),
-        Some("toml_edit--CURRENT_VERSION_TEST")
+        get_toml(&manifest)["dependencies"]["my-prerelease"].as_str(),
+        Some("0.12.0")
     );
-    assert_eq!(dep1["registry"].as_str(), Some("alternative"));
 
-    let dep2 = &toml["dependencies"]["regex"];
+    // There is no stable release of `0.13.0` yet, so the prerelease is kept.
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    execute_command(
+        &["add", "my-prerelease", "--vers", "0.13.0-alpha.1"],
+        &manifest,
+    );
+    execute_command(
+        &["upgrade", "--local-registry", registry.to_str().unwrap()],
+        &manifest,
+    );
     assert_eq!(
-        dep2["version"].as_str(),
-        Some("regex--CURRENT_VERSION_TEST")
+        get_toml(&manifest)["dependencies"]["my-prerelease"].as_str(),
+        Some("0.13.0-alpha.1")
     );
-    assert_eq!(dep2["registry"].as_str(), Some("alternative"));
 }
 
 #[test]
-fn upgrade_alt_registry_dependency_inline_specified_only() {
-    let (tmpdir, manifest) = clone_out_test("tests/fixtures/upgrade/Cargo.toml.alt_registry");
-    setup_alt_registry_config(tmpdir.path());
+fn upgrade_from_replaced_source() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let vendor = std::env::current_dir()
+        .unwrap()
+        .join("tests/fixtures/vendor");
+    std::fs::create_dir(tmpdir.path().join(".cargo")).unwrap();
+    std::fs::write(
+        tmpdir.path().join(".cargo").join("config"),
+        format!(
+            "[source.crates-io]\n\
+             replace-with = \"vendored-sources\"\n\
+             \n\
+             [source.vendored-sources]\n\
+             directory = {:?}\n",
+            vendor.to_str().unwrap()
+        ),
+    )
+    .unwrap();
 
-    execute_command_in_dir(&["upgrade", "toml_edit"], tmpdir.path());
+    execute_command(&["add", "my-package", "--vers", "0.1"], &manifest);
+    execute_command_in_dir(&["upgrade"], tmpdir.path());
 
-    let toml = get_toml(&manifest);
-    let dep = &toml["dependencies"]["toml_edit"];
     assert_eq!(
-        dep["version"].as_str(),
-        Some("toml_edit--CURRENT_VERSION_TEST")
+        get_toml(&manifest)["dependencies"]["my-package"].as_str(),
+        Some("0.2")
     );
-    assert_eq!(dep["registry"].as_str(), Some("alternative"));
 }
 
 #[test]
-fn upgrade_alt_registry_dependency_table_specified_only() {
-    let (tmpdir, manifest) = clone_out_test("tests/fixtures/upgrade/Cargo.toml.alt_registry");
-    setup_alt_registry_config(tmpdir.path());
-
-    execute_command_in_dir(&["upgrade", "regex"], tmpdir.path());
-
-    let toml = get_toml(&manifest);
-    let dep = &toml["dependencies"]["regex"];
-    assert_eq!(dep["version"].as_str(), Some("regex--CURRENT_VERSION_TEST"));
-    assert_eq!(dep["registry"].as_str(), Some("alternative"));
+fn upgrade_to_lockfile_notes_breaking_releases() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    // A directory source to lock against, as cargo needs the crates themselves for that.
+    let vendor = tmpdir.path().join("vendor");
+    for version in &["0.1.0", "0.2.1"] {
+        let dir = vendor.join(format!("my-package-{}", version));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/lib.rs"), "").unwrap();
+        std::fs::write(
+            dir.join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"my-package\"\nversion = \"{}\"\n",
+                version
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(".cargo-checksum.json"),
+            r#"{"files":{},"package":null}"#,
+        )
+        .unwrap();
+    }
+    std::fs::create_dir(tmpdir.path().join(".cargo")).unwrap();
+    std::fs::write(
+        tmpdir.path().join(".cargo/config.toml"),
+        format!(
+            "[source.crates-io]\n\
+             replace-with = \"vendored-sources\"\n\
+             \n\
+             [source.vendored-sources]\n\
+             directory = {:?}\n",
+            vendor.to_str().unwrap()
+        ),
+    )
+    .unwrap();
+    execute_command(&["add", "my-package", "--vers", "0.1"], &manifest);
+    let status = std::process::Command::new("cargo")
+        .args(["generate-lockfile", "--offline", "--quiet"])
+        .current_dir(tmpdir.path())
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let upgrade = |extra: &[&str]| {
+        assert_cli::Assert::command(&[
+            get_command_path("upgrade").as_str(),
+            "upgrade",
+            "--to-lockfile",
+            "--dry-run",
+        ])
+        .with_args(extra)
+        .current_dir(tmpdir.path())
+        .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    };
+    upgrade(&[])
+        .succeeds()
+        .and()
+        .stderr()
+        .contains("note: my-package v0.1.0 -> v0.2.1 available, not applied (semver incompatible)")
+        .unwrap();
+    upgrade(&["--no-notices"])
+        .succeeds()
+        .and()
+        .stderr()
+        .doesnt_contain("note:")
+        .unwrap();
 }
 
 #[test]
@@ -344,7 +1298,7 @@ fn upgrade_at() {
     // Verify that `docopt` has been updated to the specified version.
     assert_eq!(
         get_toml(&manifest)["dependencies"]["docopt"].as_str(),
-        Some("1000000.0.0")
+        Some("1000000.0")
     );
 }
 
@@ -396,6 +1350,199 @@ fn upgrade_workspace_workspace() {
     }
 }
 
+#[test]
+fn upgrade_workspace_aligns_incompatible_requirements() {
+    let (_tmpdir, root_manifest, workspace_manifests) = copy_workspace_test();
+    let rand = |member: usize| {
+        get_toml(&workspace_manifests[member])["dependencies"]["rand"]
+            .as_str()
+            .map(ToOwned::to_owned)
+    };
+
+    // The members one and two require semver incompatible versions of rand.
+    assert_cli::Assert::command(&[
+        get_command_path("upgrade").as_str(),
+        "upgrade",
+        "--workspace",
+        "--dry-run",
+        &format!("--manifest-path={}", root_manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .and()
+    .stderr()
+    .contains("WARN: Members require incompatible versions of `rand`: 0.2 (in two), 0.3 (in one).")
+    .unwrap();
+
+    assert_cli::Assert::command(&[
+        get_command_path("upgrade").as_str(),
+        "upgrade",
+        "--workspace",
+        "--align",
+        "skip",
+        &format!("--manifest-path={}", root_manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .and()
+    .stderr()
+    .contains("note: Skipping `rand`")
+    .unwrap();
+    assert_eq!(rand(0).as_deref(), Some("0.3"));
+    assert_eq!(rand(1).as_deref(), Some("0.2"));
+    assert_eq!(
+        get_toml(&workspace_manifests[0])["dependencies"]["libc"].as_str(),
+        Some("libc--CURRENT_VERSION_TEST")
+    );
+
+    let (_tmpdir, root_manifest, workspace_manifests) = copy_workspace_test();
+    let rand = |member: usize| {
+        get_toml(&workspace_manifests[member])["dependencies"]["rand"]
+            .as_str()
+            .map(ToOwned::to_owned)
+    };
+    assert_cli::Assert::command(&[
+        get_command_path("upgrade").as_str(),
+        "upgrade",
+        "--workspace",
+        "--align",
+        "lowest",
+        &format!("--manifest-path={}", root_manifest),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    .succeeds()
+    .and()
+    .stderr()
+    .contains("note: Aligning `rand` on 0.2, the lowest of the incompatible versions")
+    .and()
+    .stdout()
+    .contains("rand v0.3 -> v0.2")
+    .unwrap();
+    assert_eq!(rand(0).as_deref(), Some("0.2"));
+    assert_eq!(rand(1).as_deref(), Some("0.2"));
+}
+
+#[test]
+fn upgrade_workspace_default_members() {
+    let (_tmpdir, root_manifest, workspace_manifests) = copy_workspace_test();
+    std::fs::write(
+        &root_manifest,
+        "[workspace]\nmembers = [\"one\", \"two\", \"explicit/*\"]\ndefault-members = [\"two\"]\n",
+    )
+    .unwrap();
+
+    execute_command(&["upgrade", "--workspace"], &root_manifest);
+
+    // Only `two` is a default member.
+    for workspace_member in &workspace_manifests {
+        let expected = if workspace_member.ends_with("two/Cargo.toml") {
+            "libc--CURRENT_VERSION_TEST"
+        } else {
+            "0.2.28"
+        };
+        assert_eq!(
+            get_toml(workspace_member)["dependencies"]["libc"].as_str(),
+            Some(expected)
+        );
+    }
+}
+
+#[test]
+fn upgrade_workspace_to_precise() {
+    let (_tmpdir, root_manifest, workspace_manifests) = copy_workspace_test();
+
+    execute_command(
+        &["upgrade", "libc@0.2.100", "--workspace", "--to-precise"],
+        &root_manifest,
+    );
+
+    // Every member is pinned to the same version.
+    for workspace_member in workspace_manifests {
+        assert_eq!(
+            get_toml(&workspace_member)["dependencies"]["libc"].as_str(),
+            Some("=0.2.100")
+        );
+    }
+
+    // Only a single version can be pinned.
+    execute_bad_command(
+        &["upgrade", "libc@0.2", "--workspace", "--to-precise"],
+        &root_manifest,
+    );
+}
+
+#[test]
+fn upgrade_workspace_all_members() {
+    let (_tmpdir, root_manifest, workspace_manifests) = copy_workspace_test();
+    std::fs::write(
+        &root_manifest,
+        "[workspace]\nmembers = [\"one\", \"two\", \"explicit/*\"]\ndefault-members = [\"two\"]\n",
+    )
+    .unwrap();
+
+    execute_command(&["upgrade", "--workspace", "--all-members"], &root_manifest);
+
+    for workspace_member in workspace_manifests {
+        assert_eq!(
+            get_toml(&workspace_member)["dependencies"]["libc"].as_str(),
+            Some("libc--CURRENT_VERSION_TEST")
+        );
+    }
+}
+
+#[test]
+fn upgrade_workspace_skips_excluded() {
+    let (_tmpdir, root_manifest, workspace_manifests) = copy_workspace_test();
+    std::fs::write(
+        &root_manifest,
+        "[workspace]\nmembers = [\"one\", \"two\", \"explicit/*\"]\nexclude = [\"implicit\"]\n",
+    )
+    .unwrap();
+
+    execute_command(&["upgrade"], &root_manifest);
+
+    // `three` is only a path dependency under an excluded directory.
+    for workspace_member in &workspace_manifests {
+        let expected = if workspace_member.ends_with("three/Cargo.toml") {
+            "0.2.28"
+        } else {
+            "libc--CURRENT_VERSION_TEST"
+        };
+        assert_eq!(
+            get_toml(workspace_member)["dependencies"]["libc"].as_str(),
+            Some(expected)
+        );
+    }
+}
+
+#[test]
+fn upgrade_workspace_groups_output_by_member() {
+    let (tmpdir, _root_manifest, _workspace_manifests) = copy_workspace_test();
+
+    let output = std::process::Command::new(get_command_path("upgrade"))
+        .args(["upgrade", "rand", "--workspace"])
+        .env("CARGO_IS_TEST", "1")
+        .current_dir(tmpdir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    // Each member gets a heading with its manifest path, followed by its own changes.
+    let section = |path: &str| {
+        let heading = format!(" ({}):\n", path);
+        let start = stdout.find(&heading).unwrap() + heading.len();
+        let end = stdout[start..]
+            .find("\n\n")
+            .map_or(stdout.len(), |end| start + end);
+        stdout[start..end].to_owned()
+    };
+    assert!(section("one/Cargo.toml").contains("rand v0.3 -> vrand--CURRENT_VERSION_TEST"));
+    assert!(section("two/Cargo.toml").contains("rand v0.2 -> vrand--CURRENT_VERSION_TEST"));
+    assert!(section("implicit/three/Cargo.toml").contains("up to date"));
+    assert!(section("explicit/four/Cargo.toml").contains("up to date"));
+}
+
 #[test]
 fn upgrade_dependency_in_workspace_member() {
     let (tmpdir, _root_manifest, workspace_manifests) = copy_workspace_test();
@@ -415,26 +1562,19 @@ fn upgrade_dependency_in_workspace_member() {
     );
 }
 
-/// Detect if attempting to run against a workspace root and give a helpful warning.
+/// Running against a workspace root upgrades all of its members.
 #[test]
-#[cfg(feature = "test-external-apis")]
 fn detect_workspace() {
-    let (_tmpdir, root_manifest, _workspace_manifests) = copy_workspace_test();
+    let (_tmpdir, root_manifest, workspace_manifests) = copy_workspace_test();
 
-    assert_cli::Assert::command(&[
-        get_command_path("upgrade").as_str(),
-        "upgrade",
-        "--manifest-path",
-        &root_manifest,
-    ])
-    .fails_with(1)
-    .and()
-    .stderr()
-    .is(
-        "Command failed due to unhandled error: Found virtual manifest, but this command \
-         requires running against an actual package in this workspace. Try adding `--workspace`.",
-    )
-    .unwrap();
+    execute_command(&["upgrade"], &root_manifest);
+
+    for workspace_member in workspace_manifests {
+        assert_eq!(
+            get_toml(&workspace_member)["dependencies"]["libc"].as_str(),
+            Some("libc--CURRENT_VERSION_TEST")
+        );
+    }
 }
 
 #[test]
@@ -447,7 +1587,7 @@ fn invalid_manifest() {
         "--manifest-path",
         &manifest,
     ])
-    .with_env(&[("CARGO_IS_TEST", "1")])
+    .with_env([("CARGO_IS_TEST", "1")])
     .fails_with(1)
     .and()
     .stderr()
@@ -475,7 +1615,7 @@ fn invalid_root_manifest_all() {
         "--manifest-path",
         &manifest,
     ])
-    .with_env(&[("CARGO_IS_TEST", "1")])
+    .with_env([("CARGO_IS_TEST", "1")])
     .fails_with(1)
     .and()
     .stderr()
@@ -494,7 +1634,7 @@ fn invalid_root_manifest_workspace() {
         "--manifest-path",
         &manifest,
     ])
-    .with_env(&[("CARGO_IS_TEST", "1")])
+    .with_env([("CARGO_IS_TEST", "1")])
     .fails_with(1)
     .and()
     .stderr()
@@ -510,7 +1650,7 @@ fn unknown_flags() {
         "foo",
         "--flag",
     ])
-    .with_env(&[("CARGO_IS_TEST", "1")])
+    .with_env([("CARGO_IS_TEST", "1")])
     .fails_with(1)
     .and()
     .stderr()
@@ -543,6 +1683,40 @@ fn upgrade_to_lockfile() {
     assert_eq!(target.to_string(), upgraded.to_string());
 }
 
+#[test]
+#[cfg(feature = "test-external-apis")]
+fn upgrade_check_lock_changes_nothing() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/upgrade/Cargo.toml.lockfile_source");
+    std::fs::copy(
+        std::path::Path::new("tests/fixtures/upgrade/Cargo.lock"),
+        tmpdir.path().join("Cargo.lock"),
+    )
+    .unwrap_or_else(|err| panic!("could not copy test lock file: {}", err));
+    let original = std::fs::read_to_string(&manifest).unwrap();
+
+    execute_command(&["upgrade", "--check-lock"], &manifest);
+
+    assert_eq!(std::fs::read_to_string(&manifest).unwrap(), original);
+}
+
+#[test]
+fn upgrade_check_lock_conflicts_with_to_lockfile() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/upgrade/Cargo.toml.source");
+
+    assert_cli::Assert::command(&[
+        get_command_path("upgrade").as_str(),
+        "upgrade",
+        "--check-lock",
+        "--to-lockfile",
+        &format!("--manifest-path={}", manifest),
+    ])
+    .fails()
+    .and()
+    .stderr()
+    .contains("cannot be used with")
+    .unwrap();
+}
+
 #[test]
 #[cfg(feature = "test-external-apis")]
 fn upgrade_workspace_to_lockfile_all() {
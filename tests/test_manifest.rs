@@ -1,5 +1,5 @@
 mod utils;
-use crate::utils::get_command_path;
+use crate::utils::{execute_command, get_command_path};
 
 #[test]
 fn invalid_manifest() {
@@ -34,3 +34,30 @@ While parsing a Float"#,
     )
     .unwrap();
 }
+
+#[test]
+fn keeps_line_endings() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = dir.path().join("Cargo.toml");
+    std::fs::write(
+        &manifest,
+        "[package]\r\nname = \"a\"\r\nversion = \"0.1.0\"\r\n\r\n[dependencies]\r\nfoo = \"1.0\"",
+    )
+    .unwrap();
+    let manifest = manifest.to_str().unwrap();
+
+    execute_command(&["add", "bar"], manifest);
+    let contents = std::fs::read_to_string(manifest).unwrap();
+    assert_eq!(
+        contents,
+        "[package]\r\nname = \"a\"\r\nversion = \"0.1.0\"\r\n\r\n[dependencies]\r\n\
+         bar = \"bar--CURRENT_VERSION_TEST\"\r\nfoo = \"1.0\""
+    );
+
+    execute_command(&["rm", "bar"], manifest);
+    let contents = std::fs::read_to_string(manifest).unwrap();
+    assert_eq!(
+        contents,
+        "[package]\r\nname = \"a\"\r\nversion = \"0.1.0\"\r\n\r\n[dependencies]\r\nfoo = \"1.0\""
+    );
+}
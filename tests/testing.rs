@@ -0,0 +1,521 @@
+//! Tests of cargo-edit's binaries against a mock registry, which need the `testing` feature.
+
+use cargo_edit::testing::{MockRegistry, MockVersion, TempManifest};
+
+mod utils;
+use crate::utils::{
+    execute_command, execute_command_in_dir, get_command_path, get_toml, setup_alt_registry_config,
+};
+
+fn registry() -> MockRegistry {
+    let registry = MockRegistry::new().unwrap();
+    for version in &["1.0.0", "1.2.0", "2.0.0-rc.1"] {
+        registry
+            .publish(&MockVersion::new("mock-crate", version))
+            .unwrap();
+    }
+    registry
+        .publish(&MockVersion::new("mock-crate", "1.3.0").set_yanked(true))
+        .unwrap();
+    registry
+}
+
+#[test]
+fn adds_from_mock_registry() {
+    let registry = registry();
+    let manifest = TempManifest::new("[package]\nname = \"demo\"\nversion = \"0.1.0\"\n").unwrap();
+    let manifest_path = manifest.path().to_str().unwrap();
+
+    execute_command(
+        &[
+            "add",
+            "mock-crate",
+            "--local-registry",
+            registry.path().to_str().unwrap(),
+        ],
+        manifest_path,
+    );
+
+    let toml = get_toml(manifest_path);
+    assert_eq!(toml["dependencies"]["mock-crate"].as_str(), Some("1.2.0"));
+}
+
+#[test]
+fn upgrades_from_mock_registry() {
+    let registry = registry();
+    let manifest = TempManifest::new(
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\nmock-crate = \"1.0\"\n",
+    )
+    .unwrap();
+    let manifest_path = manifest.path().to_str().unwrap();
+
+    execute_command(
+        &[
+            "upgrade",
+            "--local-registry",
+            registry.path().to_str().unwrap(),
+        ],
+        manifest_path,
+    );
+
+    let toml = get_toml(manifest_path);
+    assert_eq!(toml["dependencies"]["mock-crate"].as_str(), Some("1.2"));
+}
+
+#[test]
+fn upgrade_fixes_stale_features() {
+    let registry = MockRegistry::new().unwrap();
+    registry
+        .publish(
+            &MockVersion::new("feature-crate", "1.0.0")
+                .set_feature("old_name", &[])
+                .set_feature("gone", &[]),
+        )
+        .unwrap();
+    registry
+        .publish(&MockVersion::new("feature-crate", "2.0.0").set_feature("old-name", &[]))
+        .unwrap();
+    let manifest = TempManifest::new(
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\n\
+         feature-crate = { version = \"1.0\", features = [\"old_name\", \"gone\"] }\n",
+    )
+    .unwrap();
+    let manifest_path = manifest.path().to_str().unwrap();
+
+    assert_cli::Assert::command(&[
+        get_command_path("upgrade").as_str(),
+        "upgrade",
+        "--dry-run",
+        "--local-registry",
+        registry.path().to_str().unwrap(),
+        &format!("--manifest-path={}", manifest_path),
+    ])
+    .stderr()
+    .contains("`feature-crate` 2.0.0 has no `gone` feature")
+    .and()
+    .stderr()
+    .contains("it may have been renamed to `old-name`")
+    .unwrap();
+
+    execute_command(
+        &[
+            "upgrade",
+            "--fix-features",
+            "--local-registry",
+            registry.path().to_str().unwrap(),
+        ],
+        manifest_path,
+    );
+
+    let toml = get_toml(manifest_path);
+    let dep = &toml["dependencies"]["feature-crate"];
+    assert_eq!(dep["version"].as_str(), Some("2.0"));
+    let features: Vec<_> = dep["features"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f.as_str().unwrap())
+        .collect();
+    assert_eq!(features, vec!["old-name"]);
+}
+
+#[test]
+fn upgrade_respects_min_age() {
+    let registry = MockRegistry::new().unwrap();
+    for (version, published) in &[
+        ("1.0.0", "2020-01-01T00:00:00Z"),
+        ("1.1.0", "2020-06-01T00:00:00Z"),
+        // Published long after the cooldown could have passed.
+        ("1.2.0", "2999-01-01T00:00:00Z"),
+    ] {
+        registry
+            .publish(&MockVersion::new("cool-crate", version).set_published(published))
+            .unwrap();
+    }
+    // Without a publish time, a version can't be shown to be old enough.
+    registry
+        .publish(&MockVersion::new("cool-crate", "1.3.0"))
+        .unwrap();
+    let upgrade = |requirement: &str| {
+        let manifest = TempManifest::new(&format!(
+            "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\n\
+             cool-crate = \"{}\"\n",
+            requirement
+        ))
+        .unwrap();
+        let manifest_path = manifest.path().to_str().unwrap();
+        assert_cli::Assert::command(&[
+            get_command_path("upgrade").as_str(),
+            "upgrade",
+            "--min-age",
+            "7d",
+            "--local-registry",
+            registry.path().to_str().unwrap(),
+            &format!("--manifest-path={}", manifest_path),
+        ])
+        .succeeds()
+        .unwrap();
+        get_toml(manifest_path)["dependencies"]["cool-crate"]
+            .as_str()
+            .unwrap()
+            .to_owned()
+    };
+
+    assert_eq!(upgrade("1.0"), "1.1");
+    // Moving to the newest version old enough would be a downgrade.
+    assert_eq!(upgrade("1.2"), "1.2");
+}
+
+#[test]
+fn staleness_reports_releases_behind_and_age() {
+    let registry = MockRegistry::new().unwrap();
+    for (version, published) in &[
+        ("1.0.0", "2020-01-01T00:00:00Z"),
+        ("1.1.0", "2020-06-01T00:00:00Z"),
+        ("2.0.0", "2021-01-01T00:00:00Z"),
+    ] {
+        registry
+            .publish(&MockVersion::new("old-crate", version).set_published(published))
+            .unwrap();
+    }
+    // Prereleases aren't counted as releases behind.
+    registry
+        .publish(&MockVersion::new("old-crate", "3.0.0-rc.1"))
+        .unwrap();
+    let manifest = TempManifest::new(
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\n\
+         old-crate = \"1.0\"\n",
+    )
+    .unwrap();
+
+    assert_cli::Assert::command(&[
+        get_command_path("staleness").as_str(),
+        "staleness",
+        "--local-registry",
+        registry.path().to_str().unwrap(),
+        &format!("--manifest-path={}", manifest.path().display()),
+    ])
+    .succeeds()
+    .and()
+    .stdout()
+    // Without a lock file, the version in use is the newest the requirement allows.
+    .contains("old-crate  1.0          -       1.1.0    2.0.0   1       ")
+    .and()
+    .stdout()
+    .contains("Releases behind: 1 in all, 1.0 per dependency, with 0 of 1 dependencies up to date")
+    .and()
+    .stdout()
+    .contains("Age of the versions in use: ")
+    .unwrap();
+}
+
+#[test]
+fn policy_is_enforced() {
+    let registry = registry();
+    let dir = tempfile::tempdir().unwrap();
+    let policy_path = dir.path().join("policy.toml");
+    std::fs::write(
+        &policy_path,
+        "[crates.mock-crate]\nallowed = \"<1.2\"\n\n\
+         [crates.banned-crate]\nbanned = true\nreason = \"unmaintained\"\n",
+    )
+    .unwrap();
+    let policy_path = policy_path.to_str().unwrap();
+    registry
+        .publish(&MockVersion::new("banned-crate", "1.0.0"))
+        .unwrap();
+    let manifest = TempManifest::new(
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\nmock-crate = \"1.0\"\n",
+    )
+    .unwrap();
+    let manifest_path = manifest.path().to_str().unwrap();
+
+    assert_cli::Assert::command(&[
+        get_command_path("add").as_str(),
+        "add",
+        "banned-crate",
+        "--policy",
+        policy_path,
+        "--local-registry",
+        registry.path().to_str().unwrap(),
+        &format!("--manifest-path={}", manifest_path),
+    ])
+    .fails_with(1)
+    .and()
+    .stderr()
+    .contains("Policy violation for `banned-crate`: the crate is banned (unmaintained)")
+    .unwrap();
+
+    assert_cli::Assert::command(&[
+        get_command_path("upgrade").as_str(),
+        "upgrade",
+        "--local-registry",
+        registry.path().to_str().unwrap(),
+        &format!("--manifest-path={}", manifest_path),
+    ])
+    .with_env(assert_cli::Environment::inherit().insert("CARGO_EDIT_POLICY", policy_path))
+    .succeeds()
+    .and()
+    .stderr()
+    .contains("WARN: Upgrading `mock-crate` to 1.0.0, as the policy doesn't allow 1.2.0")
+    .unwrap();
+
+    let toml = get_toml(manifest_path);
+    assert_eq!(toml["dependencies"]["mock-crate"].as_str(), Some("1.0"));
+    assert!(toml["dependencies"]["banned-crate"].is_none());
+}
+
+#[test]
+fn upgrade_warns_about_unmaintained_crates() {
+    let registry = MockRegistry::new().unwrap();
+    registry
+        .publish(&MockVersion::new("failure", "0.1.8"))
+        .unwrap();
+    registry
+        .publish(&MockVersion::new("gone-crate", "1.0.0").set_yanked(true))
+        .unwrap();
+    let db = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(db.path().join("crates/failure")).unwrap();
+    std::fs::write(
+        db.path().join("crates/failure/RUSTSEC-2020-0036.md"),
+        "```toml\n[advisory]\nid = \"RUSTSEC-2020-0036\"\npackage = \"failure\"\n\
+         informational = \"unmaintained\"\n\n[versions]\npatched = []\n```\n\n\
+         # failure is officially deprecated/unmaintained\n\n\
+         - [`anyhow`](https://crates.io/crates/anyhow)\n\
+         - [`thiserror`](https://crates.io/crates/thiserror)\n",
+    )
+    .unwrap();
+    let manifest = TempManifest::new(
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\n\
+         failure = \"0.1\"\ngone-crate = \"1.0\"\n",
+    )
+    .unwrap();
+
+    assert_cli::Assert::command(&[
+        get_command_path("upgrade").as_str(),
+        "upgrade",
+        "--dry-run",
+        "--keep-going",
+        "--advisory-db",
+        db.path().to_str().unwrap(),
+        "--local-registry",
+        registry.path().to_str().unwrap(),
+        &format!("--manifest-path={}", manifest.path().to_str().unwrap()),
+    ])
+    .fails()
+    .and()
+    .stderr()
+    .contains(
+        "WARN: `failure` is unmaintained (RUSTSEC-2020-0036); consider `anyhow` or `thiserror` \
+         instead",
+    )
+    .and()
+    .stderr()
+    .contains("WARN: Every version of `gone-crate` has been yanked")
+    .unwrap();
+}
+
+#[test]
+fn upgrade_writes_ci_reports() {
+    let registry = registry();
+    for version in &["0.1.0", "0.2.0"] {
+        registry
+            .publish(&MockVersion::new("leaky", version))
+            .unwrap();
+    }
+    let db = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(db.path().join("crates/leaky")).unwrap();
+    std::fs::write(
+        db.path().join("crates/leaky/RUSTSEC-2021-0001.md"),
+        "```toml\n[advisory]\nid = \"RUSTSEC-2021-0001\"\npackage = \"leaky\"\n\n\
+         [versions]\npatched = [\">= 0.2.0\"]\n```\n\n# Leaky leaks memory\n",
+    )
+    .unwrap();
+    let manifest = TempManifest::new(
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\n\
+         mock-crate = \"1.0\"\nleaky = \"0.1\"\n",
+    )
+    .unwrap();
+    let report = |format: &str| {
+        let path = manifest.dir().join(format!("report.{}", format));
+        execute_command(
+            &[
+                "upgrade",
+                "--dry-run",
+                "--advisory-db",
+                db.path().to_str().unwrap(),
+                "--local-registry",
+                registry.path().to_str().unwrap(),
+                "--report",
+                format,
+                "--report-path",
+                path.to_str().unwrap(),
+            ],
+            manifest.path().to_str().unwrap(),
+        );
+        std::fs::read_to_string(path).unwrap()
+    };
+
+    let sarif: serde_json::Value = serde_json::from_str(&report("sarif")).unwrap();
+    let results = sarif["runs"][0]["results"].as_array().unwrap();
+    let result = |rule: &str, dependency: &str| {
+        results
+            .iter()
+            .find(|r| {
+                r["ruleId"] == rule && r["message"]["text"].as_str().unwrap().contains(dependency)
+            })
+            .unwrap_or_else(|| panic!("no `{}` result for {}", rule, dependency))
+    };
+    assert_eq!(results.len(), 3);
+    assert_eq!(
+        result("vulnerable", "leaky")["message"]["text"],
+        "`leaky` v0.1.0 has a known vulnerability (RUSTSEC-2021-0001)"
+    );
+    assert_eq!(result("vulnerable", "leaky")["level"], "error");
+    assert_eq!(
+        result("outdated", "mock-crate")["message"]["text"],
+        "`mock-crate` can be upgraded from 1.0 to 1.2"
+    );
+    let region = &result("outdated", "leaky")["locations"][0]["physicalLocation"]["region"];
+    assert_eq!(region["startLine"], 7);
+    assert_eq!(region["startColumn"], 1);
+    assert_eq!(region["endColumn"], 14);
+
+    let junit = report("junit");
+    assert!(junit.contains("<testsuites name=\"cargo-upgrade\" tests=\"2\" failures=\"2\">"));
+    assert!(junit.contains("<failure type=\"vulnerable,outdated\""));
+    assert!(junit.contains("line=\"6\""));
+
+    // Nothing is written for a report of a dry run.
+    assert!(manifest.read().unwrap().contains("mock-crate = \"1.0\""));
+}
+
+#[test]
+fn upgrade_looks_up_alternative_registry_dependencies_there() {
+    let registry = registry();
+    let manifest = TempManifest::new(
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\n\
+         mock-crate = \"1.0\"\n\
+         internal = { package = \"mock-crate\", version = \"0.9\", registry = \"alternative\" }\n",
+    )
+    .unwrap();
+    setup_alt_registry_config(manifest.dir());
+
+    // Crates from the alternative registry aren't looked up in the local registry standing in for
+    // crates.io, but in the (fake, when testing) alternative one.
+    execute_command_in_dir(
+        &[
+            "upgrade",
+            "--local-registry",
+            registry.path().to_str().unwrap(),
+        ],
+        manifest.dir(),
+    );
+
+    let toml = get_toml(manifest.path().to_str().unwrap());
+    assert_eq!(toml["dependencies"]["mock-crate"].as_str(), Some("1.2"));
+    let alternative = &toml["dependencies"]["internal"];
+    assert_eq!(
+        alternative["version"].as_str(),
+        Some("mock-crate--CURRENT_VERSION_TEST")
+    );
+    assert_eq!(alternative["registry"].as_str(), Some("alternative"));
+}
+
+#[test]
+fn pin_falls_back_to_registry_without_lock_file() {
+    let registry = registry();
+    let manifest = TempManifest::new(
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\nmock-crate = \"1.0\"\n",
+    )
+    .unwrap();
+    // Replace crates.io with the mock registry, as `--local-registry` does for other commands.
+    std::fs::create_dir(manifest.dir().join(".cargo")).unwrap();
+    std::fs::write(
+        manifest.dir().join(".cargo/config.toml"),
+        format!(
+            "[source.crates-io]\nreplace-with = \"mock\"\n\n[source.mock]\nlocal-registry = {:?}\n",
+            registry.path()
+        ),
+    )
+    .unwrap();
+
+    execute_command_in_dir(&["pin"], manifest.dir());
+
+    let toml = get_toml(manifest.path().to_str().unwrap());
+    assert_eq!(toml["dependencies"]["mock-crate"].as_str(), Some("=1.2.0"));
+}
+
+#[test]
+fn upgrade_drops_patches_behind_the_new_version() {
+    let registry = registry();
+    let manifest = TempManifest::new(
+        r#"[package]
+name = "demo"
+version = "0.1.0"
+
+[dependencies]
+mock-crate = "1.0"
+
+[patch.crates-io]
+mock-crate = { path = "fork" }
+"#,
+    )
+    .unwrap();
+    let manifest_path = manifest.path().to_str().unwrap();
+    let fork = manifest.dir().join("fork");
+    std::fs::create_dir_all(fork.join("src")).unwrap();
+    std::fs::write(fork.join("src/lib.rs"), "").unwrap();
+    let write_fork = |version: &str| {
+        std::fs::write(
+            fork.join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"mock-crate\"\nversion = \"{}\"\n",
+                version
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            manifest.dir().join("Cargo.lock"),
+            format!(
+                "[[package]]\nname = \"mock-crate\"\nversion = \"{}\"\n",
+                version
+            ),
+        )
+        .unwrap();
+    };
+    let upgrade = || {
+        assert_cli::Assert::command(&[
+            get_command_path("upgrade").as_str(),
+            "upgrade",
+            "--drop-patches",
+            "--local-registry",
+            registry.path().to_str().unwrap(),
+            &format!("--manifest-path={}", manifest_path),
+        ])
+        .with_env(assert_cli::Environment::inherit().insert("CARGO_IS_TEST", "1"))
+    };
+
+    // The fork is as new as the latest release, which may lack its changes.
+    write_fork("1.2.0");
+    upgrade()
+        .succeeds()
+        .and()
+        .stderr()
+        .contains("`mock-crate` 1.2.0 is not newer than the patched 1.2.0")
+        .unwrap();
+    let toml = get_toml(manifest_path);
+    assert_eq!(toml["dependencies"]["mock-crate"].as_str(), Some("1.0"));
+    assert!(!toml["patch"]["crates-io"]["mock-crate"].is_none());
+
+    write_fork("1.1.0");
+    upgrade()
+        .succeeds()
+        .and()
+        .stdout()
+        .contains("[patch.crates-io] entry for mock-crate v1.1.0, as v1.2.0 is newer")
+        .unwrap();
+    let toml = get_toml(manifest_path);
+    assert_eq!(toml["dependencies"]["mock-crate"].as_str(), Some("1.2"));
+    assert!(toml["patch"].is_none());
+}
@@ -84,7 +84,7 @@ where
 {
     let subcommand_name = &command[0].as_ref();
 
-    let call = process::Command::new(&get_command_path(subcommand_name))
+    let call = process::Command::new(get_command_path(subcommand_name))
         .args(command)
         .arg(format!("--manifest-path={}", manifest))
         .env("CARGO_IS_TEST", "1")
@@ -111,11 +111,11 @@ where
     let subcommand_name = &command[0].as_ref();
     let cwd = cwd.as_ref();
 
-    let call = process::Command::new(&get_command_path(subcommand_name))
+    let call = process::Command::new(get_command_path(subcommand_name))
         .args(command)
         .arg("--package")
         .arg(pkgid)
-        .current_dir(&cwd)
+        .current_dir(cwd)
         .env("CARGO_IS_TEST", "1")
         .output()
         .expect("call to test command failed");
@@ -138,7 +138,7 @@ where
 {
     let subcommand_name = &command[0].as_ref();
 
-    let call = process::Command::new(&get_command_path(subcommand_name))
+    let call = process::Command::new(get_command_path(subcommand_name))
         .args(command)
         .arg(format!("--manifest-path={}", manifest))
         .env("CARGO_IS_TEST", "1")
@@ -163,7 +163,7 @@ where
 {
     let subcommand_name = &command[0].as_ref();
 
-    let call = process::Command::new(&get_command_path(subcommand_name))
+    let call = process::Command::new(get_command_path(subcommand_name))
         .args(command)
         .env("CARGO_IS_TEST", "1")
         .current_dir(dir)
@@ -189,7 +189,10 @@ pub fn get_toml(manifest_path: &str) -> toml_edit::Document {
     s.parse().expect("toml parse error")
 }
 
-pub fn get_command_path(s: impl AsRef<OsStr>) -> String {
+/// The executable that runs the command `_command`, e.g. `add`: `cargo-edit`, which runs the
+/// command its first argument names, as the other executables are only built with the
+/// `separate-bins` feature.
+pub fn get_command_path(_command: impl AsRef<OsStr>) -> String {
     let target_dir: PathBuf = match env::var_os("CARGO_TARGET_DIR") {
         Some(dir) => dir.into(),
         None => env::current_dir()
@@ -197,12 +200,9 @@ pub fn get_command_path(s: impl AsRef<OsStr>) -> String {
             .join("target"),
     };
 
-    let mut binary_name = OsString::from("cargo-");
-    binary_name.push(s.as_ref());
-
     target_dir
         .join("debug")
-        .join(binary_name)
+        .join("cargo-edit")
         .to_str()
         .unwrap()
         .to_string()